@@ -2,27 +2,34 @@ use gdnative::prelude::*;
 
 use legion::*;
 use octree::Octree;
+use serde::{Serialize, Deserialize};
+
+use std::collections::HashMap;
+
 use crate::{
     game_state::{NewState, GameState, GameStateTraits},
     systems::{
         actor,
         camera,
         history::History,
+        input,
         level_map,
+        prefab,
         selection_box,
-        selection_box::SelectionBox,
+        shared_rng,
         networking::{
             ClientID,
+            MessageSender,
             ServerMessageSender,
             DataType,
             MessageType,
         }
     },
     node,
-    node::NodeRef,
 };
 
 type AABB = octree::geometry::aabb::AABB<i32>;
+type Point = nalgebra::Vector3<i32>;
 
 pub struct Editor {
     game_state: GameState,
@@ -38,7 +45,51 @@ impl GameStateTraits for Editor {
         resources.insert(self.map);
         resources.insert(level_map::document::Document::default());
         resources.insert(PaletteSelection(0));
+        resources.insert(SecondaryPaletteSelection::default());
         resources.insert(SelectedTool(selection_box::ToolBoxType::TerrainToolBox));
+        resources.insert(ClientRole::default());
+        resources.insert(ClientColors::default());
+        resources.insert(selection_box::BoundsSequence::default());
+        resources.insert(selection_box::BoundsThrottle::default());
+        resources.insert(selection_box::ClearRegionConfirmation::default());
+        resources.insert(selection_box::RemoveOutsideConfirmation::default());
+        resources.insert(shared_rng::SharedRng::from_entropy());
+        resources.insert(ScatterDensity::default());
+        resources.insert(RotationStep::default());
+        resources.insert(FillMode::default());
+        resources.insert(MovementMode::default());
+        resources.insert(MovementRate::default());
+        resources.insert(RelativeMovement::default());
+        resources.insert(EdgeBehavior::default());
+        resources.insert(input::GamepadSettings::default());
+        resources.insert(input::InputCaptured::default());
+        resources.insert(ShowDimensions::default());
+        resources.insert(ShowGridLines::default());
+        resources.insert(FollowSelection::default());
+        resources.insert(ActorFilter::default());
+        resources.insert(MeasureAgainst::default());
+        resources.insert(MeasureMode::default());
+        resources.insert(InsertCooldown::default());
+        resources.insert(ShowGhost::default());
+        resources.insert(FocusDuration::default());
+        resources.insert(camera::CameraBookmarks::from_file());
+        resources.insert(ExpandFromFarSide::default());
+        resources.insert(AxisLock::default());
+        resources.insert(LockedLayer::default());
+        resources.insert(DisplayOrigin::default());
+        resources.insert(EditorPaused::default());
+        resources.insert(prefab::SelectedPrefab::default());
+        resources.insert(PasteRotation::default());
+        resources.insert(GridLinesMaxVolume::default());
+        resources.insert(FlattenFillsEmpty::default());
+        resources.insert(SmoothSettings::default());
+        resources.insert(BoxStyle::default());
+        resources.insert(BoxRenderMode::default());
+        resources.insert(BoxPulse::default());
+        resources.insert(SymmetrySettings::default());
+        resources.insert(ConfirmHoldTime::default());
+        resources.insert(SnapToGround::default());
+        resources.insert(AllowOverlap::default());
 
         // if let Some(actor_definitions) = ActorDefinitions::from_config("res://config/actors.ron") {
             // resources.insert(actor_definitions);
@@ -80,6 +131,13 @@ impl GameStateTraits for Editor {
             //Activate tool if this box belongs to the client
             if client_id.val() == connection_id {
 
+                //Let the server know if this client joined as a spectator so it can reject its edits
+                if let Some(client_role) = resources.get::<ClientRole>().map(|client_role| *client_role) {
+                    if client_role == ClientRole::Spectator {
+                        send_client_role(world, client_id, client_role);
+                    }
+                }
+
                 let selected_tool = resources.get::<SelectedTool>().unwrap();
 
                 world.push((
@@ -103,92 +161,150 @@ impl GameStateTraits for Editor {
 
     }
 
-    fn on_disconnection(&self, connection_id: u32, world: &mut World, _: &mut Resources) {
+    fn on_disconnection(&self, connection_id: u32, world: &mut World, resources: &mut Resources) {
 
-        let mut query = <(Read<ClientID>, Read<NodeRef>)>::query().filter(component::<SelectionBox>());
+        //Free up the color so it can be used by the next client that connects
+        if let Some(mut client_colors) = resources.get_mut::<ClientColors>() {
+            client_colors.remove(connection_id);
+        }
 
-        if let Some(node) = query.iter(world)
-            .filter(|(id, _)| connection_id == id.val())
-            .map(|(_, node_ref)| node_ref.val())
-            .next() {
-                node::free(world, node);
-            }
+        if let Some(mut bounds_sequence) = resources.get_mut::<selection_box::BoundsSequence>() {
+            bounds_sequence.forget(connection_id);
+        }
 
-        let mut query = <(Entity, Read<ClientID>)>::query().filter(component::<History>());
-        query.iter(world).filter(|(_, id)| id.val() == connection_id)
-            .map(|(entity, _)| *entity)
-            .collect::<Vec<Entity>>().into_iter()
-            .for_each(|entity| {world.remove(entity);});
+        selection_box::free_all_for_client(world, ClientID::new(connection_id));
 
     }
 
-    fn on_client_connected(&self, connection_id: u32, world: &mut World, _: &mut Resources) {
+    fn on_client_connected(&self, connection_id: u32, world: &mut World, resources: &mut Resources) {
 
-        //Get all of the selection boxes to send them to the new client
-        let mut query = <(Entity, Read<selection_box::SelectionBox>, Read<ClientID>, Read<level_map::CoordPos>)>::query();
+        //Hand the new client the state of the shared rng so it makes the same random decisions as everyone else from here on
+        if let Some(shared_rng) = resources.get::<shared_rng::SharedRng>() {
+            world.push(
+                (
+                    ServerMessageSender {
+                        client_id: connection_id,
+                        data_type: DataType::SessionSeed {
+                            seed: shared_rng.state()
+                        },
+                        message_type: MessageType::Ordered
+                    },
+                )
+            );
+        }
 
-        let results = query.iter(world)
-            .map(|(entity, selection_box, client_id, coord_pos)| (*entity, selection_box.aabb, *client_id, *coord_pos))
-            .collect::<Vec<(Entity, AABB, ClientID, level_map::CoordPos)>>();
+        //Let the new client know about everyone else's colors, then assign it one and let everyone know about it
+        if let Some(mut client_colors) = resources.get_mut::<ClientColors>() {
 
-        let mut query = <(Read<History>, Read<ClientID>)>::query();
+            client_colors.iter()
+                .map(|(client_id, color)| (
+                    ServerMessageSender {
+                        client_id: connection_id,
+                        data_type: DataType::ClientColorAssignment {
+                            client_id,
+                            color: [color.r, color.g, color.b, color.a]
+                        },
+                        message_type: MessageType::Reliable
+                    },
+                ))
+                .collect::<Vec<(ServerMessageSender,)>>()
+                .into_iter()
+                .for_each(|message| { world.push(message); });
 
-        results.into_iter().for_each(|(entity, aabb, client_id, coord_pos)| {
+            let color = client_colors.next_free();
 
-            let history = query.iter(world)
-                .filter(|(_, id)| client_id.val() == id.val())
-                .map(|(history, _)| history.clone())
-                .next().unwrap_or_else(History::new);
+            //Insert it right away so that another client connecting before the message comes back doesn't get the same color
+            client_colors.insert(connection_id, color);
 
-            if let Some(entry) = world.entry(entity) {
+            world.push(
+                (
+                    MessageSender {
+                        data_type: DataType::ClientColorAssignment {
+                            client_id: connection_id,
+                            color: [color.r, color.g, color.b, color.a]
+                        },
+                        message_type: MessageType::Ordered
+                    },
+                )
+            );
+        }
 
-                let box_type: Option<selection_box::ToolBoxType> = if let Ok(actor_tool) = entry.get_component::<selection_box::ActorToolBox>() {
-                    Some(selection_box::ToolBoxType::ActorToolBox(actor_tool.get_selection()))
-                } else if entry.get_component::<selection_box::TerrainToolBox>().is_ok() {
-                    Some(selection_box::ToolBoxType::TerrainToolBox)
-                } else {
-                    None
-                };
+        //Get all of the selection boxes in their current slots to send them to the new client, boxes in other slots are created for it
+        // as they get switched to
+        let snapshots = selection_box::selection_box_snapshots(world);
 
-                let rotation = if let Ok(rotation) = entry.get_component::<selection_box::SelectionBoxRotation>() {
-                    rotation.value
-                } else {
-                    nalgebra::Rotation::identity()
-                }; 
+        let mut query = <(Read<History>, Read<ClientID>)>::query();
 
-                let active = entry.get_component::<selection_box::Active>().is_ok();
+        snapshots.into_iter().for_each(|snapshot| {
 
-                world.extend(vec![
-                    (
-                        ServerMessageSender {
-                            client_id: connection_id,
-                            data_type: DataType::CreateSelectionBox {
-                                active,
-                                box_type: box_type.unwrap(),
-                                client_id: client_id.val(),
-                                rotation,
-                                aabb,
-                                coord_pos: coord_pos.value
-                            },
-                            message_type: MessageType::Reliable,
+            let history = query.iter(world)
+                .filter(|(_, id)| snapshot.client_id == id.val())
+                .map(|(history, _)| history.clone())
+                .next().unwrap_or_else(History::new);
+
+            world.extend(vec![
+                (
+                    ServerMessageSender {
+                        client_id: connection_id,
+                        data_type: DataType::CreateSelectionBox {
+                            active: snapshot.active,
+                            box_type: snapshot.box_type,
+                            client_id: snapshot.client_id,
+                            rotation: snapshot.rotation,
+                            aabb: snapshot.aabb,
+                            coord_pos: snapshot.coord_pos
                         },
-                    ),
-                    (
-                        ServerMessageSender {
-                            client_id: connection_id,
-                            data_type: DataType::CreateHistory{
-                                client_id: client_id.val(),
-                                history
-                            },
-                            message_type: MessageType::Ordered,
+                        message_type: MessageType::Reliable,
+                    },
+                ),
+                (
+                    ServerMessageSender {
+                        client_id: connection_id,
+                        data_type: DataType::CreateHistory{
+                            client_id: snapshot.client_id,
+                            history
                         },
-                    )
-                    ]
-                );
-            }
+                        message_type: MessageType::Ordered,
+                    },
+                )
+                ]
+            );
 
         });
 
+        //send everyone's claimed regions so that the new client outlines them too
+        let mut query = <(Read<selection_box::RegionClaim>, Read<ClientID>)>::query();
+
+        query.iter(world)
+            .map(|(claim, client_id)| (
+                ServerMessageSender {
+                    client_id: connection_id,
+                    data_type: DataType::ClaimRegion {
+                        client_id: client_id.val(),
+                        aabb: claim.aabb
+                    },
+                    message_type: MessageType::Reliable
+                },
+            ))
+            .collect::<Vec<(ServerMessageSender,)>>()
+            .into_iter()
+            .for_each(|message| { world.push(message); });
+
+        //the new client keeps its boxes and edits within the same bounds as everyone else
+        if let Some(bounds) = resources.get::<level_map::Map>().and_then(|map| map.bounds()) {
+            world.push(
+                (
+                    ServerMessageSender {
+                        client_id: connection_id,
+                        data_type: DataType::SetMapBounds {
+                            bounds: Some(bounds)
+                        },
+                        message_type: MessageType::Ordered
+                    },
+                )
+            );
+        }
+
         //send all of the current map data as map inputs to the new client
         let mut query = <Read<level_map::MapChunkData>>::query();
 
@@ -216,9 +332,7 @@ impl GameStateTraits for Editor {
                     ServerMessageSender{
                         client_id: connection_id,
                         data_type: DataType::ActorChange {
-                            change: actor::ActorChange::ActorInsertion {
-                                serialized
-                            },
+                            change: actor::ActorChange::insertion(serialized),
                             store_history: None, 
                         },
                         message_type: MessageType::Ordered,
@@ -253,6 +367,9 @@ impl NewState for Editor {
     }
 }
 
+/// How many tiles the tile palette offers, tile ids go from 0 up to but not including this
+pub const TILE_COUNT: u32 = 64;
+
 #[derive(Copy, Clone)]
 pub struct PaletteSelection(u32);
 
@@ -267,6 +384,21 @@ impl PaletteSelection {
     }
 }
 
+/// The other tile used alongside PaletteSelection by fills that alternate between two tiles, like checkerboard_fill
+#[derive(Copy, Clone, Default)]
+pub struct SecondaryPaletteSelection(u32);
+
+impl SecondaryPaletteSelection {
+
+    pub fn new(id: u32) -> SecondaryPaletteSelection {
+        SecondaryPaletteSelection(id)
+    }
+
+    pub fn val(&self) -> u32 {
+        self.0
+    }
+}
+
 #[derive(Copy, Clone)]
 pub struct ActorPaletteSelection(i64);
 
@@ -282,4 +414,576 @@ impl ActorPaletteSelection {
 }
 
 #[derive(Copy, Clone, PartialEq)]
-pub struct SelectedTool(pub selection_box::ToolBoxType);
\ No newline at end of file
+pub struct SelectedTool(pub selection_box::ToolBoxType);
+
+/// How many actors the scatter brush places per cell in the box, between 0 and 1
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ScatterDensity(pub f32);
+
+impl Default for ScatterDensity {
+    fn default() -> Self {
+        ScatterDensity(0.1)
+    }
+}
+
+/// The angle in radians that randomized actor rotations get quantized to
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RotationStep(pub f32);
+
+impl Default for RotationStep {
+    fn default() -> Self {
+        RotationStep(std::f32::consts::FRAC_PI_2)
+    }
+}
+
+/// Resource that determines how the selection box moves while a movement key is held
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MovementMode {
+    /// Jumps a cell at a time on a fixed repeat
+    Stepped,
+    /// Travels at the MovementRate, drawn smoothly between cells locally while only sending an update each time it crosses into a new cell
+    Continuous,
+}
+
+impl Default for MovementMode {
+    fn default() -> Self {
+        MovementMode::Stepped
+    }
+}
+
+/// What happens when the selection box is moved past the edge of the map's bounds
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EdgeBehavior {
+    /// Pushes the box back so that it sits against the edge
+    Clamp,
+    /// Cancels the movement, leaving the box where it was
+    Stop,
+    /// Sends the box over to the opposite edge
+    Wrap,
+}
+
+impl Default for EdgeBehavior {
+    fn default() -> Self {
+        EdgeBehavior::Clamp
+    }
+}
+
+/// When on, boxes that have been rotated, like the actor box, move relative to the way they're facing rather than along the world's 
+/// axes
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RelativeMovement(pub bool);
+
+/// How many cells per second the selection box travels in the continuous movement mode
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MovementRate(pub f32);
+
+impl Default for MovementRate {
+    fn default() -> Self {
+        MovementRate(8.)
+    }
+}
+
+/// Whether the camera pans back over to the active selection box once the box ends up completely out of its view
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FollowSelection(pub bool);
+
+impl Default for FollowSelection {
+    fn default() -> Self {
+        FollowSelection(true)
+    }
+}
+
+/// Whether or not the selection boxes draw lines through their volume at every cell boundary
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ShowGridLines(pub bool);
+
+/// Selection boxes with more cells than this don't draw grid lines, even when ShowGridLines is on
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct GridLinesMaxVolume(pub i32);
+
+impl Default for GridLinesMaxVolume {
+    fn default() -> Self {
+        GridLinesMaxVolume(32768)
+    }
+}
+
+/// Whether flattening puts a tile of the palette selection at the floor of columns that are empty, rather than skipping them
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct FlattenFillsEmpty(pub bool);
+
+/// Settings for the smoothing brush
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SmoothSettings {
+    /// Between 0 and 1, how readily columns move towards the height of their neighbours. At 1 any column more than half a cell away 
+    /// from the local average moves, lower values leave more of the terrain's roughness intact.
+    pub strength: f32,
+}
+
+impl Default for SmoothSettings {
+    fn default() -> Self {
+        SmoothSettings {
+            strength: 1.
+        }
+    }
+}
+
+/// How the selection box meshes are drawn
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BoxStyle {
+    /// How far in from each edge the corner bevel reaches, in world units. It gets clamped to half of the box's smallest axis so 
+    /// that thin boxes don't end up with inverted faces.
+    pub margin: f32,
+    /// Written to the line_thickness parameter of the selection box material when it is a ShaderMaterial
+    pub line_thickness: f32,
+}
+
+impl Default for BoxStyle {
+    fn default() -> Self {
+        BoxStyle {
+            margin: 0.9,
+            line_thickness: 0.1,
+        }
+    }
+}
+
+impl BoxStyle {
+    /// Returns a copy with values that can't be drawn replaced, so negative values become 0 and values that aren't finite fall 
+    /// back to the default. The margin is clamped to the size of each box separately when its mesh is built.
+    pub fn sanitized(self) -> Self {
+        let default = BoxStyle::default();
+
+        let sanitize = |value: f32, default: f32| if value.is_finite() { value.max(0.) } else { default };
+
+        BoxStyle {
+            margin: sanitize(self.margin, default.margin),
+            line_thickness: sanitize(self.line_thickness, default.line_thickness),
+        }
+    }
+}
+
+/// How the selection box meshes are built
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BoxRenderMode {
+    /// Solid faces with beveled corners, sized by BoxStyle
+    Beveled,
+    /// Only the 12 edges of the box drawn as lines, so that small tiles behind it stay visible
+    Wireframe,
+}
+
+impl Default for BoxRenderMode {
+    fn default() -> Self {
+        BoxRenderMode::Beveled
+    }
+}
+
+/// Settings for the pulse that makes the local client's active box stand out
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BoxPulse {
+    pub enabled: bool,
+    /// Between 0 and 1, how far the pulse dips from the box's static look. At 1 it fades all the way down to the bottom of the pulse.
+    pub amplitude: f32,
+}
+
+impl Default for BoxPulse {
+    fn default() -> Self {
+        BoxPulse {
+            enabled: true,
+            amplitude: 1.
+        }
+    }
+}
+
+/// Whether or not the selection boxes show the length of each of their axes
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ShowDimensions(pub bool);
+
+/// Resource that determines which cells of the selection box the tile tool fills on insertion
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FillMode {
+    Solid,
+    /// Only the outer faces of the box
+    Shell,
+    /// Only the 12 edges of the box
+    Frame,
+}
+
+impl Default for FillMode {
+    fn default() -> Self {
+        FillMode::Solid
+    }
+}
+
+impl FillMode {
+    /// Splits the aabb into the non-overlapping aabbs that make up the cells to be filled in this mode. A box that is one cell 
+    /// thick along any axis is always filled solid.
+    pub fn split(&self, aabb: AABB) -> Vec<AABB> {
+
+        let dimensions = aabb.dimensions.abs();
+
+        if dimensions.x <= 1 || dimensions.y <= 1 || dimensions.z <= 1 {
+            return vec![aabb]
+        }
+
+        match self {
+            FillMode::Solid => vec![aabb],
+            FillMode::Shell => level_map::shell_aabbs(aabb),
+            FillMode::Frame => level_map::frame_aabbs(aabb),
+        }
+    }
+}
+
+/// Live symmetry for the tile tool. While enabled, every terrain insertion and removal is also made mirrored across the plane that 
+/// crosses the axis, where 0 is x, 1 is y and 2 is z, through the middle of the cells at plane on that axis.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SymmetrySettings {
+    pub enabled: bool,
+    pub axis: usize,
+    pub plane: i32,
+}
+
+impl SymmetrySettings {
+
+    /// Returns the mirror image of aabb, which is part of the box being edited, with whatever lands back inside of that box along 
+    /// the axis left out. When the box straddles the plane its mirror image overlaps it, and only the part that sticks out past the 
+    /// box is returned so that no cell gets changed twice. Returns None if symmetry is off or nothing is left to mirror.
+    pub fn mirror(&self, aabb: AABB, edited: AABB) -> Option<AABB> {
+
+        if !self.enabled || self.axis > 2 {
+            return None
+        }
+
+        let axis = self.axis;
+        let min = aabb.get_min();
+        let max = aabb.get_max();
+
+        let lower = 2 * self.plane - max[axis];
+        let upper = 2 * self.plane - min[axis];
+
+        let edited_lower = edited.get_min()[axis];
+        let edited_upper = edited.get_max()[axis];
+
+        //the mirror image can only stick out past one side of the box, so what's left is always a single range
+        let (lower, upper) = if upper < edited_lower || lower > edited_upper {
+            (lower, upper)
+        } else if lower < edited_lower {
+            (lower, edited_lower - 1)
+        } else if upper > edited_upper {
+            (edited_upper + 1, upper)
+        } else {
+            return None
+        };
+
+        let mut mirrored_min = min;
+        let mut mirrored_max = max;
+
+        mirrored_min[axis] = lower;
+        mirrored_max[axis] = upper;
+
+        Some(AABB::from_extents(mirrored_min, mirrored_max))
+    }
+
+    /// Returns the orientation that a tile placed with the given orientation has in the mirror image
+    pub fn mirror_orientation(&self, orientation: level_map::TileOrientation) -> level_map::TileOrientation {
+        use level_map::TileOrientation::*;
+
+        match (self.axis, orientation) {
+            (0, East) => West,
+            (0, West) => East,
+            (2, North) => South,
+            (2, South) => North,
+            (_, orientation) => orientation
+        }
+    }
+}
+
+/// How long in seconds insertion has to be held before the tile tool fills a terrain box with more than min_volume cells, so that big 
+/// fills aren't made by accident. Boxes up to min_volume, or any box when seconds is 0, are filled as soon as insertion is pressed.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ConfirmHoldTime {
+    pub seconds: f32,
+    pub min_volume: i64,
+}
+
+impl Default for ConfirmHoldTime {
+    fn default() -> Self {
+        ConfirmHoldTime {
+            seconds: 0.,
+            min_volume: 4096,
+        }
+    }
+}
+
+impl ConfirmHoldTime {
+    /// Whether filling the aabb has to be held to confirm
+    pub fn applies_to(&self, aabb: AABB) -> bool {
+        let dimensions = aabb.dimensions.abs();
+
+        self.seconds > 0. && dimensions.x as i64 * dimensions.y as i64 * dimensions.z as i64 > self.min_volume
+    }
+}
+
+/// When enabled, actors inserted with the actor tool are dropped onto the ground beneath the box so that the bottom of their Bounds 
+/// rests on the highest tile below. Actors stay at the box if there's no ground within max_drop cells below it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SnapToGround {
+    pub enabled: bool,
+    pub max_drop: i32,
+}
+
+impl Default for SnapToGround {
+    fn default() -> Self {
+        SnapToGround {
+            enabled: false,
+            max_drop: 64,
+        }
+    }
+}
+
+/// When on, the actor tool inserts actors even where they would overlap existing ones, for stacking props on purpose
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct AllowOverlap(pub bool);
+
+/// How many quarter turns about the y axis the selected prefab is stamped with, cycled by the rotate_paste action
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PasteRotation(pub u8);
+
+/// When on, expanding the terrain box always grows it away from the camera instead of depending on which side of it the camera is on
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExpandFromFarSide(pub bool);
+
+/// Axes that the local client's terrain box can't be expanded or moved along, for keeping edits to a flat pad or a single plane. 
+/// This sits on top of the box's own ExpansionAxisLock, which only ever stops expansion.
+pub type AxisLock = selection_box::ExpansionAxisLock;
+
+/// The coordinate that positions are shown relative to in the SelectionBoxObserver, such as the corner of a building being worked 
+/// on. It only changes what gets shown, never the coordinates of tiles, boxes or anything that gets sent.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DisplayOrigin(pub Point);
+
+impl Default for DisplayOrigin {
+    fn default() -> Self {
+        DisplayOrigin(Point::zeros())
+    }
+}
+
+/// Freezes editing for things like cutscenes and menus while it's on. The selection box systems that act on input return early 
+/// without tearing anything down, while boxes keep being drawn and updated by other clients.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct EditorPaused(pub bool);
+
+/// Pauses or unpauses editing. Actions that were held while paused are ignored until they're released, so nothing builds up to be 
+/// applied all at once after unpausing.
+pub fn set_editor_paused(resources: &mut Resources, paused: bool) {
+    resources.insert(EditorPaused(paused));
+}
+
+/// The y coordinate that the local client's terrain box is kept on while editing floor by floor, or None when it can move freely
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct LockedLayer(pub Option<i32>);
+
+/// Roughly how many seconds the camera takes to move over to the selection box with the focus_selection action
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FocusDuration(pub f32);
+
+impl Default for FocusDuration {
+    fn default() -> Self {
+        FocusDuration(0.5)
+    }
+}
+
+/// Whether or not a translucent preview of what the terrain box would insert is drawn inside of it
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ShowGhost(pub bool);
+
+impl Default for ShowGhost {
+    fn default() -> Self {
+        ShowGhost(true)
+    }
+}
+
+/// Seconds after an insertion or removal during which another one from the same client is ignored, for input that bounces
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct InsertCooldown(pub f32);
+
+/// While on, the selection box only reports measurements and the editing tools are disabled
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct MeasureMode(pub bool);
+
+/// Resource for which selection box the local client's active box gets measured against
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MeasureAgainst {
+    Nothing,
+    /// This client's box in the given slot, for the same tool type as the active box
+    Slot(u8),
+    /// The active box of another client
+    Client(u32),
+}
+
+impl Default for MeasureAgainst {
+    fn default() -> Self {
+        MeasureAgainst::Nothing
+    }
+}
+
+/// Resource that restricts which actors the actor tool's removal and clear region sweeps affect, matched by the actors' names
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ActorFilter {
+    All,
+    /// Only actors with one of these names
+    Only(Vec<String>),
+    /// Every actor except the ones with these names
+    Except(Vec<String>),
+}
+
+impl Default for ActorFilter {
+    fn default() -> Self {
+        ActorFilter::All
+    }
+}
+
+impl ActorFilter {
+    pub fn allows(&self, entry: &world::EntryRef) -> bool {
+        let name = entry.get_component::<actor::Actor>().ok().map(|actor| actor.0.clone());
+
+        match self {
+            ActorFilter::All => true,
+            ActorFilter::Only(names) => name.map(|name| names.contains(&name)).unwrap_or(false),
+            ActorFilter::Except(names) => name.map(|name| !names.contains(&name)).unwrap_or(true),
+        }
+    }
+}
+
+/// Resource that determines whether or not this client is allowed to make changes to the document
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ClientRole {
+    Editor,
+    /// Can move around and receives all updates, but can't edit
+    Spectator
+}
+
+impl ClientRole {
+    pub fn can_edit(&self) -> bool {
+        *self == ClientRole::Editor
+    }
+}
+
+impl Default for ClientRole {
+    fn default() -> Self {
+        ClientRole::Editor
+    }
+}
+
+/// Sets the ClientRole resource and lets the server know about the change
+pub fn set_client_role(world: &mut World, resources: &mut Resources, client_role: ClientRole) {
+    resources.insert(client_role);
+
+    if let Some(client_id) = resources.get::<ClientID>().map(|client_id| *client_id) {
+        send_client_role(world, client_id, client_role);
+    }
+}
+
+fn send_client_role(world: &mut World, client_id: ClientID, client_role: ClientRole) {
+    world.push(
+        (
+            MessageSender{
+                data_type: DataType::SetClientRole {
+                    client_id: client_id.val(),
+                    client_role
+                },
+                message_type: MessageType::Ordered
+            },
+        )
+    );
+}
+
+/// Colors that get handed out to clients as they connect so that their selection boxes can be told apart
+pub const CLIENT_COLORS: [Color; 8] = [
+    Color { r: 0.2, g: 0.6, b: 1.0, a: 1.0 },
+    Color { r: 1.0, g: 0.4, b: 0.2, a: 1.0 },
+    Color { r: 0.3, g: 0.9, b: 0.3, a: 1.0 },
+    Color { r: 1.0, g: 0.85, b: 0.1, a: 1.0 },
+    Color { r: 0.8, g: 0.3, b: 1.0, a: 1.0 },
+    Color { r: 0.1, g: 0.9, b: 0.9, a: 1.0 },
+    Color { r: 1.0, g: 0.4, b: 0.7, a: 1.0 },
+    Color { r: 0.6, g: 0.6, b: 0.6, a: 1.0 },
+];
+
+/// Resource that keeps track of the color assigned to each connected client
+#[derive(Clone, Default)]
+pub struct ClientColors(HashMap<u32, Color>);
+
+impl ClientColors {
+
+    pub fn get(&self, client_id: u32) -> Option<Color> {
+        self.0.get(&client_id).copied()
+    }
+
+    pub fn insert(&mut self, client_id: u32, color: Color) {
+        self.0.insert(client_id, color);
+    }
+
+    pub fn remove(&mut self, client_id: u32) {
+        self.0.remove(&client_id);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (u32, Color)> + '_ {
+        self.0.iter().map(|(client_id, color)| (*client_id, *color))
+    }
+
+    /// Returns the first color from CLIENT_COLORS that isn't in use, or the least used one if they've all been taken
+    pub fn next_free(&self) -> Color {
+        *CLIENT_COLORS.iter()
+            .min_by_key(|color| self.0.values().filter(|used| used == color).count())
+            .unwrap()
+    }
+}
+
+/// Hands whatever was reserved for a client that lost its connection over to the id it rejoined with. The history carries over so 
+/// that the rejoining client can still undo its earlier changes, while its old selection boxes get freed as it has new ones. Does
+/// nothing if there isn't anything left for the previous id, so it's safe to apply more than once.
+pub fn reassign_client(world: &mut World, resources: &mut Resources, from: u32, to: u32) {
+
+    let mut query = <(Entity, Read<ClientID>)>::query().filter(component::<History>());
+
+    let histories = query.iter(world)
+        .map(|(entity, id)| (*entity, *id))
+        .collect::<Vec<(Entity, ClientID)>>();
+
+    if let Some((previous_history, _)) = histories.iter().find(|(_, id)| id.val() == from) {
+
+        histories.iter()
+            .filter(|(_, id)| id.val() == to)
+            .for_each(|(entity, _)| { world.remove(*entity); });
+
+        if let Some(mut entry) = world.entry(*previous_history) {
+            entry.add_component(ClientID::new(to));
+        }
+    }
+
+    selection_box::free_all_for_client(world, ClientID::new(from));
+
+    if let Some(mut client_colors) = resources.get_mut::<ClientColors>() {
+        client_colors.remove(from);
+    }
+}
+
+/// Clears out the selection boxes, histories, map and actors, which the server sends again to a client when it reconnects
+pub fn free_for_resync(world: &mut World, resources: &mut Resources) {
+
+    selection_box::free_all(world);
+
+    let mut query = <Entity>::query().filter(component::<History>());
+
+    query.iter(world)
+        .copied()
+        .collect::<Vec<Entity>>()
+        .into_iter()
+        .for_each(|entity| { world.remove(entity); });
+
+    level_map::map_reset(world, resources);
+    actor::free_all(world);
+
+    if let Some(mut client_colors) = resources.get_mut::<ClientColors>() {
+        *client_colors = ClientColors::default();
+    }
+}
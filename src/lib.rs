@@ -129,10 +129,13 @@ impl WolfGang {
                         .add_system(systems::networking::create_client_multicast_connection_system())
                         .add_system(systems::networking::create_server_system())
                         .add_system(systems::networking::create_client_system())
+                        .add_system(systems::networking::create_message_metrics_system())
                         .add_thread_local_fn(systems::networking::create_on_client_connection_thread_local_fn())
                         .add_thread_local_fn(systems::networking::create_set_client_id_thread_local_fn())
                         .add_thread_local_fn(systems::networking::create_new_connection_thread_local_fn())
                         .add_thread_local_fn(systems::networking::create_disconnection_thread_local_fn())
+                        .add_thread_local_fn(systems::networking::create_resync_thread_local_fn())
+                        .add_thread_local_fn(systems::networking::create_selection_state_thread_local_fn())
                         .add_thread_local_fn(systems::networking::create_data_handler_threal_local_fn())
                         .build(),
                     world, resources
@@ -147,6 +150,7 @@ impl WolfGang {
 
                     .add_system(systems::input::create_input_system())                             
                     .flush() //flush to avoid accidental double inputs
+                    .add_system(systems::selection_box::create_paused_input_system())
 
                     .add_system(systems::smoothing::create_system())
                     .add_system(systems::camera::create_movement_system())
@@ -155,15 +159,39 @@ impl WolfGang {
                     .add_system(systems::selection_box::create_system())
                     .flush()
                     .add_system(systems::selection_box::create_update_bounds_system())
+                    .add_system(systems::selection_box::create_stale_update_bounds_system())
                     .flush()
+                    .add_system(systems::selection_box::create_validity_feedback_system())
+                    .add_system(systems::selection_box::create_client_color_system())
+                    .add_thread_local(systems::selection_box::create_observer_system())
+                    .add_thread_local(systems::selection_box::create_measurement_system())
+                    .add_thread_local(systems::selection_box::create_measure_stats_system())
                     
                     .add_system(systems::selection_box::create_tile_tool_system())
+                    .add_system(systems::selection_box::create_rotate_paste_system())
+                    .add_system(systems::selection_box::create_reset_box_system())
+                    .add_system(systems::selection_box::create_pick_tile_system())
+                    .add_system(systems::selection_box::create_slot_switch_system())
+                    .add_system(systems::selection_box::create_presence_system())
+                    .add_system(systems::selection_box::create_bounds_throttle_system())
+                    .add_system(systems::selection_box::create_flatten_system())
+                    .add_system(systems::selection_box::create_remove_outside_system())
+                    .add_system(systems::selection_box::create_smooth_system())
+                    .add_system(systems::selection_box::create_checkerboard_fill_system())
+                    .add_system(systems::selection_box::create_sculpt_system())
                     .add_system(systems::selection_box::create_actor_tool_system())
+                    .add_system(systems::selection_box::create_nudge_actor_system())
+                    .add_system(systems::selection_box::create_select_actors_system())
+                    .add_system(systems::selection_box::create_rotate_selected_system())
+                    .add_system(systems::selection_box::create_group_actors_system())
+                    .add_system(systems::selection_box::create_align_actors_system())
 
                     .add_system(systems::actor::create_move_to_coord_system())
 
                     .add_system(systems::selection_box::create_terrain_tool_activate_system())
                     .add_system(systems::selection_box::create_actor_tool_activate_system())
+                    .add_system(systems::selection_box::create_cycle_tool_system())
+                    .add_thread_local_fn(systems::selection_box::create_palette_cycle_system())
                     .add_thread_local_fn(systems::selection_box::create_actor_selection_chooser_system())
 
                     .add_thread_local(systems::custom_mesh::create_tag_system())
@@ -171,17 +199,34 @@ impl WolfGang {
                     .add_system(systems::camera::create_camera_angle_system())
                     .add_system(systems::camera::create_focal_point_system())
                     .add_system(systems::camera::create_follow_selection_box_system())
+                    .add_thread_local(systems::camera::create_keep_in_view_system())
+                    .add_system(systems::camera::create_focus_selection_system())
+                    .add_thread_local(systems::camera::create_bookmark_system())
 
                     .add_system(systems::selection_box::create_orthogonal_dir_system())
                     .add_system(systems::selection_box::create_movement_system()) 
+                    .add_system(systems::selection_box::create_axis_lock_system())
+                    .add_system(systems::selection_box::create_layer_lock_system())
+                    .add_system(systems::selection_box::create_display_origin_system())
+                    .add_system(systems::selection_box::create_box_drag_system())
+                    .add_system(systems::selection_box::create_show_dimensions_toggle_system())
+                    .add_system(systems::selection_box::create_measure_mode_toggle_system())
                     .add_system(systems::selection_box::create_expansion_system())
+                    .add_system(systems::selection_box::create_extrude_system())
                     .add_system(systems::selection_box::create_rotation_system())
+                    .add_system(systems::selection_box::create_face_camera_system())
 
                     .add_system(systems::level_map::mesh::create_add_components_system())
                     .flush()
                     .add_thread_local_fn(systems::level_map::mesh::create_drawing_system())
                     
                     .add_thread_local(systems::custom_mesh::create_draw_system())
+                    .add_thread_local(systems::selection_box::create_actor_bounds_system())
+                    .add_thread_local(systems::selection_box::create_dimension_labels_system())
+                    .add_thread_local(systems::selection_box::create_grid_lines_system())
+                    .add_thread_local(systems::selection_box::create_ghost_system())
+                    .add_thread_local(systems::selection_box::create_pulse_system())
+                    .add_thread_local(systems::selection_box::create_line_thickness_system())
 
                     .add_thread_local(systems::transform::rotation::create_system())
                     .add_thread_local(systems::transform::position::create_system())
@@ -195,6 +240,728 @@ impl WolfGang {
         });
     }
 
+    /// Describes what's within the local client's active box for inspectors and HUDs. The Dictionary holds the box's "min" and 
+    /// "dimensions", the points of every occupied cell under "tiles" and the number of actors under "actor_count". It's empty if 
+    /// there's no active box.
+    #[export]
+    fn inspect_active_box(&mut self, _owner: &Node) -> Dictionary {
+
+        let world = &mut *self.world.write().unwrap();
+        let resources = &*self.resources.borrow();
+
+        let dictionary = Dictionary::new();
+
+        let contents = match (resources.get::<systems::networking::ClientID>(), resources.get::<systems::level_map::Map>()) {
+            (Some(client_id), Some(map)) => systems::selection_box::inspect_active(world, &map, *client_id),
+            _ => None
+        };
+
+        if let Some(contents) = contents {
+            let min = contents.aabb.get_min();
+            let dimensions = contents.aabb.dimensions.abs();
+
+            let tiles = VariantArray::new();
+            for tile_data in &contents.tiles {
+                tiles.push(Vector3::new(tile_data.point.x as f32, tile_data.point.y as f32, tile_data.point.z as f32));
+            }
+
+            dictionary.insert("min", Vector3::new(min.x as f32, min.y as f32, min.z as f32));
+            dictionary.insert("dimensions", Vector3::new(dimensions.x as f32, dimensions.y as f32, dimensions.z as f32));
+            dictionary.insert("tiles", tiles.into_shared());
+            dictionary.insert("actor_count", contents.actors.len() as i64);
+        }
+
+        dictionary.into_shared()
+    }
+
+    /// Claims the region covered by the local client's active box for exclusive editing, so that the server rejects other clients' 
+    /// edits within it. Returns false if there's no active box to claim with.
+    #[export]
+    fn claim_region(&mut self, _owner: &Node) -> bool {
+
+        let world = &mut *self.world.write().unwrap();
+        let resources = &*self.resources.borrow();
+
+        let client_id = match resources.get::<systems::networking::ClientID>().map(|client_id| *client_id) {
+            Some(client_id) => client_id,
+            None => return false
+        };
+
+        let mut query = <(Read<systems::level_map::CoordPos>, Read<systems::selection_box::SelectionBox>, Read<systems::networking::ClientID>)>::query()
+            .filter(component::<systems::selection_box::Active>());
+
+        let aabb = match query.iter(world).find(|(_, _, id)| **id == client_id) {
+            Some((coord_pos, selection_box, _)) => octree::geometry::aabb::AABB::new(coord_pos.value, selection_box.aabb.dimensions),
+            None => return false
+        };
+
+        world.push(
+            (
+                systems::networking::MessageSender{
+                    data_type: systems::networking::DataType::ClaimRegion{ client_id: client_id.val(), aabb },
+                    message_type: systems::networking::MessageType::Reliable
+                },
+            )
+        );
+
+        true
+    }
+
+    /// Gives up the local client's claimed region
+    #[export]
+    fn release_region(&mut self, _owner: &Node) {
+
+        let world = &mut *self.world.write().unwrap();
+        let resources = &*self.resources.borrow();
+
+        if let Some(client_id) = resources.get::<systems::networking::ClientID>().map(|client_id| *client_id) {
+            world.push(
+                (
+                    systems::networking::MessageSender{
+                        data_type: systems::networking::DataType::ReleaseRegion{ client_id: client_id.val() },
+                        message_type: systems::networking::MessageType::Reliable
+                    },
+                )
+            );
+        }
+    }
+
+    /// Lets the UI place the local client's active box at an exact position with exact dimensions, optionally clamped to the map's 
+    /// bounds. Returns false if the bounds were rejected.
+    #[export]
+    fn set_bounds(&mut self, _owner: &Node, x: i64, y: i64, z: i64, width: i64, height: i64, depth: i64, clamp: bool) -> bool {
+
+        let world = &mut *self.world.write().unwrap();
+        let resources = &*self.resources.borrow();
+
+        let client_id = match resources.get::<systems::networking::ClientID>().map(|client_id| *client_id) {
+            Some(client_id) => client_id,
+            None => return false
+        };
+
+        let coord_pos = nalgebra::Vector3::new(x as i32, y as i32, z as i32);
+        let dimensions = nalgebra::Vector3::new(width as i32, height as i32, depth as i32);
+
+        match systems::selection_box::set_bounds(world, resources, client_id, coord_pos, dimensions, clamp) {
+            Ok(_) => true,
+            Err(err) => {
+                godot_print!("Couldn't set selection box bounds: {}", err);
+                false
+            }
+        }
+    }
+
+    /// Lets the UI set exact dimensions for the local client's terrain tool box, returns false if the dimensions were rejected
+    #[export]
+    fn set_selection_box_dimensions(&mut self, _owner: &Node, x: i64, y: i64, z: i64) -> bool {
+
+        let world = &mut *self.world.write().unwrap();
+        let resources = &*self.resources.borrow();
+
+        match resources.get::<systems::networking::ClientID>().map(|client_id| *client_id) {
+            Some(client_id) => {
+                match systems::selection_box::set_selection_box_dimensions(world, resources, client_id, nalgebra::Vector3::new(x as i32, y as i32, z as i32)) {
+                    Ok(_) => true,
+                    Err(err) => {
+                        godot_print!("Couldn't set selection box dimensions: {}", err);
+                        false
+                    }
+                }
+            },
+            None => false
+        }
+    }
+
+    /// Deselects every actor the local client has selected with the select_actors action
+    #[export]
+    fn clear_actor_selection(&mut self, _owner: &Node) {
+
+        let world = &mut *self.world.write().unwrap();
+        let resources = &*self.resources.borrow();
+
+        if let Some(client_id) = resources.get::<systems::networking::ClientID>().map(|client_id| *client_id) {
+            systems::selection_box::clear_actor_selection(world, client_id);
+        }
+    }
+
+    /// Lines up the actors under the local client's active box along axis 0, 1 or 2. An alignment below 0 lines them up on their 
+    /// smallest coordinate, 0 on their center and above 0 on their largest coordinate. Returns how many actors were moved, or -1 if 
+    /// they couldn't be aligned.
+    #[export]
+    fn align_actors(&mut self, _owner: &Node, axis: i64, alignment: i64) -> i64 {
+
+        let world = &mut *self.world.write().unwrap();
+        let resources = &*self.resources.borrow();
+
+        let alignment = match alignment {
+            a if a < 0 => systems::selection_box::Alignment::Min,
+            0 => systems::selection_box::Alignment::Center,
+            _ => systems::selection_box::Alignment::Max,
+        };
+
+        match resources.get::<systems::networking::ClientID>().map(|client_id| *client_id) {
+            Some(client_id) => {
+                match systems::selection_box::align_actors(world, resources, client_id, axis as usize, alignment) {
+                    Ok(moved) => moved as i64,
+                    Err(err) => {
+                        godot_print!("Couldn't align actors: {}", err);
+                        -1
+                    }
+                }
+            },
+            None => -1
+        }
+    }
+
+    /// Turns dropping inserted actors onto the ground beneath the box on or off, for ground up to max_drop cells below it
+    #[export]
+    fn set_snap_to_ground(&mut self, _owner: &Node, enabled: bool, max_drop: i64) {
+
+        let resources = &mut *self.resources.borrow_mut();
+
+        resources.insert(editor::SnapToGround { enabled, max_drop: max_drop.max(0) as i32 });
+    }
+
+    /// Lets the actor tool insert actors where they would overlap existing ones
+    #[export]
+    fn set_allow_overlap(&mut self, _owner: &Node, allow: bool) {
+
+        let resources = &mut *self.resources.borrow_mut();
+
+        resources.insert(editor::AllowOverlap(allow));
+    }
+
+    /// Freezes or unfreezes editing, for cutscenes or menus
+    #[export]
+    fn set_editor_paused(&mut self, _owner: &Node, paused: bool) {
+
+        let resources = &mut *self.resources.borrow_mut();
+
+        editor::set_editor_paused(resources, paused);
+    }
+
+    /// Sets the coordinate that the SelectionBoxObserver shows positions relative to
+    #[export]
+    fn set_display_origin(&mut self, _owner: &Node, x: i64, y: i64, z: i64) {
+
+        let resources = &mut *self.resources.borrow_mut();
+
+        resources.insert(editor::DisplayOrigin(nalgebra::Vector3::new(x as i32, y as i32, z as i32)));
+    }
+
+    /// Locks or unlocks moving and expanding the local client's terrain box along each axis
+    #[export]
+    fn set_axis_lock(&mut self, _owner: &Node, x: bool, y: bool, z: bool) {
+
+        let resources = &mut *self.resources.borrow_mut();
+
+        resources.insert(editor::AxisLock { x, y, z });
+    }
+
+    /// Flips the lock on the axis, where 0 is x, 1 is y and 2 is z. Returns whether the axis is now locked.
+    #[export]
+    fn toggle_axis_lock(&mut self, _owner: &Node, axis: i64) -> bool {
+
+        let resources = &*self.resources.borrow();
+
+        match resources.get_mut::<editor::AxisLock>().and_then(|mut axis_lock| axis_lock.toggle(axis as usize)) {
+            Some(locked) => locked,
+            None => {
+                godot_print!("{} isn't an axis that can be locked", axis);
+                false
+            }
+        }
+    }
+
+    /// Sets how far the corner bevel of the selection boxes reaches in from their edges, and the line thickness given to their material
+    #[export]
+    fn set_box_style(&mut self, _owner: &Node, margin: f32, line_thickness: f32) {
+
+        let resources = &mut *self.resources.borrow_mut();
+
+        resources.insert(editor::BoxStyle { margin, line_thickness });
+    }
+
+    /// Switches the selection boxes between drawing only their edges as lines and drawing their beveled faces
+    #[export]
+    fn set_box_wireframe(&mut self, _owner: &Node, wireframe: bool) {
+
+        let resources = &mut *self.resources.borrow_mut();
+
+        resources.insert(if wireframe { editor::BoxRenderMode::Wireframe } else { editor::BoxRenderMode::Beveled });
+    }
+
+    /// Turns the pulse of the active selection box on or off, with amplitude between 0 and 1 setting how strong it is
+    #[export]
+    fn set_box_pulse(&mut self, _owner: &Node, enabled: bool, amplitude: f32) {
+
+        let resources = &mut *self.resources.borrow_mut();
+
+        resources.insert(editor::BoxPulse { enabled, amplitude });
+    }
+
+    /// Turns live symmetry for the tile tool on or off, mirroring insertions and removals across the plane through the cells at 
+    /// plane along the axis, where 0 is x, 1 is y and 2 is z
+    #[export]
+    fn set_symmetry(&mut self, _owner: &Node, enabled: bool, axis: i64, plane: i64) {
+
+        let resources = &mut *self.resources.borrow_mut();
+
+        resources.insert(editor::SymmetrySettings { enabled, axis: axis as usize, plane: plane as i32 });
+    }
+
+    /// Sets how long insertion has to be held before boxes with more than min_volume cells get filled, where 0 fills them right away
+    #[export]
+    fn set_confirm_hold_time(&mut self, _owner: &Node, seconds: f32, min_volume: i64) {
+
+        let resources = &mut *self.resources.borrow_mut();
+
+        resources.insert(editor::ConfirmHoldTime { seconds: seconds.max(0.), min_volume });
+    }
+
+    /// Sets how many seconds apart this client sends heartbeats, and how long the server waits to hear from a client before letting it 
+    /// go as if it had disconnected
+    #[export]
+    fn set_heartbeat(&mut self, _owner: &Node, interval: f32, timeout: f32) {
+
+        let resources = &mut *self.resources.borrow_mut();
+
+        resources.insert(systems::networking::HeartbeatInterval(interval.max(0.)));
+        resources.insert(systems::networking::HeartbeatTimeout(timeout.max(interval)));
+    }
+
+    /// Turns counting the messages sent by this client, and received and relayed by the server when hosting, on or off, with a 
+    /// summary printed every few seconds while it's on
+    #[export]
+    fn set_metrics_enabled(&mut self, _owner: &Node, enabled: bool) {
+
+        let resources = &mut *self.resources.borrow_mut();
+
+        resources.insert(systems::networking::MetricsEnabled(enabled));
+
+        if let Some(mut message_metrics) = resources.get_mut::<systems::networking::MessageMetrics>() {
+            message_metrics.clear();
+        }
+    }
+
+    /// Starts recording the messages that change the map and actors, from a snapshot of how they are now
+    #[export]
+    fn start_recording(&mut self, _owner: &Node) {
+
+        let world = &mut self.world.write().unwrap();
+        let resources = &*self.resources.borrow();
+
+        match resources.get_mut::<systems::replay::MessageRecording>() {
+            Some(mut recording) => recording.start(world, resources),
+            None => godot_print!("Can't record messages before networking has started")
+        }
+    }
+
+    /// Stops recording and saves the recorded messages to path, along with a digest of the current map and actors to check replays 
+    /// against. Returns whether it was saved.
+    #[export]
+    fn stop_recording(&mut self, _owner: &Node, path: String) -> bool {
+
+        let world = &mut self.world.write().unwrap();
+        let resources = &*self.resources.borrow();
+
+        let messages = match resources.get_mut::<systems::replay::MessageRecording>() {
+            Some(mut recording) if recording.is_recording() => recording.stop(),
+            _ => {
+                godot_print!("There's no recording to stop");
+                return false
+            }
+        };
+
+        let recording = systems::replay::Recording {
+            messages,
+            digest: systems::replay::state_digest(world)
+        };
+
+        match recording.save(&path) {
+            Ok(_) => true,
+            Err(err) => {
+                godot_print!("Couldn't save the recording: {}", err);
+                false
+            }
+        }
+    }
+
+    /// Replays the recording saved at path into a separate world and returns whether it ends up with the same map and actors as when 
+    /// it was recorded. The current session is left untouched.
+    #[export]
+    fn replay_recording(&mut self, _owner: &Node, path: String) -> bool {
+
+        let map = match self.resources.borrow().get::<systems::level_map::Map>().map(|map| *map) {
+            Some(map) => map,
+            None => return false
+        };
+
+        let recording = match systems::replay::Recording::from_file(&path) {
+            Ok(recording) => recording,
+            Err(err) => {
+                godot_print!("Couldn't load the recording: {}", err);
+                return false
+            }
+        };
+
+        let digest = systems::replay::replay_messages(map, recording.messages);
+
+        if digest != recording.digest {
+            godot_print!("Replaying {} ended with digest {:x} instead of the recorded {:x}", path, digest, recording.digest);
+        }
+
+        digest == recording.digest
+    }
+
+    /// Should be called by any UI that takes keyboard focus, so that the editor's actions don't fire while the user is typing
+    #[export]
+    fn set_input_captured(&mut self, _owner: &Node, captured: bool) {
+
+        let resources = &mut *self.resources.borrow_mut();
+
+        resources.insert(systems::input::InputCaptured(captured));
+    }
+
+    /// Keeps everyone's selection boxes and map edits within the cells from min to max, with boxes following the EdgeBehavior when 
+    /// they're moved past an edge
+    #[export]
+    fn set_map_bounds(&mut self, _owner: &Node, min_x: i64, min_y: i64, min_z: i64, max_x: i64, max_y: i64, max_z: i64) {
+
+        let world = &mut *self.world.write().unwrap();
+
+        let min = nalgebra::Vector3::new(min_x.min(max_x) as i32, min_y.min(max_y) as i32, min_z.min(max_z) as i32);
+        let max = nalgebra::Vector3::new(min_x.max(max_x) as i32, min_y.max(max_y) as i32, min_z.max(max_z) as i32);
+
+        world.push(
+            (
+                systems::networking::MessageSender{
+                    data_type: systems::networking::DataType::SetMapBounds{ 
+                        bounds: Some(octree::geometry::aabb::AABB::from_extents(min, max)) 
+                    },
+                    message_type: systems::networking::MessageType::Ordered
+                },
+            )
+        );
+    }
+
+    /// Lets everyone's selection boxes and map edits go anywhere again
+    #[export]
+    fn clear_map_bounds(&mut self, _owner: &Node) {
+
+        let world = &mut *self.world.write().unwrap();
+
+        world.push(
+            (
+                systems::networking::MessageSender{
+                    data_type: systems::networking::DataType::SetMapBounds{ bounds: None },
+                    message_type: systems::networking::MessageType::Ordered
+                },
+            )
+        );
+    }
+
+    /// Sets what happens when a selection box is moved past the edge of the map's bounds, 0 clamps, 1 stops and 2 wraps around
+    #[export]
+    fn set_edge_behavior(&mut self, _owner: &Node, edge_behavior: i64) {
+
+        let resources = &mut *self.resources.borrow_mut();
+
+        resources.insert(match edge_behavior {
+            1 => editor::EdgeBehavior::Stop,
+            2 => editor::EdgeBehavior::Wrap,
+            _ => editor::EdgeBehavior::Clamp,
+        });
+    }
+
+    /// Moves a client's active selection box to the exact coordinate for everyone in the session
+    #[export]
+    fn set_selection_coord(&mut self, _owner: &Node, client_id: i64, x: i64, y: i64, z: i64) {
+
+        let world = &mut *self.world.write().unwrap();
+
+        world.push((systems::networking::MessageSender{
+            data_type: systems::networking::DataType::SetSelectionCoord{
+                client_id: client_id as u32,
+                coord_pos: nalgebra::Vector3::new(x as i32, y as i32, z as i32)
+            },
+            message_type: systems::networking::MessageType::Ordered
+        },));
+    }
+
+    /// Restricts actor removal to the actors with the given names, or to every actor but them when exclude is true. An empty list 
+    /// removes the restriction.
+    #[export]
+    fn set_actor_filter(&mut self, _owner: &Node, names: StringArray, exclude: bool) {
+
+        let resources = &mut *self.resources.borrow_mut();
+
+        let names = names.read().iter().map(|name| name.to_string()).collect::<Vec<String>>();
+
+        resources.insert(if names.is_empty() {
+            editor::ActorFilter::All
+        } else if exclude {
+            editor::ActorFilter::Except(names)
+        } else {
+            editor::ActorFilter::Only(names)
+        });
+    }
+
+    /// Writes the map and all of its actors to a standalone file at path, returns false if the file couldn't be opened
+    #[export]
+    fn export_map(&mut self, _owner: &Node, path: GodotString) -> bool {
+
+        let world = &mut *self.world.write().unwrap();
+
+        let file = gdnative::api::File::new();
+
+        if file.open(path, gdnative::api::File::WRITE).is_err() {
+            return false
+        }
+
+        let exported = systems::level_map::export_map(world);
+
+        file.store_buffer(systems::level_map::document::vec_to_byte_array(exported));
+        file.close();
+
+        true
+    }
+
+    /// Replaces the map and its actors with the contents of a file written by export_map, returns false if it was rejected
+    #[export]
+    fn import_map(&mut self, _owner: &Node, path: GodotString) -> bool {
+
+        let world = &mut *self.world.write().unwrap();
+        let resources = &mut *self.resources.borrow_mut();
+
+        let file = gdnative::api::File::new();
+
+        if file.open(path, gdnative::api::File::READ).is_err() {
+            return false
+        }
+
+        let bytes = file.get_buffer(file.get_len()).read().to_vec();
+        file.close();
+
+        match systems::level_map::import_map(world, resources, &bytes) {
+            Ok(_) => true,
+            Err(err) => {
+                godot_print!("Couldn't import map: {}", err);
+                false
+            }
+        }
+    }
+
+    /// Raises terrain out of the grayscale PNG at path within the footprint of the local client's active terrain box, using the 
+    /// selected tile. Brightness times scale gives the height of each column in tiles. Returns false if it couldn't be imported.
+    #[export]
+    fn import_heightmap(&mut self, _owner: &Node, path: GodotString, scale: f64) -> bool {
+
+        let world = &mut *self.world.write().unwrap();
+        let resources = &*self.resources.borrow();
+
+        let (client_id, tile_selection) = match (resources.get::<systems::networking::ClientID>(), resources.get::<editor::PaletteSelection>()) {
+            (Some(client_id), Some(tile_selection)) => (*client_id, *tile_selection),
+            _ => return false
+        };
+
+        let mut query = <(Read<systems::level_map::CoordPos>, Read<systems::selection_box::SelectionBox>, Read<systems::networking::ClientID>)>::query()
+            .filter(component::<systems::selection_box::TerrainToolBox>() & component::<systems::selection_box::Active>());
+
+        let aabb = match query.iter(world).find(|(_, _, id)| **id == client_id) {
+            Some((coord_pos, selection_box, _)) => octree::geometry::aabb::AABB::new(coord_pos.value, selection_box.aabb.dimensions),
+            None => return false
+        };
+
+        let file = gdnative::api::File::new();
+
+        if file.open(path, gdnative::api::File::READ).is_err() {
+            return false
+        }
+
+        let bytes = file.get_buffer(file.get_len()).read().to_vec();
+        file.close();
+
+        let tile_data = systems::level_map::TileData::new(tile_selection.val(), nalgebra::Vector3::zeros());
+
+        match systems::level_map::import_heightmap(&bytes, scale as f32, tile_data, aabb) {
+            Ok(changes) => {
+                if let Some(message_sender) = systems::level_map::batch_changes(changes, Some(client_id.val())) {
+                    world.push((message_sender,));
+                }
+                true
+            },
+            Err(err) => {
+                godot_print!("Couldn't import heightmap: {}", err);
+                false
+            }
+        }
+    }
+
+    /// Writes the tiles within the local client's active selection box to an OBJ file at path, returns false if it couldn't be written
+    #[export]
+    fn export_selection_obj(&mut self, _owner: &Node, path: GodotString) -> bool {
+
+        let world = &mut *self.world.write().unwrap();
+        let resources = &*self.resources.borrow();
+
+        let (client_id, map) = match (resources.get::<systems::networking::ClientID>(), resources.get::<systems::level_map::Map>()) {
+            (Some(client_id), Some(map)) => (*client_id, *map),
+            _ => return false
+        };
+
+        let mut query = <(Read<systems::level_map::CoordPos>, Read<systems::selection_box::SelectionBox>, Read<systems::networking::ClientID>)>::query()
+            .filter(component::<systems::selection_box::Active>());
+
+        let aabb = match query.iter(world).find(|(_, _, id)| **id == client_id) {
+            Some((coord_pos, selection_box, _)) => octree::geometry::aabb::AABB::new(coord_pos.value, selection_box.aabb.dimensions),
+            None => return false
+        };
+
+        let file = gdnative::api::File::new();
+
+        if file.open(path, gdnative::api::File::WRITE).is_err() {
+            return false
+        }
+
+        file.store_string(systems::custom_mesh::export_region_obj(world, &map, aabb));
+        file.close();
+
+        true
+    }
+
+    /// Measures the local client's active box against its box in another slot, a negative slot stops measuring
+    #[export]
+    fn measure_against_slot(&mut self, _owner: &Node, slot: i64) {
+
+        let resources = &mut *self.resources.borrow_mut();
+
+        resources.insert(if slot < 0 {
+            editor::MeasureAgainst::Nothing
+        } else {
+            editor::MeasureAgainst::Slot(slot as u8)
+        });
+    }
+
+    /// Measures the local client's active box against another client's active box, a negative client_id stops measuring
+    #[export]
+    fn measure_against_client(&mut self, _owner: &Node, client_id: i64) {
+
+        let resources = &mut *self.resources.borrow_mut();
+
+        resources.insert(if client_id < 0 {
+            editor::MeasureAgainst::Nothing
+        } else {
+            editor::MeasureAgainst::Client(client_id as u32)
+        });
+    }
+
+    /// Sets the second tile used by fills that alternate between two tiles
+    #[export]
+    fn set_secondary_palette_selection(&mut self, _owner: &Node, index: i64) {
+
+        let resources = &mut *self.resources.borrow_mut();
+
+        resources.insert(editor::SecondaryPaletteSelection::new(index as u32));
+    }
+
+    /// Makes rotated boxes move relative to the way they're facing instead of along the world's axes
+    #[export]
+    fn set_relative_movement(&mut self, _owner: &Node, relative: bool) {
+
+        let resources = &mut *self.resources.borrow_mut();
+
+        resources.insert(editor::RelativeMovement(relative));
+    }
+
+    /// Makes expanding the terrain box always grow it away from the camera
+    #[export]
+    fn set_expand_from_far_side(&mut self, _owner: &Node, far_side: bool) {
+
+        let resources = &mut *self.resources.borrow_mut();
+
+        resources.insert(editor::ExpandFromFarSide(far_side));
+    }
+
+    /// Saves the tiles within the local client's active terrain box as a prefab, only replacing an existing one with the same name 
+    /// when force is true. Returns false if it couldn't be saved.
+    #[export]
+    fn save_prefab(&mut self, _owner: &Node, name: GodotString, force: bool) -> bool {
+
+        let world = &mut *self.world.write().unwrap();
+        let resources = &*self.resources.borrow();
+
+        let (client_id, map) = match (resources.get::<systems::networking::ClientID>(), resources.get::<systems::level_map::Map>()) {
+            (Some(client_id), Some(map)) => (*client_id, *map),
+            _ => return false
+        };
+
+        match systems::prefab::save_stamp(world, map, client_id, &name.to_string(), force) {
+            Ok(_) => true,
+            Err(err) => {
+                godot_print!("Couldn't save prefab: {}", err);
+                false
+            }
+        }
+    }
+
+    #[export]
+    fn list_prefabs(&mut self, _owner: &Node) -> StringArray {
+        let mut names = StringArray::new();
+
+        for name in systems::prefab::list_prefabs() {
+            names.push(GodotString::from(name));
+        }
+
+        names
+    }
+
+    /// Makes the tile tool stamp the named prefab on insertion, resizing the local client's box to preview it. An empty name goes 
+    /// back to filling the box.
+    #[export]
+    fn select_prefab(&mut self, _owner: &Node, name: GodotString) -> bool {
+
+        let world = &mut *self.world.write().unwrap();
+        let resources = &mut *self.resources.borrow_mut();
+
+        if name.is_empty() {
+            resources.insert(systems::prefab::SelectedPrefab(None));
+            return true
+        }
+
+        if let Err(err) = systems::prefab::load_stamp(resources, &name.to_string()) {
+            godot_print!("Couldn't load prefab: {}", err);
+            return false
+        }
+
+        //a newly selected prefab starts out unturned
+        resources.insert(editor::PasteRotation::default());
+
+        let dimensions = resources.get::<systems::prefab::SelectedPrefab>()
+            .and_then(|selected| selected.0.as_ref().map(|prefab| prefab.dimensions));
+
+        if let (Some(client_id), Some(dimensions)) = (resources.get::<systems::networking::ClientID>().map(|client_id| *client_id), dimensions) {
+            if let Err(err) = systems::selection_box::set_selection_box_dimensions(world, resources, client_id, dimensions) {
+                godot_print!("Couldn't resize the selection box for prefab {}: {}", name, err);
+            }
+        }
+
+        true
+    }
+
+    #[export]
+    fn set_spectator(&mut self, _owner: &Node, spectator: bool) {
+
+        let world = &mut *self.world.write().unwrap();
+        let resources = &mut *self.resources.borrow_mut();
+
+        editor::set_client_role(world, resources, if spectator {
+            editor::ClientRole::Spectator
+        } else {
+            editor::ClientRole::Editor
+        });
+    }
+
     #[export]
     fn _process(&mut self, _owner: &Node, delta: f64) {
 
@@ -234,6 +1001,7 @@ fn init(handle: InitHandle) {
     handle.add_class::<nodes::tool_list::ToolList>();
     handle.add_class::<nodes::palette::Palette>();
     handle.add_class::<nodes::actor_palette::ActorPalette>();
+    handle.add_class::<nodes::selection_box_observer::SelectionBoxObserver>();
 }
 
 godot_init!(init);
\ No newline at end of file
@@ -4,7 +4,11 @@ use crate::{
         networking::{
             ClientID,
             Disconnection,
+            HeartbeatInterval,
+            HeartbeatTimeout,
+            MessageMetrics,
             MessageSender,
+            MetricsEnabled,
             ServerMessageSender,
         }
     }
@@ -217,6 +221,11 @@ impl GameStateTraits for Networking {
         }
 
         resources.insert(ClientID::default());
+        resources.insert(MessageMetrics::default());
+        resources.get_or_default::<MetricsEnabled>();
+        resources.get_or_default::<HeartbeatInterval>();
+        resources.get_or_default::<HeartbeatTimeout>();
+        resources.get_or_default::<crate::systems::replay::MessageRecording>();
 
         if let ConnectionType::Host = connection.conn_type {
             let entity = world.push(
@@ -99,6 +99,20 @@ impl ActorPalette{
         if !item_list.is_anything_selected() && item_list.get_item_count() > 0 {
             item_list.select(0, true);
         }
+
+        //keep the highlighted item in line with selections made outside of the palette, like the prev_tile and next_tile actions
+        let selection = crate::WolfGang::get_resources()
+            .and_then(|resources| {
+                let resources = resources.borrow();
+                let selection = resources.get::<ActorPaletteSelection>().map(|selection| selection.val());
+                selection
+            });
+
+        if let Some(selection) = selection {
+            if selection < item_list.get_item_count() && !item_list.is_selected(selection) {
+                item_list.select(selection, true);
+            }
+        }
     }
 
     fn deserialize_entities(&self) {
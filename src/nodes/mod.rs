@@ -6,6 +6,7 @@ pub mod file_confirmation;
 pub mod connect_menu;
 pub mod connet_dialog;
 pub mod palette;
+pub mod selection_box_observer;
 pub mod tool_list;
 
 pub mod utils;
\ No newline at end of file
@@ -40,5 +40,19 @@ impl Palette{
         if !item_list.is_anything_selected() {
             item_list.select(0, true);
         }
+
+        //keep the highlighted item in line with selections made outside of the palette, like the eyedropper
+        let selection = crate::WolfGang::get_resources()
+            .and_then(|resources| {
+                let resources = resources.borrow();
+                let selection = resources.get::<PaletteSelection>().map(|selection| selection.val() as i64);
+                selection
+            });
+
+        if let Some(selection) = selection {
+            if selection < item_list.get_item_count() && !item_list.is_selected(selection) {
+                item_list.select(selection, true);
+            }
+        }
     }
 }
\ No newline at end of file
@@ -0,0 +1,318 @@
+use gdnative::prelude::*;
+
+use crate::systems::selection_box::ToolBoxType;
+
+use std::cell::RefCell;
+
+type Point = nalgebra::Vector3<i32>;
+
+thread_local! {
+    /// Written every frame by selection_box::create_observer_system with the state of the local client's active selection box
+    pub static OBSERVED_SELECTION_BOX: RefCell<Option<ObservedSelectionBox>> = RefCell::new(None);
+
+    /// Cells the eyedropper was used on that turned out to be empty since the last time the observer emitted
+    pub static TILE_PICK_REJECTIONS: RefCell<Vec<Point>> = RefCell::new(Vec::new());
+
+    /// Actor counts of clear region actions that are waiting on a confirming second press
+    pub static CLEAR_REGION_REQUESTS: RefCell<Vec<usize>> = RefCell::new(Vec::new());
+
+    /// Palette ids of actors that couldn't be chosen or inserted because they're missing from the actor palette
+    pub static ACTOR_REJECTIONS: RefCell<Vec<i64>> = RefCell::new(Vec::new());
+
+    /// Where actor insertions were turned down for overlapping existing actors while AllowOverlap is off
+    pub static ACTOR_OVERLAP_REJECTIONS: RefCell<Vec<Point>> = RefCell::new(Vec::new());
+
+    /// Min and dimensions of the local client's edits that the server dropped for conflicting with another client's edit or claim, 
+    /// or for reaching outside of the map bounds
+    pub static EDIT_REJECTIONS: RefCell<Vec<(Point, Point)>> = RefCell::new(Vec::new());
+
+    /// Written every frame by selection_box::create_measurement_system while the active box is being measured against another one
+    pub static OBSERVED_MEASUREMENT: RefCell<Option<Measurement>> = RefCell::new(None);
+
+    /// Written by selection_box::create_measure_stats_system whenever the active box changes while MeasureMode is on
+    pub static OBSERVED_MEASURE_STATS: RefCell<Option<MeasureStats>> = RefCell::new(None);
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ObservedSelectionBox {
+    pub coord_pos: Point,
+    pub dimensions: Point,
+    pub tool_type: ToolBoxType,
+}
+
+/// Distances between the centers of two selection boxes in cells, and the dimensions of the region they overlap, which is all 
+/// zeroes when they don't
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Measurement {
+    pub manhattan: f32,
+    pub euclidean: f32,
+    pub overlap: Point,
+}
+
+/// The number of cells in the active box, how many of them have tiles, and the length of its diagonal in world units
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MeasureStats {
+    pub volume: i64,
+    pub occupied: usize,
+    pub diagonal: f32,
+}
+
+/// Node that lets GDScript UI observe the local client's active selection box, emitting selection_box_changed whenever its
+/// position, dimensions or tool type change, tile_pick_rejected when the eyedropper finds an empty cell, clear_region_requested
+/// when the clear region action needs a second press to remove its actors, actor_rejected when the chosen actor couldn't be found 
+/// in the palette, actor_overlap_rejected when an actor insertion would overlap existing actors, edit_rejected when the server 
+/// drops an edit for conflicting with another client's edit or claim or for reaching outside of the map bounds, 
+/// measurement_changed when the distance to the box it's being measured against changes, and measure_stats_changed when the 
+/// active box changes in measure mode
+#[derive(NativeClass)]
+#[inherit(Node)]
+#[register_with(Self::register_signals)]
+#[user_data(user_data::LocalCellData<SelectionBoxObserver>)]
+pub struct SelectionBoxObserver {
+    last_emitted: Option<ObservedSelectionBox>,
+    last_measurement: Option<Measurement>,
+    last_stats: Option<MeasureStats>,
+}
+
+#[methods]
+impl SelectionBoxObserver {
+    fn new(_: &Node) -> Self {
+        SelectionBoxObserver {
+            last_emitted: None,
+            last_measurement: None,
+            last_stats: None,
+        }
+    }
+
+    fn register_signals(builder: &ClassBuilder<Self>) {
+        builder.add_signal(Signal {
+            name: "selection_box_changed",
+            args: &[
+                SignalArgument {
+                    name: "coord_pos",
+                    default: Variant::from_vector3(&Vector3::zero()),
+                    export_info: ExportInfo::new(VariantType::Vector3),
+                    usage: PropertyUsage::DEFAULT
+                },
+                SignalArgument {
+                    name: "dimensions",
+                    default: Variant::from_vector3(&Vector3::zero()),
+                    export_info: ExportInfo::new(VariantType::Vector3),
+                    usage: PropertyUsage::DEFAULT
+                },
+                //0 for the terrain tool, 1 for the actor tool, matching the indices in the ToolList
+                SignalArgument {
+                    name: "tool_type",
+                    default: Variant::from_i64(0),
+                    export_info: ExportInfo::new(VariantType::I64),
+                    usage: PropertyUsage::DEFAULT
+                },
+                //the actor palette selection when tool_type is the actor tool, -1 otherwise
+                SignalArgument {
+                    name: "actor_selection",
+                    default: Variant::from_i64(-1),
+                    export_info: ExportInfo::new(VariantType::I64),
+                    usage: PropertyUsage::DEFAULT
+                },
+            ]
+        });
+
+        builder.add_signal(Signal {
+            name: "tile_pick_rejected",
+            args: &[SignalArgument {
+                name: "coord_pos",
+                default: Variant::from_vector3(&Vector3::zero()),
+                export_info: ExportInfo::new(VariantType::Vector3),
+                usage: PropertyUsage::DEFAULT
+            }]
+        });
+
+        builder.add_signal(Signal {
+            name: "clear_region_requested",
+            args: &[SignalArgument {
+                name: "count",
+                default: Variant::from_i64(0),
+                export_info: ExportInfo::new(VariantType::I64),
+                usage: PropertyUsage::DEFAULT
+            }]
+        });
+
+        builder.add_signal(Signal {
+            name: "actor_rejected",
+            args: &[SignalArgument {
+                name: "actor_id",
+                default: Variant::from_i64(-1),
+                export_info: ExportInfo::new(VariantType::I64),
+                usage: PropertyUsage::DEFAULT
+            }]
+        });
+
+        builder.add_signal(Signal {
+            name: "actor_overlap_rejected",
+            args: &[SignalArgument {
+                name: "coord_pos",
+                default: Variant::from_vector3(&Vector3::zero()),
+                export_info: ExportInfo::new(VariantType::Vector3),
+                usage: PropertyUsage::DEFAULT
+            }]
+        });
+
+        builder.add_signal(Signal {
+            name: "edit_rejected",
+            args: &[
+                SignalArgument {
+                    name: "min",
+                    default: Variant::from_vector3(&Vector3::zero()),
+                    export_info: ExportInfo::new(VariantType::Vector3),
+                    usage: PropertyUsage::DEFAULT
+                },
+                SignalArgument {
+                    name: "dimensions",
+                    default: Variant::from_vector3(&Vector3::zero()),
+                    export_info: ExportInfo::new(VariantType::Vector3),
+                    usage: PropertyUsage::DEFAULT
+                },
+            ]
+        });
+
+        builder.add_signal(Signal {
+            name: "measurement_changed",
+            args: &[
+                SignalArgument {
+                    name: "manhattan",
+                    default: Variant::from_f64(0.),
+                    export_info: ExportInfo::new(VariantType::F64),
+                    usage: PropertyUsage::DEFAULT
+                },
+                SignalArgument {
+                    name: "euclidean",
+                    default: Variant::from_f64(0.),
+                    export_info: ExportInfo::new(VariantType::F64),
+                    usage: PropertyUsage::DEFAULT
+                },
+                SignalArgument {
+                    name: "overlap",
+                    default: Variant::from_vector3(&Vector3::zero()),
+                    export_info: ExportInfo::new(VariantType::Vector3),
+                    usage: PropertyUsage::DEFAULT
+                },
+            ]
+        });
+
+        builder.add_signal(Signal {
+            name: "measure_stats_changed",
+            args: &[
+                SignalArgument {
+                    name: "volume",
+                    default: Variant::from_i64(0),
+                    export_info: ExportInfo::new(VariantType::I64),
+                    usage: PropertyUsage::DEFAULT
+                },
+                SignalArgument {
+                    name: "occupied",
+                    default: Variant::from_i64(0),
+                    export_info: ExportInfo::new(VariantType::I64),
+                    usage: PropertyUsage::DEFAULT
+                },
+                SignalArgument {
+                    name: "diagonal",
+                    default: Variant::from_f64(0.),
+                    export_info: ExportInfo::new(VariantType::F64),
+                    usage: PropertyUsage::DEFAULT
+                },
+            ]
+        });
+    }
+
+    #[export]
+    fn _process(&mut self, owner: &Node, _: f64) {
+
+        let rejections = TILE_PICK_REJECTIONS.with(|r| r.replace(Vec::new()));
+
+        for coord_pos in rejections {
+            owner.emit_signal("tile_pick_rejected", &[Variant::from_vector3(&to_vector3(coord_pos))]);
+        }
+
+        let requests = CLEAR_REGION_REQUESTS.with(|r| r.replace(Vec::new()));
+
+        for count in requests {
+            owner.emit_signal("clear_region_requested", &[Variant::from_i64(count as i64)]);
+        }
+
+        let actor_rejections = ACTOR_REJECTIONS.with(|r| r.replace(Vec::new()));
+
+        for actor_id in actor_rejections {
+            owner.emit_signal("actor_rejected", &[Variant::from_i64(actor_id)]);
+        }
+
+        let overlap_rejections = ACTOR_OVERLAP_REJECTIONS.with(|r| r.replace(Vec::new()));
+
+        for coord_pos in overlap_rejections {
+            owner.emit_signal("actor_overlap_rejected", &[Variant::from_vector3(&to_vector3(coord_pos))]);
+        }
+
+        let edit_rejections = EDIT_REJECTIONS.with(|r| r.replace(Vec::new()));
+
+        for (min, dimensions) in edit_rejections {
+            owner.emit_signal("edit_rejected", &[
+                Variant::from_vector3(&to_vector3(min)),
+                Variant::from_vector3(&to_vector3(dimensions)),
+            ]);
+        }
+
+        let measurement = OBSERVED_MEASUREMENT.with(|m| *m.borrow());
+
+        if measurement != self.last_measurement {
+            self.last_measurement = measurement;
+
+            if let Some(measurement) = measurement {
+                owner.emit_signal("measurement_changed", &[
+                    Variant::from_f64(measurement.manhattan as f64),
+                    Variant::from_f64(measurement.euclidean as f64),
+                    Variant::from_vector3(&to_vector3(measurement.overlap)),
+                ]);
+            }
+        }
+
+        let stats = OBSERVED_MEASURE_STATS.with(|m| *m.borrow());
+
+        if stats != self.last_stats {
+            self.last_stats = stats;
+
+            if let Some(stats) = stats {
+                owner.emit_signal("measure_stats_changed", &[
+                    Variant::from_i64(stats.volume),
+                    Variant::from_i64(stats.occupied as i64),
+                    Variant::from_f64(stats.diagonal as f64),
+                ]);
+            }
+        }
+
+        let observed = OBSERVED_SELECTION_BOX.with(|o| *o.borrow());
+
+        if observed == self.last_emitted {
+            return
+        }
+
+        self.last_emitted = observed;
+
+        if let Some(observed) = observed {
+
+            let (tool_type, actor_selection) = match observed.tool_type {
+                ToolBoxType::TerrainToolBox => (0, -1),
+                ToolBoxType::ActorToolBox(selection) => (1, selection)
+            };
+
+            owner.emit_signal("selection_box_changed", &[
+                Variant::from_vector3(&to_vector3(observed.coord_pos)),
+                Variant::from_vector3(&to_vector3(observed.dimensions)),
+                Variant::from_i64(tool_type),
+                Variant::from_i64(actor_selection),
+            ]);
+        }
+    }
+}
+
+fn to_vector3(point: Point) -> Vector3 {
+    Vector3::new(point.x as f32, point.y as f32, point.z as f32)
+}
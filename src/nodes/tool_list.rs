@@ -18,6 +18,8 @@ use crate::{
 #[inherit(ItemList)]
 #[user_data(user_data::LocalCellData<ToolList>)]
 pub struct ToolList {
+    /// The SelectedTool as of the last frame, so that the list only follows it when it changes
+    last_tool: Option<selection_box::ToolBoxType>,
 }
 
 #[methods]
@@ -26,7 +28,9 @@ impl ToolList {
 
         unsafe { item_list.connect("item_selected", item_list.assume_shared(), "item_selected", VariantArray::default(), 0).ok(); }
 
-        ToolList {}
+        ToolList {
+            last_tool: None
+        }
     }
 
     #[export]
@@ -87,10 +91,39 @@ impl ToolList {
     }
 
     #[export]
-    fn _process(&self, item_list: &ItemList, _: f64) {
+    fn _process(&mut self, item_list: &ItemList, _: f64) {
         if !item_list.is_anything_selected() {
             item_list.select(0, true);
         }
+
+        //follow tool changes that didn't come from the list, such as the cycle_tool action
+        let selected_tool = crate::WolfGang::get_resources()
+            .and_then(|resources| {
+                let resources = resources.borrow();
+                let selected_tool = resources.get::<editor::SelectedTool>().map(|selected_tool| selected_tool.0);
+                selected_tool
+            });
+
+        if selected_tool == self.last_tool {
+            return
+        }
+
+        self.last_tool = selected_tool;
+
+        let index = match selected_tool {
+            Some(selection_box::ToolBoxType::TerrainToolBox) => 0,
+            Some(selection_box::ToolBoxType::ActorToolBox(_)) => 1,
+            None => return
+        };
+
+        if !item_list.is_selected(index) {
+            item_list.select(index, true);
+
+            unsafe {
+                get_palette(item_list).get_parent().unwrap().assume_safe().cast::<ScrollContainer>().unwrap().set_visible(index == 0);
+                get_actor_palette(item_list).get_parent().unwrap().assume_safe().cast::<ScrollContainer>().unwrap().set_visible(index == 1);
+            }
+        }
     }
 }
 
@@ -115,7 +148,7 @@ unsafe fn populate_palette(palette: &ItemList) {
     let texture_resource = ResourceLoader::godot_singleton().load("res://images/ground.png", "StreamTexture", false).unwrap();
     let texture = texture_resource.cast::<StreamTexture>().unwrap();
 
-    for i in 0..64 {
+    for i in 0..editor::TILE_COUNT {
 
         let x = i % 16;
         let y = i / 16;
@@ -22,20 +22,23 @@ use crate::{
     },
     systems::{
         history::{History, StepType},
-        level_map::{CoordPos, TILE_DIMENSIONS, map_coords_to_world},
+        level_map::{CoordPos, TILE_DIMENSIONS, map_coords_to_world, enclosing_rotated},
         transform::{
             position::Position,
             rotation::Rotation,
         },
         networking::ClientID,
+        shared_rng::SharedRng,
     },
 };
 
 type AABB = octree::geometry::aabb::AABB<i32>;
 
 use std::{
+    borrow::Cow,
     collections::HashMap,
     cell::RefCell,
+    sync::atomic::{AtomicU64, Ordering as AtomicOrdering},
 };
 
 thread_local! {
@@ -51,6 +54,7 @@ thread_local! {
             registry.register::<Health>("health".to_string());
             registry.register::<CoordPos>("coord_pos".to_string());
             registry.register::<Rotation>("rotation".to_string());
+            registry.register::<ActorGroup>("actor_group".to_string());
             
             registry
         }
@@ -68,6 +72,7 @@ thread_local! {
             merger.register_copy::<Health>();
             merger.register_copy::<CoordPos>();
             merger.register_copy::<Rotation>();
+            merger.register_copy::<ActorGroup>();
 
             merger
         }
@@ -103,7 +108,15 @@ impl Bounds {
 
         let aabb = AABB::new(Point::zeros(), scaled);
 
-        aabb.rotate(rotation)
+        enclosing_rotated(aabb, rotation)
+    }
+
+    /// The cells covered by an actor with these bounds placed at coord_pos and turned by rotation
+    pub fn region_at(&self, coord_pos: Point, rotation: nalgebra::Rotation3<f32>) -> AABB {
+        let mut aabb = self.get_scaled_and_rotated_aabb(rotation);
+        aabb.center = coord_pos;
+
+        aabb
     }
 }
 
@@ -166,26 +179,170 @@ impl Health {
     }
 }
 
+/// Serialized actor data larger than this many bytes gets compressed before being sent in an ActorInsertion
+pub const COMPRESSION_THRESHOLD: usize = 1024;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ActorChange {
-    ActorInsertion {
+    /// The insertion from before compression was added, which is never compressed. bincode tells variants apart by their position, 
+    /// so it stays first for older messages and recordings to keep being read, and new insertions are made with ActorInsertion.
+    LegacyActorInsertion {
         serialized: Vec<u8>
     },
-    ActorRemoval(u128)
+    ActorRemoval(u128),
+    /// Moves an existing actor to coord_pos and turns it to rotation, keeping it as the same actor rather than removing and 
+    /// reinserting it
+    ActorMove {
+        actor_id: u128,
+        coord_pos: Point,
+        rotation: nalgebra::Rotation3<f32>,
+    },
+    /// Several changes that are applied in order and take a single step in history
+    ActorBatch(Vec<ActorChange>),
+    /// Puts every actor in actor_ids into group, or takes them out of whatever group they're in if group is None
+    GroupAssign {
+        actor_ids: Vec<u128>,
+        group: Option<u64>,
+    },
+    ActorInsertion {
+        serialized: Vec<u8>,
+        /// Whether or not serialized has been compressed with snap, and needs to be decompressed before deserializing
+        compressed: bool,
+    },
+}
+
+impl ActorChange {
+
+    /// The regions of the map that this change touches, for checking it against claimed regions. actors has the bounds and current 
+    /// region of every actor in the world by its ActorID. Moved actors touch both where they were and where they're going, and 
+    /// inserted actors the regions they're serialized with.
+    pub fn regions(&self, actors: &HashMap<u128, (Bounds, AABB)>) -> Vec<AABB> {
+        match self {
+            ActorChange::LegacyActorInsertion{ serialized } => {
+                ActorChange::ActorInsertion{ serialized: serialized.clone(), compressed: false }.regions(actors)
+            },
+            ActorChange::ActorInsertion{ serialized, compressed } => {
+                deserialize_actor_world(serialized, *compressed)
+                    .map(|actor_world| <(Read<CoordPos>, Read<Bounds>, Read<Rotation>)>::query().iter(&actor_world)
+                        .map(|(coord_pos, bounds, rotation)| bounds.region_at(coord_pos.value, rotation.value))
+                        .collect()
+                    )
+                    .unwrap_or_default()
+            },
+            ActorChange::ActorRemoval(actor_id) => {
+                actors.get(actor_id).map(|(_, region)| vec![*region]).unwrap_or_default()
+            },
+            ActorChange::ActorMove{ actor_id, coord_pos, rotation } => {
+                actors.get(actor_id)
+                    .map(|(bounds, region)| vec![*region, bounds.region_at(*coord_pos, *rotation)])
+                    .unwrap_or_default()
+            },
+            ActorChange::ActorBatch(changes) => {
+                changes.iter().flat_map(|change| change.regions(actors)).collect()
+            },
+            ActorChange::GroupAssign{ actor_ids, .. } => {
+                actor_ids.iter().filter_map(|actor_id| actors.get(actor_id)).map(|(_, region)| *region).collect()
+            },
+        }
+    }
+
+    /// Creates an ActorInsertion from the serialized actor data, compressing it if it exceeds COMPRESSION_THRESHOLD
+    pub fn insertion(serialized: Vec<u8>) -> Self {
+
+        if serialized.len() > COMPRESSION_THRESHOLD {
+            match snap::raw::Encoder::new().compress_vec(&serialized) {
+                Ok(compressed) => return ActorChange::ActorInsertion {
+                    serialized: compressed,
+                    compressed: true
+                },
+                Err(err) => println!("Failed to compress actor insertion, sending it uncompressed: {:?}", err)
+            }
+        }
+
+        ActorChange::ActorInsertion {
+            serialized,
+            compressed: false
+        }
+    }
 }
 
 #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub struct ActorID(u128);
 
+/// Counts the ActorIDs made by this client, so that no two of them can ever be the same
+static NEXT_ACTOR_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
 impl ActorID {
 
-    pub fn new() -> Self {
-        Self(uuid::Uuid::new_v4().as_u128())
+    /// Creates an ActorID for an actor inserted by the client. The client id takes up the highest 32 bits so that clients inserting 
+    /// at the same time can never make the same id, followed by a count of the ids this client has made and 32 random bits to tell 
+    /// apart ids made by different sessions that were handed the same client id.
+    pub fn new(client_id: u32) -> Self {
+        let sequence = NEXT_ACTOR_SEQUENCE.fetch_add(1, AtomicOrdering::Relaxed);
+        let random = uuid::Uuid::new_v4().as_u128() as u32;
+
+        Self(((client_id as u128) << 96) | ((sequence as u128) << 32) | random as u128)
+    }
+
+    /// The id of the client that inserted the actor, for ids made with new
+    pub fn client_id(&self) -> u32 {
+        (self.0 >> 96) as u32
     }
 
     pub fn val(&self) -> u128 {
         self.0
     }
+
+    /// Creates an ActorID from the shared rng, so that every client creates the same id when handling the same message
+    pub fn from_shared_rng(rng: &mut SharedRng) -> Self {
+        Self(((rng.next_u64() as u128) << 64) | rng.next_u64() as u128)
+    }
+}
+
+/// Ties actors together so that removing or rotating one of them acts on all of them
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ActorGroup(pub u64);
+
+impl ActorGroup {
+
+    /// Creates a group id for a group made by the client, with the client id in the highest 32 bits so that clients grouping at the 
+    /// same time can't make the same group
+    pub fn new(client_id: u32) -> Self {
+        let random = uuid::Uuid::new_v4().as_u128() as u32;
+
+        Self(((client_id as u64) << 32) | random as u64)
+    }
+
+    pub fn val(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Returns the actor_ids along with the ActorIDs of every other actor that shares a group with one of them, without duplicates
+pub fn with_group_members(world: &World, actor_ids: Vec<u128>) -> Vec<u128> {
+
+    let mut groups_query = <(Read<ActorID>, Read<ActorGroup>)>::query();
+
+    let groups = groups_query.iter(world)
+        .filter(|(actor_id, _)| actor_ids.contains(&actor_id.val()))
+        .map(|(_, group)| *group)
+        .collect::<std::collections::HashSet<ActorGroup>>();
+
+    let mut expanded = actor_ids;
+
+    if groups.is_empty() {
+        return expanded
+    }
+
+    groups_query.iter(world)
+        .filter(|(_, group)| groups.contains(group))
+        .for_each(|(actor_id, _)| {
+            if !expanded.contains(&actor_id.val()) {
+                expanded.push(actor_id.val());
+            }
+        });
+
+    expanded
 }
 
 pub fn create_initialize_actor_scene_fn() -> Box<dyn FnMut(&mut World, &mut Resources)> {
@@ -243,9 +400,12 @@ pub fn create_move_to_coord_system() -> impl systems::Runnable {
 pub fn position_actor_helper(world: &mut World, actor_entity: Entity, aabb: AABB) {
     if let Some(mut entry) = world.entry(actor_entity) {
 
-        let min = map_coords_to_world(aabb.get_min());
+        //rotations can flip the sign of the dimensions, which shouldn't move the actor off of the aabb's center
+        let dimensions = aabb.dimensions.abs();
+
+        let min = map_coords_to_world(aabb.center - dimensions / 2);
 
-        let bounds = map_coords_to_world(aabb.dimensions);
+        let bounds = map_coords_to_world(dimensions);
         
         let position = Position {
             value: nalgebra::Vector3::new(min.x, min.y, min.z) + nalgebra::Vector3::new(bounds.x/2., 0., bounds.z/2.)
@@ -292,83 +452,226 @@ pub fn serialize_single_actor_in_world(world: &mut World, entity: Entity) -> Res
     })
 }
 
-pub fn change(world: &mut World, change: &ActorChange, store_history: Option<u32>) {
-    match change {
+/// Serializes a copy of the actor entity as a new actor with a fresh ActorID for the client at coord_pos, keeping the rest of its 
+/// components
+pub fn serialize_new_actor_from(world: &World, entity: Entity, client_id: u32, coord_pos: CoordPos) -> Result<Vec<u8>, bincode::Error> {
+    serialize_new_actor_with(world, entity, ActorID::new(client_id), coord_pos, None)
+}
+
+/// Serializes a copy of the actor entity as a new actor with the given ActorID and coord_pos, optionally replacing its rotation. The 
+/// copy is left out of any group the actor was in.
+pub fn serialize_new_actor_with(world: &World, entity: Entity, actor_id: ActorID, coord_pos: CoordPos, rotation: Option<Rotation>) -> Result<Vec<u8>, bincode::Error> {
+    let mut actor_world = World::default();
+    MERGER.with(|m| {
+        let mut merger = m.borrow_mut();
+        let new_entity = actor_world.clone_from_single(world, entity, &mut *merger);
+
+        if let Some(mut entry) = actor_world.entry(new_entity) {
+            entry.add_component(actor_id);
+            entry.add_component(coord_pos);
+            entry.remove_component::<ActorGroup>();
+
+            if let Some(rotation) = rotation {
+                entry.add_component(rotation);
+            }
+        }
 
-        ActorChange::ActorInsertion{serialized} => {
         REGISTRY.with(|r| {
             let registry = r.borrow();
 
-            CANON.with(move |c| {
+            CANON.with(|c| {
                 let canon = c.borrow();
-                
-                let mut deserialized = bincode::de::Deserializer::from_slice(
-                    &serialized[..], 
-                    bincode::config::DefaultOptions::new()
-                        .with_fixint_encoding()
-                        .allow_trailing_bytes()
-                );
-
-                let actor_world: World = registry.as_deserialize(& *canon).deserialize(&mut deserialized).unwrap();
-
-                let mut query = <(Entity, Read<ActorID>)>::query();
-                query.iter(&actor_world)
+
+                bincode::serialize(&actor_world.as_serializable(component::<Actor>(), & *registry, & *canon))
+            })
+        })
+    })
+}
+
+pub fn change(world: &mut World, change: &ActorChange, store_history: Option<u32>) {
+
+    let undo = apply_change(world, change, store_history.is_some());
+
+    if let (Some(store_history), Some(undo)) = (store_history, undo) {
+        let mut history_query = <(Write<History>, Read<ClientID>)>::query();
+
+        if let Some((history, _)) = history_query.iter_mut(world).find(|(_, id)| id.val() == store_history) {
+            history.add_step(StepType::ActorChange((undo, change.clone())));
+        }
+    }
+}
+
+/// Deserializes actors serialized by the serialize functions above into a world of their own, decompressing them first if they were 
+/// compressed by ActorChange::insertion. Returns None if either step fails.
+fn deserialize_actor_world(serialized: &[u8], compressed: bool) -> Option<World> {
+
+    let serialized = if compressed {
+        match snap::raw::Decoder::new().decompress_vec(serialized) {
+            Ok(decompressed) => Cow::Owned(decompressed),
+            Err(err) => {
+                println!("Failed to decompress actor insertion with error: {:?}", err);
+                return None
+            }
+        }
+    } else {
+        Cow::Borrowed(serialized)
+    };
+
+    REGISTRY.with(|r| {
+        let registry = r.borrow();
+
+        CANON.with(|c| {
+            let canon = c.borrow();
+            
+            let mut deserialized = bincode::de::Deserializer::from_slice(
+                &serialized[..], 
+                bincode::config::DefaultOptions::new()
+                    .with_fixint_encoding()
+                    .allow_trailing_bytes()
+            );
+
+            match registry.as_deserialize(& *canon).deserialize(&mut deserialized) {
+                Ok(actor_world) => Some(actor_world),
+                Err(err) => {
+                    println!("Failed to deserialize actor insertion with error: {:?}", err);
+                    None
+                }
+            }
+        })
+    })
+}
+
+/// Applies the change to the world. If undoable is true, returns the change that would revert it, or None if nothing was changed.
+fn apply_change(world: &mut World, change: &ActorChange, undoable: bool) -> Option<ActorChange> {
+    match change {
+
+        ActorChange::LegacyActorInsertion{serialized} => {
+            apply_change(world, &ActorChange::ActorInsertion{ serialized: serialized.clone(), compressed: false }, undoable)
+        },
+        ActorChange::ActorInsertion{serialized, compressed} => {
+
+        let mut undo = Vec::new();
+
+        let actor_world = deserialize_actor_world(serialized, *compressed)?;
+
+        let mut query = <(Entity, Read<ActorID>)>::query();
+        query.iter(&actor_world)
+            .map(|(actor_entity, actor_id)| (*actor_entity, *actor_id))
+            .collect::<Vec<(Entity, ActorID)>>()
+            .into_iter()
+            .for_each(|(actor_entity, actor_id)| {
+
+                let world_actors = query.iter(world)
                     .map(|(actor_entity, actor_id)| (*actor_entity, *actor_id))
-                    .collect::<Vec<(Entity, ActorID)>>()
-                    .into_iter()
-                    .for_each(|(actor_entity, actor_id)| {
-
-                        let world_actors = query.iter(world)
-                            .map(|(actor_entity, actor_id)| (*actor_entity, *actor_id))
-                            .collect::<Vec<(Entity, ActorID)>>();
-
-                        if world_actors.is_empty() || !world_actors.into_iter().any(|(_,id)| id.val() == actor_id.val()) {
-                            
-                            if let Some(store_history) = store_history {
-                                let mut history_query = <(Write<History>, Read<ClientID>)>::query();
-
-                                history_query.iter_mut(world).filter(|(_, id)| id.val() == store_history).for_each(|(history, _)| {
-                                    history.add_step(
-                                        StepType::ActorChange(
-                                            (ActorChange::ActorRemoval(actor_id.val()), change.clone())
-                                        )
-                                    );
-                                });
-                            }
-
-                            MERGER.with(|m| {
-                                let mut merger = m.borrow_mut();
-                                world.clone_from_single(&actor_world, actor_entity, &mut *merger);
-
-                            });
-                        }
-                });
-            });
+                    .collect::<Vec<(Entity, ActorID)>>();
+
+                if world_actors.is_empty() || !world_actors.into_iter().any(|(_,id)| id.val() == actor_id.val()) {
+                    
+                    undo.push(ActorChange::ActorRemoval(actor_id.val()));
+
+                    MERGER.with(|m| {
+                        let mut merger = m.borrow_mut();
+                        world.clone_from_single(&actor_world, actor_entity, &mut *merger);
+
+                    });
+                }
         });
+
+        match undo.len() {
+            0 => None,
+            1 if undoable => undo.pop(),
+            _ if undoable => Some(ActorChange::ActorBatch(undo)),
+            _ => None
+        }
         },
         ActorChange::ActorRemoval(actor_id) => {
 
-            let mut query = <(Entity, Read<ActorID>, Read<NodeRef>)>::query();
-            if let Some((entity, node)) = query.iter(world)
+            let mut query = <(Entity, Read<ActorID>, TryRead<NodeRef>)>::query();
+            let (entity, node) = query.iter(world)
                 .find(|(_, id, _)| id.val() == *actor_id)
-                .map(|(entity, _, node_ref)| (*entity, node_ref.val())) {
-
-                    if let Some(store_history) = store_history {
-                        let mut history_query = <(Write<History>, Read<ClientID>)>::query();
-                        if let Ok(serialized) = serialize_single_actor_in_world(world, entity) {
-                            if let Some((history, _)) = history_query.iter_mut(world).find(|(_, id)| id.val() == store_history) {
-                                history.add_step(
-                                    StepType::ActorChange(
-                                        (ActorChange::ActorInsertion{
-                                            serialized: serialized.to_vec()
-                                        }, change.clone())
-                                    )
-                                )
-                            }
-                        }
+                .map(|(entity, _, node_ref)| (*entity, node_ref.map(|node_ref| node_ref.val())))?;
+
+            let undo = if undoable {
+                serialize_single_actor_in_world(world, entity).ok().map(ActorChange::insertion)
+            } else {
+                None
+            };
+
+            //actors whose node hasn't been made yet, or that never get one like the ones in a replayed world, only have an entity
+            match node {
+                Some(node) => node::free(world, node),
+                None => { world.remove(entity); }
+            }
+
+            undo
+        },
+        ActorChange::ActorMove{actor_id, coord_pos, rotation} => {
+
+            let mut query = <(Entity, Read<ActorID>, Read<CoordPos>, Read<Bounds>, Read<Rotation>)>::query();
+            let (entity, previous_pos, bounds, previous_rotation) = query.iter(world)
+                .find(|(_, id, _, _, _)| id.val() == *actor_id)
+                .map(|(entity, _, coord_pos, bounds, rotation)| (*entity, *coord_pos, *bounds, *rotation))?;
+
+            if previous_pos.value == *coord_pos && previous_rotation.value == *rotation {
+                return None
+            }
+
+            if let Some(mut entry) = world.entry(entity) {
+                entry.add_component(CoordPos::new(*coord_pos));
+                entry.add_component(Rotation{ value: *rotation });
+            }
+
+            position_actor_helper(world, entity, bounds.region_at(*coord_pos, *rotation));
+
+            if undoable {
+                Some(ActorChange::ActorMove{ actor_id: *actor_id, coord_pos: previous_pos.value, rotation: previous_rotation.value })
+            } else {
+                None
+            }
+        },
+        ActorChange::GroupAssign{actor_ids, group} => {
+
+            let mut query = <(Entity, Read<ActorID>, TryRead<ActorGroup>)>::query();
+            let previous = query.iter(world)
+                .filter(|(_, id, _)| actor_ids.contains(&id.val()))
+                .map(|(entity, id, previous_group)| (*entity, id.val(), previous_group.map(|group| group.val())))
+                .filter(|(_, _, previous_group)| previous_group != group)
+                .collect::<Vec<(Entity, u128, Option<u64>)>>();
+
+            let mut undo = Vec::new();
+
+            previous.into_iter().for_each(|(entity, actor_id, previous_group)| {
+                if let Some(mut entry) = world.entry(entity) {
+                    match group {
+                        Some(group) => entry.add_component(ActorGroup(*group)),
+                        None => entry.remove_component::<ActorGroup>(),
                     }
-                    node::free(world, node);
                 }
+
+                undo.push(ActorChange::GroupAssign{ actor_ids: vec![actor_id], group: previous_group });
+            });
+
+            match undo.len() {
+                0 => None,
+                1 if undoable => undo.pop(),
+                _ if undoable => Some(ActorChange::ActorBatch(undo)),
+                _ => None
+            }
+        },
+        ActorChange::ActorBatch(changes) => {
+
+            //undoing has to happen in the opposite order to the changes
+            let undo = changes.iter()
+                .filter_map(|change| apply_change(world, change, undoable))
+                .collect::<Vec<ActorChange>>()
+                .into_iter().rev()
+                .collect::<Vec<ActorChange>>();
+
+            if undo.is_empty() {
+                None
+            } else {
+                Some(ActorChange::ActorBatch(undo))
+            }
         }
     }
 }
@@ -386,6 +689,13 @@ pub fn free_all(world: &mut World) {
 }
 
 pub fn select_actors_from_range(world: &mut World, range: AABB) -> Vec<Entity> {
+    select_actors_from_range_filtered(world, range, |_| true)
+}
+
+/// Same as select_actors_from_range, but only keeps the actors whose entries satisfy the predicate, so that tools can act on 
+/// actors of a certain kind or with a certain component
+pub fn select_actors_from_range_filtered<F>(world: &mut World, range: AABB, predicate: F) -> Vec<Entity> 
+where F: Fn(&world::EntryRef) -> bool {
     let mut actor_query = <(Entity, Read<Bounds>, Read<Rotation>, Read<CoordPos>)>::query().filter(component::<ActorID>());
 
     actor_query.iter(world)
@@ -396,5 +706,177 @@ pub fn select_actors_from_range(world: &mut World, range: AABB) -> Vec<Entity> {
             range.intersects_bounds(aabb)
         })
         .map(|(entity, _, _, _)| *entity)
+        .filter(|entity| world.entry_ref(*entity).map(|entry| predicate(&entry)).unwrap_or(false))
         .collect::<Vec<Entity>>()
 }
+
+/// Returns the ActorID of the actor in range whose center is nearest to point. Ties are resolved by the lowest ActorID so that 
+/// every client picks the same actor.
+pub fn select_nearest_actor_from_range(world: &mut World, range: AABB, point: Point) -> Option<ActorID> {
+    let mut actor_query = <(Read<ActorID>, Read<Bounds>, Read<Rotation>, Read<CoordPos>)>::query();
+
+    actor_query.iter(world)
+        .filter(|(_, bounds, rotation, coord_pos)| {
+            let mut aabb = bounds.get_scaled_and_rotated_aabb(rotation.value);
+            aabb.center = coord_pos.value;
+
+            range.intersects_bounds(aabb)
+        })
+        .map(|(actor_id, _, _, coord_pos)| {
+            let diff = (coord_pos.value - point).map(|v| v as i64);
+
+            (diff.dot(&diff), *actor_id)
+        })
+        .min_by_key(|(distance, actor_id)| (*distance, actor_id.val()))
+        .map(|(_, actor_id)| actor_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn groups_in(world: &World) -> Vec<(u128, Option<u64>)> {
+        let mut query = <(Read<ActorID>, TryRead<ActorGroup>)>::query();
+
+        let mut groups = query.iter(world)
+            .map(|(actor_id, group)| (actor_id.val(), group.map(|group| group.val())))
+            .collect::<Vec<(u128, Option<u64>)>>();
+
+        groups.sort();
+        groups
+    }
+
+    #[test]
+    fn undoing_a_group_assign_restores_the_previous_groups() {
+        let mut world = World::default();
+
+        world.push((ActorID(1), ActorGroup(7)));
+        world.push((ActorID(2),));
+
+        let before = groups_in(&world);
+
+        let undo = apply_change(&mut world, &ActorChange::GroupAssign{ actor_ids: vec![1, 2], group: Some(9) }, true)
+            .expect("assigning a new group should be undoable");
+
+        assert_eq!(groups_in(&world), vec![(1, Some(9)), (2, Some(9))]);
+
+        apply_change(&mut world, &undo, false);
+
+        assert_eq!(groups_in(&world), before);
+    }
+
+    #[test]
+    fn groups_survive_serializing_the_actors_in_the_world() {
+        let mut world = World::default();
+
+        world.push((ActorID(1), ActorGroup(7)));
+        world.push((ActorID(2),));
+
+        let serialized = serialize_actors_in_world(&mut world).unwrap();
+
+        let mut loaded = World::default();
+        apply_change(&mut loaded, &ActorChange::insertion(serialized), false);
+
+        assert_eq!(groups_in(&loaded), vec![(1, Some(7)), (2, None)]);
+    }
+
+    #[test]
+    fn clients_inserting_in_the_same_tick_get_unique_ids() {
+        //each client allocates its ids locally, so interleave them as if both inserted several actors before hearing of the other
+        let ids = (0..50)
+            .flat_map(|_| vec![ActorID::new(1), ActorID::new(2)])
+            .collect::<Vec<ActorID>>();
+
+        let unique = ids.iter().map(|actor_id| actor_id.val()).collect::<std::collections::HashSet<u128>>();
+
+        assert_eq!(unique.len(), ids.len());
+    }
+
+    #[test]
+    fn actor_ids_know_which_client_made_them() {
+        assert_eq!(ActorID::new(0).client_id(), 0);
+        assert_eq!(ActorID::new(7).client_id(), 7);
+        assert_eq!(ActorID::new(std::u32::MAX).client_id(), std::u32::MAX);
+    }
+
+    #[test]
+    fn actor_changes_touch_the_regions_of_the_actors_they_change() {
+        let bounds = Bounds(nalgebra::Vector3::new(TILE_DIMENSIONS.x, TILE_DIMENSIONS.y, TILE_DIMENSIONS.z));
+        let region = bounds.region_at(Point::new(1, 0, 1), nalgebra::Rotation3::identity());
+
+        let mut actors = HashMap::new();
+        actors.insert(1, (bounds, region));
+
+        let moved = ActorChange::ActorMove{ actor_id: 1, coord_pos: Point::new(5, 0, 1), rotation: nalgebra::Rotation3::identity() };
+
+        assert_eq!(moved.regions(&actors), vec![region, bounds.region_at(Point::new(5, 0, 1), nalgebra::Rotation3::identity())]);
+        assert_eq!(ActorChange::GroupAssign{ actor_ids: vec![1, 2], group: Some(3) }.regions(&actors), vec![region]);
+
+        //actors that aren't in the world don't touch anything
+        assert!(ActorChange::ActorRemoval(2).regions(&actors).is_empty());
+    }
+
+    #[test]
+    fn only_large_insertions_get_compressed() {
+        let mut world = World::default();
+
+        world.push((ActorID(1), ActorGroup(7)));
+
+        let serialized = serialize_actors_in_world(&mut world).unwrap();
+        assert!(serialized.len() <= COMPRESSION_THRESHOLD);
+
+        assert!(matches!(ActorChange::insertion(serialized), ActorChange::ActorInsertion{ compressed: false, .. }));
+
+        let large = vec![7u8; COMPRESSION_THRESHOLD * 4];
+
+        match ActorChange::insertion(large.clone()) {
+            ActorChange::ActorInsertion{ serialized, compressed: true } => {
+                assert!(serialized.len() < large.len());
+                assert_eq!(snap::raw::Decoder::new().decompress_vec(&serialized).unwrap(), large);
+            },
+            other => panic!("Expected a compressed insertion, got {:?}", other)
+        }
+    }
+
+    #[test]
+    fn insertions_from_before_compression_are_still_read() {
+        //the layout of the enum before compression was added, with the insertion in the first position
+        #[derive(Serialize)]
+        enum OldActorChange {
+            ActorInsertion {
+                serialized: Vec<u8>
+            },
+        }
+
+        let mut world = World::default();
+
+        world.push((ActorID(1), ActorGroup(7)));
+        world.push((ActorID(2),));
+
+        let old = bincode::serialize(&OldActorChange::ActorInsertion{ serialized: serialize_actors_in_world(&mut world).unwrap() }).unwrap();
+
+        let mut loaded = World::default();
+        apply_change(&mut loaded, &bincode::deserialize::<ActorChange>(&old).unwrap(), false);
+
+        assert_eq!(groups_in(&loaded), vec![(1, Some(7)), (2, None)]);
+    }
+
+    #[test]
+    fn actor_moves_keep_their_rotation_through_bincode() {
+        let rotation = nalgebra::Rotation3::from_axis_angle(&nalgebra::Vector3::y_axis(), 1.2) * nalgebra::Rotation3::from_axis_angle(&nalgebra::Vector3::x_axis(), -0.3);
+
+        let change = ActorChange::ActorMove{ actor_id: 1, coord_pos: Point::new(3, -1, 2), rotation };
+
+        let serialized = bincode::serialize(&change).unwrap();
+
+        match bincode::deserialize::<ActorChange>(&serialized).unwrap() {
+            ActorChange::ActorMove{ actor_id, coord_pos, rotation: deserialized } => {
+                assert_eq!(actor_id, 1);
+                assert_eq!(coord_pos, Point::new(3, -1, 2));
+                //bincode writes the f32s out as they are, so the matrix should come back exactly
+                assert_eq!(deserialized, rotation);
+            },
+            other => panic!("Expected an ActorMove, got {:?}", other)
+        }
+    }
+}
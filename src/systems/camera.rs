@@ -2,10 +2,13 @@ use gdnative::prelude::*;
 
 use gdnative::api::{
     Camera,
+    File,
 };
 
 use legion::*;
 
+use crate::editor;
+
 use crate::systems::{
     selection_box,
     smoothing::Smoothing,
@@ -13,14 +16,19 @@ use crate::systems::{
         position::Position,
         rotation::{Rotation, Direction}
     },
-    input::{ Action, InputActionComponent },
-    level_map
+    input::{ Action, ActionRegistry, InputActionComponent },
+    level_map,
+    networking::ClientID,
 };
 
 use crate::node;
 
+use serde::{Serialize, Deserialize};
+
 type Vector3D = nalgebra::Vector3<f32>;
 type Rotation3D = nalgebra::Rotation3<f32>;
+type Point = nalgebra::Vector3<i32>;
+type AABB = octree::geometry::aabb::AABB<i32>;
 
 #[derive(Copy, Clone)]
 pub struct FocalPoint(pub Vector3D);
@@ -43,6 +51,73 @@ impl Default for Zoom {
 
 const SPEED : f32 = 4.;
 
+pub const BOOKMARK_COUNT: usize = 3;
+const BOOKMARKS_PATH: &str = "user://camera_bookmarks.ron";
+
+/// A saved vantage point, the camera's transform along with what it was looking at
+#[derive(Copy, Clone, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub position: Position,
+    pub rotation: Rotation,
+    pub focal_point: Vector3D,
+    pub focal_angle: [f32; 3],
+    pub zoom: f32,
+}
+
+/// Resource holding the camera bookmarks for the session, which are also written to disk whenever one is saved when persist is on
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CameraBookmarks {
+    pub bookmarks: [Option<Bookmark>; BOOKMARK_COUNT],
+    #[serde(skip)]
+    pub persist: bool,
+}
+
+impl Default for CameraBookmarks {
+    fn default() -> Self {
+        CameraBookmarks {
+            bookmarks: [None; BOOKMARK_COUNT],
+            persist: true,
+        }
+    }
+}
+
+impl CameraBookmarks {
+
+    /// Loads the bookmarks saved from previous sessions, starting with none when there aren't any
+    pub fn from_file() -> CameraBookmarks {
+
+        let file = File::new();
+        match file.open(BOOKMARKS_PATH, File::READ) {
+            Ok(_) => {
+                let bookmarks = ron::de::from_str::<CameraBookmarks>(file.get_as_text().to_string().as_str())
+                    .map(|bookmarks| CameraBookmarks { persist: true, ..bookmarks })
+                    .unwrap_or_default();
+
+                file.close();
+
+                bookmarks
+            },
+            Err(_) => CameraBookmarks::default()
+        }
+    }
+
+    pub fn save(&self) {
+
+        let file = File::new();
+        if file.open(BOOKMARKS_PATH, File::WRITE).is_err() {
+            godot_print!("Couldn't open {} to save camera bookmarks", BOOKMARKS_PATH);
+            return
+        }
+
+        match ron::ser::to_string_pretty(&self, ron::ser::PrettyConfig::default()) {
+            Ok(ron) => file.store_string(GodotString::from(ron)),
+            Err(err) => godot_print!("Failed to serialize camera bookmarks: {:?}", err)
+        }
+
+        file.close();
+    }
+}
+
 pub fn initialize_camera(world: &mut legion::world::World) -> Ref<Node> {
     
     let camera = Camera::new();
@@ -108,10 +183,10 @@ pub fn create_rotation_system() -> impl systems::Runnable {
 
 /// Handles the input for rotating the camera around the focal point
 pub fn create_camera_angle_system() -> impl systems::Runnable {
-    let camera_rotate_left = Action("camera_rotate_left".to_string());
-    let camera_rotate_right = Action("camera_rotate_right".to_string());
-    let camera_rotate_up = Action("camera_rotate_up".to_string());
-    let camera_rotate_down = Action("camera_rotate_down".to_string());
+    let camera_rotate_left = ActionRegistry::CAMERA_ROTATE_LEFT;
+    let camera_rotate_right = ActionRegistry::CAMERA_ROTATE_RIGHT;
+    let camera_rotate_up = ActionRegistry::CAMERA_ROTATE_UP;
+    let camera_rotate_down = ActionRegistry::CAMERA_ROTATE_DOWN;
 
     SystemBuilder::new("camera_angle_system")
         .with_query(<(Read<InputActionComponent>, Read<Action>)>::query())
@@ -200,34 +275,182 @@ pub fn create_follow_selection_box_system() -> impl systems::Runnable {
 
                     let mid = (max + min)/2.;
 
-                    let heading = center + mid;
-
-                    let entity = *entity;
-                    let focal_point = *focal_point;
-
-                    commands.exec_mut(move |world, _| {
-                        if let Some(mut entry) = world.entry(entity) {
-                            let smoothing = entry.get_component_mut::<Smoothing>();
-                            match smoothing {
-                                Ok(mut smoothing) => {
-                                    smoothing.heading = heading;
-                                    return {}
-                                },
-                                _ => {
-                                    entry.add_component(
-                                        Smoothing{
-                                            current: focal_point.0,
-                                            heading,
-                                            speed: SPEED
-                                        }
-                                    )
-                                }
-                            }
-                        }
+                    pan_to(commands, *entity, *focal_point, center + mid, SPEED);
+                }
+            });
+        })
+}
 
-                    });
+/// A fallback to following the box as it moves, for when the camera has been moved away by hand. When FollowSelection is on and the 
+/// active selection box ends up completely outside of the camera's view, the camera pans back over to the box. Boxes that are only 
+/// partly in view are left alone so that this doesn't fight with moving the camera by hand.
+pub fn create_keep_in_view_system() -> impl systems::Runnable {
+
+    SystemBuilder::new("keep_selection_box_in_view_system")
+        .read_resource::<editor::FollowSelection>()
+        .with_query(<(Read<selection_box::RelativeCamera>, Read<level_map::CoordPos>, Read<selection_box::SelectionBox>)>::query()
+            .filter(component::<selection_box::Active>())
+        )
+        .with_query(<(Entity, Read<FocalPoint>, Read<node::NodeRef>)>::query()
+            .filter(!component::<Smoothing>())
+        )
+        .build(|commands, world, follow_selection, queries| {
+
+            if !follow_selection.0 {
+                return
+            }
+
+            let (selection_box_query, cam_query) = queries;
+
+            selection_box_query.for_each(world, |(relative_cam, coord_pos, selection_box)| {
+
+                let aabb = AABB::new(coord_pos.value, selection_box.aabb.dimensions);
+
+                let min = level_map::map_coords_to_world(aabb.get_min());
+                let max = level_map::map_coords_to_world(aabb.get_max() + Point::new(1,1,1));
+
+                let corners = (0..8).map(|i| Vector3::new(
+                    if i & 1 == 0 { min.x } else { max.x },
+                    if i & 2 == 0 { min.y } else { max.y },
+                    if i & 4 == 0 { min.z } else { max.z },
+                )).collect::<Vec<Vector3>>();
+
+                for (entity, focal_point, node_ref) in cam_query.iter(world).filter(|(_, _, node_ref)| node_ref.val() == relative_cam.val()) {
 
+                    let camera = match unsafe { node_ref.val().assume_safe().cast::<Camera>() } {
+                        Some(camera) => camera,
+                        None => continue
+                    };
+
+                    //frustum planes face outwards, so the box is out of view when all of its corners are on the outside of any one plane
+                    let out_of_view = camera.get_frustum().iter()
+                        .filter_map(|plane| plane.try_to_plane())
+                        .any(|plane| corners.iter().all(|corner| plane.normal.dot(*corner) > plane.d));
+
+                    if out_of_view {
+                        pan_to(commands, *entity, *focal_point, level_map::map_coords_to_world(coord_pos.value) + Vector3D::new(0.5, 0.5, 0.5), SPEED);
+                    }
                 }
             });
         })
-}
\ No newline at end of file
+}
+
+/// Moves the focal point over to the center of the local client's active selection box with the focus_selection action, taking 
+/// about FocusDuration to get there
+pub fn create_focus_selection_system() -> impl systems::Runnable {
+
+    let focus_selection = ActionRegistry::FOCUS_SELECTION;
+
+    SystemBuilder::new("camera_focus_selection_system")
+        .read_resource::<ClientID>()
+        .read_resource::<editor::FocusDuration>()
+        .with_query(<(Read<InputActionComponent>, Read<Action>)>::query())
+        .with_query(<(Read<selection_box::RelativeCamera>, Read<level_map::CoordPos>, Read<selection_box::SelectionBox>, Read<ClientID>)>::query()
+            .filter(component::<selection_box::Active>())
+        )
+        .with_query(<(Entity, Read<FocalPoint>, Read<node::NodeRef>)>::query())
+        .build(|commands, world, (client_id, focus_duration), queries| {
+
+            let (input_query, selection_box_query, cam_query) = queries;
+
+            if !input_query.iter(world).any(|(input_component, action)| action == &focus_selection && input_component.just_pressed()) {
+                return
+            }
+
+            if let Some((relative_cam, coord_pos, selection_box, _)) = selection_box_query.iter(world).find(|(_, _, _, id)| **id == **client_id) {
+
+                let aabb = AABB::new(coord_pos.value, selection_box.aabb.dimensions);
+                let center = level_map::map_coords_to_world(aabb.get_min()) + level_map::map_coords_to_world(aabb.dimensions.abs()) / 2.;
+
+                //smoothing closes the same fraction of the distance every second, so this gets within 1% of the center by the end
+                let speed = 0.01f32.ln().abs() / focus_duration.0.max(std::f32::EPSILON);
+
+                for (entity, focal_point, _) in cam_query.iter(world).filter(|(_, _, node_ref)| node_ref.val() == relative_cam.val()) {
+                    pan_to(commands, *entity, *focal_point, center, speed);
+                }
+            }
+        })
+}
+
+/// Saves the camera's current vantage point to a bookmark with the save_bookmark actions, and jumps back to it with the 
+/// goto_bookmark actions. Going to a bookmark that hasn't been saved does nothing.
+pub fn create_bookmark_system() -> impl systems::Runnable {
+
+    let save_bookmarks = [ActionRegistry::SAVE_BOOKMARK_1, ActionRegistry::SAVE_BOOKMARK_2, ActionRegistry::SAVE_BOOKMARK_3];
+    let goto_bookmarks = [ActionRegistry::GOTO_BOOKMARK_1, ActionRegistry::GOTO_BOOKMARK_2, ActionRegistry::GOTO_BOOKMARK_3];
+
+    SystemBuilder::new("camera_bookmark_system")
+        .write_resource::<CameraBookmarks>()
+        .with_query(<(Read<InputActionComponent>, Read<Action>)>::query())
+        .with_query(<(Entity, Write<Position>, Write<Rotation>, Write<FocalPoint>, Write<FocalAngle>, Write<Zoom>)>::query())
+        .build(move |commands, world, camera_bookmarks, queries| {
+
+            let (input_query, cam_query) = queries;
+
+            let pressed = input_query.iter(world)
+                .filter(|(input_component, _)| input_component.just_pressed())
+                .map(|(_, action)| action.clone())
+                .collect::<Vec<Action>>();
+
+            let save_index = save_bookmarks.iter().position(|action| pressed.contains(action));
+            let goto_index = goto_bookmarks.iter().position(|action| pressed.contains(action));
+
+            if let Some(index) = save_index {
+
+                if let Some((_, position, rotation, focal_point, focal_angle, zoom)) = cam_query.iter_mut(world).next() {
+                    camera_bookmarks.bookmarks[index] = Some(Bookmark {
+                        position: *position,
+                        rotation: *rotation,
+                        focal_point: focal_point.0,
+                        focal_angle: [focal_angle.0, focal_angle.1, focal_angle.2],
+                        zoom: zoom.0,
+                    });
+
+                    if camera_bookmarks.persist {
+                        camera_bookmarks.save();
+                    }
+                }
+            }
+
+            let bookmark = match goto_index.and_then(|index| camera_bookmarks.bookmarks[index]) {
+                Some(bookmark) => bookmark,
+                None => return
+            };
+
+            for (entity, position, rotation, focal_point, focal_angle, zoom) in cam_query.iter_mut(world) {
+
+                //stop any panning that's underway, otherwise it would drag the focal point back to where it was heading
+                commands.remove_component::<Smoothing>(*entity);
+
+                *position = bookmark.position;
+                *rotation = bookmark.rotation;
+                focal_point.0 = bookmark.focal_point;
+                *focal_angle = FocalAngle(bookmark.focal_angle[0], bookmark.focal_angle[1], bookmark.focal_angle[2]);
+                zoom.0 = bookmark.zoom;
+            }
+        })
+}
+
+/// Smoothly moves the camera's focal point towards heading, redirecting it if it's already moving
+fn pan_to(commands: &mut systems::CommandBuffer, entity: Entity, focal_point: FocalPoint, heading: Vector3D, speed: f32) {
+    commands.exec_mut(move |world, _| {
+        if let Some(mut entry) = world.entry(entity) {
+            let smoothing = entry.get_component_mut::<Smoothing>();
+            match smoothing {
+                Ok(mut smoothing) => {
+                    smoothing.heading = heading;
+                    smoothing.speed = speed;
+                },
+                _ => {
+                    entry.add_component(
+                        Smoothing{
+                            current: focal_point.0,
+                            heading,
+                            speed
+                        }
+                    )
+                }
+            }
+        }
+    });
+}
@@ -1,22 +1,40 @@
 
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
 use gdnative::prelude::*;
 use gdnative::api::{
     GeometryInstance,
     ImmediateGeometry,
     Mesh,
+    ShaderMaterial,
+    SpatialMaterial,
 };
 
 use crate::node;
+use crate::systems::level_map;
 
 use legion::*;
+use octree::PointData;
+
+type AABB = octree::geometry::aabb::AABB<i32>;
+type Point = nalgebra::Vector3<i32>;
+
+thread_local! {
+    /// Tinted copies of material resources by the material's name and the bits of the tint, so that each tint only gets one copy no 
+    /// matter how often the meshes using it are redrawn
+    static TINTED_RESOURCES: RefCell<HashMap<(&'static str, [u32; 4]), Ref<Resource>>> = RefCell::new(HashMap::new());
+}
 
 pub struct MeshData {
     pub verts: Vec<Vector3>,
     pub uvs: Vec<Vector2>,
     pub uv2s: Vec<Vector2>,
     pub normals: Vec<Vector3>,
+    pub colors: Vec<Color>,
     pub indices: Vec<i32>,
+    /// The Mesh primitive that the indices are drawn as, which is triangles unless set otherwise
+    pub primitive: i64,
 }
 
 impl MeshData {
@@ -25,26 +43,47 @@ impl MeshData {
         self.uvs.clear();
         self.uv2s.clear();
         self.normals.clear();
+        self.colors.clear();
         self.indices.clear();
     }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Material {
     name: Option<&'static str>,
+    tint: Option<Color>,
 }
 
 impl Material {
     pub fn new() -> Self {
         Material {
-            name: None
+            name: None,
+            tint: None,
         }
     }
 
     pub fn from_str(s: &'static str) -> Self {
         Material {
-            name: Some(s)
+            name: Some(s),
+            tint: None,
+        }
+    }
+
+    /// Returns a copy of this material that gets drawn tinted with the given color
+    pub fn with_tint(self, tint: Color) -> Self {
+        Material {
+            tint: Some(tint),
+            ..self
         }
     }
+
+    pub fn tint(&self) -> Option<Color> {
+        self.tint
+    }
+
+    pub fn name(&self) -> Option<&'static str> {
+        self.name
+    }
 }
 
 impl MeshData {
@@ -54,7 +93,9 @@ impl MeshData {
             uvs: Vec::new(),
             uv2s: Vec::new(),
             normals: Vec::new(),
-            indices: Vec::new()
+            colors: Vec::new(),
+            indices: Vec::new(),
+            primitive: Mesh::PRIMITIVE_TRIANGLES,
         }
     }
 }
@@ -88,7 +129,7 @@ pub fn create_draw_system() -> impl systems::Runnable {
         .with_query(<(Entity, Read<MeshData>, Read<node::NodeRef>)>::query()
             .filter(
                 (component::<RequiresManualChange>() & component::<ManuallyChange>()) |
-                (!component::<RequiresManualChange>() & maybe_changed::<MeshData>())
+                (!component::<RequiresManualChange>() & (maybe_changed::<MeshData>() | maybe_changed::<Material>()))
             )
         )
         .build(move |commands, world, _, query|{
@@ -103,6 +144,7 @@ pub fn create_draw_system() -> impl systems::Runnable {
                 let uvs = &mesh_data.uvs;
                 let uv2s = &mesh_data.uv2s;
                 let normals = &mesh_data.normals;
+                let colors = &mesh_data.colors;
                 let indices = &mesh_data.indices;
                 
                 let immediate_geometry: Ref<ImmediateGeometry> = unsafe { 
@@ -116,9 +158,10 @@ pub fn create_draw_system() -> impl systems::Runnable {
                     let immediate_geometry = immediate_geometry.assume_safe();            
 
                     immediate_geometry.clear();
-                    immediate_geometry.begin(Mesh::PRIMITIVE_TRIANGLES, Null::null());
+                    immediate_geometry.begin(mesh_data.primitive, Null::null());
                     
                     let uv2s_len = uv2s.len();
+                    let colors_len = colors.len();
 
                     for index in indices {
                         let index = *index as usize;
@@ -128,6 +171,9 @@ pub fn create_draw_system() -> impl systems::Runnable {
                         if index < uv2s_len {
                             immediate_geometry.set_uv2(uv2s[index]);
                         }
+                        if index < colors_len {
+                            immediate_geometry.set_color(colors[index]);
+                        }
                         immediate_geometry.add_vertex(verts[index]);
                     }
 
@@ -141,21 +187,28 @@ pub fn create_draw_system() -> impl systems::Runnable {
                 commands.exec_mut(move |world, _| {
                     if let Some(mut entry) = world.entry(entity) {
                         if let Ok(material) = entry.get_component::<Material>() {
-                            let resource = ResourceLoader::godot_singleton().load(match material.name {
+                            let name = match material.name {
                                 Some(r) => r,
                                 None => { 
                                     //TODO: make it so it grabs a default material if no name value is set.
                                     panic!("Material name returned None");
                                 }
-                            }, "Material", false);
+                            };
+
+                            let resource = ResourceLoader::godot_singleton().load(name, "Material", false);
                 
+                            let resource = match resource {
+                                Some(r) => r,
+                                None => {
+                                    //TODO: Same thing, gotta get a default material if none is found
+                                    panic!("Resource {:?} does not exist", material.name);
+                                }
+                            };
+
                             unsafe {
-                                immediate_geometry.assume_safe().upcast::<GeometryInstance>().set_material_override(match resource {
-                                        Some(r) => r,
-                                        None => {
-                                            //TODO: Same thing, gotta get a default material if none is found
-                                            panic!("Resource {:?} does not exist", material.name);
-                                        }
+                                immediate_geometry.assume_safe().upcast::<GeometryInstance>().set_material_override(match material.tint {
+                                        Some(tint) => tinted_resource(name, resource, tint),
+                                        None => resource
                                     }
                                     .cast::<gdnative::api::Material>().unwrap()
                                 );
@@ -171,3 +224,172 @@ pub fn create_draw_system() -> impl systems::Runnable {
     
 }
 
+/// Returns a copy of the material resource called name with its color multiplied by tint, so that other meshes sharing the resource 
+/// aren't affected and the material's own color and transparency still show through. Copies are cached in TINTED_RESOURCES.
+unsafe fn tinted_resource(name: &'static str, resource: Ref<Resource>, tint: Color) -> Ref<Resource> {
+    let key = (name, [tint.r.to_bits(), tint.g.to_bits(), tint.b.to_bits(), tint.a.to_bits()]);
+
+    if let Some(cached) = TINTED_RESOURCES.with(|t| t.borrow().get(&key).cloned()) {
+        return cached
+    }
+
+    let copy = match resource.assume_safe().duplicate(false) {
+        Some(copy) => copy,
+        None => return resource
+    };
+
+    if let Some(spatial_material) = copy.assume_safe().cast::<SpatialMaterial>() {
+        let albedo = spatial_material.albedo();
+        spatial_material.set_albedo(Color::rgba(albedo.r * tint.r, albedo.g * tint.g, albedo.b * tint.b, albedo.a * tint.a));
+    } else if let Some(shader_material) = copy.assume_safe().cast::<ShaderMaterial>() {
+        shader_material.set_shader_param("tint", tint);
+    }
+
+    TINTED_RESOURCES.with(|t| t.borrow_mut().insert(key, copy.clone()));
+
+    copy
+}
+
+/// Writes the tiles within aabb out as Wavefront OBJ text so that a built structure can be taken into other tools. Faces between 
+/// two solid tiles are skipped, and coplanar faces are greedily merged into larger quads to keep the face count down. Tiles outside 
+/// of the aabb are treated as empty so that the exported region is always closed.
+pub fn export_region_obj(world: &World, map: &level_map::Map, aabb: AABB) -> String {
+
+    let solid = map.tiles_in(world, aabb).into_iter()
+        .map(|tile_data| tile_data.get_point())
+        .collect::<HashSet<Point>>();
+
+    let min = aabb.get_min();
+    let dimensions = aabb.dimensions.abs();
+
+    let mut positions: Vec<Point> = Vec::new();
+    let mut normals: Vec<Point> = Vec::new();
+    //index of the quad's first position and its normal, both 1 based
+    let mut faces: Vec<(usize, usize)> = Vec::new();
+
+    for d in 0..3 {
+        let u = (d + 1) % 3;
+        let v = (d + 2) % 3;
+
+        for sign in [1, -1].iter() {
+
+            let mut normal = Point::zeros();
+            normal[d] = *sign;
+
+            normals.push(normal);
+            let normal_index = normals.len();
+
+            let index = |i: i32, j: i32| (i + j * dimensions[u]) as usize;
+
+            for slice in min[d]..min[d] + dimensions[d] {
+
+                //which cells in this slice have a face exposed in the normal's direction
+                let mut mask = vec![false; (dimensions[u] * dimensions[v]) as usize];
+
+                for j in 0..dimensions[v] {
+                    for i in 0..dimensions[u] {
+                        let mut pt = Point::zeros();
+                        pt[d] = slice;
+                        pt[u] = min[u] + i;
+                        pt[v] = min[v] + j;
+
+                        mask[index(i, j)] = solid.contains(&pt) && !solid.contains(&(pt + normal));
+                    }
+                }
+
+                for j in 0..dimensions[v] {
+                    let mut i = 0;
+
+                    while i < dimensions[u] {
+
+                        if !mask[index(i, j)] {
+                            i += 1;
+                            continue
+                        }
+
+                        let mut width = 1;
+                        while i + width < dimensions[u] && mask[index(i + width, j)] {
+                            width += 1;
+                        }
+
+                        let mut height = 1;
+                        while j + height < dimensions[v] && (0..width).all(|k| mask[index(i + k, j + height)]) {
+                            height += 1;
+                        }
+
+                        for h in 0..height {
+                            for k in 0..width {
+                                mask[index(i + k, j + h)] = false;
+                            }
+                        }
+
+                        let mut base = Point::zeros();
+                        base[d] = if *sign > 0 { slice + 1 } else { slice };
+                        base[u] = min[u] + i;
+                        base[v] = min[v] + j;
+
+                        let mut du = Point::zeros();
+                        du[u] = width;
+
+                        let mut dv = Point::zeros();
+                        dv[v] = height;
+
+                        //u cross v is the positive direction, so the corners get reversed to stay counter clockwise from outside
+                        let mut corners = vec![base, base + du, base + du + dv, base + dv];
+                        if *sign < 0 {
+                            corners.reverse();
+                        }
+
+                        faces.push((positions.len() + 1, normal_index));
+                        positions.extend(corners);
+
+                        i += width;
+                    }
+                }
+            }
+        }
+    }
+
+    let mut obj = String::new();
+
+    let _ = writeln!(obj, "# {} quads exported from wolf-gang", faces.len());
+
+    for position in positions {
+        let position = level_map::map_coords_to_world(position);
+        let _ = writeln!(obj, "v {} {} {}", position.x, position.y, position.z);
+    }
+
+    for normal in normals {
+        let _ = writeln!(obj, "vn {} {} {}", normal.x, normal.y, normal.z);
+    }
+
+    for (first, normal_index) in faces {
+        let _ = writeln!(obj, "f {a}//{n} {b}//{n} {c}//{n} {d}//{n}", a = first, b = first + 1, c = first + 2, d = first + 3, n = normal_index);
+    }
+
+    obj
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_single_tile_exports_as_6_quads() {
+        let mut world = World::default();
+        let map = level_map::Map::default();
+
+        let mut octree = octree::Octree::new(AABB::new(Point::new(5, 5, 5), Point::new(10, 10, 10)), octree::DEFAULT_MAX);
+        assert!(octree.insert(level_map::TileData::new(0, Point::new(2, 3, 4))).is_ok());
+        map.insert_mapchunk_with_octree(&octree, &mut world, false);
+
+        let obj = export_region_obj(&world, &map, AABB::from_extents(Point::new(0, 0, 0), Point::new(4, 4, 4)));
+
+        let quads = obj.lines().filter(|line| line.starts_with("f ")).count();
+        let positions = obj.lines().filter(|line| line.starts_with("v ")).count();
+
+        //each quad is two triangles
+        assert_eq!(quads, 6);
+        assert_eq!(quads * 2, 12);
+        assert_eq!(positions, 24);
+    }
+}
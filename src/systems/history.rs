@@ -9,7 +9,7 @@ use crate::{
         actor,
         actor::ActorChange,
         input::{
-            InputActionComponent, Action
+            InputActionComponent, Action, ActionRegistry
         },
         level_map::{Map, TileData,},
         networking::{ 
@@ -132,8 +132,8 @@ pub fn send_move_by_step(commands: &mut legion::systems::CommandBuffer, client_i
 
 pub fn create_history_input_system() -> impl systems::Runnable {
 
-    let undo = Action("undo".to_string());
-    let redo = Action("redo".to_string());
+    let undo = ActionRegistry::UNDO;
+    let redo = ActionRegistry::REDO;
 
     SystemBuilder::new("history_input_system")
         .read_resource::<ClientID>()
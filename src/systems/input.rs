@@ -7,7 +7,10 @@ use legion::*;
 use ron::ser::{PrettyConfig};
 use serde::{Deserialize, Serialize};
 
-use std::collections::{ HashMap, HashSet };
+use std::{
+    borrow::Cow,
+    collections::{ HashMap, HashSet },
+};
 
 const USER_CONFIG_PATH: &str = "user://input_map.ron";
 const RESOURCE_CONFIG_PATH: &str = "res://config/input_map.ron";
@@ -140,6 +143,13 @@ pub fn initialize_input_config(world: &mut legion::world::World) {
 
     input_config.transcode_to_input_map();
 
+    //let whoever is adding a new action know that it still needs to be configured
+    for action in ActionRegistry::ALL {
+        if !input_config.actions.contains_key(action.name()) {
+            godot_print!("{:?} is used by the editor but has no input configured", action);
+        }
+    }
+
     let mut modifiers: Vec<(Action, TypeTag, Modifier, InputData)> = Vec::new();
     let mut non_modifiers: Vec<(Action, TypeTag, InputData)> = Vec::new();
 
@@ -151,8 +161,8 @@ pub fn initialize_input_config(world: &mut legion::world::World) {
             for i in 0.. input_data.len() {
                 let input = input_data[i];
                 match input {
-                    Some(input_data) if i == 0 => modifiers.push((Action(name.clone()), TypeTag(input_type), Modifier{}, input_data)),
-                    Some(input_data) => non_modifiers.push((Action(name.clone()), TypeTag(input_type), input_data)),
+                    Some(input_data) if i == 0 => modifiers.push((Action(Cow::Owned(name.clone())), TypeTag(input_type), Modifier{}, input_data)),
+                    Some(input_data) => non_modifiers.push((Action(Cow::Owned(name.clone())), TypeTag(input_type), input_data)),
                     None => {}
                 }
             }
@@ -167,11 +177,248 @@ pub fn initialize_input_config(world: &mut legion::world::World) {
 pub struct Modifier{}
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
-pub struct Action(pub String);
+pub struct Action(pub Cow<'static, str>);
+
+impl Action {
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+}
+
+/// The logical actions that the editor reads input for. Keys are remapped through Godot's InputMap by action name, so systems 
+/// should always refer to actions through these rather than by literal strings.
+pub struct ActionRegistry;
+
+impl ActionRegistry {
+    pub const MOVE_FORWARD: Action = Action(Cow::Borrowed("move_forward"));
+    pub const MOVE_BACK: Action = Action(Cow::Borrowed("move_back"));
+    pub const MOVE_LEFT: Action = Action(Cow::Borrowed("move_left"));
+    pub const MOVE_RIGHT: Action = Action(Cow::Borrowed("move_right"));
+    pub const MOVE_UP: Action = Action(Cow::Borrowed("move_up"));
+    pub const MOVE_DOWN: Action = Action(Cow::Borrowed("move_down"));
+    pub const INSERTION: Action = Action(Cow::Borrowed("insertion"));
+    pub const INSERT_SNAPPED: Action = Action(Cow::Borrowed("insert_snapped"));
+    pub const REMOVAL: Action = Action(Cow::Borrowed("removal"));
+    pub const REMOVE_SINGLE: Action = Action(Cow::Borrowed("remove_single"));
+    pub const PICK_TILE: Action = Action(Cow::Borrowed("pick_tile"));
+    pub const FLATTEN: Action = Action(Cow::Borrowed("flatten"));
+    pub const REMOVE_OUTSIDE: Action = Action(Cow::Borrowed("remove_outside"));
+    pub const SMOOTH: Action = Action(Cow::Borrowed("smooth"));
+    pub const CHECKERBOARD_FILL: Action = Action(Cow::Borrowed("checkerboard_fill"));
+    pub const RAISE_TERRAIN: Action = Action(Cow::Borrowed("raise_terrain"));
+    pub const LOWER_TERRAIN: Action = Action(Cow::Borrowed("lower_terrain"));
+    pub const DUPLICATE_ACTOR: Action = Action(Cow::Borrowed("duplicate_actor"));
+    pub const REPLACE_ACTOR: Action = Action(Cow::Borrowed("replace_actor"));
+    pub const CLEAR_REGION: Action = Action(Cow::Borrowed("clear_region"));
+    pub const SCATTER_ACTORS: Action = Action(Cow::Borrowed("scatter_actors"));
+    pub const NUDGE_ACTOR_FORWARD: Action = Action(Cow::Borrowed("nudge_actor_forward"));
+    pub const NUDGE_ACTOR_BACK: Action = Action(Cow::Borrowed("nudge_actor_back"));
+    pub const NUDGE_ACTOR_LEFT: Action = Action(Cow::Borrowed("nudge_actor_left"));
+    pub const NUDGE_ACTOR_RIGHT: Action = Action(Cow::Borrowed("nudge_actor_right"));
+    pub const NUDGE_ACTOR_UP: Action = Action(Cow::Borrowed("nudge_actor_up"));
+    pub const NUDGE_ACTOR_DOWN: Action = Action(Cow::Borrowed("nudge_actor_down"));
+    pub const BOX_DRAG: Action = Action(Cow::Borrowed("box_drag"));
+    pub const SELECT_ACTORS: Action = Action(Cow::Borrowed("select_actors"));
+    pub const ROTATE_SELECTED_LEFT: Action = Action(Cow::Borrowed("rotate_selected_left"));
+    pub const ROTATE_SELECTED_RIGHT: Action = Action(Cow::Borrowed("rotate_selected_right"));
+    pub const GROUP_ACTORS: Action = Action(Cow::Borrowed("group_actors"));
+    pub const UNGROUP_ACTORS: Action = Action(Cow::Borrowed("ungroup_actors"));
+    pub const ALIGN_X_MIN: Action = Action(Cow::Borrowed("align_x_min"));
+    pub const ALIGN_X_CENTER: Action = Action(Cow::Borrowed("align_x_center"));
+    pub const ALIGN_X_MAX: Action = Action(Cow::Borrowed("align_x_max"));
+    pub const ALIGN_Y_MIN: Action = Action(Cow::Borrowed("align_y_min"));
+    pub const ALIGN_Y_CENTER: Action = Action(Cow::Borrowed("align_y_center"));
+    pub const ALIGN_Y_MAX: Action = Action(Cow::Borrowed("align_y_max"));
+    pub const ALIGN_Z_MIN: Action = Action(Cow::Borrowed("align_z_min"));
+    pub const ALIGN_Z_CENTER: Action = Action(Cow::Borrowed("align_z_center"));
+    pub const ALIGN_Z_MAX: Action = Action(Cow::Borrowed("align_z_max"));
+    pub const ROTATE_SELECTION_LEFT: Action = Action(Cow::Borrowed("rotate_selection_left"));
+    pub const ROTATE_SELECTION_RIGHT: Action = Action(Cow::Borrowed("rotate_selection_right"));
+    pub const FACE_CAMERA: Action = Action(Cow::Borrowed("face_camera"));
+    pub const ROTATE_PASTE: Action = Action(Cow::Borrowed("rotate_paste"));
+    pub const RESET_BOX: Action = Action(Cow::Borrowed("reset_box"));
+    pub const LOCK_AXIS_X: Action = Action(Cow::Borrowed("lock_axis_x"));
+    pub const LOCK_AXIS_Y: Action = Action(Cow::Borrowed("lock_axis_y"));
+    pub const LOCK_AXIS_Z: Action = Action(Cow::Borrowed("lock_axis_z"));
+    pub const CYCLE_TOOL: Action = Action(Cow::Borrowed("cycle_tool"));
+    pub const NEXT_TILE: Action = Action(Cow::Borrowed("next_tile"));
+    pub const PREV_TILE: Action = Action(Cow::Borrowed("prev_tile"));
+    pub const LOCK_LAYER: Action = Action(Cow::Borrowed("lock_layer"));
+    pub const SET_DISPLAY_ORIGIN: Action = Action(Cow::Borrowed("set_display_origin"));
+    pub const TOGGLE_DIMENSIONS: Action = Action(Cow::Borrowed("toggle_dimensions"));
+    pub const NEXT_BOX: Action = Action(Cow::Borrowed("next_box"));
+    pub const PREV_BOX: Action = Action(Cow::Borrowed("prev_box"));
+    pub const TOGGLE_MEASURE: Action = Action(Cow::Borrowed("toggle_measure"));
+    pub const EXPAND_SELECTION_FORWARD: Action = Action(Cow::Borrowed("expand_selection_forward"));
+    pub const EXPAND_SELECTION_BACK: Action = Action(Cow::Borrowed("expand_selection_back"));
+    pub const EXPAND_SELECTION_LEFT: Action = Action(Cow::Borrowed("expand_selection_left"));
+    pub const EXPAND_SELECTION_RIGHT: Action = Action(Cow::Borrowed("expand_selection_right"));
+    pub const EXPAND_SELECTION_UP: Action = Action(Cow::Borrowed("expand_selection_up"));
+    pub const EXPAND_SELECTION_DOWN: Action = Action(Cow::Borrowed("expand_selection_down"));
+    pub const EXTRUDE_FORWARD: Action = Action(Cow::Borrowed("extrude_forward"));
+    pub const UNDO: Action = Action(Cow::Borrowed("undo"));
+    pub const REDO: Action = Action(Cow::Borrowed("redo"));
+    pub const CAMERA_ROTATE_LEFT: Action = Action(Cow::Borrowed("camera_rotate_left"));
+    pub const CAMERA_ROTATE_RIGHT: Action = Action(Cow::Borrowed("camera_rotate_right"));
+    pub const CAMERA_ROTATE_UP: Action = Action(Cow::Borrowed("camera_rotate_up"));
+    pub const CAMERA_ROTATE_DOWN: Action = Action(Cow::Borrowed("camera_rotate_down"));
+    pub const FOCUS_SELECTION: Action = Action(Cow::Borrowed("focus_selection"));
+    pub const SAVE_BOOKMARK_1: Action = Action(Cow::Borrowed("save_bookmark_1"));
+    pub const SAVE_BOOKMARK_2: Action = Action(Cow::Borrowed("save_bookmark_2"));
+    pub const SAVE_BOOKMARK_3: Action = Action(Cow::Borrowed("save_bookmark_3"));
+    pub const GOTO_BOOKMARK_1: Action = Action(Cow::Borrowed("goto_bookmark_1"));
+    pub const GOTO_BOOKMARK_2: Action = Action(Cow::Borrowed("goto_bookmark_2"));
+    pub const GOTO_BOOKMARK_3: Action = Action(Cow::Borrowed("goto_bookmark_3"));
+
+    /// Every logical action used by the editor
+    pub const ALL: &'static [Action] = &[
+        Self::MOVE_FORWARD,
+        Self::MOVE_BACK,
+        Self::MOVE_LEFT,
+        Self::MOVE_RIGHT,
+        Self::MOVE_UP,
+        Self::MOVE_DOWN,
+        Self::INSERTION,
+        Self::INSERT_SNAPPED,
+        Self::REMOVAL,
+        Self::REMOVE_SINGLE,
+        Self::PICK_TILE,
+        Self::FLATTEN,
+        Self::REMOVE_OUTSIDE,
+        Self::SMOOTH,
+        Self::CHECKERBOARD_FILL,
+        Self::RAISE_TERRAIN,
+        Self::LOWER_TERRAIN,
+        Self::DUPLICATE_ACTOR,
+        Self::REPLACE_ACTOR,
+        Self::CLEAR_REGION,
+        Self::SCATTER_ACTORS,
+        Self::NUDGE_ACTOR_FORWARD,
+        Self::NUDGE_ACTOR_BACK,
+        Self::NUDGE_ACTOR_LEFT,
+        Self::NUDGE_ACTOR_RIGHT,
+        Self::NUDGE_ACTOR_UP,
+        Self::NUDGE_ACTOR_DOWN,
+        Self::BOX_DRAG,
+        Self::SELECT_ACTORS,
+        Self::ROTATE_SELECTED_LEFT,
+        Self::ROTATE_SELECTED_RIGHT,
+        Self::GROUP_ACTORS,
+        Self::UNGROUP_ACTORS,
+        Self::ALIGN_X_MIN,
+        Self::ALIGN_X_CENTER,
+        Self::ALIGN_X_MAX,
+        Self::ALIGN_Y_MIN,
+        Self::ALIGN_Y_CENTER,
+        Self::ALIGN_Y_MAX,
+        Self::ALIGN_Z_MIN,
+        Self::ALIGN_Z_CENTER,
+        Self::ALIGN_Z_MAX,
+        Self::ROTATE_SELECTION_LEFT,
+        Self::ROTATE_SELECTION_RIGHT,
+        Self::FACE_CAMERA,
+        Self::ROTATE_PASTE,
+        Self::RESET_BOX,
+        Self::LOCK_AXIS_X,
+        Self::LOCK_AXIS_Y,
+        Self::LOCK_AXIS_Z,
+        Self::LOCK_LAYER,
+        Self::SET_DISPLAY_ORIGIN,
+        Self::CYCLE_TOOL,
+        Self::NEXT_TILE,
+        Self::PREV_TILE,
+        Self::TOGGLE_DIMENSIONS,
+        Self::NEXT_BOX,
+        Self::PREV_BOX,
+        Self::TOGGLE_MEASURE,
+        Self::EXPAND_SELECTION_FORWARD,
+        Self::EXPAND_SELECTION_BACK,
+        Self::EXPAND_SELECTION_LEFT,
+        Self::EXPAND_SELECTION_RIGHT,
+        Self::EXPAND_SELECTION_UP,
+        Self::EXPAND_SELECTION_DOWN,
+        Self::EXTRUDE_FORWARD,
+        Self::UNDO,
+        Self::REDO,
+        Self::CAMERA_ROTATE_LEFT,
+        Self::CAMERA_ROTATE_RIGHT,
+        Self::CAMERA_ROTATE_UP,
+        Self::CAMERA_ROTATE_DOWN,
+        Self::FOCUS_SELECTION,
+        Self::SAVE_BOOKMARK_1,
+        Self::SAVE_BOOKMARK_2,
+        Self::SAVE_BOOKMARK_3,
+        Self::GOTO_BOOKMARK_1,
+        Self::GOTO_BOOKMARK_2,
+        Self::GOTO_BOOKMARK_3,
+    ];
+
+    /// Looks up the Action for a logical action by its name, returning None if the editor doesn't use it
+    pub fn get(name: &str) -> Option<&'static Action> {
+        Self::ALL.iter().find(|action| action.name() == name)
+    }
+}
 
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct TypeTag(InputType);
 
+/// Resource set by the UI while something like a text field has keyboard focus. While it's on, every action reads as released, 
+/// so nothing fires while the user is typing and nothing is left held once focus is let go.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct InputCaptured(pub bool);
+
+/// Resource for reading the analog stick that moves the selection box
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GamepadSettings {
+    pub device: i64,
+    /// Godot joy axis indices for the horizontal and vertical axes of the stick
+    pub axes: (i64, i64),
+    /// How far the stick has to be pushed, between 0 and 1, before it counts as a movement
+    pub deadzone: f32,
+}
+
+impl Default for GamepadSettings {
+    fn default() -> Self {
+        GamepadSettings {
+            device: 0,
+            axes: (0, 1),
+            deadzone: 0.25,
+        }
+    }
+}
+
+impl GamepadSettings {
+
+    /// Slowest and fastest auto-repeat intervals for the stick, at the edge of the deadzone and at full deflection
+    const MAX_REPEAT_INTERVAL: f32 = 0.4;
+    const MIN_REPEAT_INTERVAL: f32 = 0.1;
+
+    /// Returns the stick's deflection with the deadzone cut out and the rest rescaled back to a magnitude between 0 and 1
+    pub fn movement_stick(&self) -> nalgebra::Vector2<f32> {
+        let inputs = Input::godot_singleton();
+
+        let stick = nalgebra::Vector2::new(
+            inputs.get_joy_axis(self.device, self.axes.0) as f32,
+            inputs.get_joy_axis(self.device, self.axes.1) as f32
+        );
+
+        let magnitude = stick.norm();
+
+        if magnitude <= self.deadzone || self.deadzone >= 1. {
+            return nalgebra::Vector2::zeros()
+        }
+
+        stick / magnitude * ((magnitude - self.deadzone) / (1. - self.deadzone)).min(1.)
+    }
+
+    /// How long to wait between steps for a stick pushed by the given magnitude
+    pub fn repeat_interval(magnitude: f32) -> f32 {
+        let magnitude = magnitude.max(0.).min(1.);
+        Self::MAX_REPEAT_INTERVAL + (Self::MIN_REPEAT_INTERVAL - Self::MAX_REPEAT_INTERVAL) * magnitude
+    }
+}
+
+
 ///Repeater incremenets by delta time each frame so that individual systems can arbitrarily control length of repeating as needed by checking against it.
 /// Also a good way of checking how long a button has been pressed.
 /// Strength is zero when action has just been released.
@@ -192,6 +439,10 @@ impl InputActionComponent {
     pub fn just_released(&self) -> bool {
         self.strength == 0.0
     }
+    /// How many seconds the action has been held down for
+    pub fn held_time(&self) -> f32 {
+        self.repeater
+    }
     pub fn repeated(&self, delta: f32, increment: f32) -> bool {
         self.repeater % increment < delta && self.strength > 0.0
     }
@@ -201,6 +452,7 @@ pub fn create_input_system() -> impl systems::Runnable {
 
     SystemBuilder::new("input_system")
         .read_resource::<crate::Time>()
+        .read_resource::<InputCaptured>()
         .with_query(<(Entity, Read<InputData>, Read<Action>)>::query() //input data that is a modifier
             .filter(component::<Modifier>())
         )
@@ -208,10 +460,12 @@ pub fn create_input_system() -> impl systems::Runnable {
             .filter(!component::<Modifier>())
         )
         .with_query(<(Entity, Write<InputActionComponent>, Read<Action>)>::query()) 
-        .build(|commands, world, time, queries| {
+        .build(|commands, world, (time, input_captured), queries| {
 
             let inputs = Input::godot_singleton();
 
+            let captured = input_captured.0;
+
             let mut already_pressed: HashSet<String> = HashSet::new();
 
             let mut delete_entities: Vec<Entity> = Vec::new();
@@ -233,10 +487,11 @@ pub fn create_input_system() -> impl systems::Runnable {
 
                 if let Some((entity, mut input_component, _)) = input_component_query.iter_mut(world).find(|(_, _, a)| *a == action) {
 
-                    let mut pressed = inputs.is_action_pressed(GodotString::from(action.0.clone()));
+                    //held actions get released as usual when input is captured, so that they don't get stuck
+                    let mut pressed = !captured && inputs.is_action_pressed(GodotString::from(action.name()));
 
                     pressed = match modifier {
-                        Some(_) if pressed => inputs.is_action_pressed(GodotString::from(format!("{}{}", action.0.clone(), MODIFIER_SUFFIX))),
+                        Some(_) if pressed => inputs.is_action_pressed(GodotString::from(format!("{}{}", action.name(), MODIFIER_SUFFIX))),
                         _ => pressed
                     };
 
@@ -248,7 +503,7 @@ pub fn create_input_system() -> impl systems::Runnable {
                             for (_, other_input, _) in non_modifiers.iter().filter(|(_,_,a)| a == other_action) {
                                 if other_input.code == input_data.code {
                                     pressed = {
-                                        if inputs.is_action_pressed(GodotString::from(format!("{}{}", other_action.0.clone(), MODIFIER_SUFFIX))) {
+                                        if inputs.is_action_pressed(GodotString::from(format!("{}{}", other_action.name(), MODIFIER_SUFFIX))) {
                                             false
                                         } else {
                                             pressed
@@ -266,10 +521,10 @@ pub fn create_input_system() -> impl systems::Runnable {
                         pressed  
                     };
 
-                    if pressed && !already_pressed.contains(&action.0) {
-                        already_pressed.insert(action.0.clone());
+                    if pressed && !already_pressed.contains(action.name()) {
+                        already_pressed.insert(action.name().to_string());
 
-                        input_component.strength = inputs.get_action_strength(GodotString::from(&action.0));
+                        input_component.strength = inputs.get_action_strength(GodotString::from(action.name()));
                         input_component.repeater += time.delta;
                     } else if input_component.strength < std::f32::EPSILON.into() { 
                         // If strength is already 0.0, then we've already passed on "on release" frame
@@ -295,11 +550,11 @@ pub fn create_input_system() -> impl systems::Runnable {
                 //check to see if this action has a modifier
                 let modifier_input = modifiers.iter().find(|(_,_,a)| a == action);
 
-                let mut pressed = inputs.is_action_pressed(GodotString::from(action.0.clone()));
+                let mut pressed = !captured && inputs.is_action_pressed(GodotString::from(action.name()));
 
                 //If there is a modifier configured, check that it is pressed, otherwise just return pressed
                 pressed = match modifier_input {
-                    Some(_) if pressed => inputs.is_action_pressed(GodotString::from(format!("{}{}", action.0.clone(), MODIFIER_SUFFIX))),
+                    Some(_) if pressed => inputs.is_action_pressed(GodotString::from(format!("{}{}", action.name(), MODIFIER_SUFFIX))),
                     _ => pressed
                 };
 
@@ -309,7 +564,7 @@ pub fn create_input_system() -> impl systems::Runnable {
                         
                         if let Some((_, _, _)) = non_modifiers.iter().find(|(_,input,a)| a == other_action && input.code == input_data.code) {
                             pressed = {
-                                if inputs.is_action_pressed(GodotString::from(format!("{}{}", other_action.0.clone(), MODIFIER_SUFFIX))) {
+                                if inputs.is_action_pressed(GodotString::from(format!("{}{}", other_action.name(), MODIFIER_SUFFIX))) {
                                     false
                                 } else {
                                     pressed
@@ -324,10 +579,10 @@ pub fn create_input_system() -> impl systems::Runnable {
                     pressed  
                 };
 
-                if !already_pressed.contains(&action.0) && pressed {
+                if !already_pressed.contains(action.name()) && pressed {
 
                     insert_data.push((action.clone(), InputActionComponent{ 
-                        strength: inputs.get_action_strength(&action.0), 
+                        strength: inputs.get_action_strength(action.name()), 
                         repeater: 0. 
                     }));
                 }
@@ -19,6 +19,13 @@ use serde::{Serialize, Deserialize};
 use std::collections::HashSet;
 
 type Octree = octree::Octree<i32, level_map::TileData>;
+type Point = nalgebra::Vector3<i32>;
+
+/// Saved documents start with these bytes, files without them are from before the header was added and are read as version 1
+pub const DOCUMENT_MAGIC: [u8; 4] = *b"WGDC";
+/// Bumped whenever the layout of the data following the header changes, older versions are migrated when they're loaded. Version 2 
+/// added the orientation to TileData.
+pub const DOCUMENT_VERSION: u8 = 2;
 
 pub struct ResetMap{}
 
@@ -85,9 +92,7 @@ impl Document {
                 (
                     MessageSender{
                         data_type: DataType::ActorChange {
-                            change: actor::ActorChange::ActorInsertion {
-                                serialized: actor_data.to_vec()
-                            },
+                            change: actor::ActorChange::insertion(actor_data.to_vec()),
                             store_history: None,
                         },
                         message_type: MessageType::Ordered,
@@ -104,6 +109,15 @@ impl Document {
         encoded
     }
 
+    /// Returns the serialized document preceded by DOCUMENT_MAGIC and DOCUMENT_VERSION, which is how documents are saved
+    pub fn to_versioned(&self) -> Vec<u8> {
+        let mut versioned = DOCUMENT_MAGIC.to_vec();
+        versioned.push(DOCUMENT_VERSION);
+        versioned.extend(self.to_raw());
+
+        versioned
+    }
+
     pub fn save(&self) {
 
         match self.file_path.clone() {
@@ -119,7 +133,7 @@ impl Document {
                 let file = File::new();
 
                 if file.open(GodotString::from(file_path), File::WRITE).is_ok() {
-                    let encoded = self.to_raw();
+                    let encoded = self.to_versioned();
 
                     let byte_array = vec_to_byte_array(encoded);
 
@@ -202,13 +216,75 @@ impl Document {
 
         let raw = Self::raw_from_file(file_path);
 
-        bincode::deserialize::<Self>(&raw)
+        Self::from_versioned(&raw)
         
     }
 
     pub fn from_raw(raw: &[u8]) -> Result<Self, Box<bincode::ErrorKind>> {
         bincode::deserialize::<Self>(raw)
     }
+
+    /// Reads a document written by to_versioned, or one without the header from before it was added
+    pub fn from_versioned(bytes: &[u8]) -> Result<Self, Box<bincode::ErrorKind>> {
+        let header_len = DOCUMENT_MAGIC.len();
+
+        if bytes.len() > header_len && bytes[..header_len] == DOCUMENT_MAGIC {
+            Self::from_raw_version(&bytes[header_len+1..], bytes[header_len])
+        } else {
+            Self::from_raw_version(bytes, 1)
+        }
+    }
+
+    /// Reads a document serialized with the layout of the given version, migrating it if it's older than DOCUMENT_VERSION
+    pub fn from_raw_version(raw: &[u8], version: u8) -> Result<Self, Box<bincode::ErrorKind>> {
+        match version {
+            1 => bincode::deserialize::<DocumentV1>(raw).map(Document::from),
+            DOCUMENT_VERSION => Self::from_raw(raw),
+            _ => Err(Box::new(bincode::ErrorKind::Custom(format!("Unsupported document version {}, expected {}", version, DOCUMENT_VERSION))))
+        }
+    }
+}
+
+/// Documents as they were saved before tiles had an orientation
+#[derive(Serialize, Deserialize)]
+struct DocumentV1 {
+    file_path: Option<String>,
+    title: String,
+    map_chunks: Vec<octree::Octree<i32, TileDataV1>>,
+    actor_data: Option<Vec<u8>>,
+}
+
+#[derive(Serialize, Deserialize, Eq, Hash, PartialEq, Copy, Clone, Debug)]
+struct TileDataV1 {
+    tile: u32,
+    point: Point,
+}
+
+impl octree::PointData<i32> for TileDataV1 {
+    fn get_point(&self) -> Point {
+        self.point
+    }
+}
+
+impl From<DocumentV1> for Document {
+    fn from(document: DocumentV1) -> Self {
+        Document {
+            file_path: document.file_path,
+            title: document.title,
+            map_chunks: document.map_chunks.into_iter()
+                .map(|chunk| {
+                    let mut octree = Octree::new(chunk.get_aabb(), octree::DEFAULT_MAX);
+
+                    chunk.into_iter().for_each(|tile| {
+                        octree.insert(level_map::TileData::new(tile.tile, tile.point)).ok();
+                    });
+
+                    octree
+                })
+                .collect(),
+            actor_data: document.actor_data,
+        }
+    }
 }
 
 /// Helper function to get a ByteArray for use in Godot's buffer and file classes
@@ -226,4 +302,50 @@ impl Default for Document {
     fn default() -> Self {
         Document::new(Option::<String>::None, "Untitled")
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type AABB = octree::geometry::aabb::AABB<i32>;
+
+    fn tiles_in(document: Document) -> Vec<level_map::TileData> {
+        document.map_chunks.into_iter()
+            .flat_map(|chunk| chunk.into_iter())
+            .collect()
+    }
+
+    #[test]
+    fn oriented_tiles_survive_saving_and_loading() {
+        let tile_data = level_map::TileData::new(3, Point::new(1, 0, -1)).with_orientation(level_map::TileOrientation::West);
+
+        let mut octree = Octree::new(AABB::new(Point::zeros(), Point::new(4, 4, 4)), octree::DEFAULT_MAX);
+        assert!(octree.insert(tile_data).is_ok());
+
+        let document = Document {
+            map_chunks: vec![octree],
+            ..Document::default()
+        };
+
+        let loaded = Document::from_versioned(&document.to_versioned()).unwrap();
+
+        assert_eq!(tiles_in(loaded), vec![tile_data]);
+    }
+
+    #[test]
+    fn documents_from_before_the_header_are_migrated() {
+        let mut octree = octree::Octree::<i32, TileDataV1>::new(AABB::new(Point::zeros(), Point::new(4, 4, 4)), octree::DEFAULT_MAX);
+        assert!(octree.insert(TileDataV1 { tile: 3, point: Point::new(1, 0, -1) }).is_ok());
+
+        let document = DocumentV1 {
+            file_path: None,
+            title: "Untitled".to_string(),
+            map_chunks: vec![octree],
+            actor_data: None,
+        };
+
+        let loaded = Document::from_versioned(&bincode::serialize(&document).unwrap()).unwrap();
+
+        assert_eq!(tiles_in(loaded), vec![level_map::TileData::new(3, Point::new(1, 0, -1))]);
+    }
+}
@@ -179,6 +179,7 @@ pub fn create_add_components_system() -> impl systems::Runnable {
 
                                 let point = tile.get_point();
                                 let tile_selection = tile.get_tile();
+                                let orientation = tile.get_orientation();
 
                                 let checked = {
                                     let mut checked_lock = checked.lock();
@@ -364,11 +365,13 @@ pub fn create_add_components_system() -> impl systems::Runnable {
                                             bottom_right
                                         ]);
 
+                                        let tile_offset = Vector2::new(tile_col_offset, tile_row_offset);
+
                                         vertex_data.uvs.extend(&[
-                                            Vector2::new(TILE_SIZE + tile_col_offset, TILE_SIZE + tile_row_offset),
-                                            Vector2::new(tile_col_offset, TILE_SIZE + tile_row_offset),
-                                            Vector2::new(tile_col_offset, tile_row_offset),
-                                            Vector2::new(TILE_SIZE + tile_col_offset, tile_row_offset)
+                                            orient_uv(Vector2::new(TILE_SIZE, TILE_SIZE), orientation) + tile_offset,
+                                            orient_uv(Vector2::new(0., TILE_SIZE), orientation) + tile_offset,
+                                            orient_uv(Vector2::new(0., 0.), orientation) + tile_offset,
+                                            orient_uv(Vector2::new(TILE_SIZE, 0.), orientation) + tile_offset
                                         ]);
 
                                         vertex_data.uv2s.extend(&[
@@ -489,7 +492,7 @@ pub fn create_add_components_system() -> impl systems::Runnable {
                                                             (
                                                                 right,
                                                                 Vector3::new(0., 1., 0.),
-                                                                Vector2::new(u + tile_col_offset, v + tile_row_offset),
+                                                                orient_uv(Vector2::new(u, v), orientation) + Vector2::new(tile_col_offset, tile_row_offset),
                                                                 Vector2::default(),
                                                                 offset + i as i32
                                                             ),
@@ -620,7 +623,7 @@ pub fn create_add_components_system() -> impl systems::Runnable {
                                                 (
                                                     scaled_right,
                                                     normal,
-                                                    Vector2::new(u + tile_col_offset, v + tile_row_offset),
+                                                    orient_uv(Vector2::new(u, v), orientation) + Vector2::new(tile_col_offset, tile_row_offset),
                                                     Vector2::default()
                                                 )
                                             )).ok();
@@ -958,6 +961,12 @@ fn get_true_top(pt: Point, map_datas: &[(Entity, MapChunkData, Point)], map_data
     true_top
 }
 
+/// Turns a uv within a single tile of the atlas about the middle of the tile, a quarter turn for each of the orientation's quarter 
+/// turns, so that the top of the tile faces the same way as the tile
+fn orient_uv(uv: Vector2, orientation: TileOrientation) -> Vector2 {
+    (0..orientation.quarter_turns()).fold(uv, |uv, _| Vector2::new(TILE_SIZE - uv.y, uv.x))
+}
+
 /// Expands the changed range to include positions on the border of the change, and gets the intersection with aabb to ensure it is within the bounds of the map data's aabb
 fn get_aabb_change_in_range(change: AABB, aabb: AABB) -> AABB {
     let expand_aabb = AABB::from_extents(change.get_min() - Point::new(1,1,1), change.get_max() + Point::new(1,1,1));
@@ -1551,4 +1560,22 @@ impl VertexData {
         self.indices.par_extend(other.indices.into_par_iter());
 
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orienting_uvs_turns_them_a_quarter_at_a_time() {
+        let uv = Vector2::new(0., 0.);
+
+        assert_eq!(orient_uv(uv, TileOrientation::North), uv);
+        assert_eq!(orient_uv(uv, TileOrientation::East), Vector2::new(TILE_SIZE, 0.));
+        assert_eq!(orient_uv(uv, TileOrientation::South), Vector2::new(TILE_SIZE, TILE_SIZE));
+        assert_eq!(orient_uv(uv, TileOrientation::West), Vector2::new(0., TILE_SIZE));
+
+        let uv = Vector2::new(TILE_SIZE * 0.25, TILE_SIZE * 0.5);
+        let turned = orient_uv(orient_uv(uv, TileOrientation::South), TileOrientation::South);
+        assert!((turned - uv).length() < 0.0001);
+    }
+}
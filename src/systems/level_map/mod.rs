@@ -17,7 +17,7 @@ use std::io::{Error, ErrorKind};
 use crate::{ 
     systems::{
         custom_mesh,
-        networking::{ClientID, ServerMessageSender, DataType, MessageType},
+        networking::{ClientID, MessageSender, ServerMessageSender, DataType, MessageType},
         history::{History, StepType},
     },
     networking::UdpSocket,
@@ -33,6 +33,12 @@ type Octree = octree::Octree<i32, TileData>;
 type Point = nalgebra::Vector3<i32>;
 type Vector3D = nalgebra::Vector3<f32>;
 
+/// Marks the start of every file written by export_map
+pub const MAP_EXPORT_MAGIC: [u8; 4] = *b"WGMP";
+/// Bumped whenever the layout of the data following the header changes, which is the layout of a Document. Older versions are migrated 
+/// when they're imported.
+pub const MAP_EXPORT_VERSION: u8 = document::DOCUMENT_VERSION;
+
 ///ChangeType stores the range of the changes so that we can determine whether or not adjacent MapChunks actually need to change, and
 /// the range of the original change for making comparisons
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -60,6 +66,16 @@ pub enum MapChange {
     MapRemoval(AABB),
 }
 
+impl MapChange {
+    /// The range that this change affects
+    pub fn get_aabb(&self) -> AABB {
+        match self {
+            MapChange::MapInsertion{ aabb, .. } => *aabb,
+            MapChange::MapRemoval(aabb) => *aabb,
+        }
+    }
+}
+
 pub struct TileDimensions {
     pub x: f32,
     pub y: f32,
@@ -101,18 +117,42 @@ pub fn map_coords_to_world(map_coord: Point) -> nalgebra::Vector3<f32> {
 #[derive(Copy, Clone)]
 pub struct Map {
     chunk_dimensions: Point,
+    /// The region that selection boxes and map edits are kept within, the map is unbounded if this is None
+    bounds: Option<AABB>,
 }
 
 impl Default for Map {
     fn default() -> Self {
         Map { 
-            chunk_dimensions: Point::new(10,10,10)
+            chunk_dimensions: Point::new(10,10,10),
+            bounds: None
         }
     }
 }
 
 impl Map {
 
+    pub fn bounds(&self) -> Option<AABB> {
+        self.bounds
+    }
+
+    pub fn set_bounds(&mut self, bounds: Option<AABB>) {
+        self.bounds = bounds;
+    }
+
+    /// Whether every cell of aabb is within the bounds, which is always the case when the map is unbounded
+    pub fn within_bounds(&self, aabb: AABB) -> bool {
+        match self.bounds {
+            Some(bounds) => {
+                let (min, max) = (aabb.get_min(), aabb.get_max());
+                let (bounds_min, bounds_max) = (bounds.get_min(), bounds.get_max());
+
+                (0..3).all(|i| min[i] >= bounds_min[i] && max[i] <= bounds_max[i])
+            },
+            None => true
+        }
+    }
+
     /// Executes changes to the world map in octree. Takes an optional u32 as a client_id for store_history
     pub fn change(&self, world: &mut legion::world::World, octree: Octree, store_history: Option<u32>) {
 
@@ -211,6 +251,309 @@ impl Map {
         
     }
 
+    /// Applies a batch of changes in order as a single change to the map, so that it only takes a single step in history. The changes 
+    /// are folded over the current state of the map into one octree which covers the range of all of them.
+    pub fn change_batch(&self, world: &mut legion::world::World, changes: &[MapChange], store_history: Option<u32>) {
+
+        let aabb = match changes.iter()
+            .map(|change| change.get_aabb())
+            .fold(None, |acc: Option<AABB>, aabb| Some(match acc {
+                Some(acc) => aabb_union(acc, aabb),
+                None => aabb
+            })) {
+                Some(aabb) => aabb,
+                None => return
+            };
+
+        let mut map_query = <(Entity, Read<MapChunkData>, Read<Point>)>::query();
+        let map_datas = map_query.iter(world)
+            .map(|(entity, map_data, pt)| (*entity, (*map_data).clone(), *pt))
+            .collect::<Vec<(Entity, MapChunkData, Point)>>();
+
+        let mut tiles = self.query_chunk_range(map_datas, aabb).into_iter()
+            .map(|tile_data| (tile_data.point, tile_data))
+            .collect::<HashMap<Point, TileData>>();
+
+        for change in changes {
+            match change {
+                MapChange::MapInsertion{ aabb, tile_data } => {
+                    fill_octree_from_aabb(*aabb, Some(*tile_data)).into_iter().for_each(|tile_data| {
+                        tiles.insert(tile_data.point, tile_data);
+                    });
+                },
+                MapChange::MapRemoval(aabb) => {
+                    let min = aabb.get_min();
+                    let max = aabb.get_max();
+                    tiles.retain(|pt, _| !(0..3).all(|i| pt[i] >= min[i] && pt[i] <= max[i]));
+                }
+            }
+        }
+
+        let mut octree = Octree::new(aabb, octree::DEFAULT_MAX);
+
+        tiles.into_iter().for_each(|(_, tile_data)| {
+            octree.insert(tile_data).ok();
+        });
+
+        self.change(world, octree, store_history);
+    }
+
+    /// Returns the height of the highest occupied cell at or below max_y within the x and z range of footprint, or None if that part
+    /// of the column is empty
+    pub fn top_in_column(&self, world: &World, footprint: AABB, max_y: i32) -> Option<i32> {
+
+        let (bottom, _) = self.height_range(world)?;
+
+        if bottom > max_y {
+            return None
+        }
+
+        let min = footprint.get_min();
+        let max = footprint.get_max();
+
+        let column = AABB::from_extents(
+            Point::new(min.x, bottom, min.z), 
+            Point::new(max.x, max_y, max.z)
+        );
+
+        self.tiles_in(world, column).into_iter()
+            .map(|tile_data| tile_data.point.y)
+            .max()
+    }
+
+    /// Returns the tile at the given point, or None if the cell is empty
+    pub fn tile_at(&self, world: &World, pt: Point) -> Option<TileData> {
+        self.tiles_in(world, AABB::from_extents(pt, pt)).into_iter()
+            .find(|tile_data| tile_data.point == pt)
+    }
+
+    /// Returns every tile within the cells that the aabb covers
+    pub fn tiles_in(&self, world: &World, aabb: AABB) -> Vec<TileData> {
+
+        let mut map_query = <(Entity, Read<MapChunkData>, Read<Point>)>::query();
+        let map_datas = map_query.iter(world)
+            .map(|(entity, map_data, pt)| (*entity, (*map_data).clone(), *pt))
+            .collect::<Vec<(Entity, MapChunkData, Point)>>();
+
+        let min = aabb.get_min();
+        let max = aabb.get_max();
+
+        self.query_chunk_range(map_datas, aabb).into_iter()
+            .filter(|tile_data| (0..3).all(|i| tile_data.point[i] >= min[i] && tile_data.point[i] <= max[i]))
+            .collect()
+    }
+
+    /// Returns the lowest and highest cell heights covered by the map chunks that currently exist, or None if there are none
+    pub fn height_range(&self, world: &World) -> Option<(i32, i32)> {
+        let mut chunk_query = <Read<Point>>::query().filter(component::<MapChunkData>());
+
+        let (bottom, top) = chunk_query.iter(world)
+            .fold(None, |acc: Option<(i32, i32)>, pt| Some(match acc {
+                Some((bottom, top)) => (std::cmp::min(bottom, pt.y), std::cmp::max(top, pt.y)),
+                None => (pt.y, pt.y)
+            }))?;
+
+        Some((bottom * self.chunk_dimensions.y, (top + 1) * self.chunk_dimensions.y - 1))
+    }
+
+    /// Returns the region covered by the map chunks that currently exist, or None if there are none
+    pub fn chunk_extent(&self, world: &World) -> Option<AABB> {
+        let mut chunk_query = <Read<Point>>::query().filter(component::<MapChunkData>());
+
+        let (min, max) = chunk_query.iter(world)
+            .fold(None, |acc: Option<(Point, Point)>, pt| Some(match acc {
+                Some((min, max)) => (min.inf(pt), max.sup(pt)),
+                None => (*pt, *pt)
+            }))?;
+
+        let min = min.component_mul(&self.chunk_dimensions);
+        let max = (max + Point::new(1, 1, 1)).component_mul(&self.chunk_dimensions) - Point::new(1, 1, 1);
+
+        Some(AABB::from_extents(min, max))
+    }
+
+    /// Returns the removals for everything outside of the aabb, up to the map's bounds or, for an unbounded map, up to the chunks 
+    /// that exist. The region outside is split into up to 6 slabs since a single AABB can't cover it.
+    pub fn remove_outside_changes(&self, world: &World, aabb: AABB) -> Vec<MapChange> {
+
+        let outer = match self.bounds.or_else(|| self.chunk_extent(world)) {
+            Some(outer) => outer,
+            None => return Vec::new()
+        };
+
+        complement_slabs(outer, aabb).into_iter()
+            .map(MapChange::MapRemoval)
+            .collect()
+    }
+
+    /// Returns the changes that level the x and z footprint of the aabb to its floor, removing everything above the floor and filling each 
+    /// column up to it with that column's top tile. Columns that are empty up to the floor only get a tile at the floor if fill_empty is 
+    /// given.
+    pub fn flatten_changes(&self, world: &World, aabb: AABB, fill_empty: Option<TileData>) -> Vec<MapChange> {
+
+        let min = aabb.get_min();
+        let max = aabb.get_max();
+        let floor = min.y;
+
+        let mut changes = Vec::new();
+
+        let tiles = match self.height_range(world) {
+            Some((bottom, top)) if bottom <= top => {
+
+                let tiles = self.tiles_in(world, AABB::from_extents(
+                    Point::new(min.x, bottom, min.z),
+                    Point::new(max.x, top, max.z)
+                ));
+
+                if let Some(highest) = tiles.iter().map(|tile_data| tile_data.point.y).max() {
+                    if highest > floor {
+                        changes.push(MapChange::MapRemoval(AABB::from_extents(
+                            Point::new(min.x, floor + 1, min.z),
+                            Point::new(max.x, highest, max.z)
+                        )));
+                    }
+                }
+
+                tiles
+            },
+            _ => Vec::new()
+        };
+
+        for x in min.x..=max.x {
+            for z in min.z..=max.z {
+
+                let column_top = tiles.iter()
+                    .filter(|tile_data| tile_data.point.x == x && tile_data.point.z == z && tile_data.point.y <= floor)
+                    .max_by_key(|tile_data| tile_data.point.y);
+
+                match column_top {
+                    Some(tile_data) if tile_data.point.y < floor => {
+                        changes.push(MapChange::MapInsertion{
+                            aabb: AABB::from_extents(Point::new(x, tile_data.point.y + 1, z), Point::new(x, floor, z)),
+                            tile_data: *tile_data
+                        });
+                    },
+                    Some(_) => {},
+                    None => if let Some(tile_data) = fill_empty {
+                        changes.push(MapChange::MapInsertion{
+                            aabb: AABB::from_extents(Point::new(x, floor, z), Point::new(x, floor, z)),
+                            tile_data
+                        });
+                    }
+                }
+            }
+        }
+
+        changes
+    }
+
+    /// Returns the changes for one application of the smoothing brush over the aabb. Each column's height is taken from its highest tile 
+    /// within the aabb and moved a single cell towards the average height of itself and its neighbours, as long as it's further than 
+    /// half a cell divided by strength away from it. All heights are compared against the state before the application so that 
+    /// repeated applications settle rather than oscillate.
+    pub fn smooth_changes(&self, world: &World, aabb: AABB, strength: f32) -> Vec<MapChange> {
+
+        if strength <= 0. {
+            return Vec::new()
+        }
+
+        let mut heights: HashMap<(i32, i32), TileData> = HashMap::new();
+
+        for tile_data in self.tiles_in(world, aabb) {
+            let column = (tile_data.point.x, tile_data.point.z);
+
+            match heights.get(&column) {
+                Some(top) if top.point.y >= tile_data.point.y => {},
+                _ => { heights.insert(column, tile_data); }
+            }
+        }
+
+        let max = aabb.get_max();
+
+        let threshold = 0.5 / strength;
+
+        let mut changes = Vec::new();
+
+        for ((x, z), top) in &heights {
+
+            let neighbourhood = (-1..=1)
+                .flat_map(|dx| (-1..=1).map(move |dz| (x + dx, z + dz)))
+                .filter_map(|column| heights.get(&column))
+                .map(|tile_data| tile_data.point.y as f32)
+                .collect::<Vec<f32>>();
+
+            let average = neighbourhood.iter().sum::<f32>() / neighbourhood.len() as f32;
+            let difference = average - top.point.y as f32;
+
+            if difference > threshold && top.point.y < max.y {
+                changes.push(MapChange::MapInsertion{
+                    aabb: AABB::from_extents(top.point + Point::y(), top.point + Point::y()),
+                    tile_data: *top
+                });
+            } else if difference < -threshold {
+                changes.push(MapChange::MapRemoval(AABB::from_extents(top.point, top.point)));
+            }
+        }
+
+        changes
+    }
+
+    /// Returns the changes that raise or lower the surface within the x and z footprint of the aabb by a single layer. Raising puts 
+    /// tile_data on top of each column's highest tile, or at the aabb's floor if the column is empty, and lowering removes each 
+    /// column's highest tile. The aabb's height is the range the surface is sculpted within, so columns that already reach the top 
+    /// of the aabb, or the top of the map's bounds, aren't raised. Columns whose top is more than a cell below its floor aren't raised 
+    /// either, so that nothing gets put beneath the aabb, and columns that are already below its floor aren't lowered.
+    pub fn sculpt_changes(&self, world: &World, aabb: AABB, raise: bool, tile_data: TileData) -> Vec<MapChange> {
+
+        let min = aabb.get_min();
+        let max = aabb.get_max();
+
+        let max_height = match self.bounds {
+            Some(bounds) => std::cmp::min(max.y, bounds.get_max().y),
+            None => max.y
+        };
+
+        let tiles = match self.height_range(world) {
+            Some((bottom, top)) if bottom <= top => self.tiles_in(world, AABB::from_extents(
+                Point::new(min.x, bottom, min.z),
+                Point::new(max.x, top, max.z)
+            )),
+            _ => Vec::new()
+        };
+
+        let mut heights: HashMap<(i32, i32), i32> = HashMap::new();
+
+        for tile in &tiles {
+            let height = heights.entry((tile.point.x, tile.point.z)).or_insert(tile.point.y);
+            *height = std::cmp::max(*height, tile.point.y);
+        }
+
+        let mut changes = Vec::new();
+
+        for x in min.x..=max.x {
+            for z in min.z..=max.z {
+
+                match (heights.get(&(x, z)), raise) {
+                    (top, true) => {
+                        //an empty column starts at the floor, and a column that's below the floor isn't built up to meet it
+                        let y = top.map(|top| top + 1).unwrap_or(min.y);
+
+                        if y >= min.y && y <= max_height {
+                            changes.push(MapChange::MapInsertion{
+                                aabb: AABB::from_extents(Point::new(x, y, z), Point::new(x, y, z)),
+                                tile_data
+                            });
+                        }
+                    },
+                    (Some(top), false) if *top >= min.y => changes.push(MapChange::MapRemoval(AABB::from_extents(Point::new(x, *top, z), Point::new(x, *top, z)))),
+                    _ => {}
+                }
+            }
+        }
+
+        changes
+    }
+
     /// Returns AABBs that are subdivided to fit into the constraints of the chunk dimensions, as well as the chunk pt they'd fit in
     pub fn range_sliced_to_chunks(&self, aabb: AABB) -> Vec<(Point, AABB)> {    
         let min = aabb.get_min();
@@ -399,10 +742,66 @@ impl MapChunkData {
     }
 }
 
+/// Which of the four cardinal directions a tile faces, for tiles like ramps that aren't the same from every side. North is the 
+/// tile's unrotated facing along positive z, and East, South and West are each a further quarter turn, facing negative x, negative z 
+/// and positive x respectively.
+#[derive(Serialize, Deserialize, Eq, Hash, PartialEq, Copy, Clone, Debug)]
+pub enum TileOrientation {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Default for TileOrientation {
+    fn default() -> Self {
+        TileOrientation::North
+    }
+}
+
+impl TileOrientation {
+
+    /// Returns the cardinal direction closest to the direction along the x and z plane
+    pub fn from_direction(direction: Vector3D) -> Self {
+        if direction.x.abs() > direction.z.abs() {
+            if direction.x > 0. { TileOrientation::West } else { TileOrientation::East }
+        } else if direction.z < 0. {
+            TileOrientation::South
+        } else {
+            TileOrientation::North
+        }
+    }
+
+    /// How many clockwise quarter turns about the y axis this is from North
+    pub fn quarter_turns(self) -> u8 {
+        match self {
+            TileOrientation::North => 0,
+            TileOrientation::East => 1,
+            TileOrientation::South => 2,
+            TileOrientation::West => 3,
+        }
+    }
+
+    /// Returns the orientation after turning this one by the given number of quarter turns
+    pub fn rotated(self, quarter_turns: u8) -> Self {
+        match (self.quarter_turns() + quarter_turns) % 4 {
+            0 => TileOrientation::North,
+            1 => TileOrientation::East,
+            2 => TileOrientation::South,
+            _ => TileOrientation::West,
+        }
+    }
+
+    pub fn rotation(self) -> nalgebra::Rotation3<f32> {
+        nalgebra::Rotation3::from_axis_angle(&Vector3D::y_axis(), -std::f32::consts::FRAC_PI_2 * self.quarter_turns() as f32)
+    }
+}
+
 #[derive(Serialize, Deserialize, Eq, Hash, PartialEq, Clone, Debug)]
 pub struct TileData {
     tile: u32,
-    point: Point
+    point: Point,
+    orientation: TileOrientation
 }
 
 impl Copy for TileData {}
@@ -411,13 +810,25 @@ impl TileData {
     pub fn new(tile: u32, point: Point) -> Self {
         TileData {
             tile,
-            point
+            point,
+            orientation: TileOrientation::default()
+        }
+    }
+
+    pub fn with_orientation(self, orientation: TileOrientation) -> Self {
+        TileData {
+            orientation,
+            ..self
         }
     }
 
     pub fn get_tile(&self) -> u32 {
         self.tile
     }
+
+    pub fn get_orientation(&self) -> TileOrientation {
+        self.orientation
+    }
 }
 
 impl octree::PointData<i32> for TileData {
@@ -458,6 +869,184 @@ pub fn fill_octree_from_aabb(aabb: AABB, tile_data: Option<TileData>) -> Octree
 
 }
 
+/// Bundles changes into a single MapChangeBatch message, so that tools can send edits made of several changes atomically and 
+/// have them undone as a single step. Returns None if there are no changes to send.
+pub fn batch_changes<I: IntoIterator<Item = MapChange>>(changes: I, store_history: Option<u32>) -> Option<MessageSender> {
+
+    let changes = changes.into_iter().collect::<Vec<MapChange>>();
+
+    if changes.is_empty() {
+        return None
+    }
+
+    Some(MessageSender {
+        data_type: DataType::MapChangeBatch {
+            changes,
+            store_history
+        },
+        message_type: MessageType::Ordered
+    })
+}
+
+/// Returns the smallest AABB that covers both a and b
+pub fn aabb_union(a: AABB, b: AABB) -> AABB {
+    AABB::from_extents(a.get_min().inf(&b.get_min()), a.get_max().sup(&b.get_max()))
+}
+
+/// Splits the cells of outer that aren't within inner into up to 6 non-overlapping slabs: one below and one above inner, then two on 
+/// either side of it along x and two along z, each spanning only as much as the slabs before it left over
+pub fn complement_slabs(outer: AABB, inner: AABB) -> Vec<AABB> {
+
+    if !aabbs_overlap(outer, inner) {
+        return vec![outer]
+    }
+
+    let o_min = outer.get_min();
+    let o_max = outer.get_max();
+
+    //only the part of inner that is within outer matters
+    let i_min = inner.get_min().sup(&o_min);
+    let i_max = inner.get_max().inf(&o_max);
+
+    vec![
+        (o_min, Point::new(o_max.x, i_min.y - 1, o_max.z)),
+        (Point::new(o_min.x, i_max.y + 1, o_min.z), o_max),
+        (Point::new(o_min.x, i_min.y, o_min.z), Point::new(i_min.x - 1, i_max.y, o_max.z)),
+        (Point::new(i_max.x + 1, i_min.y, o_min.z), Point::new(o_max.x, i_max.y, o_max.z)),
+        (Point::new(i_min.x, i_min.y, o_min.z), Point::new(i_max.x, i_max.y, i_min.z - 1)),
+        (Point::new(i_min.x, i_min.y, i_max.z + 1), Point::new(i_max.x, i_max.y, o_max.z)),
+    ].into_iter()
+        .filter(|(min, max)| (0..3).all(|i| max[i] >= min[i]))
+        .map(|(min, max)| AABB::from_extents(min, max))
+        .collect()
+}
+
+/// Whether or not the two AABBs share any of the cells that fill_octree_from_aabb would fill
+pub fn aabbs_overlap(a: AABB, b: AABB) -> bool {
+    let a_min = a.get_min();
+    let a_max = a.get_max();
+    let b_min = b.get_min();
+    let b_max = b.get_max();
+
+    (0..3).all(|i| a_min[i] <= b_max[i] && b_min[i] <= a_max[i])
+}
+
+/// Returns the axis-aligned AABB, about the same center, that encloses the aabb once it's been rotated. Unlike AABB::rotate, this 
+/// stays correct for rotations that aren't multiples of 90 degrees, by rotating all 8 corners and taking their extents. The signs 
+/// of the dimensions follow the rotated dimensions so that the direction the box extends in is kept.
+pub fn enclosing_rotated(aabb: AABB, rotation: nalgebra::Rotation3<f32>) -> AABB {
+    let half = aabb.dimensions.abs().map(|v| v as f32) / 2.;
+
+    let mut min = Vector3D::repeat(std::f32::MAX);
+    let mut max = Vector3D::repeat(std::f32::MIN);
+
+    for i in 0..8 {
+        let corner = rotation * Vector3D::new(
+            if i & 1 == 0 { -half.x } else { half.x },
+            if i & 2 == 0 { -half.y } else { half.y },
+            if i & 4 == 0 { -half.z } else { half.z },
+        );
+
+        min = min.inf(&corner);
+        max = max.sup(&corner);
+    }
+
+    let rotated_dimensions = rotation * aabb.dimensions.map(|v| v as f32);
+
+    //small epsilon so that floating point error on right angles doesn't add a whole extra cell
+    let extents = (max - min).map(|v| (v - 0.001).ceil().max(0.) as i32);
+
+    let dimensions = Point::new(
+        if rotated_dimensions.x < -0.001 { -extents.x } else { extents.x },
+        if rotated_dimensions.y < -0.001 { -extents.y } else { extents.y },
+        if rotated_dimensions.z < -0.001 { -extents.z } else { extents.z },
+    );
+
+    AABB::new(aabb.center, dimensions)
+}
+
+/// Rotation for the octree's AABB, which lives in another crate and so can't be given the method directly
+pub trait RotateAboutCenter {
+    /// Rotates the aabb in place around its own center, rather than around the origin like AABB::rotate does. The result is the 
+    /// axis-aligned box enclosing the rotated one, so the box never under-bounds what's inside it at free angles.
+    fn rotate_about_center(&mut self, rotation: nalgebra::Rotation3<f32>);
+}
+
+impl RotateAboutCenter for AABB {
+    fn rotate_about_center(&mut self, rotation: nalgebra::Rotation3<f32>) {
+        *self = enclosing_rotated(*self, rotation);
+    }
+}
+
+/// Splits the aabb into non-overlapping aabbs that cover only the cells on its outer faces
+pub fn shell_aabbs(aabb: AABB) -> Vec<AABB> {
+    let min = aabb.get_min();
+    let max = aabb.get_max();
+    let dimensions = aabb.dimensions.abs();
+
+    let mut aabbs = Vec::new();
+
+    //top and bottom span the whole x and z, the sides fit in between them
+    for y in boundary_cells(min.y, dimensions.y) {
+        aabbs.push(AABB::from_extents(Point::new(min.x, y, min.z), Point::new(max.x, y, max.z)));
+    }
+
+    if dimensions.y > 2 {
+        for z in boundary_cells(min.z, dimensions.z) {
+            aabbs.push(AABB::from_extents(Point::new(min.x, min.y + 1, z), Point::new(max.x, max.y - 1, z)));
+        }
+
+        if dimensions.z > 2 {
+            for x in boundary_cells(min.x, dimensions.x) {
+                aabbs.push(AABB::from_extents(Point::new(x, min.y + 1, min.z + 1), Point::new(x, max.y - 1, max.z - 1)));
+            }
+        }
+    }
+
+    aabbs
+}
+
+/// Splits the aabb into non-overlapping aabbs that cover only the cells along its 12 edges
+pub fn frame_aabbs(aabb: AABB) -> Vec<AABB> {
+    let min = aabb.get_min();
+    let max = aabb.get_max();
+    let dimensions = aabb.dimensions.abs();
+
+    let mut aabbs = Vec::new();
+
+    for y in boundary_cells(min.y, dimensions.y) {
+        //edges along x take the corners
+        for z in boundary_cells(min.z, dimensions.z) {
+            aabbs.push(AABB::from_extents(Point::new(min.x, y, z), Point::new(max.x, y, z)));
+        }
+
+        if dimensions.z > 2 {
+            for x in boundary_cells(min.x, dimensions.x) {
+                aabbs.push(AABB::from_extents(Point::new(x, y, min.z + 1), Point::new(x, y, max.z - 1)));
+            }
+        }
+    }
+
+    if dimensions.y > 2 {
+        for x in boundary_cells(min.x, dimensions.x) {
+            for z in boundary_cells(min.z, dimensions.z) {
+                aabbs.push(AABB::from_extents(Point::new(x, min.y + 1, z), Point::new(x, max.y - 1, z)));
+            }
+        }
+    }
+
+    aabbs
+}
+
+/// The first and last cell along an axis, or just the one if the axis is a single cell long
+fn boundary_cells(min: i32, length: i32) -> Vec<i32> {
+    if length > 1 {
+        vec![min, min + length - 1]
+    } else {
+        vec![min]
+    }
+}
+
 pub fn send_reset_message(world: &mut World) {
     let connections = <Write<Server<UdpSocket, BinaryRateLimiter, NoopPacketModifier>>>::query()
         .iter_mut(world).next()
@@ -489,4 +1078,148 @@ pub fn map_reset(world: &mut World, resources: &mut Resources) {
         map.free(world);
     }
 
-}
\ No newline at end of file
+}
+
+/// Serializes the whole map along with all of the actors into a standalone, versioned format for sharing builds. The data starts 
+/// with MAP_EXPORT_MAGIC and a MAP_EXPORT_VERSION byte, followed by a bincode serialized document.
+pub fn export_map(world: &mut World) -> Vec<u8> {
+    let mut doc = document::Document::default();
+    doc.update_data(world);
+
+    let mut exported = MAP_EXPORT_MAGIC.to_vec();
+    exported.push(MAP_EXPORT_VERSION);
+    exported.extend(doc.to_raw());
+
+    exported
+}
+
+/// Clears the current map and actors for everyone and rebuilds them from data written by export_map. Data that doesn't start with 
+/// the header, or was written by an unknown version, is rejected before anything is cleared. Data from older versions is migrated.
+pub fn import_map(world: &mut World, resources: &mut Resources, bytes: &[u8]) -> Result<(), Error> {
+
+    let header_len = MAP_EXPORT_MAGIC.len();
+
+    if bytes.len() <= header_len || bytes[..header_len] != MAP_EXPORT_MAGIC {
+        return Err(Error::new(ErrorKind::InvalidData, "Not an exported map"))
+    }
+
+    let version = bytes[header_len];
+
+    if version == 0 || version > MAP_EXPORT_VERSION {
+        return Err(Error::new(ErrorKind::InvalidData, format!("Unsupported exported map version {}, expected {}", version, MAP_EXPORT_VERSION)))
+    }
+
+    let doc = document::Document::from_raw_version(&bytes[header_len+1..], version)
+        .map_err(|err| Error::new(ErrorKind::InvalidData, format!("Couldn't read the exported map: {}", err)))?;
+
+    send_reset_message(world);
+    doc.populate_world(world, resources);
+
+    Ok(())
+}
+
+/// Builds the changes that raise terrain out of a grayscale PNG within the footprint of aabb. The image is resized to the footprint 
+/// and each pixel's luminance, multiplied by scale, becomes the height of a column of tile_data rising from the bottom of the box. 
+/// Heights are clamped to the box so nothing gets placed above it.
+pub fn import_heightmap(image_bytes: &[u8], scale: f32, tile_data: TileData, aabb: AABB) -> Result<Vec<MapChange>, Error> {
+
+    let image = gdnative::api::Image::new();
+
+    if image.load_png_from_buffer(document::vec_to_byte_array(image_bytes.to_vec())).is_err() {
+        return Err(Error::new(ErrorKind::InvalidData, "Couldn't decode the heightmap as a PNG"))
+    }
+
+    if image.is_empty() {
+        return Err(Error::new(ErrorKind::InvalidData, "The heightmap is empty"))
+    }
+
+    let min = aabb.get_min();
+    let dimensions = aabb.dimensions.abs();
+
+    image.resize(dimensions.x as i64, dimensions.z as i64, gdnative::api::Image::INTERPOLATE_BILINEAR);
+
+    image.lock();
+
+    let mut changes = Vec::new();
+
+    for x in 0..dimensions.x {
+        for z in 0..dimensions.z {
+            let color = image.get_pixel(x as i64, z as i64);
+            let luminance = 0.2126 * color.r + 0.7152 * color.g + 0.0722 * color.b;
+
+            let height = ((luminance * scale).round() as i32).max(0).min(dimensions.y);
+
+            if height > 0 {
+                changes.push(MapChange::MapInsertion{
+                    aabb: AABB::from_extents(Point::new(min.x + x, min.y, min.z + z), Point::new(min.x + x, min.y + height - 1, min.z + z)),
+                    tile_data
+                });
+            }
+        }
+    }
+
+    image.unlock();
+
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotating_an_off_center_box_keeps_its_center() {
+        let mut aabb = AABB::new(Point::new(5, 2, -7), Point::new(3, 1, 2));
+
+        aabb.rotate_about_center(nalgebra::Rotation3::from_axis_angle(&nalgebra::Vector3::y_axis(), std::f32::consts::FRAC_PI_2));
+
+        assert_eq!(aabb.center, Point::new(5, 2, -7));
+        assert_eq!(aabb.dimensions.abs(), Point::new(2, 1, 3));
+    }
+
+    #[test]
+    fn only_changes_inside_the_bounds_are_within_them() {
+        let mut map = Map::default();
+
+        let straddling = AABB::from_extents(Point::new(8, 0, 0), Point::new(12, 0, 0));
+        assert!(map.within_bounds(straddling));
+
+        map.set_bounds(Some(AABB::from_extents(Point::new(0, 0, 0), Point::new(9, 4, 9))));
+
+        assert!(!map.within_bounds(straddling));
+        assert!(!map.within_bounds(AABB::from_extents(Point::new(-1, 0, 0), Point::new(-1, 0, 0))));
+
+        //the max of the bounds is a cell inside of them
+        assert!(map.within_bounds(AABB::from_extents(Point::new(0, 0, 0), Point::new(9, 4, 9))));
+        assert!(map.within_bounds(AABB::from_extents(Point::new(9, 4, 9), Point::new(9, 4, 9))));
+    }
+
+    #[test]
+    fn enclosing_a_square_rotated_45_degrees_grows_to_its_diagonal() {
+        let aabb = AABB::new(Point::new(1, 0, 1), Point::new(2, 1, 2));
+
+        let rotated = enclosing_rotated(aabb, nalgebra::Rotation3::from_axis_angle(&nalgebra::Vector3::y_axis(), std::f32::consts::FRAC_PI_4));
+
+        //the diagonal is 2 * sqrt(2), about 2.83, which has to round up to whole cells so nothing pokes out
+        assert_eq!(rotated.center, aabb.center);
+        assert_eq!(rotated.dimensions.abs(), Point::new(3, 1, 3));
+    }
+
+    #[test]
+    fn enclosing_a_rectangle_rotated_45_degrees_covers_every_corner() {
+        let aabb = AABB::new(Point::zeros(), Point::new(4, 1, 2));
+        let rotation = nalgebra::Rotation3::from_axis_angle(&nalgebra::Vector3::y_axis(), std::f32::consts::FRAC_PI_4);
+
+        let rotated = enclosing_rotated(aabb, rotation);
+
+        //(4 + 2) / sqrt(2) is about 4.24 on both x and z
+        assert_eq!(rotated.dimensions.abs(), Point::new(5, 1, 5));
+
+        let half = rotated.dimensions.abs().map(|v| v as f32) / 2.;
+
+        for corner in &[Vector3D::new(2., 0., 1.), Vector3D::new(2., 0., -1.), Vector3D::new(-2., 0., 1.), Vector3D::new(-2., 0., -1.)] {
+            let corner = rotation * *corner;
+            assert!(corner.x.abs() <= half.x && corner.z.abs() <= half.z);
+        }
+    }
+}
@@ -4,7 +4,10 @@ pub mod character_animator;
 pub mod custom_mesh;
 pub mod history;
 pub mod level_map;
+pub mod prefab;
+pub mod replay;
 pub mod selection_box;
+pub mod shared_rng;
 pub mod smoothing;
 pub mod input;
 pub mod transform;
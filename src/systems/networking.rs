@@ -20,7 +20,7 @@ use cobalt::{
 };
 
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     net,
     net::SocketAddr,
     time::{
@@ -60,11 +60,20 @@ pub struct SetClientID {
     client_id: ClientID
 }
 
+/// Which of the transport's channels a message goes out on. The server relays every message on the channel it came in on.
+/// 
+/// Edits, history steps, syncs and anything else that has to be applied in the same order by every client are Ordered. Replies and 
+/// notices that stand on their own, like rejections and message fragments, are Reliable. Selection box movement in 
+/// UpdateSelectionBounds and SelectionPresence pings are Instant, so that they never wait behind a large ordered edit. Those carry 
+/// their own sequence or are resent often enough that a lost or late one doesn't matter.
 #[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 //Have to do this because cobalt::MessageKind doesn't implement serialize, deserialize. 
 pub enum MessageType {
+    /// Unreliable and unordered, any message can be lost or arrive after ones that were sent later
     Instant,
+    /// Always arrives, but in no particular order
     Reliable,
+    /// Always arrives, and in the order that it was sent along with every other Ordered message
     Ordered,
 }
 
@@ -142,6 +151,46 @@ impl OnClientConnected {
     }
 }
 
+/// Component added alongside OnClientConnected when a client rejoined in time to reclaim what was reserved for its previous id
+#[derive(Debug, Copy, Clone)]
+pub struct ReclaimClientID(u32);
+
+/// Component that the server pushes when a client sends RequestSelectionState, the inner value being the id of that client
+#[derive(Debug, Copy, Clone)]
+pub struct OnSelectionStateRequested(u32);
+
+/// Component that gets used to clear out the local state on the main thread after losing the connection, as the server will send 
+/// all of it again once the client has reconnected
+#[derive(Debug, Copy, Clone)]
+pub struct Resync{}
+
+/// How long the server holds on to a client's boxes and history after losing its connection, in case it reconnects
+const RECONNECT_RESERVATION: Duration = Duration::from_secs(60);
+
+/// How long a region claim lasts before the server releases it on the client's behalf
+const CLAIM_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Resource with how many seconds apart this client sends a Heartbeat to the server
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct HeartbeatInterval(pub f32);
+
+impl Default for HeartbeatInterval {
+    fn default() -> Self {
+        HeartbeatInterval(1.)
+    }
+}
+
+/// Resource with how many seconds the server waits to hear anything from a client before it lets the client go as if it had 
+/// disconnected, which covers clients that crashed or froze without their connection dropping
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct HeartbeatTimeout(pub f32);
+
+impl Default for HeartbeatTimeout {
+    fn default() -> Self {
+        HeartbeatTimeout(10.)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MessageFragment {
     //UUID of MessageFragment held collection
@@ -179,6 +228,11 @@ pub enum  DataType {
     ActivateActorToolBox {
         client_id: u32
     },
+    /// Switches the client's active tool over to the selection box in the given slot
+    SetActiveSlot {
+        client_id: u32,
+        slot: u8
+    },
     ActorToolSelection {
         client_id: u32,
         actor_id: i64,
@@ -198,6 +252,11 @@ pub enum  DataType {
         change: crate::systems::level_map::MapChange,
         store_history: Option<u32>
     },
+    /// Several changes to the map that are applied in order and stored as a single step in history
+    MapChangeBatch{
+        changes: Vec<crate::systems::level_map::MapChange>,
+        store_history: Option<u32>
+    },
     MapNew,
     HistoryStep{
         amount: i32,
@@ -207,8 +266,255 @@ pub enum  DataType {
     UpdateSelectionBounds{
         client_id: u32,
         coord_pos: Point,
+        aabb: AABB,
+        /// Increases with every update sent by the client, so that receivers can ignore updates that arrive out of order
+        sequence: u32,
+    },
+    /// Sent by every client each HeartbeatInterval so that the server knows it's still around. The server doesn't relay it.
+    Heartbeat{
+        client_id: u32,
+    },
+    /// Low frequency ping with where a client's active box is, so that the others can show a marker for it
+    SelectionPresence{
+        client_id: u32,
+        coord_pos: Point,
+    },
+    /// Moves the client's active selection box directly to coord_pos, for scripted control of where a client is looking
+    SetSelectionCoord{
+        client_id: u32,
+        coord_pos: Point,
+    },
+    /// Places copies of the actor from the palette at random cells within aabb. Every client makes the placements itself from the
+    /// SharedRng when handling this message so that they all end up with the same actors.
+    ScatterActors{
+        client_id: u32,
+        actor_id: i64,
+        aabb: AABB,
+        density: f32,
+        rotation_step: f32,
+    },
+    /// Sent by the server to a client when it connects, so that its SharedRng produces the same values as everyone else's
+    SessionSeed{
+        seed: u64
+    },
+    /// Sent when a client connects to let everyone know which color its selection boxes get tinted with, as rgba
+    ClientColorAssignment{
+        client_id: u32,
+        color: [f32; 4]
+    },
+    /// Sent by a client once it has connected to ask the server for everything it needs to build the current state of the session.
+    /// previous_client_id is set when the client is reconnecting after losing its connection.
+    RequestFullSync{
+        client_id: u32,
+        previous_client_id: Option<u32>
+    },
+    /// Lets everyone know that a client which lost its connection has rejoined with a new id, so whatever was reserved for the old 
+    /// id now belongs to the new one
+    ReassignClientID{
+        from: u32,
+        to: u32
+    },
+    /// Lets the server know whether a client is an editor or a spectator, so that it can reject edits from spectators
+    SetClientRole{
+        client_id: u32,
+        client_role: crate::editor::ClientRole
+    },
+    /// Sent by the server to a client whose map edit overlapped an edit from a client with a lower id in the same tick, and so was 
+    /// dropped rather than applied. aabb is the region of the dropped edit.
+    EditRejected{
+        client_id: u32,
+        aabb: AABB
+    },
+    /// Claims aabb for the client's exclusive editing, replacing any claim it already had. The server rejects claims that overlap 
+    /// another client's claim, as well as edits from other clients that overlap a claim.
+    ClaimRegion{
+        client_id: u32,
         aabb: AABB
     },
+    /// Gives up the client's claim, sent by the client itself or by the server once the claim has timed out or the client has left
+    ReleaseRegion{
+        client_id: u32
+    },
+    /// Sent by a client to ask the server for the selection boxes of everyone already in the session
+    RequestSelectionState{
+        client_id: u32
+    },
+    /// The server's answer to RequestSelectionState, with a snapshot of every client's boxes in their current slots
+    SelectionState{
+        client_id: u32,
+        snapshots: Vec<crate::systems::selection_box::SelectionBoxSnapshot>
+    },
+    /// Sets the region that everyone's selection boxes and map edits are kept within, or lets them go anywhere if bounds is None. 
+    /// The server rejects map edits that reach outside of the bounds.
+    SetMapBounds{
+        bounds: Option<AABB>
+    },
+}
+
+impl DataType {
+    /// The name of the variant, without any of its data
+    pub fn name(&self) -> &'static str {
+        match self {
+            DataType::NewConnection(_) => "NewConnection",
+            DataType::Disconnection(_) => "Disconnection",
+            DataType::MessageFragment(_) => "MessageFragment",
+            DataType::CreateSelectionBox{..} => "CreateSelectionBox",
+            DataType::CreateHistory{..} => "CreateHistory",
+            DataType::ActivateTerrainToolBox{..} => "ActivateTerrainToolBox",
+            DataType::ActivateActorToolBox{..} => "ActivateActorToolBox",
+            DataType::SetActiveSlot{..} => "SetActiveSlot",
+            DataType::ActorToolSelection{..} => "ActorToolSelection",
+            DataType::ActorToolRotation{..} => "ActorToolRotation",
+            DataType::ActorChange{..} => "ActorChange",
+            DataType::MapInput(_) => "MapInput",
+            DataType::MapChange{..} => "MapChange",
+            DataType::MapChangeBatch{..} => "MapChangeBatch",
+            DataType::MapNew => "MapNew",
+            DataType::HistoryStep{..} => "HistoryStep",
+            DataType::UpdateSelectionBounds{..} => "UpdateSelectionBounds",
+            DataType::Heartbeat{..} => "Heartbeat",
+            DataType::SelectionPresence{..} => "SelectionPresence",
+            DataType::SetSelectionCoord{..} => "SetSelectionCoord",
+            DataType::ScatterActors{..} => "ScatterActors",
+            DataType::SessionSeed{..} => "SessionSeed",
+            DataType::ClientColorAssignment{..} => "ClientColorAssignment",
+            DataType::RequestFullSync{..} => "RequestFullSync",
+            DataType::ReassignClientID{..} => "ReassignClientID",
+            DataType::SetClientRole{..} => "SetClientRole",
+            DataType::EditRejected{..} => "EditRejected",
+            DataType::ClaimRegion{..} => "ClaimRegion",
+            DataType::ReleaseRegion{..} => "ReleaseRegion",
+            DataType::RequestSelectionState{..} => "RequestSelectionState",
+            DataType::SelectionState{..} => "SelectionState",
+            DataType::SetMapBounds{..} => "SetMapBounds",
+        }
+    }
+
+    /// The regions of the map that this message changes, which the server checks against each other to find conflicting edits
+    pub fn edit_regions(&self) -> Vec<AABB> {
+        match self {
+            DataType::MapChange{ change, .. } => vec![change.get_aabb()],
+            DataType::MapChangeBatch{ changes, .. } => changes.iter().map(|change| change.get_aabb()).collect(),
+            DataType::ScatterActors{ aabb, .. } => vec![*aabb],
+            _ => Vec::new()
+        }
+    }
+
+    /// Whether or not this message makes changes to the document, which spectators aren't allowed to make
+    pub fn is_edit(&self) -> bool {
+        matches!(self, 
+            DataType::ActorChange{..} | 
+            DataType::MapInput(_) | 
+            DataType::MapChange{..} | 
+            DataType::MapChangeBatch{..} | 
+            DataType::MapNew | 
+            DataType::HistoryStep{..} |
+            DataType::SetMapBounds{..} |
+            DataType::ScatterActors{..}
+        )
+    }
+}
+
+/// How often the MessageMetrics summary is printed while MetricsEnabled is on
+pub const METRICS_SUMMARY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Resource which turns counting the messages sent by this client, and received and relayed by the server when hosting, on and off. 
+/// Nothing is counted while it's off.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct MetricsEnabled(pub bool);
+
+/// Where along the way from one client to the rest a message was counted by MessageMetrics
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum MessageStage {
+    /// Sent by this client
+    Sent,
+    /// Received by the server from a client
+    Received,
+    /// Relayed by the server to every client
+    Relayed,
+}
+
+impl MessageStage {
+    pub fn name(&self) -> &'static str {
+        match self {
+            MessageStage::Sent => "sent",
+            MessageStage::Received => "received",
+            MessageStage::Relayed => "relayed",
+        }
+    }
+}
+
+/// Resource with the number of messages counted since the last summary, bucketed by the stage they were counted at, the client that 
+/// sent them and the DataType variant
+#[derive(Debug, Clone, Default)]
+pub struct MessageMetrics {
+    counts: HashMap<(MessageStage, u32, &'static str), u64>
+}
+
+impl MessageMetrics {
+
+    pub fn record(&mut self, stage: MessageStage, client_id: u32, data_type: &DataType) {
+        *self.counts.entry((stage, client_id, data_type.name())).or_insert(0) += 1;
+    }
+
+    /// Returns the stage, client id, variant name and count of every bucket, busiest first
+    pub fn snapshot(&self) -> Vec<(MessageStage, u32, &'static str, u64)> {
+        let mut snapshot = self.counts.iter()
+            .map(|((stage, client_id, name), count)| (*stage, *client_id, *name, *count))
+            .collect::<Vec<(MessageStage, u32, &'static str, u64)>>();
+
+        snapshot.sort_by(|a, b| b.3.cmp(&a.3).then(a.0.cmp(&b.0)).then(a.1.cmp(&b.1)).then(a.2.cmp(b.2)));
+
+        snapshot
+    }
+
+    pub fn clear(&mut self) {
+        self.counts.clear();
+    }
+}
+
+/// Prints a summary of the MessageMetrics every METRICS_SUMMARY_INTERVAL while MetricsEnabled is on, and starts counting afresh
+pub fn create_message_metrics_system() -> impl systems::Runnable {
+    let mut last_summary = Instant::now();
+    let mut was_enabled = false;
+
+    SystemBuilder::new("message_metrics_system")
+        .read_resource::<MetricsEnabled>()
+        .write_resource::<MessageMetrics>()
+        .build(move |_, _, (metrics_enabled, message_metrics), _| {
+
+            if !metrics_enabled.0 {
+                was_enabled = false;
+                return
+            }
+
+            //the first summary covers the time since metrics were turned on, not since the system started
+            if !was_enabled {
+                was_enabled = true;
+                last_summary = Instant::now();
+                return
+            }
+
+            if last_summary.elapsed() < METRICS_SUMMARY_INTERVAL {
+                return
+            }
+
+            let elapsed = last_summary.elapsed().as_secs_f32();
+            last_summary = Instant::now();
+
+            let snapshot = message_metrics.snapshot();
+
+            if snapshot.is_empty() {
+                return
+            }
+
+            println!("[Metrics] Messages counted in the last {:.1}s:", elapsed);
+            for (stage, client_id, name, count) in snapshot {
+                println!("[Metrics]   {} from client {} {}: {} ({:.1}/s)", stage.name(), client_id, name, count, count as f32 / elapsed);
+            }
+
+            message_metrics.clear();
+        })
 }
 
 pub fn create_server_system() -> impl systems::ParallelRunnable {
@@ -216,22 +522,54 @@ pub fn create_server_system() -> impl systems::ParallelRunnable {
     let mut encoder = Encoder::new();
     let mut decoder = Decoder::new();
 
+    let mut spectators: HashSet<u32> = HashSet::new();
+    let mut reserved: HashMap<u32, Instant> = HashMap::new();
+
+    //map edits received this tick along with the client that sent them, held back until conflicts between them are resolved, and 
+    //whatever the same client sent after them, held back with them so that each client's messages are still relayed in order
+    let mut pending_edits: Vec<(u32, MessageSender)> = Vec::new();
+
+    //the region each client has claimed for exclusive editing, and when it was claimed
+    let mut claims: HashMap<u32, (AABB, Instant)> = HashMap::new();
+
+    //fragments of large messages that are still arriving, kept apart for each client
+    let mut message_fragments: HashMap<u32, HashMap<u128, Vec<MessageFragment>>> = HashMap::new();
+
+    //only used for its bounds, which follow the SetMapBounds messages that get relayed
+    let mut map = crate::systems::level_map::Map::default();
+
+    //when anything was last heard from each connected client, and the clients whose connections were closed for going quiet
+    let mut last_seen: HashMap<u32, Instant> = HashMap::new();
+    let mut timed_out: HashSet<u32> = HashSet::new();
+
     SystemBuilder::new("server_system")
+        .read_resource::<HeartbeatTimeout>()
+        .read_resource::<MetricsEnabled>()
+        .write_resource::<MessageMetrics>()
         .with_query(<(Entity, Write<Server<UdpSocket, BinaryRateLimiter, NoopPacketModifier>>)>::query())
         .with_query(<(Entity, Read<ServerMessageSender>)>::query())
-        .build(move |commands, world, _, queries| {
+        .with_query(<(Read<crate::systems::actor::ActorID>, Read<crate::systems::level_map::CoordPos>, Read<crate::systems::actor::Bounds>, 
+            Read<crate::systems::transform::rotation::Rotation>)>::query())
+        .build(move |commands, world, (heartbeat_timeout, metrics_enabled, message_metrics), queries| {
 
-            let (server_query, messages_query) = queries;
+            let (server_query, messages_query, actor_query) = queries;
 
             let messages = messages_query.iter(world)
                 .map(|(entity, message_sender)| (*entity, (*message_sender).clone()))
                 .collect::<Vec<(Entity, ServerMessageSender)>>();
 
+            //where every actor is, for finding out which regions actor changes touch
+            let actors = actor_query.iter(world)
+                .map(|(actor_id, coord_pos, bounds, rotation)| (actor_id.val(), (*bounds, bounds.region_at(coord_pos.value, rotation.value))))
+                .collect::<HashMap<u128, (crate::systems::actor::Bounds, AABB)>>();
+
             if let Some((entity, server)) = server_query.iter_mut(world).next() {
                 
                 while let Ok(event) = server.accept_receive() {
                     match event {
                         ServerEvent::Connection(id) => {
+                            last_seen.insert(id.0, Instant::now());
+
                             let conn = server.connection(&id).unwrap();
                             println!(
                                 "[Server] Client {} ({}, {}ms rtt) connected.",
@@ -240,13 +578,6 @@ pub fn create_server_system() -> impl systems::ParallelRunnable {
                                 conn.rtt()
                             );
 
-                            //create an entity that will call on_client_connected
-                            commands.push(
-                                (
-                                    OnClientConnected(id.0),
-                                )
-                            );
-    
                             //Let everyone know this client has connected
                             for conn in server.connections().values_mut() {
                                 conn.send(MessageKind::Reliable, encoder.compress_vec(
@@ -269,15 +600,200 @@ pub fn create_server_system() -> impl systems::ParallelRunnable {
     
                             let decompressed = decoder.decompress_vec(&message).unwrap();
                             let message: MessageSender = deserialize(&decompressed).unwrap();
-                            let payload = encoder.compress_vec(&serialize(&message).unwrap()).unwrap();
+
+                            last_seen.insert(id.0, Instant::now());
+
+                            if metrics_enabled.0 {
+                                message_metrics.record(MessageStage::Received, id.0, &message.data_type);
+                            }
+
+                            if let DataType::Heartbeat{ .. } = message.data_type {
+                                continue
+                            }
+
+                            if let DataType::SetClientRole{ client_id, client_role } = message.data_type {
+                                if client_id == id.0 {
+                                    if client_role.can_edit() {
+                                        spectators.remove(&client_id);
+                                    } else {
+                                        spectators.insert(client_id);
+                                    }
+                                }
+                            }
+
+                            //Answering needs the world, so leave that to the main thread
+                            if let DataType::RequestSelectionState{ .. } = message.data_type {
+                                commands.push((OnSelectionStateRequested(id.0),));
+
+                                continue
+                            }
+
+                            //Send the snapshot of the session through on_client_connected, reclaiming the previous id if it's still reserved
+                            if let DataType::RequestFullSync{ previous_client_id, .. } = message.data_type {
+
+                                match previous_client_id.filter(|previous| reserved.remove(previous).is_some()) {
+                                    Some(previous) => {
+                                        println!("[Server] Client {} reclaimed the reservation for client {}", id.0, previous);
+                                        commands.push((OnClientConnected(id.0), ReclaimClientID(previous)));
+                                    },
+                                    None => {
+                                        commands.push((OnClientConnected(id.0),));
+                                    }
+                                }
+
+                                continue
+                            }
+
+                            // Spectators have no business sending fragments, as those only happen for large edits, or claiming regions to edit
+                            if spectators.contains(&id.0) && (message.data_type.is_edit() 
+                                || matches!(message.data_type, DataType::MessageFragment(_) | DataType::ClaimRegion{ .. })
+                            ) {
+                                println!("[Server] Rejected an edit from spectator client {}", id.0);
+                                continue
+                            }
+
+                            //large edits arrive in fragments, so hold onto them until the whole message can be checked like any other
+                            let message = match message {
+                                MessageSender{ data_type: DataType::MessageFragment(fragment), .. } => match reassemble_fragments(fragment, &mut decoder, message_fragments.entry(id.0).or_default()) {
+                                    Some(Ok(payload)) => match deserialize::<MessageSender>(&payload) {
+                                        Ok(message) => message,
+                                        Err(_) => {
+                                            println!("[Server] Unable to reconstruct a message from client {}'s fragments", id.0);
+                                            continue
+                                        }
+                                    },
+                                    Some(Err(err)) => {
+                                        println!("[Server] Failed to decompress client {}'s fragments with error: {:?}", id.0, err);
+                                        continue
+                                    },
+                                    None => continue
+                                },
+                                message => message
+                            };
+
+                            if let DataType::ClaimRegion{ client_id, aabb } = message.data_type {
+
+                                let contested = claims.iter()
+                                    .any(|(other, (claimed, _))| *other != id.0 && crate::systems::level_map::aabbs_overlap(aabb, *claimed));
+
+                                if client_id != id.0 || contested {
+                                    println!("[Server] Rejected a claim from client {} that overlapped another client's claim", id.0);
+
+                                    conn.send(MessageKind::Reliable, encoder.compress_vec(
+                                        &bincode::serialize(&MessageSender{
+                                            data_type: DataType::EditRejected{ client_id: id.0, aabb },
+                                            message_type: MessageType::Reliable
+                                        }).unwrap()
+                                    ).unwrap());
+
+                                    continue
+                                }
+
+                                claims.insert(id.0, (aabb, Instant::now()));
+                            }
+
+                            if let DataType::ReleaseRegion{ client_id } = message.data_type {
+                                if client_id != id.0 {
+                                    continue
+                                }
+
+                                claims.remove(&id.0);
+                            }
+
+                            if let DataType::SetMapBounds{ bounds } = message.data_type {
+                                map.set_bounds(bounds);
+                            }
+
+                            //this runs on fragmented edits too, as they've been reassembled by now, so nothing reaches past the bounds
+                            let outside = message.data_type.edit_regions().into_iter()
+                                .filter(|region| !map.within_bounds(*region))
+                                .collect::<Vec<AABB>>();
+
+                            if !outside.is_empty() {
+                                println!("[Server] Rejected an edit from client {} that reached outside of the map bounds", id.0);
+
+                                for aabb in outside {
+                                    conn.send(MessageKind::Reliable, encoder.compress_vec(
+                                        &bincode::serialize(&MessageSender{
+                                            data_type: DataType::EditRejected{ client_id: id.0, aabb },
+                                            message_type: MessageType::Reliable
+                                        }).unwrap()
+                                    ).unwrap());
+                                }
+
+                                continue
+                            }
+
+                            //actor changes are checked against claims right away, map edits are checked along with the rest of the tick's
+                            if let DataType::ActorChange{ change, .. } = &message.data_type {
+
+                                let contested = change.regions(&actors).into_iter()
+                                    .filter(|region| claims.iter()
+                                        .any(|(other, (claimed, _))| *other != id.0 && crate::systems::level_map::aabbs_overlap(*region, *claimed))
+                                    )
+                                    .collect::<Vec<AABB>>();
+
+                                if !contested.is_empty() {
+                                    println!("[Server] Rejected an actor change from client {} that overlapped another client's claim", id.0);
+
+                                    for aabb in contested {
+                                        conn.send(MessageKind::Reliable, encoder.compress_vec(
+                                            &bincode::serialize(&MessageSender{
+                                                data_type: DataType::EditRejected{ client_id: id.0, aabb },
+                                                message_type: MessageType::Reliable
+                                            }).unwrap()
+                                        ).unwrap());
+                                    }
+
+                                    continue
+                                }
+                            }
+
+                            if !message.data_type.edit_regions().is_empty() || pending_edits.iter().any(|(sender, _)| *sender == id.0) {
+                                pending_edits.push((id.0, message));
+                                continue
+                            }
+
+                            if metrics_enabled.0 {
+                                message_metrics.record(MessageStage::Relayed, id.0, &message.data_type);
+                            }
+
+                            let config = server.config();
     
-                            // Send a message to all connected clients
+                            // Send a message to all connected clients, fragmenting it again if it came in fragments
                             for conn in server.connections().values_mut() {
-                                conn.send(message.message_type.as_kind(), payload.clone());
+                                message_send_helper(conn, &message, &config, &mut encoder);
                             }
     
                         },
-                        ServerEvent::ConnectionClosed(id, _) | ServerEvent::ConnectionLost(id, _) => {
+                        ServerEvent::ConnectionLost(id, _) => {
+                            last_seen.remove(&id.0);
+                            spectators.remove(&id.0);
+                            message_fragments.remove(&id.0);
+                            release_claim(server, &mut claims, &mut encoder, id.0);
+
+                            let conn = server.connection(&id).unwrap();
+                            println!(
+                                "[Server] Client {} ({}, {}ms rtt) lost connection, reserving its boxes and history.",
+                                id.0,
+                                conn.peer_addr(),
+                                conn.rtt()
+                            );
+
+                            //Hold off on letting everyone know about the disconnection in case the client reconnects
+                            reserved.insert(id.0, Instant::now());
+
+                            if server.connections().is_empty() {
+                                println!("[Server] Closing out server as there are no more connections");
+                                commands.remove(*entity);
+                            }
+                        },
+                        ServerEvent::ConnectionClosed(id, _) => {
+                            last_seen.remove(&id.0);
+                            spectators.remove(&id.0);
+                            message_fragments.remove(&id.0);
+                            release_claim(server, &mut claims, &mut encoder, id.0);
+
                             let conn = server.connection(&id).unwrap();
                             println!(
                                 "[Server] Client {} ({}, {}ms rtt) disconnected.",
@@ -285,15 +801,19 @@ pub fn create_server_system() -> impl systems::ParallelRunnable {
                                 conn.peer_addr(),
                                 conn.rtt()
                             );
-    
-                            // Let everyone know this client has disconnected
-                            for conn in server.connections().values_mut() {
-                                conn.send(MessageKind::Reliable, encoder.compress_vec(
-                                    &bincode::serialize(&MessageSender{
-                                        data_type: DataType::Disconnection(crate::systems::networking::Disconnection::new(id.0)),
-                                        message_type: MessageType::Reliable
-                                    }).unwrap()
-                                ).unwrap());
+
+                            //everyone already heard about clients that timed out when their connection was closed
+                            if !timed_out.remove(&id.0) {
+
+                                // Let everyone know this client has disconnected
+                                for conn in server.connections().values_mut() {
+                                    conn.send(MessageKind::Reliable, encoder.compress_vec(
+                                        &bincode::serialize(&MessageSender{
+                                            data_type: DataType::Disconnection(crate::systems::networking::Disconnection::new(id.0)),
+                                            message_type: MessageType::Reliable
+                                        }).unwrap()
+                                    ).unwrap());
+                                }
                             }
     
                             if server.connections().is_empty() {
@@ -322,6 +842,99 @@ pub fn create_server_system() -> impl systems::ParallelRunnable {
                     }
                 }
     
+                //Let go of clients that haven't been heard from within the HeartbeatTimeout, as if they had disconnected
+                let silent = last_seen.iter()
+                    .filter(|(_, seen_at)| seen_at.elapsed().as_secs_f32() > heartbeat_timeout.0)
+                    .map(|(id, _)| *id)
+                    .collect::<Vec<u32>>();
+
+                for id in silent {
+                    println!("[Server] Client {} timed out.", id);
+
+                    last_seen.remove(&id);
+                    spectators.remove(&id);
+                    release_claim(server, &mut claims, &mut encoder, id);
+
+                    if let Ok(conn) = server.connection(&cobalt::ConnectionID(id)) {
+                        conn.close();
+                        timed_out.insert(id);
+                    }
+
+                    for conn in server.connections().values_mut() {
+                        conn.send(MessageKind::Reliable, encoder.compress_vec(
+                            &bincode::serialize(&MessageSender{
+                                data_type: DataType::Disconnection(crate::systems::networking::Disconnection::new(id)),
+                                message_type: MessageType::Reliable
+                            }).unwrap()
+                        ).unwrap());
+                    }
+                }
+
+                //Let everyone know about the disconnection of clients that didn't make it back in time
+                let expired = reserved.iter()
+                    .filter(|(_, lost_at)| lost_at.elapsed() > RECONNECT_RESERVATION)
+                    .map(|(id, _)| *id)
+                    .collect::<Vec<u32>>();
+
+                for id in expired {
+                    reserved.remove(&id);
+
+                    for conn in server.connections().values_mut() {
+                        conn.send(MessageKind::Reliable, encoder.compress_vec(
+                            &bincode::serialize(&MessageSender{
+                                data_type: DataType::Disconnection(crate::systems::networking::Disconnection::new(id)),
+                                message_type: MessageType::Reliable
+                            }).unwrap()
+                        ).unwrap());
+                    }
+                }
+
+                //Let everyone know about claims that have run out
+                let expired_claims = claims.iter()
+                    .filter(|(_, (_, claimed_at))| claimed_at.elapsed() > CLAIM_TIMEOUT)
+                    .map(|(id, _)| *id)
+                    .collect::<Vec<u32>>();
+
+                for id in expired_claims {
+                    release_claim(server, &mut claims, &mut encoder, id);
+                }
+
+                let claimed = claims.iter()
+                    .map(|(id, (aabb, _))| (*id, *aabb))
+                    .collect::<Vec<(u32, AABB)>>();
+
+                //Relay this tick's map edits, and the messages held back behind them, now that they've all arrived, dropping any edits 
+                // that overlap an edit from a lower client id or another client's claim
+                for (sender, message, accepted) in resolve_edit_conflicts(pending_edits.drain(..).collect(), &claimed) {
+
+                    if accepted {
+                        if metrics_enabled.0 {
+                            message_metrics.record(MessageStage::Relayed, sender, &message.data_type);
+                        }
+
+                        let config = server.config();
+
+                        for conn in server.connections().values_mut() {
+                            message_send_helper(conn, &message, &config, &mut encoder);
+                        }
+
+                        continue
+                    }
+
+                    println!("[Server] Rejected an edit from client {} that conflicted with another client's edit", sender);
+
+                    if let Ok(conn) = server.connection(&cobalt::ConnectionID(sender)) {
+                        for aabb in message.data_type.edit_regions() {
+                            conn.send(MessageKind::Reliable, encoder.compress_vec(
+                                &bincode::serialize(&MessageSender{
+                                    data_type: DataType::EditRejected{ client_id: sender, aabb },
+                                    message_type: MessageType::Reliable
+                                }).unwrap()
+                            ).unwrap());
+                        }
+                    }
+                }
+
                 messages.into_iter().for_each(|(entity, message)| {
                     let id = message.client_id;
 
@@ -340,14 +953,72 @@ pub fn create_server_system() -> impl systems::ParallelRunnable {
         })
 }
 
+/// Decides which of the map edits received in a single server tick get applied. Edits are ordered by the id of the client that sent 
+/// them, with each client's edits kept in the order they arrived in, so that the outcome doesn't depend on network timing. An edit is 
+/// rejected if any of its regions overlap an edit already accepted from a different client this tick, meaning that the lower client id 
+/// always wins, and any edit overlapping another client's claim is rejected regardless. Edits that had to be fragmented are checked 
+/// once the server has reassembled them. Messages that aren't edits have no regions to conflict, so they're always accepted in their 
+/// place among the sender's edits.
+fn resolve_edit_conflicts(mut edits: Vec<(u32, MessageSender)>, claims: &[(u32, AABB)]) -> Vec<(u32, MessageSender, bool)> {
+
+    edits.sort_by_key(|(sender, _)| *sender);
+
+    //other clients' claims reject edits the same way that edits which were accepted first do
+    let mut accepted_regions: Vec<(u32, AABB)> = claims.to_vec();
+
+    edits.into_iter()
+        .map(|(sender, message)| {
+            let regions = message.data_type.edit_regions();
+
+            let conflicts = regions.iter().any(|region| accepted_regions.iter()
+                .any(|(other, accepted)| *other != sender && crate::systems::level_map::aabbs_overlap(*region, *accepted))
+            );
+
+            if !conflicts {
+                accepted_regions.extend(regions.into_iter().map(|region| (sender, region)));
+            }
+
+            (sender, message, !conflicts)
+        })
+        .collect()
+}
+
+/// Drops the client's claim, if it has one, and lets everyone know that it's been released
+fn release_claim(server: &mut Server<UdpSocket, BinaryRateLimiter, NoopPacketModifier>, claims: &mut HashMap<u32, (AABB, Instant)>, 
+    encoder: &mut Encoder, client_id: u32) {
+
+    if claims.remove(&client_id).is_none() {
+        return
+    }
+
+    for conn in server.connections().values_mut() {
+        conn.send(MessageKind::Reliable, encoder.compress_vec(
+            &bincode::serialize(&MessageSender{
+                data_type: DataType::ReleaseRegion{ client_id },
+                message_type: MessageType::Reliable
+            }).unwrap()
+        ).unwrap());
+    }
+}
+
 pub fn create_client_system() -> impl systems::ParallelRunnable {
     let mut encoder = Encoder::new();
     let mut decoder = Decoder::new();
 
+    //the id this client had before losing its connection, and the address to reconnect to
+    let mut previous_client_id: Option<u32> = None;
+    let mut reconnect_addr: Option<SocketAddr> = None;
+
+    let mut last_heartbeat = Instant::now();
+
     SystemBuilder::new("client_system")
+        .read_resource::<networking::Connection>()
+        .read_resource::<MetricsEnabled>()
+        .read_resource::<HeartbeatInterval>()
+        .write_resource::<MessageMetrics>()
         .with_query(<(Entity, Write<Client<UdpSocket, BinaryRateLimiter, NoopPacketModifier>>)>::query())
         .with_query(<(Entity, Read<MessageSender>)>::query())
-        .build(move |commands, world, _, queries| {
+        .build(move |commands, world, (connection, metrics_enabled, heartbeat_interval, message_metrics), queries| {
             
             let (client_query, messages_query) = queries;
 
@@ -377,6 +1048,32 @@ pub fn create_client_system() -> impl systems::ParallelRunnable {
                                 )
                             );
 
+                            commands.push(
+                                (
+                                    MessageSender{
+                                        data_type: DataType::RequestFullSync{
+                                            client_id: conn.id().0,
+                                            previous_client_id: previous_client_id.take()
+                                        },
+                                        message_type: MessageType::Ordered
+                                    },
+                                )
+                            );
+
+                            //ask for everyone's boxes as they are once the rest of the session has come through
+                            commands.push(
+                                (
+                                    MessageSender{
+                                        data_type: DataType::RequestSelectionState{
+                                            client_id: conn.id().0
+                                        },
+                                        message_type: MessageType::Ordered
+                                    },
+                                )
+                            );
+
+                            reconnect_addr = None;
+
                         },
                         ClientEvent::Message(message) => {
                             let conn = client.connection().unwrap();
@@ -394,7 +1091,28 @@ pub fn create_client_system() -> impl systems::ParallelRunnable {
                                 (data,)
                             );
                         },
-                        ClientEvent::ConnectionClosed(_) | ClientEvent::ConnectionLost(_) => {
+                        ClientEvent::ConnectionLost(_) => {
+                            let conn = client.connection().unwrap();
+                            println!(
+                                "[Client] ({}, {}ms rtt) lost connection, attempting to reconnect.",
+                                conn.peer_addr(),
+                                conn.rtt()
+                            );
+
+                            previous_client_id = Some(conn.id().0);
+                            reconnect_addr = Some(conn.peer_addr());
+
+                            //Everything gets sent again by the server once reconnected, so get rid of the stale state
+                            commands.push((Resync{},));
+
+                            reconnect(client, reconnect_addr, connection.get_scope());
+                        },
+                        ClientEvent::ConnectionFailed => {
+                            println!("[Client] Failed to connect.");
+
+                            reconnect(client, reconnect_addr, connection.get_scope());
+                        },
+                        ClientEvent::ConnectionClosed(_) => {
                             let conn = client.connection().unwrap();
                             println!(
                                 "[Client] ({}, {}ms rtt) disconnected.",
@@ -427,11 +1145,27 @@ pub fn create_client_system() -> impl systems::ParallelRunnable {
                 let config = client.config();
 
                 if let Ok(conn) = client.connection() {
+                    let client_id = conn.id().0;
+
                     messages.into_iter().for_each(|(entity, message)| {
+                        if metrics_enabled.0 {
+                            message_metrics.record(MessageStage::Sent, client_id, &message.data_type);
+                        }
+
                         message_send_helper(conn, &message, &config, &mut encoder);
 
                         commands.remove(entity);
-                    });                        
+                    });
+
+                    //let the server know this client is still around, even while it has nothing else to send
+                    if last_heartbeat.elapsed().as_secs_f32() >= heartbeat_interval.0 {
+                        last_heartbeat = Instant::now();
+
+                        message_send_helper(conn, &MessageSender{
+                            data_type: DataType::Heartbeat{ client_id },
+                            message_type: MessageType::Instant
+                        }, &config, &mut encoder);
+                    }
                 }
 
                 // Send all outgoing messages.
@@ -444,6 +1178,25 @@ pub fn create_client_system() -> impl systems::ParallelRunnable {
         })
 }
 
+/// Resets the client and connects it again to addr, if there is one
+fn reconnect(client: &mut Client<UdpSocket, BinaryRateLimiter, NoopPacketModifier>, addr: Option<SocketAddr>, scope: networking::Scope) {
+
+    if let Some(addr) = addr {
+        client.reset().ok();
+
+        match client.connect(addr) {
+            Ok(_) => {
+                if let networking::Scope::Multicast = scope {
+                    client.socket().unwrap().connect_multicast(networking::MULTICAST_ADDR_V4.parse::<SocketAddr>().unwrap()).ok();
+                }
+
+                println!("[Client] Reconnecting to {:?}...", addr);
+            },
+            Err(err) => println!("[Client] Couldn't reconnect to {:?}: {:?}", addr, err)
+        }
+    }
+}
+
 pub fn create_client_multicast_connection_system() -> impl systems::Runnable {
     let mut last_sent = Instant::now();
     let mut wait_for = Duration::from_millis(0);
@@ -579,15 +1332,33 @@ pub fn create_disconnection_thread_local_fn() -> Box<dyn FnMut(&mut World, &mut
 
 pub fn create_on_client_connection_thread_local_fn() -> Box<dyn FnMut(&mut World, &mut Resources)> {
 
-    let mut query = <(Entity, Read<OnClientConnected>)>::query();
+    let mut query = <(Entity, Read<OnClientConnected>, TryRead<ReclaimClientID>)>::query();
 
     Box::new(move |world, resources| {
 
         let results = query.iter(world)
-            .map(|(entity, on_connection)| (*entity, *on_connection))
-            .collect::<Vec<(Entity, OnClientConnected)>>();
-
-        results.into_iter().for_each(|(entity, on_connected)| {
+            .map(|(entity, on_connection, reclaim)| (*entity, *on_connection, reclaim.copied()))
+            .collect::<Vec<(Entity, OnClientConnected, Option<ReclaimClientID>)>>();
+
+        results.into_iter().for_each(|(entity, on_connected, reclaim)| {
+
+            //Apply the reassignment here first so that the snapshot sent by on_client_connected already reflects it, then let 
+            // everyone else know about it
+            if let Some(ReclaimClientID(previous)) = reclaim {
+                crate::editor::reassign_client(world, resources, previous, on_connected.0);
+
+                world.push(
+                    (
+                        MessageSender{
+                            data_type: DataType::ReassignClientID{
+                                from: previous,
+                                to: on_connected.0
+                            },
+                            message_type: MessageType::Ordered
+                        },
+                    )
+                );
+            }
 
             crate::STATE_MACHINE.with(|s| {
                 let state_machine = & *s.borrow();
@@ -603,6 +1374,65 @@ pub fn create_on_client_connection_thread_local_fn() -> Box<dyn FnMut(&mut World
     })
 }
 
+/// Answers RequestSelectionState by sending the client that asked a snapshot of every client's selection boxes
+pub fn create_selection_state_thread_local_fn() -> Box<dyn FnMut(&mut World, &mut Resources)> {
+
+    let mut query = <(Entity, Read<OnSelectionStateRequested>)>::query();
+
+    Box::new(move |world, _| {
+
+        let results = query.iter(world)
+            .map(|(entity, requested)| (*entity, *requested))
+            .collect::<Vec<(Entity, OnSelectionStateRequested)>>();
+
+        if results.is_empty() {
+            return
+        }
+
+        let snapshots = crate::systems::selection_box::selection_box_snapshots(world);
+
+        results.into_iter().for_each(|(entity, OnSelectionStateRequested(client_id))| {
+
+            world.push(
+                (
+                    ServerMessageSender {
+                        client_id,
+                        data_type: DataType::SelectionState {
+                            client_id,
+                            snapshots: snapshots.clone()
+                        },
+                        message_type: MessageType::Reliable
+                    },
+                )
+            );
+
+            world.remove(entity);
+        });
+    })
+}
+
+/// Clears out the state of the session when the connection is lost, as the server sends all of it again on reconnection. Input has
+/// nothing to act on until the snapshot has rebuilt this client's selection boxes.
+pub fn create_resync_thread_local_fn() -> Box<dyn FnMut(&mut World, &mut Resources)> {
+
+    let mut query = <(Entity, Read<Resync>)>::query();
+
+    Box::new(move |world, resources| {
+
+        let entities = query.iter(world)
+            .map(|(entity, _)| *entity)
+            .collect::<Vec<Entity>>();
+
+        if !entities.is_empty() {
+            crate::editor::free_for_resync(world, resources);
+        }
+
+        entities.into_iter().for_each(|entity| {
+            world.remove(entity);
+        });
+    })
+}
+
 fn client_handle_fragments(
     fragment: MessageFragment, 
     decoder: &mut Decoder, 
@@ -611,59 +1441,70 @@ fn client_handle_fragments(
     resources: &mut Resources
 ) {
 
-    match message_fragments.get_mut(&fragment.uuid) {
-        Some(frag_vec) => {
+    match reassemble_fragments(fragment, decoder, message_fragments) {
 
-            frag_vec.sort_by(|a, b| a.id.cmp(&b.id));
+        Some(Ok(payload)) => {
 
-            let MessageFragment {
-                size,
-                pieces,
-                uuid,
-                ..
-            } = fragment;
+            //if it is able to succesfully reconstruct the data, handle that data
+            match deserialize::<DataType>(&payload) {
+                Ok(data) => {
+                    println!("[Client] Succesfully reconstructed data from fragments");
+                    client_handle_data(data, world, resources);
+                },
+                Err(_) => println!("[Client] Unable to reconstruct data from fragments")
+            }
+        },
 
-            frag_vec.push(fragment.clone());
+        Some(Err(err)) => println!("[Client] Failed to decompress fragments' payload with error: {:?}", err),
 
-            //If we've received all of the pieces
-            if frag_vec.len() == pieces {
+        None => {}
+    }
+}
 
-                //reconstruct the fragmented data
-                let mut combined: Vec<u8> = Vec::with_capacity(size * frag_vec.len());
+/// Holds onto the fragment until all of its pieces have arrived, then returns the decompressed payload they make up
+fn reassemble_fragments(
+    fragment: MessageFragment, 
+    decoder: &mut Decoder, 
+    message_fragments: &mut HashMap<u128, Vec<MessageFragment>>
+) -> Option<Result<Vec<u8>, snap::Error>> {
 
-                for frag in frag_vec {
-                    combined.extend(frag.payload.iter());
-                }
+    let MessageFragment {
+        size,
+        pieces,
+        uuid,
+        ..
+    } = fragment;
 
-                //Once we're done, remove the key from message fragments
-                message_fragments.remove(&uuid);
+    let frag_vec = message_fragments.entry(uuid).or_default();
 
-                match decoder.decompress_vec(&combined) {
+    frag_vec.push(fragment);
 
-                    Ok(payload) => {
+    //If we haven't received all of the pieces yet
+    if frag_vec.len() < pieces {
+        return None
+    }
 
-                        //if it is able to succesfully reconstruct the data, handle that data
-                        match deserialize::<DataType>(&payload) {
-                            Ok(data) => {
-                                println!("[Client] Succesfully reconstructed data from fragments");
-                                client_handle_data(data, world, resources);
-                            },
-                            Err(_) => println!("[Client] Unable to reconstruct data from fragments")
-                        }
-                    },
+    frag_vec.sort_by(|a, b| a.id.cmp(&b.id));
 
-                    Err(err) => println!("[Client] Failed to decompress fragments' payload with error: {:?}", err)
-                }
+    //reconstruct the fragmented data
+    let mut combined: Vec<u8> = Vec::with_capacity(size * frag_vec.len());
 
-            }
-        },
-        None => {
-            message_fragments.insert(fragment.uuid, vec![fragment.clone()]);
-        }
+    for frag in frag_vec.iter() {
+        combined.extend(frag.payload.iter());
     }
+
+    //Once we're done, remove the key from message fragments
+    message_fragments.remove(&uuid);
+
+    Some(decoder.decompress_vec(&combined))
 }
 
-fn client_handle_data(data: DataType, world: &mut World, resources: &mut Resources) {
+pub(crate) fn client_handle_data(data: DataType, world: &mut World, resources: &mut Resources) {
+
+    if let Some(mut recording) = resources.get_mut::<crate::systems::replay::MessageRecording>() {
+        recording.record(&data);
+    }
+
     match data {
         DataType::ActorToolSelection { client_id, actor_id } => {
             use crate::{
@@ -680,7 +1521,9 @@ fn client_handle_data(data: DataType, world: &mut World, resources: &mut Resourc
                 if id.0 != client_id { //don't act on this client because this was already processed before being sent
                     
                     if let Some(selection_entity) = get_box_entity_by_client_id::<ActorToolBox>(world, ClientID(client_id)) {
-                        update_chosen_actor(world, selection_entity, actor_id);
+                        if let Err(err) = update_chosen_actor(world, selection_entity, actor_id) {
+                            println!("Couldn't choose actor {} for client {}: {}", actor_id, client_id, err);
+                        }
                     }
                 }
             }
@@ -725,6 +1568,10 @@ fn client_handle_data(data: DataType, world: &mut World, resources: &mut Resourc
 
             if let Some(map) = resources.get::<crate::systems::level_map::Map>().map(|map| *map) {
 
+                if !map.within_bounds(change.get_aabb()) {
+                    return
+                }
+
                 match change {
                     MapChange::MapInsertion { aabb, tile_data } => {
                         map.change(world, level_map::fill_octree_from_aabb(aabb, Some(tile_data)), store_history);
@@ -736,6 +1583,17 @@ fn client_handle_data(data: DataType, world: &mut World, resources: &mut Resourc
 
             }
         },
+        DataType::MapChangeBatch{ changes, store_history } => {
+            if let Some(map) = resources.get::<crate::systems::level_map::Map>().map(|map| *map) {
+
+                //the batch is applied as a whole or not at all
+                if !changes.iter().all(|change| map.within_bounds(change.get_aabb())) {
+                    return
+                }
+
+                map.change_batch(world, &changes, store_history);
+            }
+        },
         DataType::MapNew => {
             crate::systems::level_map::map_reset(world, resources);
             crate::systems::actor::free_all(world);
@@ -752,9 +1610,9 @@ fn client_handle_data(data: DataType, world: &mut World, resources: &mut Resourc
 
             commands.flush(world, resources);
         },
-        DataType::UpdateSelectionBounds{client_id: id, coord_pos, aabb} => {
+        DataType::UpdateSelectionBounds{client_id: id, coord_pos, aabb, sequence} => {
 
-            use crate::systems::selection_box::UpdateBounds;
+            use crate::systems::selection_box::BoundsSequence;
 
             //This may seem convoluded, but we only want messages to act on clients that were not the sender,
             // as their update was already handled at the time the message was sent to avoid any perceived input
@@ -762,70 +1620,68 @@ fn client_handle_data(data: DataType, world: &mut World, resources: &mut Resourc
             if let Some(client_id) = resources.get::<ClientID>() {
                 if id != client_id.0 {
 
-                    world.push((
-                        ClientID::new(id),
-                        UpdateBounds{
-                            coord_pos,
-                            aabb
+                    //ignore any update that is older than the last one that was applied for this client
+                    if let Some(mut bounds_sequence) = resources.get_mut::<BoundsSequence>() {
+                        if !bounds_sequence.accept(id, sequence) {
+                            return
                         }
-                    ));
+                    }
+
+                    crate::systems::selection_box::set_pending_update_bounds(world, ClientID::new(id), coord_pos, aabb);
 
                 }
             };
 
         },
-        DataType::CreateHistory{client_id, history} => {
-            world.push((
-                ClientID::new(client_id),
-                history
-            ));
+        DataType::SelectionPresence{client_id: id, coord_pos} => {
+
+            //the local client's own box is always visible to it
+            if let Some(client_id) = resources.get::<ClientID>().map(|client_id| *client_id) {
+                if client_id.val() != id {
+                    crate::systems::selection_box::update_presence(world, resources, ClientID::new(id), coord_pos);
+                }
+            }
         },
-        DataType::CreateSelectionBox{client_id: id, box_type, active, rotation, coord_pos, aabb} => {
+        DataType::SetSelectionCoord{client_id, coord_pos} => {
+            crate::systems::selection_box::set_selection_coord(world, ClientID::new(client_id), coord_pos);
+        },
+        DataType::CreateHistory{client_id, history} => {
 
-            use crate::{
-                systems::{
-                    selection_box::{
-                        ActorToolBox, TerrainToolBox,
-                        ToolBoxType, SelectionBox,
-                        set_active_selection_box,
-                        update_chosen_actor,
-                        actor_tool_rotation
-                    },
-                    level_map::CoordPos,
-                },
-            };
+            //replace the history if there already is one for this client so that a snapshot doesn't leave duplicates behind
+            let mut query = <(Write<crate::systems::history::History>, Read<ClientID>)>::query();
 
-            let entity = crate::systems::selection_box::initialize_selection_box(world, resources, id, box_type, None);
-            
-            if let Some(mut entry) = world.entry(entity) {
-                if let Ok(pos) = entry.get_component_mut::<CoordPos>() {
-                    pos.value = coord_pos;
-                }
-                if let Ok(selection_box) = entry.get_component_mut::<SelectionBox>() {
-                    selection_box.aabb = aabb;
-                }
+            let mut history = Some(history);
 
-                match box_type {
-                    ToolBoxType::TerrainToolBox => {
-                        if active {
-                            set_active_selection_box::<TerrainToolBox>(world, ClientID(id));
-                        }
-                    },
-                    ToolBoxType::ActorToolBox(actor_id) => {
+            if let Some((existing, _)) = query.iter_mut(world).find(|(_, id)| id.val() == client_id) {
+                *existing = history.take().unwrap();
+            }
 
-                        
-                        update_chosen_actor(world, entity, actor_id);
-                        
-                        actor_tool_rotation(world, entity, rotation);
+            if let Some(history) = history {
+                world.push((
+                    ClientID::new(client_id),
+                    history
+                ));
+            }
+        },
+        DataType::CreateSelectionBox{client_id: id, box_type, active, rotation, coord_pos, aabb} => {
 
-                        if active {
-                            set_active_selection_box::<ActorToolBox>(world, ClientID(id));
-                        }
-                    }
-                }
-                
+            crate::systems::selection_box::instantiate_snapshot(world, resources, crate::systems::selection_box::SelectionBoxSnapshot {
+                client_id: id,
+                box_type,
+                active,
+                coord_pos,
+                rotation,
+                aabb
+            });
+        },
+        DataType::SelectionState{client_id: id, snapshots} => {
+
+            //only build the boxes if this client is the one that asked for them
+            if resources.get::<ClientID>().map_or(false, |client_id| client_id.val() == id) {
+                snapshots.into_iter().for_each(|snapshot| {
+                    crate::systems::selection_box::instantiate_snapshot(world, resources, snapshot);
+                });
             }
-            
         },
         DataType::ActivateActorToolBox{client_id: id} => {
 
@@ -859,6 +1715,47 @@ fn client_handle_data(data: DataType, world: &mut World, resources: &mut Resourc
                 }
             }
         },
+        DataType::SetActiveSlot{client_id: id, slot} => {
+
+            //only set it if it wasn't sent from this client, since it was already handled when the message was sent
+            if let Some(client_id) = resources.get::<ClientID>().map(|client_id| *client_id) {
+                if client_id.val() != id {
+                    crate::systems::selection_box::set_active_tool_slot(world, resources, ClientID::new(id), slot);
+                }
+            }
+        },
+        DataType::ReassignClientID{ from, to } => {
+            crate::editor::reassign_client(world, resources, from, to);
+        },
+        DataType::ScatterActors{ client_id, actor_id, aabb, density, rotation_step } => {
+            if let Some(mut rng) = resources.get_mut::<crate::systems::shared_rng::SharedRng>() {
+                crate::systems::selection_box::scatter_actors(world, &mut rng, Some(client_id), actor_id, aabb, density, rotation_step);
+            }
+        },
+        DataType::SessionSeed{ seed } => {
+            resources.insert(crate::systems::shared_rng::SharedRng::new(seed));
+        },
+        DataType::ClaimRegion{ client_id, aabb } => {
+            crate::systems::selection_box::set_region_claim(world, resources, ClientID::new(client_id), aabb);
+        },
+        DataType::ReleaseRegion{ client_id } => {
+            crate::systems::selection_box::release_region_claim(world, ClientID::new(client_id));
+        },
+        DataType::SetMapBounds{ bounds } => {
+            if let Some(mut map) = resources.get_mut::<crate::systems::level_map::Map>() {
+                map.set_bounds(bounds);
+            }
+        },
+        DataType::EditRejected{ client_id, aabb } => {
+            if resources.get::<ClientID>().map(|local| local.val() == client_id).unwrap_or(false) {
+                world.push((crate::systems::selection_box::EditRejected{ aabb },));
+            }
+        },
+        DataType::ClientColorAssignment{ client_id, color } => {
+            if let Some(mut client_colors) = resources.get_mut::<crate::editor::ClientColors>() {
+                client_colors.insert(client_id, gdnative::core_types::Color::rgba(color[0], color[1], color[2], color[3]));
+            }
+        },
         DataType::NewConnection(r) => {
 
             world.push(
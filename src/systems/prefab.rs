@@ -0,0 +1,291 @@
+use gdnative::prelude::*;
+use gdnative::api::{
+    Directory,
+    File,
+};
+
+use legion::*;
+
+use serde::{Serialize, Deserialize};
+
+use crate::systems::{
+    level_map,
+    level_map::TileData,
+    networking::{ClientID, MessageSender},
+    selection_box,
+};
+
+use octree::{Octree, PointData};
+
+use std::{
+    collections::HashSet,
+    io::{Error, ErrorKind},
+};
+
+type Point = nalgebra::Vector3<i32>;
+type AABB = octree::geometry::aabb::AABB<i32>;
+
+const PREFAB_DIR: &str = "user://prefabs";
+const PREFAB_EXTENSION: &str = "prefab";
+
+/// Saved prefabs start with these bytes, files without them are from before the header was added and are read as version 0
+pub const PREFAB_MAGIC: [u8; 4] = *b"WGPF";
+/// Bumped whenever the layout of the data following the header changes, older versions are migrated when they're loaded. Version 2 
+/// added the orientation to TileData.
+pub const PREFAB_VERSION: u8 = 2;
+
+/// A named structure saved for stamping back into the map
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Prefab {
+    pub name: String,
+    /// Size of the box the tiles fit in, so that the selection box can be resized to preview it
+    pub dimensions: Point,
+    /// Tiles positioned relative to the prefab's minimum corner
+    pub tiles: Vec<(Point, TileData)>,
+}
+
+impl Prefab {
+
+    /// Returns a copy of the prefab turned about the y axis by the given number of quarter turns, the same way that turning a 
+    /// TileOrientation does, so that each tile's orientation turns along with it. Tiles are kept relative to the minimum corner, with 
+    /// the x and z dimensions swapping on odd turns.
+    pub fn rotated(&self, quarter_turns: u8) -> Prefab {
+
+        let mut prefab = self.clone();
+
+        for _ in 0..quarter_turns % 4 {
+            let depth = prefab.dimensions.z;
+
+            prefab.dimensions = Point::new(prefab.dimensions.z, prefab.dimensions.y, prefab.dimensions.x);
+
+            for (offset, tile_data) in prefab.tiles.iter_mut() {
+                //a quarter turn takes x and z to -z and x, which is shifted back over so that nothing ends up below the minimum
+                *offset = Point::new(depth - 1 - offset.z, offset.y, offset.x);
+                *tile_data = tile_data.with_orientation(tile_data.get_orientation().rotated(1));
+            }
+        }
+
+        prefab
+    }
+
+    /// Returns the batched insertions that place this prefab with its minimum corner at min, so that it's undone in a single step. 
+    /// Returns None when stamping it there wouldn't change any tiles, the same as filling the box with tiles that are already there.
+    pub fn stamp_at(&self, world: &mut World, map: &level_map::Map, min: Point, store_history: Option<u32>) -> Option<MessageSender> {
+
+        let aabb = AABB::from_extents(min, min + self.dimensions - Point::new(1,1,1));
+
+        //cells that the prefab leaves empty keep the tiles already in them, so those are carried over into what the map is checked against
+        let covered = self.tiles.iter()
+            .map(|(offset, _)| min + offset)
+            .collect::<HashSet<Point>>();
+
+        let mut stamped = Octree::new(aabb, octree::DEFAULT_MAX);
+
+        for tile_data in map.tiles_in(world, aabb).into_iter().filter(|tile_data| !covered.contains(&tile_data.get_point())) {
+            stamped.insert(tile_data).ok();
+        }
+
+        for (offset, tile_data) in &self.tiles {
+            stamped.insert(TileData::new(tile_data.get_tile(), min + offset).with_orientation(tile_data.get_orientation())).ok();
+        }
+
+        if map.can_change(world, &stamped).is_err() {
+            return None
+        }
+
+        level_map::batch_changes(
+            self.tiles.iter().map(|(offset, tile_data)| level_map::MapChange::MapInsertion{
+                aabb: AABB::from_extents(min + offset, min + offset),
+                tile_data: TileData::new(tile_data.get_tile(), Point::zeros()).with_orientation(tile_data.get_orientation())
+            }),
+            store_history
+        )
+    }
+}
+
+/// Resource holding the prefab that the tile tool stamps on insertion instead of filling the box, if any
+#[derive(Clone, Debug, Default)]
+pub struct SelectedPrefab(pub Option<Prefab>);
+
+/// Saves the tiles as a prefab called name in user://prefabs. The tiles are moved so that the prefab starts at the origin. An 
+/// existing prefab with the same name is only overwritten when force is true.
+pub fn save_prefab(name: &str, tiles: Vec<(Point, TileData)>, force: bool) -> Result<(), Error> {
+
+    validate_name(name)?;
+
+    if tiles.is_empty() {
+        return Err(Error::new(ErrorKind::InvalidInput, "There are no tiles to save in the prefab"))
+    }
+
+    let path = prefab_path(name);
+
+    let directory = Directory::new();
+
+    if directory.file_exists(path.as_str()) && !force {
+        return Err(Error::new(ErrorKind::AlreadyExists, format!("A prefab called {} already exists", name)))
+    }
+
+    if !directory.dir_exists(PREFAB_DIR) && directory.make_dir_recursive(PREFAB_DIR).is_err() {
+        return Err(Error::new(ErrorKind::Other, format!("Couldn't create {}", PREFAB_DIR)))
+    }
+
+    let min = tiles.iter().fold(tiles[0].0, |min, (pt, _)| min.inf(pt));
+    let max = tiles.iter().fold(tiles[0].0, |max, (pt, _)| max.sup(pt));
+
+    let prefab = Prefab {
+        name: name.to_string(),
+        dimensions: max - min + Point::new(1,1,1),
+        tiles: tiles.into_iter().map(|(pt, tile_data)| (pt - min, tile_data)).collect(),
+    };
+
+    let encoded = bincode::serialize(&prefab)
+        .map_err(|err| Error::new(ErrorKind::InvalidData, format!("Couldn't serialize prefab {}: {}", name, err)))?;
+
+    let file = File::new();
+
+    if file.open(path, File::WRITE).is_err() {
+        return Err(Error::new(ErrorKind::Other, format!("Couldn't open prefab {} for writing", name)))
+    }
+
+    let mut bytes = PREFAB_MAGIC.to_vec();
+    bytes.push(PREFAB_VERSION);
+    bytes.extend(encoded);
+
+    file.store_buffer(level_map::document::vec_to_byte_array(bytes));
+    file.close();
+
+    Ok(())
+}
+
+/// Returns the names of every saved prefab
+pub fn list_prefabs() -> Vec<String> {
+
+    let directory = Directory::new();
+
+    let mut names = Vec::new();
+
+    if directory.open(PREFAB_DIR).is_err() || directory.list_dir_begin(true, true).is_err() {
+        return names
+    }
+
+    loop {
+        let file_name = directory.get_next().to_string();
+
+        if file_name.is_empty() {
+            break
+        }
+
+        if !directory.current_is_dir() {
+            if let Some(name) = file_name.strip_suffix(&format!(".{}", PREFAB_EXTENSION)) {
+                names.push(name.to_string());
+            }
+        }
+    }
+
+    directory.list_dir_end();
+
+    names.sort();
+    names
+}
+
+pub fn load_prefab(name: &str) -> Result<Prefab, Error> {
+
+    validate_name(name)?;
+
+    let file = File::new();
+
+    if file.open(prefab_path(name), File::READ).is_err() {
+        return Err(Error::new(ErrorKind::NotFound, format!("There is no prefab called {}", name)))
+    }
+
+    let raw = file.get_buffer(file.get_len()).read().to_vec();
+    file.close();
+
+    let header_len = PREFAB_MAGIC.len();
+
+    let (version, encoded) = if raw.len() > header_len && raw[..header_len] == PREFAB_MAGIC {
+        (raw[header_len], &raw[header_len+1..])
+    } else {
+        (0, &raw[..])
+    };
+
+    let read_err = |err: bincode::Error| Error::new(ErrorKind::InvalidData, format!("Couldn't read prefab {}: {}", name, err));
+
+    match version {
+        // version 0 only lacked the header, the prefab itself is laid out the same as version 1
+        0 | 1 => bincode::deserialize::<PrefabV1>(encoded)
+            .map(Prefab::from)
+            .map_err(read_err),
+        PREFAB_VERSION => bincode::deserialize::<Prefab>(encoded)
+            .map_err(read_err),
+        _ => Err(Error::new(ErrorKind::InvalidData, format!("Prefab {} has unsupported version {}, expected {}", name, version, PREFAB_VERSION)))
+    }
+}
+
+/// Prefabs as they were saved before tiles had an orientation
+#[derive(Deserialize)]
+struct PrefabV1 {
+    name: String,
+    dimensions: Point,
+    tiles: Vec<(Point, TileV1)>,
+}
+
+#[derive(Deserialize)]
+struct TileV1 {
+    tile: u32,
+    point: Point,
+}
+
+impl From<PrefabV1> for Prefab {
+    fn from(prefab: PrefabV1) -> Self {
+        Prefab {
+            name: prefab.name,
+            dimensions: prefab.dimensions,
+            tiles: prefab.tiles.into_iter()
+                .map(|(offset, tile)| (offset, TileData::new(tile.tile, tile.point)))
+                .collect(),
+        }
+    }
+}
+
+/// Saves the tiles within the client's active terrain box as a stamp, which is a prefab called name that can be stamped back 
+/// down with load_stamp
+pub fn save_stamp(world: &World, map: level_map::Map, client_id: ClientID, name: &str, force: bool) -> Result<(), Error> {
+
+    let mut query = <(Read<level_map::CoordPos>, Read<selection_box::SelectionBox>, Read<ClientID>)>::query()
+        .filter(component::<selection_box::TerrainToolBox>() & component::<selection_box::Active>());
+
+    let aabb = query.iter(world)
+        .find(|(_, _, id)| **id == client_id)
+        .map(|(coord_pos, selection_box, _)| AABB::new(coord_pos.value, selection_box.aabb.dimensions))
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, "Client doesn't have an active terrain tool box"))?;
+
+    let tiles = map.tiles_in(world, aabb).into_iter()
+        .map(|tile_data| (octree::PointData::get_point(&tile_data), tile_data))
+        .collect();
+
+    save_prefab(name, tiles, force)
+}
+
+/// Loads the named stamp into SelectedPrefab so that the tile tool stamps it on insertion
+pub fn load_stamp(resources: &mut Resources, name: &str) -> Result<(), Error> {
+
+    let prefab = load_prefab(name)?;
+
+    resources.insert(SelectedPrefab(Some(prefab)));
+
+    Ok(())
+}
+
+fn prefab_path(name: &str) -> String {
+    format!("{}/{}.{}", PREFAB_DIR, name, PREFAB_EXTENSION)
+}
+
+/// Names end up as file names, so they can't be empty or reach outside of the prefabs directory
+fn validate_name(name: &str) -> Result<(), Error> {
+    if name.trim().is_empty() || name.contains('/') || name.contains('\\') || name.contains("..") {
+        return Err(Error::new(ErrorKind::InvalidInput, format!("{:?} isn't a valid prefab name", name)))
+    }
+
+    Ok(())
+}
@@ -0,0 +1,241 @@
+//! Recording of the messages that this client applies to its map and actors, so that a session can be replayed from the message log.
+//!
+//! Every edit reaches every client as a message, and clients are meant to end up with identical maps by applying the same messages in
+//! the same order. Recording captures that ordered stream along with a snapshot of the state it started from, and replaying applies it
+//! to a fresh World and compares digests of the result, which is a way to check that tool edits are reproducible from the log alone.
+
+use gdnative::prelude::*;
+use gdnative::api::File;
+use legion::*;
+use octree::PointData;
+use serde::{Serialize, Deserialize};
+
+use std::io::{Error, ErrorKind};
+
+use crate::systems::{
+    actor,
+    history::History,
+    level_map,
+    level_map::document,
+    networking::{self, ClientID, DataType},
+    shared_rng::SharedRng,
+    transform::rotation::Rotation,
+};
+
+/// Resource which collects the messages handled by this client while recording is on
+#[derive(Debug, Default)]
+pub struct MessageRecording {
+    recording: bool,
+    messages: Vec<DataType>,
+}
+
+impl MessageRecording {
+
+    /// Starts a new recording, beginning with messages that rebuild the current map, actors, every client's history and the shared 
+    /// rng so that the recording doesn't depend on anything that happened before it
+    pub fn start(&mut self, world: &mut World, resources: &Resources) {
+        self.recording = true;
+        self.messages.clear();
+
+        if let Some(shared_rng) = resources.get::<SharedRng>() {
+            self.messages.push(DataType::SessionSeed{ seed: shared_rng.state() });
+        }
+
+        let mut query = <Read<level_map::MapChunkData>>::query();
+
+        self.messages.extend(query.iter(world).map(|map_data| DataType::MapInput(map_data.octree.clone())));
+
+        if let Ok(serialized) = actor::serialize_actors_in_world(world) {
+            self.messages.push(DataType::ActorChange{
+                change: actor::ActorChange::insertion(serialized),
+                store_history: None
+            });
+        }
+
+        //undoing and redoing during the recording works through the histories, so they have to start out the same on replay
+        let mut history_query = <(Read<History>, Read<ClientID>)>::query();
+
+        self.messages.extend(history_query.iter(world).map(|(history, client_id)| DataType::CreateHistory{
+            client_id: client_id.val(),
+            history: history.clone()
+        }));
+    }
+
+    /// Stops recording and returns everything that was recorded
+    pub fn stop(&mut self) -> Vec<DataType> {
+        self.recording = false;
+        std::mem::take(&mut self.messages)
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    /// Adds the message to the recording if recording is on and the message is one that gets replayed
+    pub fn record(&mut self, data: &DataType) {
+        if self.recording && replays(data) {
+            self.messages.push(data.clone());
+        }
+    }
+}
+
+/// Whether or not the message is one that replaying applies. Only messages that change the map, the actors or the shared rng that
+/// edits draw from matter to the outcome, the rest are about selection boxes and connections.
+pub fn replays(data: &DataType) -> bool {
+    data.is_edit() || matches!(data,
+        DataType::CreateHistory{..} |
+        DataType::ScatterActors{..} |
+        DataType::SessionSeed{..}
+    )
+}
+
+/// A recorded stream of messages along with the digest of the state that the client was in when the recording was stopped
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Recording {
+    pub messages: Vec<DataType>,
+    pub digest: u64,
+}
+
+impl Recording {
+
+    pub fn save(&self, path: &str) -> Result<(), Error> {
+        let encoded = bincode::serialize(self).map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
+
+        let file = File::new();
+
+        file.open(GodotString::from(path), File::WRITE)
+            .map_err(|err| Error::new(ErrorKind::Other, format!("Couldn't open {}: {:?}", path, err)))?;
+
+        file.store_buffer(document::vec_to_byte_array(encoded));
+        file.close();
+
+        Ok(())
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, Error> {
+        let file = File::new();
+
+        file.open(GodotString::from(path), File::READ)
+            .map_err(|err| Error::new(ErrorKind::NotFound, format!("Couldn't open {}: {:?}", path, err)))?;
+
+        let byte_array = file.get_buffer(file.get_len());
+        file.close();
+
+        let raw = (0..byte_array.len()).map(|i| byte_array.get(i)).collect::<Vec<u8>>();
+
+        bincode::deserialize::<Self>(&raw).map_err(|err| Error::new(ErrorKind::InvalidData, err))
+    }
+}
+
+/// Hashes the tiles of the map and the id, position and rotation of every actor, sorted so that the order entities were created in
+/// doesn't matter. Two worlds with the same digest have the same map and actors. The state is serialized with bincode and hashed with 
+/// FNV-1a rather than DefaultHasher, as digests are saved in recordings and have to stay the same across platforms and Rust versions.
+pub fn state_digest(world: &World) -> u64 {
+
+    let mut chunk_query = <Read<level_map::MapChunkData>>::query();
+
+    let mut tiles = chunk_query.iter(world)
+        .flat_map(|map_data| map_data.octree.clone().into_iter())
+        .collect::<Vec<level_map::TileData>>();
+
+    tiles.sort_by_key(|tile_data| {
+        let point = tile_data.get_point();
+        (point.x, point.y, point.z)
+    });
+
+    let mut actor_query = <(Read<actor::ActorID>, Read<level_map::CoordPos>, TryRead<Rotation>)>::query();
+
+    let mut actors = actor_query.iter(world)
+        .map(|(actor_id, coord_pos, rotation)| (
+            actor_id.val(),
+            coord_pos.value,
+            rotation.map(|rotation| rotation.value.matrix().iter().map(|v| v.to_bits()).collect::<Vec<u32>>())
+        ))
+        .collect::<Vec<(u128, nalgebra::Vector3<i32>, Option<Vec<u32>>)>>();
+
+    actors.sort_by_key(|(actor_id, _, _)| *actor_id);
+
+    fnv1a(&bincode::serialize(&(tiles, actors)).unwrap())
+}
+
+/// 64 bit FNV-1a hash of the bytes
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01b3))
+}
+
+/// Applies the recorded messages in order to a fresh World, with the map settings of the current one, and returns the digest of the
+/// state it ends up in. Nothing in the replayed world gets a Godot node, so this also works without the engine running.
+pub fn replay_messages(map: level_map::Map, messages: Vec<DataType>) -> u64 {
+
+    let mut world = World::default();
+    let mut resources = Resources::default();
+
+    resources.insert(map);
+    resources.insert(SharedRng::from_entropy());
+
+    messages.into_iter()
+        .filter(replays)
+        .for_each(|data| networking::client_handle_data(data, &mut world, &mut resources));
+
+    let digest = state_digest(&world);
+
+    //free anything that got added to the scene for the replayed map and actors, as this world is thrown away
+    map.free(&mut world);
+    actor::free_all(&mut world);
+
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::systems::level_map::{MapChange, TileData};
+
+    type AABB = octree::geometry::aabb::AABB<i32>;
+    type Point = nalgebra::Vector3<i32>;
+
+    fn insertion(center: Point, tile: u32) -> DataType {
+        DataType::MapChange{
+            change: MapChange::MapInsertion{
+                aabb: AABB::new(center, Point::new(2, 2, 2)),
+                tile_data: TileData::new(tile, Point::zeros())
+            },
+            store_history: Some(1)
+        }
+    }
+
+    #[test]
+    fn replaying_the_same_messages_gives_the_same_digest() {
+        let messages = vec![
+            DataType::CreateHistory{ client_id: 1, history: History::new() },
+            insertion(Point::zeros(), 1),
+            insertion(Point::new(4, 0, 0), 2),
+        ];
+
+        assert_eq!(
+            replay_messages(level_map::Map::default(), messages.clone()),
+            replay_messages(level_map::Map::default(), messages)
+        );
+    }
+
+    #[test]
+    fn undoing_during_a_recording_replays_the_same_way() {
+        let undone = vec![
+            DataType::CreateHistory{ client_id: 1, history: History::new() },
+            insertion(Point::zeros(), 1),
+            insertion(Point::new(4, 0, 0), 2),
+            DataType::HistoryStep{ amount: -1, client_id: 1 },
+        ];
+
+        let never_made = vec![
+            DataType::CreateHistory{ client_id: 1, history: History::new() },
+            insertion(Point::zeros(), 1),
+        ];
+
+        assert_eq!(
+            replay_messages(level_map::Map::default(), undone),
+            replay_messages(level_map::Map::default(), never_made)
+        );
+    }
+}
@@ -1,7 +1,18 @@
 use gdnative::prelude::*;
 use gdnative::api::{
+    Camera,
+    CubeMesh,
+    GeometryInstance,
     ImmediateGeometry,
-    Spatial
+    Label,
+    Mesh,
+    MeshInstance,
+    ShaderMaterial,
+    Spatial,
+    SpatialMaterial,
+    Sprite3D,
+    SpriteBase3D,
+    Viewport,
 };
 use legion::*;
 use nalgebra::Rotation3;
@@ -9,6 +20,9 @@ use num::Float;
 use serde::{Serialize, Deserialize};
 
 use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind};
+use std::time::{Duration, Instant};
 
 use octree::geometry::aabb;
 
@@ -19,6 +33,19 @@ use crate::{
         ActorPalette,
         ENTITY_REFS,
     },
+    nodes::selection_box_observer::{
+        Measurement,
+        MeasureStats,
+        OBSERVED_MEASURE_STATS,
+        ObservedSelectionBox,
+        OBSERVED_MEASUREMENT,
+        OBSERVED_SELECTION_BOX,
+        TILE_PICK_REJECTIONS,
+        CLEAR_REGION_REQUESTS,
+        EDIT_REJECTIONS,
+        ACTOR_REJECTIONS,
+        ACTOR_OVERLAP_REJECTIONS,
+    },
     systems::{
         actor,
         actor::{
@@ -29,7 +56,10 @@ use crate::{
         transform,
         input,
         level_map,
+        level_map::RotateAboutCenter,
         networking::{ClientID, DataType, MessageSender, MessageType},
+        prefab,
+        shared_rng::SharedRng,
     }
 };
 
@@ -39,6 +69,38 @@ type Point = nalgebra::Vector3<i32>;
 type Vector3D = nalgebra::Vector3<f32>;
 type Vector2D = nalgebra::Vector2<f32>;
 
+const SELECTION_BOX_MATERIAL: &str = "res://materials/select_box.material";
+const INVALID_SELECTION_BOX_MATERIAL: &str = "res://materials/select_box_invalid.material";
+
+/// Radians per second that the active selection box's pulse advances by
+const PULSE_SPEED: f32 = 4.;
+/// How many times faster than the pulse a box flashes while insertion is being held to confirm
+const CONFIRM_FLASH_SPEED: f32 = 4.;
+/// Shader parameter that the pulse gets written to when the selection box uses a ShaderMaterial
+const PULSE_SHADER_PARAM: &str = "pulse";
+/// Shader parameter that BoxStyle's line thickness gets written to when the selection box uses a ShaderMaterial
+const LINE_THICKNESS_SHADER_PARAM: &str = "line_thickness";
+
+/// Name of the child node of the actor tool box that draws the exact bounds of the actor being placed
+const ACTOR_BOUNDS_NAME: &str = "ActorBounds";
+const ACTOR_BOUNDS_COLOR: Color = Color { r: 1., g: 1., b: 1., a: 0.5 };
+
+/// Name of the child node of the selection box that draws its grid lines
+const GRID_LINES_NAME: &str = "GridLines";
+const GRID_LINES_COLOR: Color = Color { r: 1., g: 1., b: 1., a: 0.25 };
+
+const PRESENCE_INTERVAL: Duration = Duration::from_secs(1);
+const PRESENCE_MARKER_SIZE: f32 = 0.3;
+
+/// How transparent the outline of a claimed region is, on top of the claiming client's color
+const CLAIM_OUTLINE_ALPHA: f32 = 0.6;
+
+const GHOST_NAME: &str = "Ghost";
+const GHOST_COLOR: Color = Color { r: 1., g: 1., b: 1., a: 0.3 };
+
+/// Names of the child nodes of the selection box that display the length of the x, y and z axes
+const DIMENSION_LABEL_NAMES: [&str; 3] = ["DimensionLabelX", "DimensionLabelY", "DimensionLabelZ"];
+
 #[derive(Copy, Clone)]
 struct EntityRef(Entity);
 
@@ -67,6 +129,11 @@ pub enum ToolBoxType {
 /// TerrainToolBox is just a struct that is used as a way of tagging the selection box that should be visible and active while the tile tool is in use
 pub struct TerrainToolBox {}
 
+/// Tags an actor tool box whose dimensions come from its chosen actor's Bounds, so that it always matches the actor's footprint and 
+/// can't be expanded or resized
+#[derive(Copy, Clone, Debug, Default)]
+pub struct FixedFootprint {}
+
 #[derive(Copy, Clone)]
 /// ActorToolBox is just a struct that is used as away of tagging the selection box that should be visible and active while the actor placement tool is in use
 pub struct ActorToolBox(i64);
@@ -81,6 +148,28 @@ impl ActorToolBox {
 /// Used to tag whichever selection box is active
 pub struct Active {}
 
+/// How many slots each client can keep a selection box in for every tool type
+pub const SELECTION_SLOTS: u8 = 4;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// Which of the client's saved selection slots a box belongs to, so that several regions can be kept around per tool type
+pub struct SelectionSlot(pub u8);
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+/// The way that the terrain box turns the directional tiles it inserts, such as ramps, relative to the way the camera faces. At North 
+/// tiles face away from the camera the same way that moving forward goes, and the rotate selection actions turn it a quarter at a time.
+pub struct BoxTileOrientation(pub level_map::TileOrientation);
+
+/// The way that tiles inserted by a terrain box face, away from the camera unless the box has been turned
+fn tile_orientation(camera_adjusted_dir: &CameraAdjustedDirection, box_orientation: Option<&BoxTileOrientation>) -> level_map::TileOrientation {
+    level_map::TileOrientation::from_direction(camera_adjusted_dir.forward)
+        .rotated(box_orientation.copied().unwrap_or_default().0.quarter_turns())
+}
+
+#[derive(Copy, Clone)]
+/// Used to tag the box in each tool type's current slot, which is the only one of that type that gets shown and activated
+pub struct CurrentSlot {}
+
 #[derive(Copy, Clone)]
 /// Component pushed to world for activating the terrain tool box and sending the message to server
 pub struct ActivateTerrainToolBox{}
@@ -93,6 +182,77 @@ pub struct ActivateActorToolBox{}
 /// Componenet pushed to world to act on the chosen selection in actor palette and send the relevant message
 pub struct MakeActorSelectionChosen{}
 
+/// Tags the terrain box while insertion is being held to confirm a fill of a box bigger than ConfirmHoldTime allows instantly, which 
+/// makes the box flash until the fill goes through or the hold is let go
+#[derive(Copy, Clone)]
+pub struct ConfirmingHold {}
+
+#[derive(Copy, Clone)]
+/// Visual pulse state of the active selection box. Holds its own copy of the box's material so that other boxes sharing the resource 
+/// stay static.
+pub struct Pulse {
+    phase: f32,
+    material: Option<Ref<gdnative::api::Material>>,
+    base_alpha: f32,
+}
+
+impl Default for Pulse {
+    fn default() -> Self {
+        Pulse {
+            phase: 0.,
+            material: None,
+            base_alpha: 1.,
+        }
+    }
+}
+
+/// How long the clear region action waits for its confirming second press
+const CLEAR_REGION_CONFIRM_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Resource which holds the region that the clear region action is waiting on a second press to clear, along with how many actors 
+/// were in it and when it was asked for
+#[derive(Debug, Default)]
+pub struct ClearRegionConfirmation {
+    pending: Option<(AABB, usize, Instant)>
+}
+
+/// Resource which holds the box that the remove outside action is waiting on a second press to remove everything around, along with 
+/// when it was asked for
+#[derive(Debug, Default)]
+pub struct RemoveOutsideConfirmation {
+    pending: Option<(AABB, Instant)>
+}
+
+#[derive(Copy, Clone)]
+/// Event pushed to world when the clear region action is waiting on confirmation to remove count actors
+pub struct ClearRegionPending {
+    pub count: usize
+}
+
+#[derive(Copy, Clone)]
+/// Event pushed to world when the actor chosen from the palette couldn't be cloned into the world for the local client
+pub struct ActorRejected {
+    pub actor_id: i64
+}
+
+#[derive(Copy, Clone)]
+/// Event pushed to world when an actor insertion was turned down because the actor would overlap existing actors
+pub struct ActorOverlapRejected {
+    pub coord_pos: Point
+}
+
+#[derive(Copy, Clone)]
+/// Event pushed to world when the server drops one of the local client's map edits because it overlapped another client's edit
+pub struct EditRejected {
+    pub aabb: AABB
+}
+
+#[derive(Copy, Clone)]
+/// Event pushed to world when the eyedropper is used on an empty cell
+pub struct TilePickRejected {
+    pub coord_pos: Point
+}
+
 #[derive(Debug, Copy, Clone)]
 pub struct SelectionBox {
     pub aabb: AABB
@@ -111,12 +271,288 @@ impl SelectionBox {
             aabb
         }
     }
+
+    /// Flips any negative dimensions of the aabb to positive, and returns the coord_pos that keeps the box over the same cells that 
+    /// it covered at coord_pos. Boxes that aren't mirrored are left as they are, with coord_pos returned unchanged.
+    pub fn normalize_in_place(&mut self, coord_pos: Point) -> Point {
+
+        if self.aabb.dimensions.iter().all(|dimension| *dimension >= 0) {
+            return coord_pos
+        }
+
+        let min = AABB::new(coord_pos, self.aabb.dimensions).get_min();
+
+        self.aabb.dimensions = self.aabb.dimensions.abs();
+
+        AABB::from_extents(min, min + self.aabb.dimensions - Point::new(1,1,1)).center
+    }
+
+    /// The eight corner cells of the aabb, normalized so that mirrored boxes with negative dimensions give the same corners as their 
+    /// positive counterparts. Corner i is on the max side of x if bit 0 of i is set, of y if bit 1 is set and of z if bit 2 is set.
+    pub fn corners(&self) -> [Point; 8] {
+        let min = self.aabb.get_min();
+        let max = min + self.aabb.dimensions.abs() - Point::new(1,1,1);
+
+        let mut corners = [min; 8];
+
+        for (i, corner) in corners.iter_mut().enumerate() {
+            *corner = Point::new(
+                if i & 1 == 0 { min.x } else { max.x },
+                if i & 2 == 0 { min.y } else { max.y },
+                if i & 4 == 0 { min.z } else { max.z },
+            );
+        }
+
+        corners
+    }
+
+    /// The world positions of the eight outer corners of the aabb, in the same order as corners(). Unlike corners() these are on 
+    /// the far side of the max cells, which is where the edges of the box get drawn.
+    pub fn world_corners(&self) -> [Vector3D; 8] {
+        let min = level_map::map_coords_to_world(self.aabb.get_min());
+        let max = level_map::map_coords_to_world(self.aabb.get_min() + self.aabb.dimensions.abs());
+
+        let mut corners = [min; 8];
+
+        for (i, corner) in corners.iter_mut().enumerate() {
+            *corner = Vector3D::new(
+                if i & 1 == 0 { min.x } else { max.x },
+                if i & 2 == 0 { min.y } else { max.y },
+                if i & 4 == 0 { min.z } else { max.z },
+            );
+        }
+
+        corners
+    }
+}
+
+/// How many seconds a remote client's box takes to ease over to a new cell
+const REMOTE_INTERPOLATION_TIME: f32 = 0.1;
+
+/// Where a remote client's box is easing from and to, and how far along it is
+#[derive(Copy, Clone, Debug)]
+pub struct RemoteInterpolation {
+    from: Vector3D,
+    to: Vector3D,
+    elapsed: f32,
+}
+
+/// Tags the small marker node that shows where a remote client's active box is, kept up to date by SelectionPresence pings
+#[derive(Copy, Clone)]
+pub struct PresenceMarker {}
+
+/// A region that a client has claimed for exclusive editing, drawn as an outline in that client's color for everyone
+#[derive(Debug, Copy, Clone)]
+pub struct RegionClaim {
+    pub aabb: AABB
+}
+
+/// Tags the actors that were picked up with the select_actors action so that they can be nudged and rotated as a group. Selection is 
+/// per-client, so this holds every client that currently has the actor selected.
+#[derive(Debug, Clone, Default)]
+pub struct Selected(pub Vec<ClientID>);
+
+/// The state of a client's selection box in its current slot, which is everything another client needs to build a copy of it
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct SelectionBoxSnapshot {
+    pub client_id: u32,
+    pub box_type: ToolBoxType,
+    pub active: bool,
+    pub coord_pos: Point,
+    pub rotation: Rotation3<f32>,
+    pub aabb: AABB,
+}
+
+/// What a client's active box covers, as returned by inspect_active
+#[derive(Debug, Clone)]
+pub struct BoxContents {
+    /// The box in map coordinates
+    pub aabb: AABB,
+    /// Every tile in the cells that the box covers
+    pub tiles: Vec<level_map::TileData>,
+    /// Every actor whose Bounds intersect the box
+    pub actors: Vec<Entity>,
 }
 
+/// When the client last committed an insertion or removal, so that ones arriving within the InsertCooldown can be ignored
+#[derive(Debug, Copy, Clone)]
+pub struct LastCommitTime(pub Instant);
+
+/// Bounds waiting to be applied to a client's box. There's only ever one per client, so anything that wants to change the pending 
+/// bounds should go through set_pending_update_bounds rather than pushing a new one.
 #[derive(Debug, Copy, Clone)]
 pub struct UpdateBounds {
     pub coord_pos: Point,
-    pub aabb: AABB
+    pub aabb: AABB,
+    /// How many frames this has been waiting to be applied, reset whenever the bounds are changed
+    pub frames: u32
+}
+
+/// UpdateBounds that have waited this many frames without being applied are swept away, as they belong to a box that isn't there
+const STALE_UPDATE_BOUNDS_FRAMES: u32 = 5;
+
+/// Resource which numbers the UpdateSelectionBounds messages sent by this client, and keeps track of the last number seen from 
+/// every other client so that an update arriving late can't rewind a box that has already moved further.
+#[derive(Debug, Default)]
+pub struct BoundsSequence {
+    sent: u32,
+    last_seen: HashMap<u32, u32>,
+}
+
+impl BoundsSequence {
+
+    /// Returns the sequence number for the next UpdateSelectionBounds message this client sends
+    pub fn next(&mut self) -> u32 {
+        self.sent = self.sent.wrapping_add(1);
+        self.sent
+    }
+
+    /// Gets the next sequence number from the resource, or 0 if it hasn't been inserted
+    pub fn next_for(resources: &Resources) -> u32 {
+        resources.get_mut::<BoundsSequence>()
+            .map(|mut bounds_sequence| bounds_sequence.next())
+            .unwrap_or(0)
+    }
+
+    /// Returns true and records the sequence if it's newer than the last one seen from the client, otherwise returns false
+    pub fn accept(&mut self, client_id: u32, sequence: u32) -> bool {
+        match self.last_seen.get(&client_id) {
+            Some(last) if (sequence.wrapping_sub(*last) as i32) <= 0 => false,
+            _ => {
+                self.last_seen.insert(client_id, sequence);
+                true
+            }
+        }
+    }
+
+    /// Forgets the last sequence seen from the client, so that a new client reusing its ID starts fresh
+    pub fn forget(&mut self, client_id: u32) {
+        self.last_seen.remove(&client_id);
+    }
+}
+
+/// Resource which collapses the UpdateSelectionBounds messages sent by this client within window into a single message with the 
+/// latest bounds. Boxes still move locally straight away, it's only what gets sent over the network that waits.
+/// 
+/// Bounds updates go out as MessageType::Instant so that they never wait behind large ordered edits, which means any of them can be 
+/// lost. Once a box has been still for window, its last bounds are sent once more as MessageType::Reliable so that the other clients 
+/// always end up with where it came to rest.
+#[derive(Debug)]
+pub struct BoundsThrottle {
+    pub window: Duration,
+    last_sent: HashMap<u32, Instant>,
+    pending: HashMap<u32, (Point, AABB)>,
+    /// The last bounds that were sent unreliably, waiting for the box to come to rest
+    resting: HashMap<u32, (Point, AABB)>,
+}
+
+impl Default for BoundsThrottle {
+    fn default() -> Self {
+        BoundsThrottle {
+            window: Duration::from_millis(50),
+            last_sent: HashMap::new(),
+            pending: HashMap::new(),
+            resting: HashMap::new(),
+        }
+    }
+}
+
+impl BoundsThrottle {
+
+    /// Returns true if the bounds can be sent right away, otherwise holds onto them in place of anything already pending so that 
+    /// only the latest gets sent once the window is up
+    fn try_send(&mut self, client_id: u32, coord_pos: Point, aabb: AABB) -> bool {
+        let now = Instant::now();
+
+        let ready = !self.pending.contains_key(&client_id) && self.last_sent.get(&client_id)
+            .map(|last_sent| now.duration_since(*last_sent) >= self.window)
+            .unwrap_or(true);
+
+        if ready {
+            self.last_sent.insert(client_id, now);
+            self.resting.insert(client_id, (coord_pos, aabb));
+        } else {
+            self.pending.insert(client_id, (coord_pos, aabb));
+        }
+
+        ready
+    }
+
+    /// Takes the bounds that have been held for at least window, so that the resting position is always sent eventually
+    fn take_due(&mut self) -> Vec<(u32, Point, AABB)> {
+        let now = Instant::now();
+        let window = self.window;
+
+        let due = self.pending.keys()
+            .filter(|client_id| self.last_sent.get(client_id).map(|last_sent| now.duration_since(*last_sent) >= window).unwrap_or(true))
+            .copied()
+            .collect::<Vec<u32>>();
+
+        due.into_iter()
+            .filter_map(|client_id| {
+                self.last_sent.insert(client_id, now);
+                self.pending.remove(&client_id).map(|(coord_pos, aabb)| {
+                    self.resting.insert(client_id, (coord_pos, aabb));
+                    (client_id, coord_pos, aabb)
+                })
+            })
+            .collect()
+    }
+
+    /// Takes the bounds of boxes that haven't had anything sent or held back for at least window, which are to be sent reliably
+    fn take_resting(&mut self) -> Vec<(u32, Point, AABB)> {
+        let now = Instant::now();
+        let window = self.window;
+
+        let rested = self.resting.keys()
+            .filter(|client_id| !self.pending.contains_key(client_id))
+            .filter(|client_id| self.last_sent.get(client_id).map(|last_sent| now.duration_since(*last_sent) >= window).unwrap_or(true))
+            .copied()
+            .collect::<Vec<u32>>();
+
+        rested.into_iter()
+            .filter_map(|client_id| self.resting.remove(&client_id).map(|(coord_pos, aabb)| (client_id, coord_pos, aabb)))
+            .collect()
+    }
+}
+
+/// Sends the UpdateSelectionBounds message for the client's box, unless one was sent within the BoundsThrottle window, in which case 
+/// it's held until the window is up
+fn push_bounds_message(world: &mut World, resources: &Resources, client_id: ClientID, coord_pos: Point, aabb: AABB) {
+
+    let send = resources.get_mut::<BoundsThrottle>()
+        .map(|mut throttle| throttle.try_send(client_id.val(), coord_pos, aabb))
+        .unwrap_or(true);
+
+    if send {
+        world.push((MessageSender{
+            data_type: DataType::UpdateSelectionBounds{ client_id: client_id.val(), coord_pos, aabb, sequence: BoundsSequence::next_for(resources) },
+            message_type: MessageType::Instant
+        },));
+    }
+}
+
+/// Sends the bounds held back by BoundsThrottle once their window is up, and resends the bounds of boxes that have come to rest reliably
+pub fn create_bounds_throttle_system() -> impl systems::Runnable {
+    SystemBuilder::new("bounds_throttle_system")
+        .write_resource::<BoundsThrottle>()
+        .write_resource::<BoundsSequence>()
+        .build(|commands, _, (throttle, bounds_sequence), _| {
+
+            for (client_id, coord_pos, aabb) in throttle.take_due() {
+                commands.push((MessageSender{
+                    data_type: DataType::UpdateSelectionBounds{ client_id, coord_pos, aabb, sequence: bounds_sequence.next() },
+                    message_type: MessageType::Instant
+                },));
+            }
+
+            for (client_id, coord_pos, aabb) in throttle.take_resting() {
+                commands.push((MessageSender{
+                    data_type: DataType::UpdateSelectionBounds{ client_id, coord_pos, aabb, sequence: bounds_sequence.next() },
+                    message_type: MessageType::Reliable
+                },));
+            }
+        })
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -124,6 +560,48 @@ pub struct SelectionBoxRotation {
     pub value: Rotation3<f32>
 }
 
+/// Component on the terrain tool box which stops expansion along any of the locked axes. It's also the editor::AxisLock resource.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct ExpansionAxisLock {
+    pub x: bool,
+    pub y: bool,
+    pub z: bool
+}
+
+impl ExpansionAxisLock {
+    /// Zeroes out the components of change that are on a locked axis
+    pub fn apply(&self, change: Point) -> Point {
+        Point::new(
+            if self.x { 0 } else { change.x },
+            if self.y { 0 } else { change.y },
+            if self.z { 0 } else { change.z },
+        )
+    }
+
+    /// Flips the lock on the axis, where 0 is x, 1 is y and 2 is z, and returns whether it's now locked
+    pub fn toggle(&mut self, axis: usize) -> Option<bool> {
+        let lock = match axis {
+            0 => &mut self.x,
+            1 => &mut self.y,
+            2 => &mut self.z,
+            _ => return None
+        };
+
+        *lock = !*lock;
+
+        Some(*lock)
+    }
+}
+
+/// Tints applied to the faces of the selection box that sit on a locked axis, in x, y, z order
+const LOCKED_AXIS_COLORS: [Color; 3] = [
+    Color { r: 1.0, g: 0.4, b: 0.4, a: 1.0 },
+    Color { r: 0.4, g: 1.0, b: 0.4, a: 1.0 },
+    Color { r: 0.4, g: 0.4, b: 1.0, a: 1.0 },
+];
+
+const UNLOCKED_AXIS_COLOR: Color = Color { r: 1.0, g: 1.0, b: 1.0, a: 1.0 };
+
 #[derive(Copy, Clone)]
 pub struct RelativeCamera(Ref<Node>);
 
@@ -155,12 +633,16 @@ pub fn initialize_selection_box(world: &mut World, _: &mut Resources, client_id:
                     level_map::CoordPos::default(),
                     transform::position::Position::default(), 
                     CameraAdjustedDirection::default(),
-                    custom_mesh::Material::from_str("res://materials/select_box.material")
+                    custom_mesh::Material::from_str(SELECTION_BOX_MATERIAL)
                 )
             );
         
             if let Some(mut entry) = world.entry(entity) {
                 entry.add_component(TerrainToolBox{});
+                entry.add_component(ExpansionAxisLock::default());
+                entry.add_component(BoxTileOrientation::default());
+                entry.add_component(SelectionSlot(0));
+                entry.add_component(CurrentSlot{});
         
                 if let Some(camera_node) = camera_node {
                     entry.add_component(RelativeCamera(camera_node))
@@ -181,7 +663,7 @@ pub fn initialize_selection_box(world: &mut World, _: &mut Resources, client_id:
                         value: Rotation3::identity()
                     },
                     CameraAdjustedDirection::default(),
-                    custom_mesh::Material::from_str("res://materials/select_box.material")
+                    custom_mesh::Material::from_str(SELECTION_BOX_MATERIAL)
                 )
             );
         
@@ -189,6 +671,8 @@ pub fn initialize_selection_box(world: &mut World, _: &mut Resources, client_id:
             if let Some(mut entry) = world.entry(entity) {
                 entry.add_component(SelectionBox::new());
                 entry.add_component(ActorToolBox(actor_id));
+                entry.add_component(SelectionSlot(0));
+                entry.add_component(CurrentSlot{});
         
                 if let Some(camera_node) = camera_node {
                     entry.add_component(RelativeCamera(camera_node))
@@ -204,7 +688,9 @@ pub fn initialize_selection_box(world: &mut World, _: &mut Resources, client_id:
 /// Removes all SelectionBox entities from the world, and frees and removes the related Godot nodes
 pub fn free_all(world: &mut World) {
     let mut selection_box_query = <Read<node::NodeRef>>::query()
-        .filter(component::<SelectionBox>());
+        .filter(component::<SelectionBox>() | component::<PresenceMarker>() | component::<RegionClaim>());
+
+    free_preview_actors(world, None);
 
     selection_box_query.iter(world)
         .map(|node_ref| node_ref.val())
@@ -215,6 +701,69 @@ pub fn free_all(world: &mut World) {
         });
 }
 
+/// Removes everything belonging to a single client's selection boxes: the boxes and their Godot nodes, the preview actors
+/// they reference, any pending UpdateBounds, and the client's history. Used when a client disconnects so that nothing is leaked 
+/// for the clients that are still connected.
+pub fn free_all_for_client(world: &mut World, client_id: ClientID) {
+
+    free_preview_actors(world, Some(client_id));
+
+    clear_actor_selection(world, client_id);
+
+    let mut selection_box_query = <(Read<node::NodeRef>, Read<ClientID>)>::query()
+        .filter(component::<SelectionBox>() | component::<PresenceMarker>() | component::<RegionClaim>());
+
+    selection_box_query.iter(world)
+        .filter(|(_, id)| **id == client_id)
+        .map(|(node_ref, _)| node_ref.val())
+        .collect::<Vec<Ref<Node>>>()
+        .into_iter()
+        .for_each(|node_ref| {
+            node::free(world, node_ref);
+        });
+
+    let mut leftover_query = <(Entity, Read<ClientID>)>::query()
+        .filter(component::<UpdateBounds>() | component::<crate::systems::history::History>() | component::<LastCommitTime>());
+
+    leftover_query.iter(world)
+        .filter(|(_, id)| **id == client_id)
+        .map(|(entity, _)| *entity)
+        .collect::<Vec<Entity>>()
+        .into_iter()
+        .for_each(|entity| {
+            world.remove(entity);
+        });
+
+    #[cfg(debug_assertions)]
+    {
+        let mut dangling_query = <Read<ClientID>>::query().filter(component::<node::NodeRef>());
+        let dangling = dangling_query.iter(world).filter(|id| **id == client_id).count();
+
+        if dangling > 0 {
+            godot_print!("{} NodeRefs were left dangling after freeing client {}", dangling, client_id.val());
+        }
+    }
+}
+
+/// Frees the preview actors referenced by the actor tool boxes, optionally only for a single client
+fn free_preview_actors(world: &mut World, client_id: Option<ClientID>) {
+    let mut preview_query = <(Read<EntityRef>, Read<ClientID>)>::query()
+        .filter(component::<SelectionBox>());
+
+    preview_query.iter(world)
+        .filter(|(_, id)| client_id.map(|client_id| **id == client_id).unwrap_or(true))
+        .map(|(entity_ref, _)| entity_ref.0)
+        .collect::<Vec<Entity>>()
+        .into_iter()
+        .for_each(|actor_entity| {
+            match world.entry(actor_entity).map(|entry| entry.get_component::<node::NodeRef>().map(|node_ref| node_ref.val()).ok()) {
+                Some(Some(node)) => node::free(world, node),
+                Some(None) => { world.remove(actor_entity); },
+                None => {}
+            }
+        });
+}
+
 /// Gets the axis closest to forward from a or b, adjusted by adjust_angle around the up axis. We adjust it so that we can smooth out the comparison at 45
 /// degree angles.
 fn get_forward_closest_axis(a: &Vector3D, b: &Vector3D, forward: &Vector3D, right: &Vector3D, up: &nalgebra::Unit<Vector3D>, adjust_angle: f32) -> std::cmp::Ordering {
@@ -241,6 +790,90 @@ fn get_forward_closest_axis(a: &Vector3D, b: &Vector3D, forward: &Vector3D, righ
     ).unwrap()
 }
 
+/// Marks the input of every action that gets held while the editor is paused, so that the selection box systems, which leave 
+/// marked input out, don't act on it after unpausing. The marker goes away along with the input once the action is released.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct HeldWhilePaused;
+
+/// Marks held input with HeldWhilePaused while the EditorPaused resource is on
+pub fn create_paused_input_system() -> impl systems::Runnable {
+    SystemBuilder::new("selection_box_paused_input_system")
+        .read_resource::<editor::EditorPaused>()
+        .with_query(<Entity>::query().filter(component::<input::InputActionComponent>() & !component::<HeldWhilePaused>()))
+        .build(move |commands, world, editor_paused, query| {
+
+            if !editor_paused.0 {
+                return
+            }
+
+            query.iter(world).for_each(|entity| {
+                commands.add_component(*entity, HeldWhilePaused);
+            });
+        })
+}
+
+/// Steps through the palette of the selected tool with the next_tile and prev_tile actions, wrapping around at either end. The terrain 
+/// tool steps PaletteSelection through the tile ids and the actor tool steps ActorPaletteSelection through the actors in the actor 
+/// palette, pushing MakeActorSelectionChosen so that the preview updates. This is thread local as the actor ids come from ENTITY_REFS.
+pub fn create_palette_cycle_system() -> Box<dyn FnMut(&mut World, &mut Resources)> {
+    let next_tile = input::ActionRegistry::NEXT_TILE;
+    let prev_tile = input::ActionRegistry::PREV_TILE;
+
+    let mut input_query = <(Read<input::InputActionComponent>, Read<input::Action>)>::query()
+        .filter(!component::<HeldWhilePaused>());
+
+    Box::new(move |world, resources| {
+
+        if resources.get::<editor::EditorPaused>().map(|paused| paused.0).unwrap_or(false) {
+            return
+        }
+
+        let step = input_query.iter(world)
+            .filter(|(input_component, _)| input_component.just_pressed())
+            .fold(0, |step, (_, action)| {
+                if action == &next_tile {
+                    step + 1
+                } else if action == &prev_tile {
+                    step - 1
+                } else {
+                    step
+                }
+            });
+
+        if step == 0 {
+            return
+        }
+
+        match resources.get::<editor::SelectedTool>().map(|selected_tool| selected_tool.0) {
+            Some(ToolBoxType::TerrainToolBox) => {
+                let current = resources.get::<editor::PaletteSelection>().map_or(0, |selection| selection.val() as i64);
+                let next = (current + step).rem_euclid(editor::TILE_COUNT as i64);
+
+                resources.insert(editor::PaletteSelection::new(next as u32));
+            },
+            Some(ToolBoxType::ActorToolBox(_)) => {
+                let mut actor_ids = ENTITY_REFS.with(|e| e.borrow().keys().copied().collect::<Vec<i64>>());
+
+                if actor_ids.is_empty() {
+                    return
+                }
+
+                actor_ids.sort_unstable();
+
+                let current = resources.get::<editor::ActorPaletteSelection>().map_or(0, |selection| selection.val());
+                let index = actor_ids.iter().position(|actor_id| *actor_id == current).unwrap_or(0) as i64;
+                let next = actor_ids[(index + step).rem_euclid(actor_ids.len() as i64) as usize];
+
+                resources.insert(editor::ActorPaletteSelection::new(next));
+                resources.insert(editor::SelectedTool(ToolBoxType::ActorToolBox(next)));
+
+                world.push((MakeActorSelectionChosen{},));
+            },
+            None => {}
+        }
+    })
+}
+
 /// System that keeps track of and swaps out the selected actor for the actor tool
 pub fn create_actor_selection_chooser_system() -> Box<dyn FnMut(&mut World, &mut Resources)> {
 
@@ -266,7 +899,10 @@ pub fn create_actor_selection_chooser_system() -> Box<dyn FnMut(&mut World, &mut
                         .into_iter()
                         .for_each(|selection_entity| {
 
-                            update_chosen_actor(world, selection_entity, actor_selection.val());
+                            if let Err(err) = update_chosen_actor(world, selection_entity, actor_selection.val()) {
+                                godot_print!("Couldn't choose actor {}: {}", actor_selection.val(), err);
+                                world.push((ActorRejected{ actor_id: actor_selection.val() },));
+                            }
 
                             world.push(
                                 (
@@ -362,6 +998,75 @@ pub fn create_actor_tool_activate_system() -> impl systems::Runnable {
         })
 }
 
+/// Switches the local client over to the next of its tools with the cycle_tool action, going from the terrain tool to the actor tool 
+/// and back around. Tools that the client has no box for in the current slot get skipped, and nothing happens when the selected tool 
+/// is the only one the client has. Activation goes through the same ActivateTerrainToolBox and ActivateActorToolBox components as 
+/// picking a tool from the tool list.
+pub fn create_cycle_tool_system() -> impl systems::Runnable {
+    let cycle_tool = input::ActionRegistry::CYCLE_TOOL;
+
+    SystemBuilder::new("cycle_tool_system")
+        .read_resource::<editor::EditorPaused>()
+        .read_resource::<ClientID>()
+        .with_query(<(Read<input::InputActionComponent>, Read<input::Action>)>::query().filter(!component::<HeldWhilePaused>()))
+        .with_query(<(Read<ClientID>, TryRead<ActorToolBox>)>::query()
+            .filter(component::<CurrentSlot>() & (component::<TerrainToolBox>() | component::<ActorToolBox>())))
+        .build(move |commands, world, (editor_paused, client_id), queries| {
+
+            if editor_paused.0 {
+                return
+            }
+
+            let (input_query, selection_box_query) = queries;
+
+            if !input_query.iter(world).any(|(input_component, action)| action == &cycle_tool && input_component.just_pressed()) {
+                return
+            }
+
+            //the tools in the order that they're cycled through
+            let tools = selection_box_query.iter(world)
+                .filter(|(id, _)| **id == **client_id)
+                .map(|(_, actor_tool)| match actor_tool {
+                    Some(actor_tool) => ToolBoxType::ActorToolBox(actor_tool.get_selection()),
+                    None => ToolBoxType::TerrainToolBox
+                })
+                .fold([None, None], |mut tools, tool| {
+                    match tool {
+                        ToolBoxType::TerrainToolBox => tools[0] = Some(tool),
+                        ToolBoxType::ActorToolBox(_) => tools[1] = Some(tool)
+                    }
+                    tools
+                });
+
+            commands.exec_mut(move |world, resources| {
+
+                let current = match resources.get::<editor::SelectedTool>().map(|selected_tool| selected_tool.0) {
+                    Some(ToolBoxType::ActorToolBox(_)) => 1,
+                    _ => 0
+                };
+
+                //with only the current tool there's nothing to swap to, so leave it be rather than activating it again
+                let next = match (1..tools.len()).map(|offset| (current + offset) % tools.len()).find_map(|i| tools[i]) {
+                    Some(next) => next,
+                    None => return
+                };
+
+                match next {
+                    ToolBoxType::TerrainToolBox => {
+                        resources.insert(editor::SelectedTool(next));
+                        world.push((ActivateTerrainToolBox{},));
+                    },
+                    ToolBoxType::ActorToolBox(actor_id) => {
+                        let actor_id = resources.get::<editor::ActorPaletteSelection>().map_or(actor_id, |selection| selection.val());
+
+                        resources.insert(editor::SelectedTool(ToolBoxType::ActorToolBox(actor_id)));
+                        world.push((ActivateActorToolBox{},));
+                    }
+                }
+            });
+        })
+}
+
 /// Calculates the orthogonal direction that should be considered forward and right when grid-like directional input is used.
 pub fn create_orthogonal_dir_system() -> impl systems::Runnable {
 
@@ -420,49 +1125,173 @@ pub fn create_orthogonal_dir_system() -> impl systems::Runnable {
     })
 } 
 
-/// This system reads input, then moves the coord position of the selection_box
-pub fn create_movement_system() -> impl systems::Runnable {
-    
-    let move_forward = input::Action("move_forward".to_string());
-    let move_back = input::Action("move_back".to_string());
-    let move_left = input::Action("move_left".to_string());
-    let move_right = input::Action("move_right".to_string());
-    let move_up = input::Action("move_up".to_string());
-    let move_down = input::Action("move_down".to_string());
+/// Converts a movement relative to the camera, where z is forward and x is right, into map coords
+fn camera_adjusted_movement(camera_adjusted_dir: &CameraAdjustedDirection, movement: Point) -> Point {
+    let forward = camera_adjusted_dir.forward;
+    let right = camera_adjusted_dir.right;
 
-    SystemBuilder::new("selection_box_movement_system")
-        .read_resource::<crate::Time>()
-        .read_resource::<ClientID>()
-        .with_query(<(Read<input::InputActionComponent>, Read<input::Action>)>::query())
-        .with_query(<(Read<CameraAdjustedDirection>, Read<ClientID>, Read<level_map::CoordPos>, Read<SelectionBox>)>::query())
-        .build(move |commands, world, (time, client_id), queries| {
+    let mut adjusted = Point::new(
+        forward.x.round() as i32,
+        0,
+        forward.z.round() as i32
+    ) * movement.z + Point::new(
+        right.x.round() as i32,
+        0,
+        right.z.round() as i32
+    ) * movement.x;
 
-            let (input_query, selection_box_query) = queries;
+    adjusted.y = movement.y;
 
-            let inputs = input_query.iter(world)
-                .map(|(input, action)| (*input, (*action).clone()))
-                .collect::<Vec<(input::InputActionComponent, input::Action)>>();
+    adjusted
+}
 
-            let mut combined_movement: Option<Point> = None;
-            let mut entity: Option<(Point, ClientID, SelectionBox)> = None;
+/// Adds distance cells travelled along direction to progress and takes back out the whole cells that are ready to be stepped, which 
+/// get returned. Progress on axes that are no longer being moved along, or that have reversed, is dropped first.
+fn accumulate_progress(progress: &mut Vector3D, direction: Point, distance: f32) -> Point {
+    *progress = progress.zip_map(&direction, |p, d| if d == 0 || p * d as f32 < 0. { 0. } else { p });
+    *progress += direction.map(|d| d as f32) * distance;
 
-            for(input_component, action) in inputs.iter().filter(|(_, a)|
-                a == &move_forward ||
-                a == &move_back ||
-                a == &move_left ||
-                a == &move_right ||
-                a == &move_up ||
-                a == &move_down
+    let step = progress.map(|p| p.trunc() as i32);
+    *progress -= step.map(|s| s as f32);
+
+    step
+}
+
+/// With RelativeMovement on, turns the camera adjusted direction by the box's rotation so that moving forward follows the way an 
+/// actor is facing instead of the world's axes
+fn movement_frame(camera_adjusted_dir: &CameraAdjustedDirection, rotation: Option<&SelectionBoxRotation>, relative: bool) -> CameraAdjustedDirection {
+    match rotation {
+        Some(rotation) if relative => CameraAdjustedDirection {
+            forward: rotation.value * camera_adjusted_dir.forward,
+            right: rotation.value * camera_adjusted_dir.right
+        },
+        _ => *camera_adjusted_dir
+    }
+}
+
+/// Returns where a box with the given dimensions should go instead of coord_pos so that it stays within the bounds, following the 
+/// EdgeBehavior, or None if it shouldn't move at all. Wrapping sends the whole box over to the opposite edge as soon as any of it 
+/// would leave, so a box never straddles the seam.
+fn keep_within_bounds(bounds: Option<AABB>, edge_behavior: editor::EdgeBehavior, coord_pos: Point, dimensions: Point) -> Option<Point> {
+
+    let bounds = match bounds {
+        Some(bounds) => bounds,
+        None => return Some(coord_pos)
+    };
+
+    let min = AABB::new(coord_pos, dimensions).get_min();
+    let max = min + dimensions.abs() - Point::new(1,1,1);
+
+    let bounds_min = bounds.get_min();
+    let bounds_max = bounds_min + bounds.dimensions.abs() - Point::new(1,1,1);
+
+    let mut shift = Point::zeros();
+
+    for i in 0..3 {
+        if min[i] >= bounds_min[i] && max[i] <= bounds_max[i] {
+            continue
+        }
+
+        shift[i] = match edge_behavior {
+            editor::EdgeBehavior::Stop => return None,
+            editor::EdgeBehavior::Clamp if min[i] < bounds_min[i] => bounds_min[i] - min[i],
+            editor::EdgeBehavior::Clamp => bounds_max[i] - max[i],
+            editor::EdgeBehavior::Wrap if max[i] > bounds_max[i] => bounds_min[i] - min[i],
+            editor::EdgeBehavior::Wrap => bounds_max[i] - max[i],
+        };
+    }
+
+    Some(coord_pos + shift)
+}
+
+/// This system reads input, then moves the coord position of the selection_box
+pub fn create_movement_system() -> impl systems::Runnable {
+    
+    let move_forward = input::ActionRegistry::MOVE_FORWARD;
+    let move_back = input::ActionRegistry::MOVE_BACK;
+    let move_left = input::ActionRegistry::MOVE_LEFT;
+    let move_right = input::ActionRegistry::MOVE_RIGHT;
+    let move_up = input::ActionRegistry::MOVE_UP;
+    let move_down = input::ActionRegistry::MOVE_DOWN;
+
+    //fractional cells travelled in continuous movement mode that haven't been turned into a step yet
+    let mut progress = Vector3D::zeros();
+
+    //the progress that the box was last drawn with, so that it's only redrawn when that changes
+    let mut drawn_progress = Vector3D::zeros();
+
+    //how long the gamepad stick has been deflected, or None while it's resting in the deadzone
+    let mut stick_repeater: Option<f32> = None;
+
+    SystemBuilder::new("selection_box_movement_system")
+        .read_resource::<crate::Time>()
+        .read_resource::<ClientID>()
+        .read_resource::<editor::MovementMode>()
+        .read_resource::<editor::MovementRate>()
+        .read_resource::<input::GamepadSettings>()
+        .read_resource::<editor::RelativeMovement>()
+        .read_resource::<input::InputCaptured>()
+        .read_resource::<editor::EditorPaused>()
+        .read_resource::<editor::AxisLock>()
+        .read_resource::<editor::LockedLayer>()
+        .with_query(<(Read<input::InputActionComponent>, Read<input::Action>)>::query().filter(!component::<HeldWhilePaused>()))
+        .with_query(<(Read<CameraAdjustedDirection>, Read<ClientID>, Read<level_map::CoordPos>, Read<SelectionBox>, TryRead<SelectionBoxRotation>)>::query())
+        .build(move |commands, world, (time, client_id, movement_mode, movement_rate, gamepad_settings, relative_movement, input_captured, editor_paused, axis_lock, locked_layer), queries| {
+
+            let (input_query, selection_box_query) = queries;
+
+            //the stick is read straight from Godot rather than through the input system, so it has to be stopped here as well, and 
+            //nothing should build up to be moved all at once after unpausing
+            if input_captured.0 || editor_paused.0 {
+                progress = Vector3D::zeros();
+                stick_repeater = None;
+
+                if drawn_progress != progress {
+                    drawn_progress = progress;
+                    let client_id = **client_id;
+                    commands.exec_mut(move |world, _| draw_with_progress(world, client_id, Vector3D::zeros()));
+                }
+                return
+            }
+
+            //moving up and down is ignored while the box is kept on a layer
+            let axis_lock = editor::AxisLock {
+                y: axis_lock.y || locked_layer.0.is_some(),
+                ..**axis_lock
+            };
+            let locked_layer = locked_layer.0;
+
+            let inputs = input_query.iter(world)
+                .map(|(input, action)| (*input, (*action).clone()))
+                .collect::<Vec<(input::InputActionComponent, input::Action)>>();
+
+            let continuous = **movement_mode == editor::MovementMode::Continuous;
+
+            let mut combined_movement: Option<Point> = None;
+            let mut continuous_direction: Option<Point> = None;
+            let mut entity: Option<(Point, ClientID, SelectionBox)> = None;
+
+            for(input_component, action) in inputs.iter().filter(|(_, a)|
+                a == &move_forward ||
+                a == &move_back ||
+                a == &move_left ||
+                a == &move_right ||
+                a == &move_up ||
+                a == &move_down
             ) {                    
 
-                if input_component.repeated(time.delta, 0.25) {
+                let stepped = !continuous && input_component.repeated(time.delta, 0.25);
+
+                if stepped || (continuous && input_component.strength > 0.0) {
 
                     selection_box_query.iter(world)
-                        .filter(|(_, id, _, _)| **id == **client_id)
-                        .for_each(|(camera_adjusted_dir, _, coord_pos, selection_box)| {
+                        .filter(|(_, id, _, _, _)| **id == **client_id)
+                        .for_each(|(camera_adjusted_dir, _, coord_pos, selection_box, rotation)| {
 
                         entity = Some((coord_pos.value, **client_id, *selection_box));
 
+                        let camera_adjusted_dir = movement_frame(camera_adjusted_dir, rotation, relative_movement.0);
+
                         let mut movement = Point::zeros();
 
                         if action.0 == move_forward.0 {
@@ -479,476 +1308,3278 @@ pub fn create_movement_system() -> impl systems::Runnable {
                             movement.y -= 1;
                         }
                         
-                        let forward = camera_adjusted_dir.forward;
-                        let right = camera_adjusted_dir.right;
+                        let adjusted = camera_adjusted_movement(&camera_adjusted_dir, movement);
+
+                        //accumulate so that holding two directions at once moves diagonally
+                        let accumulated = if stepped {
+                            &mut combined_movement
+                        } else {
+                            &mut continuous_direction
+                        };
+
+                        *accumulated = Some(match *accumulated {
+                            Some(combined) => combined + adjusted,
+                            None => adjusted
+                        });
 
-                        let mut adjusted = Point::new(
-                            forward.x.round() as i32,
-                            0,
-                            forward.z.round() as i32
-                        ) * movement.z + Point::new(
-                            right.x.round() as i32,
-                            0,
-                            right.z.round() as i32
-                        ) * movement.x;
+                    });
+                }
+            }   
+
+            //the stick steps the same way as the keys do, only repeating faster the further it's pushed
+            let stick = gamepad_settings.movement_stick();
+            let magnitude = stick.norm();
+
+            if magnitude > 0. {
 
-                        adjusted.y = movement.y;
+                let interval = input::GamepadSettings::repeat_interval(magnitude);
 
-                        combined_movement = Some(adjusted);
+                let step = match stick_repeater {
+                    None => {
+                        stick_repeater = Some(0.);
+                        true
+                    },
+                    Some(ref mut repeater) => {
+                        *repeater += time.delta;
+                        if *repeater >= interval {
+                            *repeater -= interval;
+                            true
+                        } else {
+                            false
+                        }
+                    }
+                };
+
+                if step {
+                    selection_box_query.iter(world)
+                        .filter(|(_, id, _, _, _)| **id == **client_id)
+                        .for_each(|(camera_adjusted_dir, _, coord_pos, selection_box, rotation)| {
+
+                        entity = Some((coord_pos.value, **client_id, *selection_box));
+
+                        let camera_adjusted_dir = movement_frame(camera_adjusted_dir, rotation, relative_movement.0);
 
+                        //snap the stick to the nearest of 8 directions, stick up is negative in Godot
+                        let threshold = magnitude * (std::f32::consts::PI / 8.).sin();
+                        let movement = Point::new(
+                            if stick.x.abs() > threshold { stick.x.signum() as i32 } else { 0 },
+                            0,
+                            if stick.y.abs() > threshold { -stick.y.signum() as i32 } else { 0 }
+                        );
+
+                        let adjusted = camera_adjusted_movement(&camera_adjusted_dir, movement);
+
+                        combined_movement = Some(match combined_movement {
+                            Some(combined) => combined + adjusted,
+                            None => adjusted
+                        });
                     });
                 }
-            }   
+            } else {
+                stick_repeater = None;
+            }
+
+            if continuous {
+                match continuous_direction.map(|direction| axis_lock.apply(direction)) {
+                    Some(direction) if direction != Point::zeros() => {
+                        //only whole cells get sent, so the network only ever sees grid aligned positions
+                        let step = accumulate_progress(&mut progress, direction, movement_rate.0 * time.delta);
+
+                        if step != Point::zeros() {
+                            combined_movement = Some(step);
+                        }
+                    },
+                    _ => progress = Vector3D::zeros()
+                }
+            }
             
-            if let Some(combined_movement) = combined_movement {
+            //every axis being moved along being locked leaves nothing to move
+            if let Some(combined_movement) = combined_movement.map(|combined_movement| axis_lock.apply(combined_movement)).filter(|combined_movement| *combined_movement != Point::zeros()) {
                 if let Some((coord_pos_value, client_id, selection_box)) = entity {
 
                     let move_to_pos = coord_pos_value + combined_movement;
 
-                    commands.exec_mut(move |world, _| {
-                        let mut query = <(Write<UpdateBounds>, Read<ClientID>)>::query();
-
-                        let mut existing_movement: Option<Point> = None;
+                    commands.exec_mut(move |world, resources| {
+                        let (mut coord_pos, aabb) = match pending_update_bounds(world, client_id) {
+                            Some((coord_pos, aabb)) => (coord_pos + combined_movement, aabb),
+                            None => (move_to_pos, selection_box.aabb)
+                        };
 
-                        if let Some((update_to, _)) = query.iter_mut(world).find(|(_, id)| **id == client_id) {
-                            update_to.coord_pos += combined_movement;
-                            existing_movement = Some(update_to.coord_pos);
+                        if let Some(layer) = locked_layer {
+                            coord_pos.y = layer;
                         }
 
-                        let mut update_selection = DataType::UpdateSelectionBounds{ client_id: client_id.val(), coord_pos: move_to_pos, aabb: selection_box.aabb };
+                        let bounds = resources.get::<level_map::Map>().and_then(|map| map.bounds());
+                        let edge_behavior = resources.get::<editor::EdgeBehavior>().map(|edge_behavior| *edge_behavior).unwrap_or_default();
 
-                        match existing_movement {
-                            Some(existing_movement) => {
-                                if let DataType::UpdateSelectionBounds{client_id:_, coord_pos, aabb:_} = &mut update_selection {
-                                    *coord_pos = existing_movement;
-                                } 
-                            },
-                            None => {
-                                if let DataType::UpdateSelectionBounds{client_id:_, coord_pos, aabb} = &mut update_selection {
-                                    world.push(
-                                        (
-                                            UpdateBounds {
-                                                aabb: *aabb,
-                                                coord_pos: *coord_pos
-                                            },
-                                            client_id
-                                        )
-                                    );
-                                }
-                            }
-                        }
+                        let coord_pos = match keep_within_bounds(bounds, edge_behavior, coord_pos, aabb.dimensions) {
+                            Some(coord_pos) => coord_pos,
+                            None => return
+                        };
 
-                        world.push((MessageSender{
-                            data_type: update_selection,
-                            message_type: MessageType::Ordered
-                        },));
+                        set_pending_update_bounds(world, client_id, coord_pos, aabb);
+
+                        push_bounds_message(world, resources, client_id, coord_pos, aabb);
                     });
                 }
             }
+
+            //the box is drawn part way into the next cell locally, after any step above has been made
+            if drawn_progress != progress {
+                drawn_progress = progress;
+                let client_id = **client_id;
+                commands.exec_mut(move |world, _| draw_with_progress(world, client_id, progress));
+            }
         })
 }
 
+/// Draws the client's active box progress cells past the cell it's headed to, so that continuous movement looks smooth locally even 
+/// though only whole cells get sent. No progress puts it back over the cell.
+fn draw_with_progress(world: &mut World, client_id: ClientID, progress: Vector3D) {
+
+    let pending = pending_update_bounds(world, client_id).map(|(coord_pos, _)| coord_pos);
+
+    let mut query = <(Read<level_map::CoordPos>, Write<transform::position::Position>, Read<ClientID>)>::query()
+        .filter(component::<SelectionBox>() & component::<Active>());
+
+    query.iter_mut(world)
+        .filter(|(_, _, id)| **id == client_id)
+        .for_each(|(coord_pos, position, _)| {
+            let offset = Vector3D::new(
+                progress.x * level_map::TILE_DIMENSIONS.x,
+                progress.y * level_map::TILE_DIMENSIONS.y,
+                progress.z * level_map::TILE_DIMENSIONS.z
+            );
+
+            position.value = level_map::map_coords_to_world(pending.unwrap_or(coord_pos.value)) + offset;
+        });
+}
+
+/// Moves the Position of selection boxes to wherever their CoordPos puts them. The local client's boxes get there straight away, but 
+/// remote boxes only hear about moves a cell at a time, so they ease over to it across REMOTE_INTERPOLATION_TIME instead of jumping. 
+/// A remote box is placed straight away the first time, which is when it gets its RemoteInterpolation.
 pub fn create_coord_to_pos_system() -> impl systems::Runnable {
     SystemBuilder::new("selection_box_coord_system")
-        .with_query(<(Read<level_map::CoordPos>, Write<transform::position::Position>,)>::query()
+        .read_resource::<crate::Time>()
+        .read_resource::<ClientID>()
+        .with_query(<(Entity, Read<level_map::CoordPos>, Write<transform::position::Position>, Read<ClientID>, TryWrite<RemoteInterpolation>)>::query()
             .filter(maybe_changed::<level_map::CoordPos>() & component::<SelectionBox>())
         )
-        .build(move |_, world, _, query| {
+        .with_query(<(Write<RemoteInterpolation>, Write<transform::position::Position>)>::query()
+            .filter(component::<SelectionBox>())
+        )
+        .build(move |commands, world, (time, client_id), queries| {
 
-            query.for_each_mut(world, |(coord_pos, mut position)| {
-                position.value = level_map::map_coords_to_world(coord_pos.value); 
-            })
+            let (moved_query, interpolation_query) = queries;
+
+            moved_query.for_each_mut(world, |(entity, coord_pos, position, id, interpolation)| {
+                let target = level_map::map_coords_to_world(coord_pos.value);
+
+                match interpolation {
+                    Some(interpolation) if *id != **client_id => {
+                        if interpolation.to != target {
+                            *interpolation = RemoteInterpolation{ from: position.value, to: target, elapsed: 0. };
+                        }
+                    },
+                    None if *id != **client_id => {
+                        position.value = target;
+                        commands.add_component(*entity, RemoteInterpolation{ from: target, to: target, elapsed: REMOTE_INTERPOLATION_TIME });
+                    },
+                    _ => {
+                        position.value = target;
+                    }
+                }
+            });
+
+            interpolation_query.for_each_mut(world, |(interpolation, position)| {
+                if interpolation.elapsed >= REMOTE_INTERPOLATION_TIME {
+                    return
+                }
+
+                interpolation.elapsed = Float::min(interpolation.elapsed + time.delta, REMOTE_INTERPOLATION_TIME);
+
+                //smoothstep, so that the box eases in and out of each move
+                let t = interpolation.elapsed / REMOTE_INTERPOLATION_TIME;
+                let t = t * t * (3. - 2. * t);
+
+                position.value = interpolation.from + (interpolation.to - interpolation.from) * t;
+            });
         })
 }
 
 /// The system responsible for the actor tool functions, such as insertion, removal, moving, editing, etc
 pub fn create_actor_tool_system() -> impl systems::Runnable {
-    let insertion = input::Action(("insertion").to_string());
-    let removal = input::Action(("removal").to_string());
+    let insertion = input::ActionRegistry::INSERTION;
+    let insert_snapped = input::ActionRegistry::INSERT_SNAPPED;
+    let removal = input::ActionRegistry::REMOVAL;
+    let remove_single = input::ActionRegistry::REMOVE_SINGLE;
+    let duplicate_actor = input::ActionRegistry::DUPLICATE_ACTOR;
+    let replace_actor = input::ActionRegistry::REPLACE_ACTOR;
+    let clear_region = input::ActionRegistry::CLEAR_REGION;
+    let scatter = input::ActionRegistry::SCATTER_ACTORS;
 
     SystemBuilder::new("actor_tool_system")
+        .read_resource::<editor::EditorPaused>()
         .read_resource::<ClientID>()
+        .read_resource::<level_map::Map>()
+        .read_resource::<editor::ClientRole>()
+        .read_resource::<editor::ScatterDensity>()
+        .read_resource::<editor::RotationStep>()
+        .read_resource::<editor::MeasureMode>()
+        .read_resource::<editor::SnapToGround>()
+        .read_resource::<editor::AllowOverlap>()
         // .read_resource::<editor::ActorPaletteSelection>()
-        .with_query(<(Read<SelectionBox>, Read<level_map::CoordPos>, Read<EntityRef>, Read<ClientID>)>::query() 
+        .with_query(<(Read<SelectionBox>, Read<level_map::CoordPos>, TryRead<EntityRef>, Read<ClientID>, Read<CameraAdjustedDirection>, Read<ActorToolBox>, Read<SelectionBoxRotation>)>::query() 
             .filter(component::<ActorToolBox>() & component::<Active>()))
-        .with_query(<(Read<input::InputActionComponent>, Read<input::Action>)>::query())
+        .with_query(<(Read<input::InputActionComponent>, Read<input::Action>)>::query().filter(!component::<HeldWhilePaused>()))
         .build(move |command, world, resources, queries| {
             let (selection_box_query, input_query) = queries;
-            let client_id = resources;
+            let (editor_paused, client_id, map, client_role, scatter_density, rotation_step, measure_mode, snap_to_ground, allow_overlap) = resources;
+
+            if editor_paused.0 {
+                return
+            }
+
+            if !client_role.can_edit() || measure_mode.0 {
+                return
+            }
 
             input_query.iter(world).filter(|(_, a)| {
-                *a == &insertion || *a == &removal
+                *a == &insertion || *a == &insert_snapped || *a == &removal || *a == &remove_single || *a == &duplicate_actor || *a == &replace_actor || *a == &clear_region || *a == &scatter
             }).for_each(|(input_component, action)|  {
                 // Insertion tool should check whether or not this is a valid placement for the actor
-                selection_box_query.iter(world).filter(|(_, _, _, id)| **id == **client_id).for_each(|(selection_box, coord_pos, entity_ref, _)| {
+                selection_box_query.iter(world).filter(|(_, _, _, id, _, _, _)| **id == **client_id).for_each(|(selection_box, coord_pos, entity_ref, _, camera_adjusted_dir, actor_tool_box, box_rotation)| {
 
                     if input_component.just_pressed() {
 
-                        if action == &insertion {
+                        if action == &insertion || action == &insert_snapped {
                             
                             let client_id = client_id.val();
-                            let coord_pos = *coord_pos;
-                            let actor_entity = entity_ref.0;
+                            let mut coord_pos = *coord_pos;
+                            let actor_entity = entity_ref.map(|entity_ref| entity_ref.0);
+                            let actor_id = actor_tool_box.get_selection();
+                            let map = **map;
 
-                            command.exec_mut(move |world, _| {
+                            //insert_snapped always snaps, while SnapToGround snaps every insertion as long as the ground is close enough
+                            let snapped = action == &insert_snapped || snap_to_ground.enabled;
+                            let max_drop = if action == &insert_snapped { None } else { Some(snap_to_ground.max_drop) };
+                            let allow_overlap = allow_overlap.0;
 
-                                actor::CANON.with(move |c| {
-                                    let canon = c.borrow();
+                            command.exec_mut(move |world, resources| {
 
-                                    actor::REGISTRY.with(move |r| {
-                                        let registry = r.borrow();
+                                //the preview is missing, most likely because the palette was reloaded without the actor
+                                let actor_entity = match actor_entity.filter(|actor_entity| world.contains(*actor_entity)) {
+                                    Some(actor_entity) => actor_entity,
+                                    None => {
+                                        godot_print!("Couldn't insert actor {} as it has no preview in the world", actor_id);
+                                        world.push((ActorRejected{ actor_id },));
+                                        return
+                                    }
+                                };
 
-                                        actor::MERGER.with(move |m| {
+                                if !try_commit(world, resources, ClientID::new(client_id)) {
+                                    return
+                                }
 
-                                            let mut merger = m.borrow_mut();
+                                if snapped {
+                                    if let Some(snapped_pos) = snap_actor_to_surface(world, &map, actor_entity, coord_pos, max_drop) {
+                                        coord_pos = snapped_pos;
+                                    }
+                                }
 
-                                            let mut actor_world = World::default();
-                                            let new_entity = actor_world.clone_from_single(world, actor_entity, &mut *merger);
+                                if !allow_overlap && actor_overlaps_others(world, actor_entity, coord_pos) {
+                                    world.push((ActorOverlapRejected{ coord_pos: coord_pos.value },));
+                                    mark_actor_tool_box_invalid(world, ClientID::new(client_id));
+                                    return
+                                }
 
-                                            if let Some(mut entry) = actor_world.entry(new_entity) {
-                                                let actor_id = actor::ActorID::new();
-                                                entry.add_component(actor_id);
-                                                entry.add_component(coord_pos);
-                                            }
-                                            
-                                            if let Ok(serialized) = bincode::serialize(&actor_world.as_serializable(component::<actor::Actor>(), & *registry, & *canon)) {
-                                                world.push(
-                                                    (
-                                                        MessageSender{
-                                                            data_type: DataType::ActorChange{
-                                                                store_history: Some(client_id),
-                                                                change: actor::ActorChange::ActorInsertion {
-                                                                    serialized
-                                                                },
-                                                            },
-                                                            message_type: MessageType::Ordered,
-                                                        },
-                                                    )
-                                                );
-                                            }
-                                            
-                                        });
-                                    });
-                                });
+                                match actor::serialize_new_actor_from(world, actor_entity, client_id, coord_pos) {
+                                    Ok(serialized) => {
+                                        world.push(
+                                            (
+                                                MessageSender{
+                                                    data_type: DataType::ActorChange{
+                                                        store_history: Some(client_id),
+                                                        change: actor::ActorChange::insertion(serialized),
+                                                    },
+                                                    message_type: MessageType::Ordered,
+                                                },
+                                            )
+                                        );
+                                    },
+                                    Err(err) => {
+                                        godot_print!("Couldn't insert actor {}: {:?}", actor_id, err);
+                                        world.push((ActorRejected{ actor_id },));
+                                    }
+                                }
                             });
 
-                        } else if action == &removal {
-                            
+                        } else if action == &duplicate_actor {
+
                             let coord_pos = coord_pos.value;
                             let dimensions = selection_box.aabb.dimensions;
                             let client_id = client_id.val();
-                            command.exec_mut(move |world, _| {
-                                actor::select_actors_from_range(world, AABB::new(coord_pos, dimensions))
-                                    .into_iter().for_each(|entity| {
-                                        if let Some(Some(actor_id)) = world.entry(entity).map(|entry| {
-                                                entry.get_component::<actor::ActorID>().ok().copied()
-                                            }
-                                        ) {
-                                            world.push(
-                                                (
-                                                    MessageSender{
-                                                        data_type: DataType::ActorChange {
-                                                            change: actor::ActorChange::ActorRemoval(actor_id.val()),
-                                                            store_history: Some(client_id)
-                                                        },
-                                                        message_type: MessageType::Ordered
-                                                    },
-                                                )
-                                            );
-                                        }
-                                    });
-                            })
-                            
-                        }
-                    }
-                })
-            })
-        })
-}
+                            let allow_overlap = allow_overlap.0;
 
-/// The system responsible for the tile tool functions, such as insertion, removal, and (to be added) copy, paste, painting
-pub fn create_tile_tool_system() -> impl systems::Runnable {
-    let insertion = input::Action(("insertion").to_string());
-    let removal = input::Action(("removal").to_string());
+                            // The forward direction is always one of the cartesian axes on the xz plane
+                            let offset = Point::new(
+                                camera_adjusted_dir.forward.x.round() as i32,
+                                0,
+                                camera_adjusted_dir.forward.z.round() as i32
+                            );
 
-    SystemBuilder::new("tile_tool_system")
-        .read_resource::<ClientID>()
-        .read_resource::<level_map::Map>()
-        .read_resource::<editor::PaletteSelection>()
-        .with_query(<(Read<SelectionBox>, Read<level_map::CoordPos>, Read<ClientID>)>::query() //all selection_boxes
-            .filter(component::<TerrainToolBox>() & component::<Active>()))
-        .with_query(<(Read<SelectionBox>, Read<level_map::CoordPos>, Read<ClientID>)>::query() //only moved selection_boxes
-            .filter(component::<TerrainToolBox>() & component::<Active>() & maybe_changed::<level_map::CoordPos>()))
-        .with_query(<(Read<input::InputActionComponent>, Read<input::Action>)>::query())
-        .build(move |commands, world, resources, queries| {
+                            command.exec_mut(move |world, resources| {
+                                let actors = actor::select_actors_from_range(world, AABB::new(coord_pos, dimensions));
 
-            let (selection_box_query, selection_box_moved_query, input_query) = queries;
-            let (client_id, map, tile_selection) = resources;
+                                if actors.len() != 1 {
+                                    return
+                                }
 
-            input_query.iter(world).filter(|(_, a)| {
-                *a == &insertion || *a == &removal
-            }).for_each(|(input_component, action)|  {
-                selection_box_query.iter(world).filter(|(_, _, id)| id.val() == client_id.val()).for_each(|(selection_box, coord_pos, _)| {
-                    
-                    let moved = selection_box_moved_query.iter(world).any(|(_, _, id)| id.val() == client_id.val());
+                                let actor_entity = actors[0];
 
-                    if input_component.just_pressed() 
-                    || (input_component.is_held() && moved) 
-                    {
-                        if action == &insertion {
-                            let map = **map;
-                            let tile_selection = **tile_selection;
+                                let actor_coord_pos = match world.entry_ref(actor_entity).ok()
+                                    .and_then(|entry| entry.get_component::<level_map::CoordPos>().ok().copied()) {
+                                        Some(actor_coord_pos) => actor_coord_pos,
+                                        None => return
+                                    };
 
-                            let client_id = client_id.val();
-                            let aabb = AABB::new(coord_pos.value, selection_box.aabb.dimensions);
+                                if !try_commit(world, resources, ClientID::new(client_id)) {
+                                    return
+                                }
 
-                            commands.exec_mut(move |world, _|{
-                
-                                let tile_data = level_map::TileData::new(tile_selection.val(), Point::zeros());
-            
-                                if map.can_change(world, &level_map::fill_octree_from_aabb(aabb, Some(tile_data))).is_ok() {
+                                let coord_pos = level_map::CoordPos::new(actor_coord_pos.value + offset);
+
+                                //the copy is checked the same way as placing the actor from the palette would be
+                                if !allow_overlap && actor_overlaps_others(world, actor_entity, coord_pos) {
+                                    world.push((ActorOverlapRejected{ coord_pos: coord_pos.value },));
+                                    mark_actor_tool_box_invalid(world, ClientID::new(client_id));
+                                    return
+                                }
+
+                                if let Ok(serialized) = actor::serialize_new_actor_from(world, actor_entity, client_id, coord_pos) {
                                     world.push(
                                         (
                                             MessageSender{
-                                                data_type: DataType::MapChange{
+                                                data_type: DataType::ActorChange{
                                                     store_history: Some(client_id),
-                                                    change: level_map::MapChange::MapInsertion{ aabb, tile_data },                               
+                                                    change: actor::ActorChange::insertion(serialized),
                                                 },
-                                                message_type: MessageType::Ordered
+                                                message_type: MessageType::Ordered,
                                             },
-                                        ),                  
+                                        )
                                     );
                                 }
                             });
 
-                        } else if action == &removal {
-                            let map = **map;
+                        } else if action == &replace_actor {
+
+                            let coord_pos = coord_pos.value;
+                            let dimensions = selection_box.aabb.dimensions;
                             let client_id = client_id.val();
-                            let aabb = AABB::new(coord_pos.value, selection_box.aabb.dimensions);
+                            let actor_entity = entity_ref.map(|entity_ref| entity_ref.0);
+                            let actor_id = actor_tool_box.get_selection();
+                            let rotation = transform::rotation::Rotation{ value: box_rotation.value };
+
+                            command.exec_mut(move |world, _| {
+                                let actor_entity = match actor_entity.filter(|actor_entity| world.contains(*actor_entity)) {
+                                    Some(actor_entity) => actor_entity,
+                                    None => {
+                                        godot_print!("Couldn't replace with actor {} as it has no preview in the world", actor_id);
+                                        world.push((ActorRejected{ actor_id },));
+                                        return
+                                    }
+                                };
+
+                                let actors = actor::select_actors_from_range(world, AABB::new(coord_pos, dimensions));
 
-                            commands.exec_mut(move |world, _|{
-                                if map.can_change(world, &level_map::fill_octree_from_aabb(aabb, None)).is_ok() {
+                                if actors.len() != 1 {
+                                    return
+                                }
+
+                                let replaced = match world.entry_ref(actors[0]).ok()
+                                    .and_then(|entry| Some((
+                                        entry.get_component::<actor::ActorID>().ok().copied()?, 
+                                        entry.get_component::<level_map::CoordPos>().ok().copied()?
+                                    ))) {
+                                        Some(replaced) => replaced,
+                                        None => return
+                                    };
+
+                                let (replaced_id, replaced_coord_pos) = replaced;
+
+                                if let Ok(serialized) = actor::serialize_new_actor_with(world, actor_entity, actor::ActorID::new(client_id), replaced_coord_pos, Some(rotation)) {
                                     world.push(
                                         (
                                             MessageSender{
-                                                data_type: DataType::MapChange{
+                                                data_type: DataType::ActorChange{
                                                     store_history: Some(client_id),
-                                                    change: level_map::MapChange::MapRemoval(aabb),                               
+                                                    change: actor::ActorChange::ActorBatch(vec![
+                                                        actor::ActorChange::ActorRemoval(replaced_id.val()),
+                                                        actor::ActorChange::insertion(serialized),
+                                                    ]),
                                                 },
-                                                message_type: MessageType::Ordered
+                                                message_type: MessageType::Ordered,
                                             },
-                                        ),                  
+                                        )
                                     );
                                 }
                             });
-                        }
-                        
-                    }
-                })
-            })
-        })
-}
 
-pub fn create_rotation_system() -> impl systems::Runnable {
-    let rotate_selection_left = input::Action("rotate_selection_left".to_string());
-    let rotate_selection_right = input::Action("rotate_selection_right".to_string());
+                        } else if action == &clear_region {
 
-    SystemBuilder::new("selection_rotation_system")
-        .read_resource::<crate::Time>()
-        .read_resource::<ClientID>()
-        .with_query(<(Read<input::InputActionComponent>, Read<input::Action>)>::query())
-        .with_query(<(Entity, Read<ClientID>)>::query()
-            .filter(component::<SelectionBox>() & component::<ActorToolBox>() & component::<Active>()))
-        .build(move |commands, world, (time, client_id), queries| {
-            let (input_query, selection_box_query) = queries;
+                            let aabb = AABB::new(coord_pos.value, selection_box.aabb.dimensions);
+                            let client_id = client_id.val();
 
-            let inputs = input_query.iter(world)
-                .map(|(input, action)| (*input, (*action).clone()))
-                .collect::<Vec<(input::InputActionComponent, input::Action)>>();
+                            command.exec_mut(move |world, resources| {
+                                let filter = resources.get::<editor::ActorFilter>().map(|filter| filter.clone()).unwrap_or_default();
 
-            inputs.into_iter()
-                .filter(|(_, a)|
-                    a == &rotate_selection_left
-                    || a == &rotate_selection_right
-                )
-                .for_each(|(input_component, action)| {
-                    if input_component.repeated(time.delta, 0.25) {
+                                let actor_ids = actor::select_actors_from_range_filtered(world, aabb, |entry| filter.allows(entry)).into_iter()
+                                    .filter_map(|entity| world.entry_ref(entity).ok()
+                                        .and_then(|entry| entry.get_component::<actor::ActorID>().ok().map(|actor_id| actor_id.val()))
+                                    )
+                                    .collect::<Vec<u128>>();
 
-                        selection_box_query.iter_mut(world)
-                            .filter(|(_, id)| id.val() == client_id.val())
-                            .for_each(|(entity, _)| {
+                                let removals = actor::with_group_members(world, actor_ids).into_iter()
+                                    .map(actor::ActorChange::ActorRemoval)
+                                    .collect::<Vec<actor::ActorChange>>();
 
-                                let rotation = if action == rotate_selection_left {
-                                    Rotation3::from_axis_angle(&Vector3D::y_axis(), std::f32::consts::FRAC_PI_2)
-                                } else if action == rotate_selection_right {
-                                    Rotation3::from_axis_angle(&Vector3D::y_axis(), -std::f32::consts::FRAC_PI_2)
-                                } else {
-                                    Rotation3::identity()
+                                let mut confirmation = match resources.get_mut::<ClearRegionConfirmation>() {
+                                    Some(confirmation) => confirmation,
+                                    None => return
+                                };
+
+                                if removals.is_empty() {
+                                    confirmation.pending = None;
+                                    return
+                                }
+
+                                let confirmed = match confirmation.pending.take() {
+                                    Some((pending_aabb, count, requested_at)) => {
+                                        pending_aabb.center == aabb.center && pending_aabb.dimensions == aabb.dimensions 
+                                            && count == removals.len() && requested_at.elapsed() < CLEAR_REGION_CONFIRM_TIMEOUT
+                                    },
+                                    None => false
                                 };
 
-                                let entity = *entity;
-                                let client_id = client_id.val();
+                                if confirmed {
+                                    world.push(
+                                        (
+                                            MessageSender{
+                                                data_type: DataType::ActorChange {
+                                                    change: actor::ActorChange::ActorBatch(removals),
+                                                    store_history: Some(client_id)
+                                                },
+                                                message_type: MessageType::Ordered
+                                            },
+                                        )
+                                    );
+                                } else {
+                                    godot_print!("Delete {} actors? Clear the region again to confirm", removals.len());
+
+                                    confirmation.pending = Some((aabb, removals.len(), Instant::now()));
+                                    world.push((ClearRegionPending{ count: removals.len() },));
+                                }
+                            })
+
+                        } else if action == &removal {
+                            
+                            let coord_pos = coord_pos.value;
+                            let dimensions = selection_box.aabb.dimensions;
+                            let client_id = client_id.val();
+                            command.exec_mut(move |world, resources| {
+
+                                if !try_commit(world, resources, ClientID::new(client_id)) {
+                                    return
+                                }
+
+                                let filter = resources.get::<editor::ActorFilter>().map(|filter| filter.clone()).unwrap_or_default();
+
+                                let actor_ids = actor::select_actors_from_range_filtered(world, AABB::new(coord_pos, dimensions), |entry| filter.allows(entry))
+                                    .into_iter()
+                                    .filter_map(|entity| world.entry(entity).and_then(|entry| {
+                                            entry.get_component::<actor::ActorID>().ok().map(|actor_id| actor_id.val())
+                                        })
+                                    )
+                                    .collect::<Vec<u128>>();
+
+                                //actors that are grouped with the ones in the box go with them
+                                actor::with_group_members(world, actor_ids)
+                                    .into_iter().for_each(|actor_id| {
+                                        world.push(
+                                            (
+                                                MessageSender{
+                                                    data_type: DataType::ActorChange {
+                                                        change: actor::ActorChange::ActorRemoval(actor_id),
+                                                        store_history: Some(client_id)
+                                                    },
+                                                    message_type: MessageType::Ordered
+                                                },
+                                            )
+                                        );
+                                    });
+                            })
+                            
+                        } else if action == &scatter {
+
+                            //the placements are made by every client when handling the message so that the shared rng stays in sync
+                            command.push(
+                                (
+                                    MessageSender{
+                                        data_type: DataType::ScatterActors{
+                                            client_id: client_id.val(),
+                                            actor_id: actor_tool_box.get_selection(),
+                                            aabb: AABB::new(coord_pos.value, selection_box.aabb.dimensions),
+                                            density: scatter_density.0,
+                                            rotation_step: rotation_step.0,
+                                        },
+                                        message_type: MessageType::Ordered
+                                    },
+                                )
+                            );
+
+                        } else if action == &remove_single {
+
+                            let coord_pos = coord_pos.value;
+                            let dimensions = selection_box.aabb.dimensions;
+                            let client_id = client_id.val();
+                            command.exec_mut(move |world, _| {
+                                if let Some(actor_id) = actor::select_nearest_actor_from_range(world, AABB::new(coord_pos, dimensions), coord_pos) {
+                                    let removals = actor::with_group_members(world, vec![actor_id.val()]).into_iter()
+                                        .map(actor::ActorChange::ActorRemoval)
+                                        .collect::<Vec<actor::ActorChange>>();
+
+                                    push_actor_batch(world, removals, client_id);
+                                }
+                            })
+                        }
+                    }
+                })
+            })
+        })
+}
+
+/// Moves the actor nearest to the center of the actor tool box by a cell with the nudge_actor actions, relative to the camera in the 
+/// same way as moving the box. The actor is moved in place with an ActorMove so that it keeps its ID and can be undone.
+pub fn create_nudge_actor_system() -> impl systems::Runnable {
+    let nudges = [
+        (input::ActionRegistry::NUDGE_ACTOR_FORWARD, Point::z()),
+        (input::ActionRegistry::NUDGE_ACTOR_BACK, -Point::z()),
+        (input::ActionRegistry::NUDGE_ACTOR_LEFT, -Point::x()),
+        (input::ActionRegistry::NUDGE_ACTOR_RIGHT, Point::x()),
+        (input::ActionRegistry::NUDGE_ACTOR_UP, Point::y()),
+        (input::ActionRegistry::NUDGE_ACTOR_DOWN, -Point::y()),
+    ];
+
+    SystemBuilder::new("nudge_actor_system")
+        .read_resource::<editor::EditorPaused>()
+        .read_resource::<ClientID>()
+        .read_resource::<editor::ClientRole>()
+        .read_resource::<editor::MeasureMode>()
+        .with_query(<(Read<SelectionBox>, Read<level_map::CoordPos>, Read<ClientID>, Read<CameraAdjustedDirection>)>::query()
+            .filter(component::<ActorToolBox>() & component::<Active>()))
+        .with_query(<(Read<input::InputActionComponent>, Read<input::Action>)>::query().filter(!component::<HeldWhilePaused>()))
+        .build(move |commands, world, (editor_paused, client_id, client_role, measure_mode), queries| {
+
+            if editor_paused.0 {
+                return
+            }
+
+            let (selection_box_query, input_query) = queries;
+
+            if !client_role.can_edit() || measure_mode.0 {
+                return
+            }
+
+            let movement = input_query.iter(world)
+                .filter(|(input_component, _)| input_component.just_pressed())
+                .filter_map(|(_, action)| nudges.iter().find(|(nudge, _)| nudge == action).map(|(_, movement)| *movement))
+                .fold(Point::zeros(), |combined, movement| combined + movement);
+
+            if movement == Point::zeros() {
+                return
+            }
+
+            if let Some((selection_box, coord_pos, _, camera_adjusted_dir)) = selection_box_query.iter(world).find(|(_, _, id, _)| **id == **client_id) {
+
+                let movement = camera_adjusted_movement(camera_adjusted_dir, movement);
+                let coord_pos = coord_pos.value;
+                let dimensions = selection_box.aabb.dimensions;
+                let client_id = client_id.val();
+
+                commands.exec_mut(move |world, _| {
+
+                    //a group selection takes precedence over the actor under the box
+                    let selected = selected_actors(world, ClientID::new(client_id));
+
+                    if !selected.is_empty() {
+                        let moves = selected.into_iter()
+                            .map(|(actor_id, actor_pos, rotation)| actor::ActorChange::ActorMove{ actor_id, coord_pos: actor_pos + movement, rotation })
+                            .collect::<Vec<actor::ActorChange>>();
+
+                        push_actor_batch(world, moves, client_id);
+                        return
+                    }
+
+                    let actor_id = match actor::select_nearest_actor_from_range(world, AABB::new(coord_pos, dimensions), coord_pos) {
+                        Some(actor_id) => actor_id,
+                        None => return
+                    };
+
+                    let mut query = <(Read<actor::ActorID>, Read<level_map::CoordPos>, Read<transform::rotation::Rotation>)>::query();
+
+                    if let Some((actor_pos, rotation)) = query.iter(world)
+                        .find(|(id, _, _)| id.val() == actor_id.val())
+                        .map(|(_, actor_pos, rotation)| (actor_pos.value, rotation.value)) {
+
+                        world.push(
+                            (
+                                MessageSender{
+                                    data_type: DataType::ActorChange {
+                                        change: actor::ActorChange::ActorMove{ actor_id: actor_id.val(), coord_pos: actor_pos + movement, rotation },
+                                        store_history: Some(client_id)
+                                    },
+                                    message_type: MessageType::Ordered
+                                },
+                            )
+                        );
+                    }
+                });
+            }
+        })
+}
+
+/// Selects every actor whose Bounds intersect the active actor box with the select_actors action, replacing whatever the client had 
+/// selected before. Selecting with nothing in the box clears the selection.
+pub fn create_select_actors_system() -> impl systems::Runnable {
+    let select_actors = input::ActionRegistry::SELECT_ACTORS;
+
+    SystemBuilder::new("select_actors_system")
+        .read_resource::<editor::EditorPaused>()
+        .read_resource::<ClientID>()
+        .with_query(<(Read<SelectionBox>, Read<level_map::CoordPos>, Read<ClientID>)>::query()
+            .filter(component::<ActorToolBox>() & component::<Active>()))
+        .with_query(<(Read<input::InputActionComponent>, Read<input::Action>)>::query().filter(!component::<HeldWhilePaused>()))
+        .build(move |commands, world, (editor_paused, client_id), queries| {
+
+            if editor_paused.0 {
+                return
+            }
+
+            let (selection_box_query, input_query) = queries;
+
+            if !input_query.iter(world).any(|(input_component, action)| action == &select_actors && input_component.just_pressed()) {
+                return
+            }
+
+            if let Some((selection_box, coord_pos, _)) = selection_box_query.iter(world).find(|(_, _, id)| **id == **client_id) {
+
+                let range = AABB::new(coord_pos.value, selection_box.aabb.dimensions);
+                let client_id = **client_id;
+
+                commands.exec_mut(move |world, _| {
+                    select_actors_in_range(world, client_id, range);
+                });
+            }
+        })
+}
+
+/// Turns the client's selected actors a quarter turn around the center of the group with the rotate_selected_left and 
+/// rotate_selected_right actions, so that a cluster keeps its layout while it's turned. Actors grouped with a selected actor are 
+/// turned along with it.
+pub fn create_rotate_selected_system() -> impl systems::Runnable {
+    let rotate_selected_left = input::ActionRegistry::ROTATE_SELECTED_LEFT;
+    let rotate_selected_right = input::ActionRegistry::ROTATE_SELECTED_RIGHT;
+
+    SystemBuilder::new("rotate_selected_actors_system")
+        .read_resource::<editor::EditorPaused>()
+        .read_resource::<ClientID>()
+        .read_resource::<editor::ClientRole>()
+        .with_query(<(Read<input::InputActionComponent>, Read<input::Action>)>::query().filter(!component::<HeldWhilePaused>()))
+        .build(move |commands, world, (editor_paused, client_id, client_role), input_query| {
+
+            if editor_paused.0 {
+                return
+            }
+
+            if !client_role.can_edit() {
+                return
+            }
+
+            let angle = input_query.iter(world)
+                .filter(|(input_component, _)| input_component.just_pressed())
+                .fold(0., |angle, (_, action)| {
+                    if action == &rotate_selected_left {
+                        angle + std::f32::consts::FRAC_PI_2
+                    } else if action == &rotate_selected_right {
+                        angle - std::f32::consts::FRAC_PI_2
+                    } else {
+                        angle
+                    }
+                });
+
+            if angle == 0. {
+                return
+            }
+
+            let client_id = **client_id;
+
+            commands.exec_mut(move |world, _| {
+
+                let selected = selected_actors(world, client_id);
+
+                if selected.is_empty() {
+                    return
+                }
+
+                let selected = selected_with_group_members(world, selected);
+
+                let turn = Rotation3::from_axis_angle(&Vector3D::y_axis(), angle);
+
+                let sum = selected.iter().fold(Vector3D::zeros(), |sum, (_, coord_pos, _)| sum + coord_pos.map(|v| v as f32));
+                let pivot = sum / selected.len() as f32;
+
+                let moves = selected.into_iter()
+                    .map(|(actor_id, coord_pos, rotation)| {
+                        let offset = turn * (coord_pos.map(|v| v as f32) - pivot);
+
+                        actor::ActorChange::ActorMove{ 
+                            actor_id, 
+                            coord_pos: (pivot + offset).map(|v| v.round() as i32), 
+                            rotation: turn * rotation 
+                        }
+                    })
+                    .collect::<Vec<actor::ActorChange>>();
+
+                push_actor_batch(world, moves, client_id.val());
+            });
+        })
+}
+
+/// Lines up the actors under the client's active actor box with the align actions, which go to the min, center or max of the 
+/// space they take up together along each axis
+pub fn create_align_actors_system() -> impl systems::Runnable {
+    let align_actions = [
+        (input::ActionRegistry::ALIGN_X_MIN, 0, Alignment::Min),
+        (input::ActionRegistry::ALIGN_X_CENTER, 0, Alignment::Center),
+        (input::ActionRegistry::ALIGN_X_MAX, 0, Alignment::Max),
+        (input::ActionRegistry::ALIGN_Y_MIN, 1, Alignment::Min),
+        (input::ActionRegistry::ALIGN_Y_CENTER, 1, Alignment::Center),
+        (input::ActionRegistry::ALIGN_Y_MAX, 1, Alignment::Max),
+        (input::ActionRegistry::ALIGN_Z_MIN, 2, Alignment::Min),
+        (input::ActionRegistry::ALIGN_Z_CENTER, 2, Alignment::Center),
+        (input::ActionRegistry::ALIGN_Z_MAX, 2, Alignment::Max),
+    ];
+
+    SystemBuilder::new("align_actors_system")
+        .read_resource::<editor::EditorPaused>()
+        .read_resource::<ClientID>()
+        .read_resource::<editor::ClientRole>()
+        .with_query(<(Read<input::InputActionComponent>, Read<input::Action>)>::query().filter(!component::<HeldWhilePaused>()))
+        .with_query(<Read<ClientID>>::query().filter(component::<ActorToolBox>() & component::<Active>()))
+        .build(move |commands, world, (editor_paused, client_id, client_role), queries| {
+
+            if editor_paused.0 {
+                return
+            }
+
+            if !client_role.can_edit() {
+                return
+            }
+
+            let (input_query, selection_box_query) = queries;
+
+            if !selection_box_query.iter(world).any(|id| *id == **client_id) {
+                return
+            }
+
+            let alignments = input_query.iter(world)
+                .filter(|(input_component, _)| input_component.just_pressed())
+                .filter_map(|(_, action)| align_actions.iter().find(|(align_action, _, _)| action == align_action))
+                .map(|(_, axis, alignment)| (*axis, *alignment))
+                .collect::<Vec<(usize, Alignment)>>();
+
+            let client_id = **client_id;
+
+            for (axis, alignment) in alignments {
+                commands.exec_mut(move |world, resources| {
+                    if let Err(err) = align_actors(world, resources, client_id, axis, alignment) {
+                        godot_print!("Couldn't align actors: {:?}", err);
+                    }
+                });
+            }
+        })
+}
+
+/// Puts every actor under the client's active actor box into a new group with the group_actors action, or takes the actors under 
+/// the box and everything grouped with them out of their groups with the ungroup_actors action
+pub fn create_group_actors_system() -> impl systems::Runnable {
+    let group_actors = input::ActionRegistry::GROUP_ACTORS;
+    let ungroup_actors = input::ActionRegistry::UNGROUP_ACTORS;
+
+    SystemBuilder::new("group_actors_system")
+        .read_resource::<editor::EditorPaused>()
+        .read_resource::<ClientID>()
+        .read_resource::<editor::ClientRole>()
+        .with_query(<(Read<SelectionBox>, Read<level_map::CoordPos>, Read<ClientID>)>::query()
+            .filter(component::<ActorToolBox>() & component::<Active>()))
+        .with_query(<(Read<input::InputActionComponent>, Read<input::Action>)>::query().filter(!component::<HeldWhilePaused>()))
+        .build(move |commands, world, (editor_paused, client_id, client_role), queries| {
+
+            if editor_paused.0 {
+                return
+            }
+
+            if !client_role.can_edit() {
+                return
+            }
+
+            let (selection_box_query, input_query) = queries;
+
+            let (grouping, ungrouping) = input_query.iter(world)
+                .filter(|(input_component, _)| input_component.just_pressed())
+                .fold((false, false), |(grouping, ungrouping), (_, action)| {
+                    (grouping || action == &group_actors, ungrouping || action == &ungroup_actors)
+                });
+
+            if grouping == ungrouping {
+                return
+            }
+
+            if let Some((selection_box, coord_pos, _)) = selection_box_query.iter(world).find(|(_, _, id)| **id == **client_id) {
+
+                let range = AABB::new(coord_pos.value, selection_box.aabb.dimensions);
+                let client_id = client_id.val();
+
+                commands.exec_mut(move |world, _| {
+
+                    let actor_ids = actor::select_actors_from_range(world, range).into_iter()
+                        .filter_map(|entity| world.entry_ref(entity).ok()
+                            .and_then(|entry| entry.get_component::<actor::ActorID>().ok().map(|actor_id| actor_id.val()))
+                        )
+                        .collect::<Vec<u128>>();
+
+                    if actor_ids.is_empty() {
+                        return
+                    }
+
+                    let change = if grouping {
+                        actor::ActorChange::GroupAssign{ actor_ids, group: Some(actor::ActorGroup::new(client_id).val()) }
+                    } else {
+                        actor::ActorChange::GroupAssign{ actor_ids: actor::with_group_members(world, actor_ids), group: None }
+                    };
+
+                    world.push(
+                        (
+                            MessageSender{
+                                data_type: DataType::ActorChange {
+                                    change,
+                                    store_history: Some(client_id)
+                                },
+                                message_type: MessageType::Ordered
+                            },
+                        )
+                    );
+                });
+            }
+        })
+}
+
+/// Replaces the client's actor selection with every actor whose Bounds intersect range
+pub fn select_actors_in_range(world: &mut World, client_id: ClientID, range: AABB) {
+
+    clear_actor_selection(world, client_id);
+
+    actor::select_actors_from_range(world, range)
+        .into_iter()
+        .for_each(|entity| {
+            if let Some(mut entry) = world.entry(entity) {
+                if let Ok(selected) = entry.get_component_mut::<Selected>() {
+                    selected.0.push(client_id);
+                } else {
+                    entry.add_component(Selected(vec![client_id]));
+                }
+            }
+        });
+}
+
+/// Deselects every actor the client has selected, leaving other clients' selections alone
+pub fn clear_actor_selection(world: &mut World, client_id: ClientID) {
+
+    let mut query = <(Entity, Write<Selected>)>::query();
+
+    query.iter_mut(world)
+        .filter_map(|(entity, selected)| {
+            selected.0.retain(|id| *id != client_id);
+
+            if selected.0.is_empty() {
+                Some(*entity)
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<Entity>>()
+        .into_iter()
+        .for_each(|entity| {
+            if let Some(mut entry) = world.entry(entity) {
+                entry.remove_component::<Selected>();
+            }
+        });
+}
+
+/// Returns the ActorID, position and rotation of every actor the client has selected, ordered by ActorID
+fn selected_actors(world: &World, client_id: ClientID) -> Vec<(u128, Point, Rotation3<f32>)> {
+
+    let mut query = <(Read<actor::ActorID>, Read<level_map::CoordPos>, Read<transform::rotation::Rotation>, Read<Selected>)>::query();
+
+    let mut selected = query.iter(world)
+        .filter(|(_, _, _, selected)| selected.0.contains(&client_id))
+        .map(|(actor_id, coord_pos, rotation, _)| (actor_id.val(), coord_pos.value, rotation.value))
+        .collect::<Vec<(u128, Point, Rotation3<f32>)>>();
+
+    selected.sort_by_key(|(actor_id, _, _)| *actor_id);
+
+    selected
+}
+
+/// Adds the ActorID, position and rotation of every actor grouped with one of the selected actors, ordered by ActorID
+fn selected_with_group_members(world: &World, selected: Vec<(u128, Point, Rotation3<f32>)>) -> Vec<(u128, Point, Rotation3<f32>)> {
+
+    let actor_ids = actor::with_group_members(world, selected.iter().map(|(actor_id, _, _)| *actor_id).collect());
+
+    if actor_ids.len() == selected.len() {
+        return selected
+    }
+
+    let mut query = <(Read<actor::ActorID>, Read<level_map::CoordPos>, Read<transform::rotation::Rotation>)>::query();
+
+    let mut members = query.iter(world)
+        .filter(|(actor_id, _, _)| actor_ids.contains(&actor_id.val()))
+        .map(|(actor_id, coord_pos, rotation)| (actor_id.val(), coord_pos.value, rotation.value))
+        .collect::<Vec<(u128, Point, Rotation3<f32>)>>();
+
+    members.sort_by_key(|(actor_id, _, _)| *actor_id);
+
+    members
+}
+
+/// Sends changes for several actors as a single ActorBatch so that they're applied together and undone in one step
+fn push_actor_batch(world: &mut World, changes: Vec<actor::ActorChange>, client_id: u32) {
+    world.push(
+        (
+            MessageSender{
+                data_type: DataType::ActorChange {
+                    change: actor::ActorChange::ActorBatch(changes),
+                    store_history: Some(client_id)
+                },
+                message_type: MessageType::Ordered
+            },
+        )
+    );
+}
+
+/// Normalizes the client's active terrain tool box if it's mirrored, so that history and serialized state only ever hold boxes with 
+/// positive dimensions, and broadcasts the normalized bounds. The box covers the same cells before and after.
+fn normalize_terrain_box(world: &mut World, resources: &Resources, client_id: ClientID) {
+
+    let mut query = <(Read<ClientID>, Read<level_map::CoordPos>, Read<SelectionBox>)>::query()
+        .filter(component::<TerrainToolBox>() & component::<Active>());
+
+    let (mut coord_pos, mut selection_box) = match query.iter(world).find(|(id, _, _)| **id == client_id) {
+        Some((_, coord_pos, selection_box)) => (coord_pos.value, *selection_box),
+        None => return
+    };
+
+    //build off of any update that hasn't been applied yet so that we don't lose it
+    let mut update_query = <(Read<UpdateBounds>, Read<ClientID>)>::query();
+    if let Some((update_to, _)) = update_query.iter(world).find(|(_, id)| **id == client_id) {
+        coord_pos = update_to.coord_pos;
+        selection_box.aabb = update_to.aabb;
+    }
+
+    if selection_box.aabb.dimensions.iter().all(|dimension| *dimension >= 0) {
+        return
+    }
+
+    let coord_pos = selection_box.normalize_in_place(coord_pos);
+
+    send_update_bounds(world, resources, client_id, coord_pos, selection_box.aabb);
+}
+
+/// Returns false if the client's last insertion or removal was within the InsertCooldown, which filters out input bounce. 
+/// Otherwise records now as the client's last commit and returns true.
+fn try_commit(world: &mut World, resources: &Resources, client_id: ClientID) -> bool {
+
+    let cooldown = resources.get::<editor::InsertCooldown>().map(|cooldown| cooldown.0).unwrap_or(0.);
+
+    let mut query = <(Write<LastCommitTime>, Read<ClientID>)>::query();
+
+    match query.iter_mut(world).find(|(_, id)| **id == client_id) {
+        Some((last_commit_time, _)) => {
+            if last_commit_time.0.elapsed().as_secs_f32() < cooldown {
+                return false
+            }
+
+            last_commit_time.0 = Instant::now();
+        },
+        None => {
+            world.push((LastCommitTime(Instant::now()), client_id));
+        }
+    }
+
+    true
+}
+
+/// Places copies of the palette actor at random cells within aabb, with random rotations around the y axis in multiples of rotation_step. 
+/// The number of actors is density times the number of cells, and cells that already have an actor in them are skipped. This pulls
+/// from the shared rng, so it must only be called while handling messages. Everything placed is undone as a single step.
+pub fn scatter_actors(world: &mut World, rng: &mut SharedRng, store_history: Option<u32>, actor_id: i64, aabb: AABB, density: f32, rotation_step: f32) {
+
+    let min = aabb.get_min();
+    let dimensions = aabb.dimensions.abs();
+    let volume = (dimensions.x * dimensions.y * dimensions.z) as usize;
+
+    let count = std::cmp::min(volume, (density.max(0.) * volume as f32).round() as usize);
+
+    if count == 0 {
+        return
+    }
+
+    let mut cells = (0..volume as i32)
+        .map(|i| min + Point::new(i % dimensions.x, (i / dimensions.x) % dimensions.y, i / (dimensions.x * dimensions.y)))
+        .collect::<Vec<Point>>();
+
+    //partial Fisher-Yates shuffle, so that the first count cells are a random selection
+    for i in 0..count {
+        let j = rng.next_range(i as i32, volume as i32) as usize;
+        cells.swap(i, j);
+    }
+
+    let rotation_steps = if rotation_step > 0. {
+        std::cmp::max(1, (std::f32::consts::PI * 2. / rotation_step).round() as i32)
+    } else {
+        1
+    };
+
+    //every draw from the rng happens before the palette is looked at, so that peers which can't resolve the actor still stay in step
+    let placements = cells.into_iter().take(count)
+        .map(|cell| {
+            let rotation = transform::rotation::Rotation {
+                value: Rotation3::from_axis_angle(&Vector3D::y_axis(), rng.next_range(0, rotation_steps) as f32 * rotation_step)
+            };
+
+            (cell, rotation, actor::ActorID::from_shared_rng(rng))
+        })
+        .collect::<Vec<(Point, transform::rotation::Rotation, actor::ActorID)>>();
+
+    let mut insertions = Vec::new();
+
+    if let Some(actor_world) = ActorPalette::get_world() {
+        let actor_world = &mut actor_world.borrow_mut();
+        if let Some(actor_world) = actor_world.as_mut() {
+            ENTITY_REFS.with(|e| {
+                let entity_refs = e.borrow();
+
+                if let Some(actor_entity) = entity_refs.get(&actor_id) {
+
+                    let bounds = actor_world.entry_ref(*actor_entity).ok()
+                        .and_then(|entry| entry.get_component::<actor::Bounds>().ok().copied());
+
+                    //the actors placed so far aren't in the world until the batch is applied, so they're kept track of here
+                    let mut placed: Vec<AABB> = Vec::new();
+
+                    for (cell, rotation, new_id) in placements {
+
+                        let cell_aabb = AABB::new(cell, Point::new(1,1,1));
+
+                        if !actor::select_actors_from_range(world, cell_aabb).is_empty() || 
+                            placed.iter().any(|region| level_map::aabbs_overlap(cell_aabb, *region)) {
+                            continue
+                        }
+
+                        if let Ok(serialized) = actor::serialize_new_actor_with(actor_world, *actor_entity, new_id, level_map::CoordPos::new(cell), Some(rotation)) {
+                            placed.push(bounds.map(|bounds| bounds.region_at(cell, rotation.value)).unwrap_or(cell_aabb));
+                            insertions.push(actor::ActorChange::insertion(serialized));
+                        }
+                    }
+                }
+            });
+        }
+    }
+
+    //the whole scatter is a single step in history
+    if !insertions.is_empty() {
+        actor::change(world, &actor::ActorChange::ActorBatch(insertions), store_history);
+    }
+}
+
+/// Whether the actor's Bounds, placed at coord_pos, would overlap any of the actors already in the world
+fn actor_overlaps_others(world: &mut World, actor_entity: Entity, coord_pos: level_map::CoordPos) -> bool {
+
+    let footprint = world.entry_ref(actor_entity).ok().and_then(|entry| {
+        match (entry.get_component::<actor::Bounds>(), entry.get_component::<transform::rotation::Rotation>()) {
+            (Ok(bounds), Ok(rotation)) => Some(bounds.get_scaled_and_rotated_aabb(rotation.value)),
+            _ => None
+        }
+    });
+
+    match footprint {
+        Some(mut footprint) => {
+            footprint.center = coord_pos.value;
+            !actor::select_actors_from_range(world, footprint).is_empty()
+        },
+        None => false
+    }
+}
+
+/// Gives the client's active actor tool box the invalid placement material, which create_validity_feedback_system puts back once 
+/// the box moves
+fn mark_actor_tool_box_invalid(world: &mut World, client_id: ClientID) {
+
+    let mut query = <(Entity, Read<ClientID>)>::query()
+        .filter(component::<ActorToolBox>() & component::<Active>());
+
+    let selection_entity = query.iter(world)
+        .find(|(_, id)| **id == client_id)
+        .map(|(entity, _)| *entity);
+
+    if let Some(selection_entity) = selection_entity {
+        set_placement_material(world, selection_entity, false);
+    }
+}
+
+/// Returns the coord_pos at which the actor's bottom face would rest on the highest solid tile beneath it, or None if the column
+/// under the actor is empty or, given a max_drop, the actor would have to move down further than that to reach the ground
+fn snap_actor_to_surface(world: &World, map: &level_map::Map, actor_entity: Entity, coord_pos: level_map::CoordPos, max_drop: Option<i32>) -> Option<level_map::CoordPos> {
+
+    let mut aabb = world.entry_ref(actor_entity).ok().and_then(|entry| {
+        match (entry.get_component::<actor::Bounds>(), entry.get_component::<transform::rotation::Rotation>()) {
+            (Ok(bounds), Ok(rotation)) => Some(bounds.get_scaled_and_rotated_aabb(rotation.value)),
+            _ => None
+        }
+    })?;
+
+    aabb.center = coord_pos.value;
+
+    let top = map.top_in_column(world, aabb, coord_pos.value.y)?;
+
+    let mut snapped = coord_pos;
+    snapped.value.y += top + 1 - aabb.get_min().y;
+
+    if max_drop.map_or(false, |max_drop| coord_pos.value.y - snapped.value.y > max_drop) {
+        return None
+    }
+
+    Some(snapped)
+}
+
+/// The system responsible for the tile tool functions, such as insertion, removal, and (to be added) copy, paste, painting. Insertion 
+/// into a box bigger than ConfirmHoldTime allows has to be held for that long before it goes through, and letting go sooner cancels it.
+pub fn create_tile_tool_system() -> impl systems::Runnable {
+    let insertion = input::ActionRegistry::INSERTION;
+    let removal = input::ActionRegistry::REMOVAL;
+
+    //whether the current hold of insertion has already filled the box, so that one hold only ever fills it once
+    let mut hold_confirmed = false;
+
+    SystemBuilder::new("tile_tool_system")
+        .read_resource::<editor::EditorPaused>()
+        .read_resource::<ClientID>()
+        .read_resource::<level_map::Map>()
+        .read_resource::<editor::PaletteSelection>()
+        .read_resource::<editor::ClientRole>()
+        .read_resource::<editor::FillMode>()
+        .read_resource::<editor::MeasureMode>()
+        .read_resource::<prefab::SelectedPrefab>()
+        .read_resource::<editor::PasteRotation>()
+        .read_resource::<editor::SymmetrySettings>()
+        .read_resource::<editor::ConfirmHoldTime>()
+        .with_query(<(Entity, Read<SelectionBox>, Read<level_map::CoordPos>, Read<ClientID>, Read<CameraAdjustedDirection>, TryRead<BoxTileOrientation>, TryRead<ConfirmingHold>)>::query() //all selection_boxes
+            .filter(component::<TerrainToolBox>() & component::<Active>()))
+        .with_query(<(Read<SelectionBox>, Read<level_map::CoordPos>, Read<ClientID>)>::query() //only moved selection_boxes
+            .filter(component::<TerrainToolBox>() & component::<Active>() & maybe_changed::<level_map::CoordPos>()))
+        .with_query(<(Read<input::InputActionComponent>, Read<input::Action>)>::query().filter(!component::<HeldWhilePaused>()))
+        .build(move |commands, world, resources, queries| {
+
+            let (selection_box_query, selection_box_moved_query, input_query) = queries;
+            let (editor_paused, client_id, map, tile_selection, client_role, fill_mode, measure_mode, selected_prefab, paste_rotation, symmetry, confirm_hold_time) = resources;
+
+            if editor_paused.0 {
+                return
+            }
+
+            if !client_role.can_edit() || measure_mode.0 {
+                return
+            }
+
+            input_query.iter(world).filter(|(_, a)| {
+                *a == &insertion || *a == &removal
+            }).for_each(|(input_component, action)|  {
+                selection_box_query.iter(world).filter(|(_, _, _, id, _, _, _)| id.val() == client_id.val()).for_each(|(entity, selection_box, coord_pos, _, camera_adjusted_dir, box_orientation, confirming)| {
+                    
+                    let moved = selection_box_moved_query.iter(world).any(|(_, _, id)| id.val() == client_id.val());
+
+                    let fire = if action == &insertion && confirm_hold_time.applies_to(selection_box.aabb) {
+
+                        if input_component.just_pressed() {
+                            hold_confirmed = false;
+                        }
+
+                        let holding = input_component.strength > 0. && !hold_confirmed;
+                        let confirmed = holding && input_component.held_time() >= confirm_hold_time.seconds;
+
+                        if confirmed {
+                            hold_confirmed = true;
+                        }
+
+                        match (holding && !confirmed, confirming.is_some()) {
+                            (true, false) => commands.add_component(*entity, ConfirmingHold{}),
+                            (false, true) => commands.remove_component::<ConfirmingHold>(*entity),
+                            _ => {}
+                        }
+
+                        confirmed
+                    } else {
+                        if action == &insertion && confirming.is_some() {
+                            commands.remove_component::<ConfirmingHold>(*entity);
+                        }
+
+                        input_component.just_pressed() || (input_component.is_held() && moved)
+                    };
+
+                    if fire {
+                        if action == &insertion {
+                            let map = **map;
+                            let tile_selection = **tile_selection;
+                            let fill_mode = **fill_mode;
+                            let symmetry = **symmetry;
+                            let prefab = selected_prefab.0.as_ref().map(|prefab| prefab.rotated(paste_rotation.0));
+
+                            let client_id = client_id.val();
+                            let aabb = AABB::new(coord_pos.value, selection_box.aabb.dimensions);
+                            let orientation = tile_orientation(camera_adjusted_dir, box_orientation);
+
+                            commands.exec_mut(move |world, resources|{
+
+                                if !try_commit(world, resources, ClientID::new(client_id)) {
+                                    return
+                                }
+
+                                //mirrored boxes are only kept while editing, what gets committed leaves the box with positive dimensions
+                                normalize_terrain_box(world, resources, ClientID::new(client_id));
+
+                                //a selected prefab gets stamped at the box instead of filling it, as long as that changes something
+                                if let Some(prefab) = &prefab {
+                                    if let Some(message_sender) = prefab.stamp_at(world, &map, aabb.get_min(), Some(client_id)) {
+                                        world.push((message_sender,));
+                                    }
+                                    return
+                                }
+                
+                                //tiles face away from the camera, the same way that moving forward goes, unless the box has been turned
+                                let tile_data = level_map::TileData::new(tile_selection.val(), Point::zeros()).with_orientation(orientation);
+
+                                let aabbs = fill_mode.split(aabb);
+
+                                //with symmetry on, the mirror image of each part goes along with it, with tiles facing the mirrored way
+                                let mirrored_tile_data = tile_data.with_orientation(symmetry.mirror_orientation(orientation));
+                                let mirrored = aabbs.iter()
+                                    .filter_map(|part| symmetry.mirror(*part, aabb))
+                                    .collect::<Vec<AABB>>();
+
+                                let insertions = aabbs.into_iter().map(|aabb| (aabb, tile_data))
+                                    .chain(mirrored.into_iter().map(|aabb| (aabb, mirrored_tile_data)))
+                                    .collect::<Vec<(AABB, level_map::TileData)>>();
+
+                                if insertions.iter().any(|(aabb, tile_data)| map.can_change(world, &level_map::fill_octree_from_aabb(*aabb, Some(*tile_data))).is_err()) {
+                                    return
+                                }
+
+                                //shell and frame fills and mirrored insertions go out together so that they're undone in a single step
+                                if let Some(message_sender) = level_map::batch_changes(
+                                    insertions.into_iter().map(|(aabb, tile_data)| level_map::MapChange::MapInsertion{ aabb, tile_data }), 
+                                    Some(client_id)
+                                ) {
+                                    world.push((message_sender,));
+                                }
+                            });
+
+                        } else if action == &removal {
+                            let map = **map;
+                            let symmetry = **symmetry;
+                            let client_id = client_id.val();
+                            let aabb = AABB::new(coord_pos.value, selection_box.aabb.dimensions);
+
+                            commands.exec_mut(move |world, resources|{
+
+                                if !try_commit(world, resources, ClientID::new(client_id)) {
+                                    return
+                                }
+
+                                //mirrored boxes are only kept while editing, what gets committed leaves the box with positive dimensions
+                                normalize_terrain_box(world, resources, ClientID::new(client_id));
+
+                                let removals = std::iter::once(aabb)
+                                    .chain(symmetry.mirror(aabb, aabb))
+                                    .collect::<Vec<AABB>>();
+
+                                if removals.iter().any(|aabb| map.can_change(world, &level_map::fill_octree_from_aabb(*aabb, None)).is_err()) {
+                                    return
+                                }
+
+                                if let Some(message_sender) = level_map::batch_changes(
+                                    removals.into_iter().map(level_map::MapChange::MapRemoval), 
+                                    Some(client_id)
+                                ) {
+                                    world.push((message_sender,));
+                                }
+                            });
+                        }
+                        
+                    }
+                })
+            })
+        })
+}
+
+/// Levels the terrain within the x and z footprint of the terrain tool box to the box's floor, sent as a single batch so that it 
+/// takes one step in history
+pub fn create_flatten_system() -> impl systems::Runnable {
+    let flatten = input::ActionRegistry::FLATTEN;
+
+    SystemBuilder::new("flatten_system")
+        .read_resource::<editor::EditorPaused>()
+        .read_resource::<ClientID>()
+        .read_resource::<level_map::Map>()
+        .read_resource::<editor::PaletteSelection>()
+        .read_resource::<editor::FlattenFillsEmpty>()
+        .read_resource::<editor::ClientRole>()
+        .read_resource::<editor::MeasureMode>()
+        .with_query(<(Read<SelectionBox>, Read<level_map::CoordPos>, Read<ClientID>, Read<CameraAdjustedDirection>, TryRead<BoxTileOrientation>)>::query()
+            .filter(component::<TerrainToolBox>() & component::<Active>()))
+        .with_query(<(Read<input::InputActionComponent>, Read<input::Action>)>::query().filter(!component::<HeldWhilePaused>()))
+        .build(move |commands, world, (editor_paused, client_id, map, tile_selection, fills_empty, client_role, measure_mode), queries| {
+
+            if editor_paused.0 {
+                return
+            }
+
+            let (selection_box_query, input_query) = queries;
+
+            if !client_role.can_edit() || measure_mode.0 {
+                return
+            }
+
+            if !input_query.iter(world).any(|(input_component, action)| action == &flatten && input_component.just_pressed()) {
+                return
+            }
+
+            if let Some((selection_box, coord_pos, _, camera_adjusted_dir, box_orientation)) = selection_box_query.iter(world).find(|(_, _, id, _, _)| **id == **client_id) {
+
+                let map = **map;
+                let client_id = client_id.val();
+                let aabb = AABB::new(coord_pos.value, selection_box.aabb.dimensions);
+                let fill_empty = if fills_empty.0 {
+                    Some(level_map::TileData::new(tile_selection.val(), Point::zeros()).with_orientation(tile_orientation(camera_adjusted_dir, box_orientation)))
+                } else {
+                    None
+                };
+
+                commands.exec_mut(move |world, _| {
+                    if let Some(message_sender) = level_map::batch_changes(map.flatten_changes(world, aabb, fill_empty), Some(client_id)) {
+                        world.push((message_sender,));
+                    }
+                });
+            }
+        })
+}
+
+/// Removes every tile outside of the terrain tool box, up to the map's bounds, for clearing a map around a structure. As that can wipe 
+/// most of the map it only happens on a second press of the action within CLEAR_REGION_CONFIRM_TIMEOUT of the first, with the box 
+/// left where it was. The removals are sent as a single batch so that they take one step in history.
+pub fn create_remove_outside_system() -> impl systems::Runnable {
+    let remove_outside = input::ActionRegistry::REMOVE_OUTSIDE;
+
+    SystemBuilder::new("remove_outside_system")
+        .read_resource::<editor::EditorPaused>()
+        .read_resource::<ClientID>()
+        .read_resource::<level_map::Map>()
+        .read_resource::<editor::ClientRole>()
+        .read_resource::<editor::MeasureMode>()
+        .write_resource::<RemoveOutsideConfirmation>()
+        .with_query(<(Read<SelectionBox>, Read<level_map::CoordPos>, Read<ClientID>)>::query()
+            .filter(component::<TerrainToolBox>() & component::<Active>()))
+        .with_query(<(Read<input::InputActionComponent>, Read<input::Action>)>::query().filter(!component::<HeldWhilePaused>()))
+        .build(move |commands, world, (editor_paused, client_id, map, client_role, measure_mode, confirmation), queries| {
+
+            if editor_paused.0 {
+                return
+            }
+
+            let (selection_box_query, input_query) = queries;
+
+            if !client_role.can_edit() || measure_mode.0 {
+                return
+            }
+
+            if !input_query.iter(world).any(|(input_component, action)| action == &remove_outside && input_component.just_pressed()) {
+                return
+            }
+
+            if let Some((selection_box, coord_pos, _)) = selection_box_query.iter(world).find(|(_, _, id)| **id == **client_id) {
+
+                let aabb = AABB::new(coord_pos.value, selection_box.aabb.dimensions);
+
+                let confirmed = match confirmation.pending.take() {
+                    Some((pending_aabb, requested_at)) => {
+                        pending_aabb.center == aabb.center && pending_aabb.dimensions == aabb.dimensions 
+                            && requested_at.elapsed() < CLEAR_REGION_CONFIRM_TIMEOUT
+                    },
+                    None => false
+                };
+
+                if !confirmed {
+                    godot_print!("Remove everything outside of the box? Remove outside again to confirm");
+
+                    confirmation.pending = Some((aabb, Instant::now()));
+                    return
+                }
+
+                let map = **map;
+                let client_id = client_id.val();
+
+                commands.exec_mut(move |world, resources| {
+
+                    if !try_commit(world, resources, ClientID::new(client_id)) {
+                        return
+                    }
+
+                    if let Some(message_sender) = level_map::batch_changes(map.remove_outside_changes(world, aabb), Some(client_id)) {
+                        world.push((message_sender,));
+                    }
+                });
+            }
+        })
+}
+
+/// Returns the changes that fill aabb with a checkerboard of the two tiles, alternating by (x + y + z) % 2. The whole box is filled 
+/// with the first tile in one change and only the cells of the second tile are changed one by one after it, which relies on the 
+/// batch applying its changes in order.
+fn checkerboard_changes(aabb: AABB, tiles: [level_map::TileData; 2]) -> Vec<level_map::MapChange> {
+
+    let mut changes = vec![level_map::MapChange::MapInsertion{ aabb, tile_data: tiles[0] }];
+
+    //walk the box from its minimum so that boxes with negative dimensions are covered the same way
+    let min = aabb.get_min();
+    let dimensions = aabb.dimensions.abs();
+
+    for x in min.x..min.x + dimensions.x {
+        for y in min.y..min.y + dimensions.y {
+            for z in min.z..min.z + dimensions.z {
+                if (x + y + z).rem_euclid(2) == 1 {
+                    changes.push(level_map::MapChange::MapInsertion{
+                        aabb: AABB::from_extents(Point::new(x, y, z), Point::new(x, y, z)),
+                        tile_data: tiles[1]
+                    });
+                }
+            }
+        }
+    }
+
+    changes
+}
+
+/// Fills the terrain tool box with alternating PaletteSelection and SecondaryPaletteSelection tiles, sent as a single batch so 
+/// that it takes one step in history
+pub fn create_checkerboard_fill_system() -> impl systems::Runnable {
+    let checkerboard_fill = input::ActionRegistry::CHECKERBOARD_FILL;
+
+    SystemBuilder::new("checkerboard_fill_system")
+        .read_resource::<editor::EditorPaused>()
+        .read_resource::<ClientID>()
+        .read_resource::<level_map::Map>()
+        .read_resource::<editor::PaletteSelection>()
+        .read_resource::<editor::SecondaryPaletteSelection>()
+        .read_resource::<editor::ClientRole>()
+        .read_resource::<editor::MeasureMode>()
+        .with_query(<(Read<SelectionBox>, Read<level_map::CoordPos>, Read<ClientID>, Read<CameraAdjustedDirection>, TryRead<BoxTileOrientation>)>::query()
+            .filter(component::<TerrainToolBox>() & component::<Active>()))
+        .with_query(<(Read<input::InputActionComponent>, Read<input::Action>)>::query().filter(!component::<HeldWhilePaused>()))
+        .build(move |commands, world, (editor_paused, client_id, map, tile_selection, secondary_selection, client_role, measure_mode), queries| {
+
+            if editor_paused.0 {
+                return
+            }
+
+            let (selection_box_query, input_query) = queries;
+
+            if !client_role.can_edit() || measure_mode.0 {
+                return
+            }
+
+            if !input_query.iter(world).any(|(input_component, action)| action == &checkerboard_fill && input_component.just_pressed()) {
+                return
+            }
+
+            if let Some((selection_box, coord_pos, _, camera_adjusted_dir, box_orientation)) = selection_box_query.iter(world).find(|(_, _, id, _, _)| **id == **client_id) {
+
+                let map = **map;
+                let client_id = client_id.val();
+                let aabb = AABB::new(coord_pos.value, selection_box.aabb.dimensions);
+                let orientation = tile_orientation(camera_adjusted_dir, box_orientation);
+                let tiles = [
+                    level_map::TileData::new(tile_selection.val(), Point::zeros()).with_orientation(orientation),
+                    level_map::TileData::new(secondary_selection.val(), Point::zeros()).with_orientation(orientation)
+                ];
+
+                commands.exec_mut(move |world, resources| {
+
+                    if !try_commit(world, resources, ClientID::new(client_id)) {
+                        return
+                    }
+
+                    if map.can_change(world, &level_map::fill_octree_from_aabb(aabb, Some(tiles[0]))).is_err() {
+                        return
+                    }
+
+                    if let Some(message_sender) = level_map::batch_changes(checkerboard_changes(aabb, tiles), Some(client_id)) {
+                        world.push((message_sender,));
+                    }
+                });
+            }
+        })
+}
+
+/// Raises or lowers the surface within the terrain tool box's footprint by a layer each time the raise_terrain or lower_terrain 
+/// actions repeat, with every column sent as a single batch so that each layer takes one step in history. The surface stays within 
+/// the height of the box, so holding either action sculpts toward the box's top or floor and then stops.
+pub fn create_sculpt_system() -> impl systems::Runnable {
+    let raise_terrain = input::ActionRegistry::RAISE_TERRAIN;
+    let lower_terrain = input::ActionRegistry::LOWER_TERRAIN;
+
+    SystemBuilder::new("sculpt_system")
+        .read_resource::<editor::EditorPaused>()
+        .read_resource::<crate::Time>()
+        .read_resource::<ClientID>()
+        .read_resource::<level_map::Map>()
+        .read_resource::<editor::PaletteSelection>()
+        .read_resource::<editor::ClientRole>()
+        .read_resource::<editor::MeasureMode>()
+        .with_query(<(Read<SelectionBox>, Read<level_map::CoordPos>, Read<ClientID>, Read<CameraAdjustedDirection>, TryRead<BoxTileOrientation>)>::query()
+            .filter(component::<TerrainToolBox>() & component::<Active>()))
+        .with_query(<(Read<input::InputActionComponent>, Read<input::Action>)>::query().filter(!component::<HeldWhilePaused>()))
+        .build(move |commands, world, (editor_paused, time, client_id, map, tile_selection, client_role, measure_mode), queries| {
+
+            if editor_paused.0 {
+                return
+            }
+
+            let (selection_box_query, input_query) = queries;
+
+            if !client_role.can_edit() || measure_mode.0 {
+                return
+            }
+
+            let raise = match input_query.iter(world)
+                .filter(|(input_component, _)| input_component.repeated(time.delta, 0.25))
+                .find_map(|(_, action)| if action == &raise_terrain {
+                    Some(true)
+                } else if action == &lower_terrain {
+                    Some(false)
+                } else {
+                    None
+                }) {
+                Some(raise) => raise,
+                None => return
+            };
+
+            if let Some((selection_box, coord_pos, _, camera_adjusted_dir, box_orientation)) = selection_box_query.iter(world).find(|(_, _, id, _, _)| **id == **client_id) {
+
+                let map = **map;
+                let client_id = client_id.val();
+                let aabb = AABB::new(coord_pos.value, selection_box.aabb.dimensions);
+                let tile_data = level_map::TileData::new(tile_selection.val(), Point::zeros()).with_orientation(tile_orientation(camera_adjusted_dir, box_orientation));
+
+                commands.exec_mut(move |world, _| {
+                    if let Some(message_sender) = level_map::batch_changes(map.sculpt_changes(world, aabb, raise, tile_data), Some(client_id)) {
+                        world.push((message_sender,));
+                    }
+                });
+            }
+        })
+}
+
+/// Applies one step of the smoothing brush over the terrain tool box, sent as a single batch so that it takes one step in history
+pub fn create_smooth_system() -> impl systems::Runnable {
+    let smooth = input::ActionRegistry::SMOOTH;
+
+    SystemBuilder::new("smooth_system")
+        .read_resource::<editor::EditorPaused>()
+        .read_resource::<ClientID>()
+        .read_resource::<level_map::Map>()
+        .read_resource::<editor::SmoothSettings>()
+        .read_resource::<editor::ClientRole>()
+        .read_resource::<editor::MeasureMode>()
+        .with_query(<(Read<SelectionBox>, Read<level_map::CoordPos>, Read<ClientID>)>::query()
+            .filter(component::<TerrainToolBox>() & component::<Active>()))
+        .with_query(<(Read<input::InputActionComponent>, Read<input::Action>)>::query().filter(!component::<HeldWhilePaused>()))
+        .build(move |commands, world, (editor_paused, client_id, map, smooth_settings, client_role, measure_mode), queries| {
+
+            if editor_paused.0 {
+                return
+            }
+
+            let (selection_box_query, input_query) = queries;
+
+            if !client_role.can_edit() || measure_mode.0 {
+                return
+            }
+
+            if !input_query.iter(world).any(|(input_component, action)| action == &smooth && input_component.just_pressed()) {
+                return
+            }
+
+            if let Some((selection_box, coord_pos, _)) = selection_box_query.iter(world).find(|(_, _, id)| **id == **client_id) {
+
+                let map = **map;
+                let client_id = client_id.val();
+                let aabb = AABB::new(coord_pos.value, selection_box.aabb.dimensions);
+                let strength = smooth_settings.strength;
+
+                commands.exec_mut(move |world, _| {
+                    if let Some(message_sender) = level_map::batch_changes(map.smooth_changes(world, aabb, strength), Some(client_id)) {
+                        world.push((message_sender,));
+                    }
+                });
+            }
+        })
+}
+
+/// Eyedropper which sets the local palette selection to the tile under the terrain tool box, pushing a TilePickRejected if the
+/// cell is empty. Only the local selection changes so nothing gets sent over the network.
+pub fn create_pick_tile_system() -> impl systems::Runnable {
+    let pick_tile = input::ActionRegistry::PICK_TILE;
+
+    SystemBuilder::new("pick_tile_system")
+        .read_resource::<editor::EditorPaused>()
+        .read_resource::<ClientID>()
+        .read_resource::<level_map::Map>()
+        .with_query(<(Read<level_map::CoordPos>, Read<ClientID>)>::query()
+            .filter(component::<TerrainToolBox>() & component::<Active>()))
+        .with_query(<(Read<input::InputActionComponent>, Read<input::Action>)>::query().filter(!component::<HeldWhilePaused>()))
+        .build(move |commands, world, (editor_paused, client_id, map), queries| {
+
+            if editor_paused.0 {
+                return
+            }
+
+            let (selection_box_query, input_query) = queries;
+
+            if !input_query.iter(world).any(|(input_component, action)| action == &pick_tile && input_component.just_pressed()) {
+                return
+            }
+
+            if let Some((coord_pos, _)) = selection_box_query.iter(world).find(|(_, id)| **id == **client_id) {
+
+                let map = **map;
+                let coord_pos = coord_pos.value;
+
+                commands.exec_mut(move |world, resources| {
+                    match map.tile_at(world, coord_pos) {
+                        Some(tile_data) => {
+                            resources.insert(editor::PaletteSelection::new(tile_data.get_tile()));
+                        },
+                        None => {
+                            world.push((TilePickRejected{ coord_pos },));
+                        }
+                    }
+                });
+            }
+        })
+}
+
+/// Turns the active box with the rotate selection actions, which rotates the actor box and its actor, or turns the way that the 
+/// terrain box orients directional tiles
+pub fn create_rotation_system() -> impl systems::Runnable {
+    let rotate_selection_left = input::ActionRegistry::ROTATE_SELECTION_LEFT;
+    let rotate_selection_right = input::ActionRegistry::ROTATE_SELECTION_RIGHT;
+
+    SystemBuilder::new("selection_rotation_system")
+        .read_resource::<editor::EditorPaused>()
+        .read_resource::<crate::Time>()
+        .read_resource::<ClientID>()
+        .read_resource::<editor::ClientRole>()
+        .with_query(<(Read<input::InputActionComponent>, Read<input::Action>)>::query().filter(!component::<HeldWhilePaused>()))
+        .with_query(<(Entity, Read<ClientID>)>::query()
+            .filter(component::<SelectionBox>() & component::<ActorToolBox>() & component::<Active>()))
+        .with_query(<(Write<BoxTileOrientation>, Read<ClientID>)>::query()
+            .filter(component::<TerrainToolBox>() & component::<Active>()))
+        .build(move |commands, world, (editor_paused, time, client_id, client_role), queries| {
+
+            if editor_paused.0 {
+                return
+            }
+
+            let (input_query, selection_box_query, terrain_box_query) = queries;
+
+            if !client_role.can_edit() {
+                return
+            }
+
+            let inputs = input_query.iter(world)
+                .map(|(input, action)| (*input, (*action).clone()))
+                .collect::<Vec<(input::InputActionComponent, input::Action)>>();
+
+            inputs.into_iter()
+                .filter(|(_, a)|
+                    a == &rotate_selection_left
+                    || a == &rotate_selection_right
+                )
+                .for_each(|(input_component, action)| {
+                    if input_component.repeated(time.delta, 0.25) {
+
+                        //the terrain box only turns the tiles it inserts, which nobody else needs to know about until they're inserted
+                        terrain_box_query.iter_mut(world)
+                            .filter(|(_, id)| id.val() == client_id.val())
+                            .for_each(|(box_orientation, _)| {
+                                box_orientation.0 = box_orientation.0.rotated(if action == rotate_selection_left { 3 } else { 1 });
+                            });
+
+                        selection_box_query.iter_mut(world)
+                            .filter(|(_, id)| id.val() == client_id.val())
+                            .for_each(|(entity, _)| {
+
+                                let rotation = if action == rotate_selection_left {
+                                    Rotation3::from_axis_angle(&Vector3D::y_axis(), std::f32::consts::FRAC_PI_2)
+                                } else if action == rotate_selection_right {
+                                    Rotation3::from_axis_angle(&Vector3D::y_axis(), -std::f32::consts::FRAC_PI_2)
+                                } else {
+                                    Rotation3::identity()
+                                };
+
+                                let entity = *entity;
+                                let client_id = client_id.val();
+
+                                commands.exec_mut(move |world, _| {
+                                    actor_tool_rotation(world, entity, rotation);
+
+                                    world.push(
+                                        (MessageSender{
+                                            data_type: DataType::ActorToolRotation {
+                                                client_id,
+                                                rotation
+                                            },
+                                            message_type: MessageType::Ordered
+                                        },)
+                                    );
+                                });
+
+                            });
+
+                    }
+                });
+        })
+}
+
+/// Turns the actor box with the face_camera action so that the chosen actor faces the viewer. CameraAdjustedDirection already holds 
+/// whichever cardinal direction is closest to the camera's forward, so the actor is turned by whichever quarter turn points its 
+/// forward (-z, as in Godot) the opposite way.
+pub fn create_face_camera_system() -> impl systems::Runnable {
+    let face_camera = input::ActionRegistry::FACE_CAMERA;
+
+    SystemBuilder::new("selection_face_camera_system")
+        .read_resource::<editor::EditorPaused>()
+        .read_resource::<ClientID>()
+        .read_resource::<editor::ClientRole>()
+        .with_query(<(Read<input::InputActionComponent>, Read<input::Action>)>::query().filter(!component::<HeldWhilePaused>()))
+        .with_query(<(Entity, Read<ClientID>, Read<CameraAdjustedDirection>, Read<SelectionBoxRotation>)>::query()
+            .filter(component::<SelectionBox>() & component::<ActorToolBox>() & component::<Active>()))
+        .build(move |commands, world, (editor_paused, client_id, client_role), queries| {
+
+            if editor_paused.0 {
+                return
+            }
+
+            let (input_query, selection_box_query) = queries;
+
+            if !client_role.can_edit() {
+                return
+            }
+
+            if !input_query.iter(world).any(|(input_component, action)| action == &face_camera && input_component.just_pressed()) {
+                return
+            }
+
+            if let Some((entity, _, camera_adjusted_dir, box_rotation)) = selection_box_query.iter(world).find(|(_, id, _, _)| **id == **client_id) {
+
+                let toward_camera = -camera_adjusted_dir.forward;
+
+                let rotation = (0..4)
+                    .map(|quarter_turns| Rotation3::from_axis_angle(&Vector3D::y_axis(), quarter_turns as f32 * std::f32::consts::FRAC_PI_2))
+                    .max_by(|a, b| {
+                        let a_dot = (box_rotation.value * a * -Vector3D::z()).dot(&toward_camera);
+                        let b_dot = (box_rotation.value * b * -Vector3D::z()).dot(&toward_camera);
+
+                        a_dot.partial_cmp(&b_dot).unwrap_or(Ordering::Equal)
+                    })
+                    .unwrap_or_else(Rotation3::identity);
+
+                //already facing the camera
+                if rotation == Rotation3::identity() {
+                    return
+                }
+
+                let entity = *entity;
+                let client_id = client_id.val();
+
+                commands.exec_mut(move |world, _| {
+                    actor_tool_rotation(world, entity, rotation);
+
+                    world.push(
+                        (MessageSender{
+                            data_type: DataType::ActorToolRotation {
+                                client_id,
+                                rotation
+                            },
+                            message_type: MessageType::Ordered
+                        },)
+                    );
+                });
+            }
+        })
+}
+
+/// Toggles the ExpansionAxisLock on the active terrain tool box
+pub fn create_axis_lock_system() -> impl systems::Runnable {
+
+    let lock_axis_x = input::ActionRegistry::LOCK_AXIS_X;
+    let lock_axis_y = input::ActionRegistry::LOCK_AXIS_Y;
+    let lock_axis_z = input::ActionRegistry::LOCK_AXIS_Z;
+
+    SystemBuilder::new("selection_axis_lock_system")
+        .read_resource::<editor::EditorPaused>()
+        .read_resource::<ClientID>()
+        .with_query(<(Read<input::InputActionComponent>, Read<input::Action>)>::query().filter(!component::<HeldWhilePaused>()))
+        .with_query(<(Write<ExpansionAxisLock>, Read<ClientID>)>::query()
+            .filter(component::<TerrainToolBox>() & component::<Active>()))
+        .build(move |_, world, (editor_paused, client_id), queries| {
+
+            if editor_paused.0 {
+                return
+            }
+
+            let (input_query, selection_box_query) = queries;
+
+            let toggles = input_query.iter(world)
+                .filter(|(input_component, a)| input_component.just_pressed() && (
+                    *a == &lock_axis_x ||
+                    *a == &lock_axis_y ||
+                    *a == &lock_axis_z
+                ))
+                .map(|(_, action)| (*action).clone())
+                .collect::<Vec<input::Action>>();
+
+            if toggles.is_empty() {
+                return
+            }
+
+            selection_box_query.iter_mut(world)
+                .filter(|(_, id)| **id == **client_id)
+                .for_each(|(axis_lock, _)| {
+                    for action in &toggles {
+                        if action == &lock_axis_x {
+                            axis_lock.toggle(0);
+                        } else if action == &lock_axis_y {
+                            axis_lock.toggle(1);
+                        } else if action == &lock_axis_z {
+                            axis_lock.toggle(2);
+                        }
+                    }
+                });
+        })
+}
+
+/// Lets the local client drag out its terrain box with the mouse. Pressing box_drag anchors the cell under the cursor, and while it's 
+/// held the box spans from the anchor to the cell under the cursor, whichever way that is. Cells are picked on the plane of the box's 
+/// floor and the box keeps its height. The box only follows along locally during the drag, and the bounds get sent to everyone else 
+/// once it's released.
+pub fn create_box_drag_system() -> impl systems::Runnable {
+    let box_drag = input::ActionRegistry::BOX_DRAG;
+
+    //the cell that the drag started from, and the bounds the box was last dragged to
+    let mut anchor: Option<Point> = None;
+    let mut dragged: Option<(Point, AABB)> = None;
+
+    SystemBuilder::new("selection_box_drag_system")
+        .read_resource::<editor::EditorPaused>()
+        .read_resource::<ClientID>()
+        .with_query(<(Read<input::InputActionComponent>, Read<input::Action>)>::query().filter(!component::<HeldWhilePaused>()))
+        .with_query(<(Read<ClientID>, Read<level_map::CoordPos>, Read<SelectionBox>, Read<RelativeCamera>)>::query()
+            .filter(component::<TerrainToolBox>() & component::<Active>()))
+        .build(move |commands, world, (editor_paused, client_id), queries| {
+
+            if editor_paused.0 {
+                return
+            }
+
+            let (input_query, selection_box_query) = queries;
+
+            let client_id = **client_id;
+
+            let held = input_query.iter(world)
+                .find(|(input_component, action)| *action == &box_drag && input_component.strength > 0.)
+                .map(|(input_component, _)| *input_component);
+
+            if held.is_none() {
+                anchor = None;
+
+                if let Some((coord_pos, aabb)) = dragged.take() {
+                    commands.exec_mut(move |world, resources| {
+                        send_update_bounds(world, resources, client_id, coord_pos, aabb);
+                    });
+                }
+
+                return
+            }
+
+            let (coord_pos, selection_box, relative_camera) = match selection_box_query.iter(world).find(|(id, _, _, _)| **id == client_id) {
+                Some((_, coord_pos, selection_box, relative_camera)) => (*coord_pos, *selection_box, relative_camera.val()),
+                None => return
+            };
+
+            let min = AABB::new(coord_pos.value, selection_box.aabb.dimensions).get_min();
+
+            let cell = match unsafe { cell_under_cursor(relative_camera, min.y) } {
+                Some(cell) => cell,
+                None => return
+            };
+
+            if held.map_or(false, |input_component| input_component.just_pressed()) {
+                anchor = Some(cell);
+            }
+
+            let anchor = match anchor {
+                Some(anchor) => anchor,
+                None => return
+            };
+
+            let drag_min = anchor.inf(&cell);
+            let mut drag_max = anchor.sup(&cell);
+            drag_max.y = drag_min.y + selection_box.aabb.dimensions.y.abs() - 1;
+
+            let world_aabb = AABB::from_extents(drag_min, drag_max);
+
+            let mut aabb = selection_box.aabb;
+            aabb.dimensions = world_aabb.dimensions;
+
+            if dragged == Some((world_aabb.center, aabb)) {
+                return
+            }
+
+            dragged = Some((world_aabb.center, aabb));
+
+            let coord_pos = world_aabb.center;
+
+            commands.exec_mut(move |world, _| {
+                set_pending_update_bounds(world, client_id, coord_pos, aabb);
+            });
+        })
+}
+
+/// Returns the cell on layer y that is under the mouse cursor, as seen through the camera, or None if the camera isn't looking towards 
+/// that layer
+unsafe fn cell_under_cursor(camera: Ref<Node>, y: i32) -> Option<Point> {
+    let camera = camera.assume_safe().cast::<Camera>()?;
+    let viewport = camera.get_viewport()?;
+
+    let mouse_position = viewport.assume_safe().get_mouse_position();
+
+    let origin = camera.project_ray_origin(mouse_position);
+    let normal = camera.project_ray_normal(mouse_position);
+
+    let plane_y = y as f32 * level_map::TILE_DIMENSIONS.y;
+
+    if normal.y.abs() < std::f32::EPSILON {
+        return None
+    }
+
+    let distance = (plane_y - origin.y) / normal.y;
+
+    if distance < 0. {
+        return None
+    }
+
+    let hit = origin + normal * distance;
+
+    Some(Point::new(
+        (hit.x / level_map::TILE_DIMENSIONS.x).floor() as i32,
+        y,
+        (hit.z / level_map::TILE_DIMENSIONS.z).floor() as i32
+    ))
+}
+
+/// Keeps the terrain box on the layer it's currently on with the lock_layer action, or lets it move freely again if it was already 
+/// kept on one
+pub fn create_layer_lock_system() -> impl systems::Runnable {
+    let lock_layer = input::ActionRegistry::LOCK_LAYER;
+
+    SystemBuilder::new("selection_layer_lock_system")
+        .read_resource::<editor::EditorPaused>()
+        .read_resource::<ClientID>()
+        .write_resource::<editor::LockedLayer>()
+        .with_query(<(Read<input::InputActionComponent>, Read<input::Action>)>::query().filter(!component::<HeldWhilePaused>()))
+        .with_query(<(Read<level_map::CoordPos>, Read<ClientID>)>::query()
+            .filter(component::<TerrainToolBox>() & component::<Active>()))
+        .build(move |_, world, (editor_paused, client_id, locked_layer), queries| {
+
+            if editor_paused.0 {
+                return
+            }
+
+            let (input_query, selection_box_query) = queries;
+
+            if !input_query.iter(world).any(|(input_component, action)| action == &lock_layer && input_component.just_pressed()) {
+                return
+            }
+
+            if locked_layer.0.is_some() {
+                locked_layer.0 = None;
+                return
+            }
+
+            if let Some((coord_pos, _)) = selection_box_query.iter(world).find(|(_, id)| **id == **client_id) {
+                locked_layer.0 = Some(coord_pos.value.y);
+            }
+        })
+}
+
+/// Makes wherever the local client's active box is the DisplayOrigin with the set_display_origin action
+pub fn create_display_origin_system() -> impl systems::Runnable {
+    let set_display_origin = input::ActionRegistry::SET_DISPLAY_ORIGIN;
+
+    SystemBuilder::new("selection_display_origin_system")
+        .read_resource::<editor::EditorPaused>()
+        .read_resource::<ClientID>()
+        .write_resource::<editor::DisplayOrigin>()
+        .with_query(<(Read<input::InputActionComponent>, Read<input::Action>)>::query().filter(!component::<HeldWhilePaused>()))
+        .with_query(<(Read<level_map::CoordPos>, Read<ClientID>)>::query()
+            .filter(component::<SelectionBox>() & component::<Active>()))
+        .build(move |_, world, (editor_paused, client_id, display_origin), queries| {
+
+            if editor_paused.0 {
+                return
+            }
+
+            let (input_query, selection_box_query) = queries;
+
+            if !input_query.iter(world).any(|(input_component, action)| action == &set_display_origin && input_component.just_pressed()) {
+                return
+            }
+
+            if let Some((coord_pos, _)) = selection_box_query.iter(world).find(|(_, id)| **id == **client_id) {
+                display_origin.0 = coord_pos.value;
+            }
+        })
+}
+
+/// Draws a translucent wireframe of the actor's exact Bounds inside the actor tool box, since the box itself is snapped to whole cells
+pub fn create_actor_bounds_system() -> impl systems::Runnable {
+    SystemBuilder::new("selection_box_actor_bounds_system")
+        .read_component::<actor::Bounds>()
+        .with_query(<(Read<SelectionBox>, Read<SelectionBoxRotation>, Read<EntityRef>, Read<node::NodeRef>)>::query()
+            .filter(component::<ActorToolBox>() & (
+                maybe_changed::<SelectionBox>() | 
+                maybe_changed::<SelectionBoxRotation>() | 
+                maybe_changed::<EntityRef>() | 
+                maybe_changed::<node::NodeRef>()
+            ))
+        )
+        .build(move |_, world, _, query| {
+
+            let boxes = query.iter(world)
+                .map(|(selection_box, rotation, entity_ref, node_ref)| (*selection_box, rotation.value, entity_ref.0, node_ref.val()))
+                .collect::<Vec<(SelectionBox, Rotation3<f32>, Entity, Ref<Node>)>>();
+
+            for (selection_box, rotation, actor_entity, node) in boxes {
+
+                let bounds = match world.entry_ref(actor_entity).ok()
+                    .and_then(|entry| entry.get_component::<actor::Bounds>().ok().copied()) {
+                        Some(bounds) => bounds.0,
+                        None => continue
+                    };
+
+                //same local space as the mesh in create_system, the actor sits centered on the floor of the box
+                let center = level_map::map_coords_to_world(selection_box.aabb.center);
+                let min = level_map::map_coords_to_world(selection_box.aabb.get_min()) - center;
+                let max = level_map::map_coords_to_world(selection_box.aabb.get_max() + Point::new(1,1,1)) - center;
+                let base = Vector3D::new((min.x + max.x) / 2., Float::min(min.y, max.y), (min.z + max.z) / 2.);
+
+                let corners = (0..8).map(|i| {
+                    let corner = Vector3D::new(
+                        if i & 1 == 0 { -bounds.x / 2. } else { bounds.x / 2. },
+                        if i & 2 == 0 { 0. } else { bounds.y },
+                        if i & 4 == 0 { -bounds.z / 2. } else { bounds.z / 2. },
+                    );
+
+                    let pt = rotation.transform_vector(&corner) + base;
+                    Vector3::new(pt.x, pt.y, pt.z)
+                }).collect::<Vec<Vector3>>();
+
+                unsafe {
+                    let node = node.assume_safe();
+
+                    let geometry = match node::get_node(&node, ACTOR_BOUNDS_NAME, false) {
+                        Some(geometry) => geometry.assume_safe().cast::<ImmediateGeometry>().unwrap(),
+                        None => {
+                            let geometry = ImmediateGeometry::new();
+                            geometry.set_name(ACTOR_BOUNDS_NAME);
+
+                            let material = SpatialMaterial::new();
+                            material.set_flag(SpatialMaterial::FLAG_UNSHADED, true);
+                            material.set_feature(SpatialMaterial::FEATURE_TRANSPARENT, true);
+                            material.set_albedo(ACTOR_BOUNDS_COLOR);
+                            geometry.set_material_override(material);
+
+                            node::add_node(&node, geometry.upcast::<Node>()).assume_safe().cast::<ImmediateGeometry>().unwrap()
+                        }
+                    };
+
+                    geometry.clear();
+                    geometry.begin(Mesh::PRIMITIVE_LINES, Null::null());
+
+                    //every pair of corners whose indices differ by a single bit shares an edge
+                    for i in 0..8 {
+                        for bit in &[1, 2, 4] {
+                            if i & bit == 0 {
+                                geometry.add_vertex(corners[i]);
+                                geometry.add_vertex(corners[i | bit]);
+                            }
+                        }
+                    }
+
+                    geometry.end();
+                }
+            }
+        })
+}
+
+/// Returns the ends of the lines along every cell boundary of a box starting at min, both on its faces and through its volume. The 
+/// lines run the whole length of the box along each axis, and the ones on the box's own edges are left out as the box draws those.
+fn grid_lines(min: Vector3D, cell: Vector3D, dimensions: Point) -> Vec<(Vector3D, Vector3D)> {
+
+    let mut lines = Vec::new();
+
+    for axis in 0..3 {
+        let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+
+        for i in 0..=dimensions[u] {
+            for j in 0..=dimensions[v] {
+
+                if (i == 0 || i == dimensions[u]) && (j == 0 || j == dimensions[v]) {
+                    continue
+                }
+
+                let mut start = min;
+                start[u] += cell[u] * i as f32;
+                start[v] += cell[v] * j as f32;
+
+                let mut end = start;
+                end[axis] += cell[axis] * dimensions[axis] as f32;
+
+                lines.push((start, end));
+            }
+        }
+    }
+
+    lines
+}
+
+/// Draws lines at every cell boundary through the selection boxes while ShowGridLines is on, skipping any box with more cells than 
+/// GridLinesMaxVolume
+pub fn create_grid_lines_system() -> impl systems::Runnable {
+
+    let mut drawn: Option<(bool, i32)> = None;
+
+    SystemBuilder::new("selection_box_grid_lines_system")
+        .read_resource::<editor::ShowGridLines>()
+        .read_resource::<editor::GridLinesMaxVolume>()
+        .with_query(<(Read<SelectionBox>, Read<node::NodeRef>)>::query())
+        .with_query(<(Read<SelectionBox>, Read<node::NodeRef>)>::query()
+            .filter(maybe_changed::<SelectionBox>() | maybe_changed::<node::NodeRef>())
+        )
+        .build(move |_, world, (show_grid_lines, max_volume), queries| {
+
+            let (all_query, changed_query) = queries;
+
+            let settings = (show_grid_lines.0, max_volume.0);
+
+            let boxes = if drawn != Some(settings) {
+                drawn = Some(settings);
+                all_query.iter(world).map(|(selection_box, node_ref)| (*selection_box, node_ref.val())).collect::<Vec<(SelectionBox, Ref<Node>)>>()
+            } else {
+                changed_query.iter(world).map(|(selection_box, node_ref)| (*selection_box, node_ref.val())).collect::<Vec<(SelectionBox, Ref<Node>)>>()
+            };
+
+            for (selection_box, node) in boxes {
+
+                let dimensions = selection_box.aabb.dimensions.abs();
+                let show = show_grid_lines.0 && dimensions.x * dimensions.y * dimensions.z <= max_volume.0;
+
+                unsafe {
+                    let node = node.assume_safe();
+
+                    let geometry = match node::get_node(&node, GRID_LINES_NAME, false) {
+                        Some(geometry) => geometry.assume_safe().cast::<ImmediateGeometry>().unwrap(),
+                        None if !show => continue,
+                        None => {
+                            let geometry = ImmediateGeometry::new();
+                            geometry.set_name(GRID_LINES_NAME);
+
+                            let material = SpatialMaterial::new();
+                            material.set_flag(SpatialMaterial::FLAG_UNSHADED, true);
+                            material.set_feature(SpatialMaterial::FEATURE_TRANSPARENT, true);
+                            material.set_albedo(GRID_LINES_COLOR);
+                            geometry.set_material_override(material);
+
+                            node::add_node(&node, geometry.upcast::<Node>()).assume_safe().cast::<ImmediateGeometry>().unwrap()
+                        }
+                    };
+
+                    geometry.clear();
+                    geometry.set_visible(show);
+
+                    if !show {
+                        continue
+                    }
+
+                    //same local space as the mesh in create_system
+                    let center = level_map::map_coords_to_world(selection_box.aabb.center);
+                    let min = level_map::map_coords_to_world(selection_box.aabb.get_min()) - center;
+                    let cell = level_map::map_coords_to_world(Point::new(1,1,1));
+
+                    geometry.begin(Mesh::PRIMITIVE_LINES, Null::null());
+
+                    for (a, b) in grid_lines(min, cell, dimensions) {
+                        geometry.add_vertex(Vector3::new(a.x, a.y, a.z));
+                        geometry.add_vertex(Vector3::new(b.x, b.y, b.z));
+                    }
+
+                    geometry.end();
+                }
+            }
+        })
+}
+
+/// Cycles the PasteRotation through quarter turns with the rotate_paste action, resizing the local client's terrain box to fit the 
+/// turned prefab
+pub fn create_rotate_paste_system() -> impl systems::Runnable {
+    let rotate_paste = input::ActionRegistry::ROTATE_PASTE;
+
+    SystemBuilder::new("rotate_paste_system")
+        .read_resource::<editor::EditorPaused>()
+        .read_resource::<ClientID>()
+        .read_resource::<prefab::SelectedPrefab>()
+        .write_resource::<editor::PasteRotation>()
+        .with_query(<(Read<input::InputActionComponent>, Read<input::Action>)>::query().filter(!component::<HeldWhilePaused>()))
+        .build(move |commands, world, (editor_paused, client_id, selected_prefab, paste_rotation), input_query| {
+
+            if editor_paused.0 {
+                return
+            }
+
+            if !input_query.iter(world).any(|(input_component, action)| action == &rotate_paste && input_component.just_pressed()) {
+                return
+            }
+
+            paste_rotation.0 = (paste_rotation.0 + 1) % 4;
+
+            if let Some(prefab) = &selected_prefab.0 {
+                let dimensions = prefab.rotated(paste_rotation.0).dimensions;
+                let client_id = **client_id;
+
+                commands.exec_mut(move |world, resources| {
+                    if let Err(err) = set_selection_box_dimensions(world, resources, client_id, dimensions) {
+                        godot_print!("Couldn't resize the selection box for the rotated prefab: {}", err);
+                    }
+                });
+            }
+        })
+}
+
+/// Shrinks the local client's active box back down with the reset_box action. The terrain box goes back to a single cell at its 
+/// current center, while the actor box goes back to the natural Bounds of the chosen actor.
+pub fn create_reset_box_system() -> impl systems::Runnable {
+    let reset_box = input::ActionRegistry::RESET_BOX;
+
+    SystemBuilder::new("selection_box_reset_system")
+        .read_resource::<editor::EditorPaused>()
+        .read_resource::<ClientID>()
+        .read_component::<actor::Bounds>()
+        .with_query(<(Read<input::InputActionComponent>, Read<input::Action>)>::query().filter(!component::<HeldWhilePaused>()))
+        .with_query(<(Read<ClientID>, Read<level_map::CoordPos>, Read<SelectionBox>, TryRead<SelectionBoxRotation>, TryRead<EntityRef>, TryRead<ActorToolBox>)>::query()
+            .filter(component::<Active>()))
+        .build(move |commands, world, (editor_paused, client_id), queries| {
+
+            if editor_paused.0 {
+                return
+            }
+
+            let (input_query, selection_box_query) = queries;
+
+            if !input_query.iter(world).any(|(input_component, action)| action == &reset_box && input_component.just_pressed()) {
+                return
+            }
+
+            let active = selection_box_query.iter(world)
+                .find(|(id, _, _, _, _, _)| **id == **client_id)
+                .map(|(_, coord_pos, selection_box, rotation, entity_ref, actor_tool_box)| (
+                    coord_pos.value, 
+                    selection_box.aabb, 
+                    rotation.map(|rotation| rotation.value), 
+                    entity_ref.map(|entity_ref| entity_ref.0), 
+                    actor_tool_box.is_some()
+                ));
+
+            let (coord_pos, aabb, rotation, actor_entity, is_actor_tool) = match active {
+                Some(active) => active,
+                None => return
+            };
+
+            let mut new_aabb = if is_actor_tool {
+                let bounds = actor_entity
+                    .and_then(|actor_entity| world.entry_ref(actor_entity).ok())
+                    .and_then(|entry| entry.get_component::<actor::Bounds>().ok().copied());
+
+                match (bounds, rotation) {
+                    (Some(bounds), Some(rotation)) => bounds.get_scaled_and_rotated_aabb(rotation),
+                    //nothing has been chosen yet, so there are no bounds to go back to
+                    _ => return
+                }
+            } else {
+                AABB::new(aabb.center, Point::new(1,1,1))
+            };
+
+            new_aabb.center = aabb.center;
+
+            if new_aabb == aabb {
+                return
+            }
+
+            let client_id = **client_id;
+
+            commands.exec_mut(move |world, resources| {
+                send_update_bounds(world, resources, client_id, coord_pos, new_aabb);
+            });
+        })
+}
+
+/// Draws a translucent preview of what an insertion would place inside the local client's active terrain box, following the fill 
+/// mode or the selected prefab. The ghost is hidden after a commit until the box changes again, and never touches the Map.
+pub fn create_ghost_system() -> impl systems::Runnable {
+
+    //the box and settings the ghost was last drawn for
+    let mut drawn: Option<(Point, Point, editor::FillMode, Option<(String, u8)>)> = None;
+    //the box that was committed to, which the ghost stays hidden for
+    let mut committed: Option<(Point, Point)> = None;
+
+    SystemBuilder::new("selection_box_ghost_system")
+        .read_resource::<ClientID>()
+        .read_resource::<editor::ShowGhost>()
+        .read_resource::<editor::FillMode>()
+        .read_resource::<prefab::SelectedPrefab>()
+        .read_resource::<editor::PasteRotation>()
+        .with_query(<(Read<SelectionBox>, Read<level_map::CoordPos>, Read<ClientID>, Read<node::NodeRef>, TryRead<Active>)>::query()
+            .filter(component::<TerrainToolBox>()))
+        .with_query(<Read<ClientID>>::query()
+            .filter(component::<LastCommitTime>() & maybe_changed::<LastCommitTime>()))
+        .build(move |_, world, (client_id, show_ghost, fill_mode, selected_prefab, paste_rotation), queries| {
+
+            let (selection_box_query, commit_query) = queries;
+
+            let local_box = selection_box_query.iter(world)
+                .find(|(_, _, id, _, _)| **id == **client_id)
+                .map(|(selection_box, coord_pos, _, node_ref, active)| (*selection_box, coord_pos.value, node_ref.val(), active.is_some()));
+
+            let (selection_box, coord_pos, node, active) = match local_box {
+                Some(local_box) => local_box,
+                None => return
+            };
+
+            let current = (coord_pos, selection_box.aabb.dimensions);
+
+            if commit_query.iter(world).any(|id| *id == **client_id) {
+                committed = Some(current);
+            }
+
+            let show = show_ghost.0 && active && committed != Some(current);
+
+            let prefab = selected_prefab.0.as_ref();
+            let settings = (coord_pos, selection_box.aabb.dimensions, **fill_mode, prefab.map(|prefab| (prefab.name.clone(), paste_rotation.0)));
+
+            unsafe {
+                let node = node.assume_safe();
+
+                let geometry = match node::get_node(&node, GHOST_NAME, false) {
+                    Some(geometry) => geometry.assume_safe().cast::<ImmediateGeometry>().unwrap(),
+                    None if !show => return,
+                    None => {
+                        let geometry = ImmediateGeometry::new();
+                        geometry.set_name(GHOST_NAME);
+
+                        let material = SpatialMaterial::new();
+                        material.set_flag(SpatialMaterial::FLAG_UNSHADED, true);
+                        material.set_feature(SpatialMaterial::FEATURE_TRANSPARENT, true);
+                        material.set_albedo(GHOST_COLOR);
+                        geometry.set_material_override(material);
+
+                        node::add_node(&node, geometry.upcast::<Node>()).assume_safe().cast::<ImmediateGeometry>().unwrap()
+                    }
+                };
+
+                geometry.set_visible(show);
+
+                if !show || drawn.as_ref() == Some(&settings) {
+                    return
+                }
+
+                let aabb = AABB::new(coord_pos, selection_box.aabb.dimensions);
+
+                let cuboids = match prefab.map(|prefab| prefab.rotated(paste_rotation.0)) {
+                    Some(prefab) => prefab.tiles.iter()
+                        .map(|(offset, _)| AABB::from_extents(aabb.get_min() + offset, aabb.get_min() + offset))
+                        .collect::<Vec<AABB>>(),
+                    None => fill_mode.split(aabb)
+                };
+
+                drawn = Some(settings);
+
+                //same local space as the mesh in create_system
+                let offset = selection_box.aabb.center - coord_pos;
+                let center = level_map::map_coords_to_world(selection_box.aabb.center);
+
+                geometry.clear();
+                geometry.begin(Mesh::PRIMITIVE_TRIANGLES, Null::null());
+
+                for cuboid in cuboids {
+                    let min = level_map::map_coords_to_world(cuboid.get_min() + offset) - center;
+                    let size = level_map::map_coords_to_world(cuboid.dimensions.abs());
+
+                    for axis in 0..3 {
+                        let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+
+                        for far in [false, true].iter() {
+                            let mut corners = [min; 4];
+
+                            if *far {
+                                for corner in corners.iter_mut() {
+                                    corner[axis] += size[axis];
+                                }
+                            }
+                            corners[1][u] += size[u];
+                            corners[2][u] += size[u];
+                            corners[2][v] += size[v];
+                            corners[3][v] += size[v];
+
+                            //u cross v points along the axis, and Godot's front faces wind clockwise when seen from outside, so 
+                            //only the near face keeps the corners' order
+                            let order = if *far { [0, 2, 1, 0, 3, 2] } else { [0, 1, 2, 0, 2, 3] };
+
+                            for i in order.iter() {
+                                let corner = corners[*i];
+                                geometry.add_vertex(Vector3::new(corner.x, corner.y, corner.z));
+                            }
+                        }
+                    }
+                }
+
+                geometry.end();
+            }
+        })
+}
+
+/// Flips the ShowDimensions resource
+pub fn create_show_dimensions_toggle_system() -> impl systems::Runnable {
+
+    let toggle_dimensions = input::ActionRegistry::TOGGLE_DIMENSIONS;
+
+    SystemBuilder::new("selection_box_show_dimensions_toggle_system")
+        .read_resource::<editor::EditorPaused>()
+        .write_resource::<editor::ShowDimensions>()
+        .with_query(<(Read<input::InputActionComponent>, Read<input::Action>)>::query().filter(!component::<HeldWhilePaused>()))
+        .build(move |_, world, (editor_paused, show_dimensions), query| {
+
+            if editor_paused.0 {
+                return
+            }
+
+            if query.iter(world).any(|(input_component, action)| action == &toggle_dimensions && input_component.just_pressed()) {
+                show_dimensions.0 = !show_dimensions.0;
+            }
+        })
+}
+
+/// Keeps billboarded labels at the centers of the selection box faces that show the length of each axis, updating them whenever the 
+/// SelectionBox changes or ShowDimensions is toggled
+pub fn create_dimension_labels_system() -> impl systems::Runnable {
+
+    let mut shown: Option<bool> = None;
+
+    SystemBuilder::new("selection_box_dimension_labels_system")
+        .read_resource::<editor::ShowDimensions>()
+        .with_query(<(Read<SelectionBox>, Read<node::NodeRef>)>::query())
+        .with_query(<(Read<SelectionBox>, Read<node::NodeRef>)>::query()
+            .filter(maybe_changed::<SelectionBox>() | maybe_changed::<node::NodeRef>())
+        )
+        .build(move |_, world, show_dimensions, queries| {
+
+            let (all_query, changed_query) = queries;
+
+            let show = show_dimensions.0;
+
+            let boxes = if shown != Some(show) {
+                shown = Some(show);
+                all_query.iter(world).map(|(selection_box, node_ref)| (*selection_box, node_ref.val())).collect::<Vec<(SelectionBox, Ref<Node>)>>()
+            } else {
+                changed_query.iter(world).map(|(selection_box, node_ref)| (*selection_box, node_ref.val())).collect::<Vec<(SelectionBox, Ref<Node>)>>()
+            };
+
+            for (selection_box, node) in boxes {
+
+                //same local space as the mesh in create_system
+                let center = level_map::map_coords_to_world(selection_box.aabb.center);
+                let min = level_map::map_coords_to_world(selection_box.aabb.get_min()) - center;
+                let max = level_map::map_coords_to_world(selection_box.aabb.get_max() + Point::new(1,1,1)) - center;
+                let mid = (max + min) / 2.0;
+
+                let dimensions = selection_box.aabb.dimensions.abs();
+
+                //each length is shown on a face that the axis runs across
+                let labels = [
+                    (format!("x: {}", dimensions.x), Vector3::new(mid.x, mid.y, max.z)),
+                    (format!("y: {}", dimensions.y), Vector3::new(max.x, mid.y, mid.z)),
+                    (format!("z: {}", dimensions.z), Vector3::new(mid.x, max.y, mid.z)),
+                ];
+
+                unsafe {
+                    let node = node.assume_safe();
+
+                    for (name, (text, translation)) in DIMENSION_LABEL_NAMES.iter().zip(labels.iter()) {
+
+                        let sprite = match node::get_node(&node, name, false) {
+                            Some(sprite) => sprite.assume_safe().cast::<Sprite3D>().unwrap(),
+                            None if !show => continue,
+                            None => create_dimension_label(&node, name)
+                        };
+
+                        sprite.set_visible(show);
+
+                        if show {
+                            sprite.set_translation(*translation);
+
+                            if let Some(label) = node::get_child_by_type::<Label>(&sprite.upcast::<Node>(), true) {
+                                label.assume_safe().set_text(text.as_str());
+                            }
+                        }
+                    }
+                }
+            }
+        })
+}
+
+/// Creates a camera facing Sprite3D that displays a Label through its own Viewport
+unsafe fn create_dimension_label<'a>(parent: &'a Node, name: &str) -> TRef<'a, Sprite3D> {
+
+    let viewport = Viewport::new();
+    viewport.set_name("Viewport");
+    viewport.set_size(Vector2::new(96., 32.));
+    viewport.set_transparent_background(true);
+    viewport.set_usage(Viewport::USAGE_2D);
+    viewport.set_update_mode(Viewport::UPDATE_ALWAYS);
+    viewport.set_vflip(true);
+
+    let label = Label::new();
+    label.set_name("Label");
+    label.set_align(Label::ALIGN_CENTER);
+    label.set_valign(Label::VALIGN_CENTER);
+    label.set_size(Vector2::new(96., 32.), false);
+    viewport.add_child(label, false);
+
+    let viewport = viewport.into_shared();
+
+    let sprite = Sprite3D::new();
+    sprite.set_name(name);
+    sprite.set_billboard_mode(SpatialMaterial::BILLBOARD_ENABLED);
+    sprite.set_draw_flag(SpriteBase3D::FLAG_DISABLE_DEPTH_TEST, true);
+    sprite.set_pixel_size(0.01);
+    sprite.add_child(viewport, false);
+    sprite.set_texture(viewport.assume_safe().get_texture());
+
+    let sprite = node::add_node(parent, sprite.upcast::<Node>());
+
+    sprite.assume_safe().cast::<Sprite3D>().unwrap()
+}
+
+/// Returns how much the expansion action grows the box by, with forward/back and left/right turned to follow the camera
+fn expansion_for_action(action: &input::Action, camera_adjusted_dir: &CameraAdjustedDirection) -> Point {
+
+    let mut expansion = Point::zeros();
+
+    if *action == input::ActionRegistry::EXPAND_SELECTION_FORWARD {
+        expansion.z += 1;
+    } else if *action == input::ActionRegistry::EXPAND_SELECTION_BACK {
+        expansion.z -= 1;
+    } else if *action == input::ActionRegistry::EXPAND_SELECTION_LEFT {
+        expansion.x -= 1;
+    } else if *action == input::ActionRegistry::EXPAND_SELECTION_RIGHT {
+        expansion.x += 1;
+    } else if *action == input::ActionRegistry::EXPAND_SELECTION_DOWN {
+        expansion.y -= 1;
+    } else if *action == input::ActionRegistry::EXPAND_SELECTION_UP {
+        expansion.y += 1;
+    }
+
+    let forward = camera_adjusted_dir.forward;
+    let right = camera_adjusted_dir.right;
+
+    let mut adjusted = Point::new(
+        forward.x.round().abs() as i32,
+        0,
+        forward.z.round().abs() as i32
+    ) * expansion.z as i32 + Point::new(
+        right.x.round().abs() as i32,
+        0,
+        right.z.round().abs() as i32
+    ) * expansion.x as i32;
+
+    adjusted.y = expansion.y as i32;
+
+    adjusted
+}
+
+/// Zeroes out the parts of the expansion on axes locked by the box or by the editor, and on y while the box is kept on a layer
+fn locked_expansion(expansion: Point, axis_lock: ExpansionAxisLock, global_axis_lock: editor::AxisLock, layer_locked: bool) -> Point {
+
+    let mut expansion = global_axis_lock.apply(axis_lock.apply(expansion));
+
+    //the box can't grow off of the layer it's been kept on
+    if layer_locked {
+        expansion.y = 0;
+    }
+
+    expansion
+}
+
+/// Expands the dimensions of the selection box
+pub fn create_expansion_system() -> impl systems::Runnable {    
+
+    let expand_selection_forward = input::ActionRegistry::EXPAND_SELECTION_FORWARD;
+    let expand_selection_back = input::ActionRegistry::EXPAND_SELECTION_BACK;
+    let expand_selection_left = input::ActionRegistry::EXPAND_SELECTION_LEFT;
+    let expand_selection_right = input::ActionRegistry::EXPAND_SELECTION_RIGHT;
+    let expand_selection_up = input::ActionRegistry::EXPAND_SELECTION_UP;
+    let expand_selection_down = input::ActionRegistry::EXPAND_SELECTION_DOWN;
+
+    SystemBuilder::new("selection_expansion_system")
+        .read_resource::<editor::EditorPaused>()
+        .read_resource::<crate::Time>()
+        .read_resource::<ClientID>()
+        .read_resource::<editor::ClientRole>()
+        .read_resource::<editor::AxisLock>()
+        .read_resource::<editor::LockedLayer>()
+        .with_query(<(Read<input::InputActionComponent>, Read<input::Action>)>::query().filter(!component::<HeldWhilePaused>()))
+        .with_query(<(Read<CameraAdjustedDirection>, Read<ClientID>, Read<level_map::CoordPos>, Read<SelectionBox>, Read<ExpansionAxisLock>)>::query()
+            .filter(component::<TerrainToolBox>() & component::<Active>()))
+        .build(move |commands, world, (editor_paused, time, client_id, client_role, global_axis_lock, locked_layer), queries| {
+
+            if editor_paused.0 {
+                return
+            }
+
+            let (input_query, selection_box_query) = queries;
+
+            if !client_role.can_edit() {
+                return
+            }
+
+            let inputs = input_query.iter(world)
+                .map(|(input, action)| (*input, (*action).clone()))
+                .collect::<Vec<(input::InputActionComponent, input::Action)>>();
+
+            //left: movement, right: expansion
+            let mut combined_expansion: Option<Point> = None;
+            let mut entity: Option<(CameraAdjustedDirection, Point, AABB, ClientID, ExpansionAxisLock)> = None;
+
+            for(input_component, action) in inputs.iter().filter(|(_, a)|
+                a == &expand_selection_forward ||
+                a == &expand_selection_back ||
+                a == &expand_selection_left ||
+                a == &expand_selection_right ||
+                a == &expand_selection_up ||
+                a == &expand_selection_down
+            ) {                    
+                
+                if input_component.repeated(time.delta, 0.25) {
+
+                    selection_box_query.iter(world)
+                        .filter(|(_, id, _, _, _)| **id == **client_id)
+                        .for_each(|(camera_adjusted_dir, client_id, coord_pos, selection_box, axis_lock)| {
+
+                        entity = Some((*camera_adjusted_dir, coord_pos.value, selection_box.aabb, *client_id, *axis_lock));
+
+                        combined_expansion = Some(expansion_for_action(action, camera_adjusted_dir));
+
+                    }); 
+                }
+            }
+
+            if let Some(combined_expansion) = combined_expansion {
+                if let Some((camera_adjusted_dir, coord_pos_value, aabb, client_id, axis_lock)) = entity {
+
+                    let combined_expansion = locked_expansion(combined_expansion, axis_lock, **global_axis_lock, locked_layer.0.is_some());
+
+                    //every axis being expanded on is locked, so there is nothing to do
+                    if combined_expansion == Point::zeros() {
+                        return
+                    }
+                    
+                    commands.exec_mut(move |world, resources| {
+                        expand_selection(world, resources, client_id, coord_pos_value, aabb, camera_adjusted_dir, combined_expansion);
+                    });
+                }
+            }  
+        })
+}
+
+/// Returns the client's terrain box coord_pos and aabb from before and after growing it by expansion, building on top of any bounds 
+/// update that is still pending. Nothing is applied or sent.
+fn expanded_bounds(world: &World, resources: &Resources, client_id: ClientID, coord_pos: Point, aabb: AABB, 
+    camera_adjusted_dir: CameraAdjustedDirection, expansion: Point) -> ((Point, AABB), (Point, AABB)) {
+
+    let from_far_side = resources.get::<editor::ExpandFromFarSide>().map(|far_side| far_side.0).unwrap_or(false);
+
+    //expand from wherever the box is going to end up so that the message always matches the local state
+    let before = pending_update_bounds(world, client_id).unwrap_or((coord_pos, aabb));
+
+    let mut new_aabb = before.1;
+                
+    let diff = expansion_movement_helper(expansion, camera_adjusted_dir, from_far_side, &mut new_aabb);
+
+    (before, (before.0 - diff, new_aabb))
+}
+
+/// Grows the client's terrain box by expansion, building on top of any bounds update that is still pending, and sends the update
+fn expand_selection(world: &mut World, resources: &Resources, client_id: ClientID, coord_pos: Point, aabb: AABB, 
+    camera_adjusted_dir: CameraAdjustedDirection, expansion: Point) {
+
+    let (_, after) = expanded_bounds(world, resources, client_id, coord_pos, aabb, camera_adjusted_dir, expansion);
+
+    set_pending_update_bounds(world, client_id, after.0, after.1);
+
+    push_bounds_message(world, resources, client_id, after.0, after.1);
+}
+
+/// Grows the terrain box one cell towards the camera's forward direction with the extrude_forward action, and fills the newly 
+/// covered slab with the current palette selection as a single undoable change. The box is left as it is if the fill would be rejected.
+pub fn create_extrude_system() -> impl systems::Runnable {
+
+    let extrude_forward = input::ActionRegistry::EXTRUDE_FORWARD;
+
+    SystemBuilder::new("selection_extrude_system")
+        .read_resource::<editor::EditorPaused>()
+        .read_resource::<crate::Time>()
+        .read_resource::<ClientID>()
+        .read_resource::<level_map::Map>()
+        .read_resource::<editor::PaletteSelection>()
+        .read_resource::<editor::ClientRole>()
+        .read_resource::<editor::MeasureMode>()
+        .read_resource::<editor::AxisLock>()
+        .with_query(<(Read<input::InputActionComponent>, Read<input::Action>)>::query().filter(!component::<HeldWhilePaused>()))
+        .with_query(<(Read<CameraAdjustedDirection>, Read<ClientID>, Read<level_map::CoordPos>, Read<SelectionBox>, Read<ExpansionAxisLock>, TryRead<BoxTileOrientation>)>::query()
+            .filter(component::<TerrainToolBox>() & component::<Active>()))
+        .build(move |commands, world, (editor_paused, time, client_id, map, tile_selection, client_role, measure_mode, global_axis_lock), queries| {
+
+            if editor_paused.0 {
+                return
+            }
+
+            let (input_query, selection_box_query) = queries;
+
+            if !client_role.can_edit() || measure_mode.0 {
+                return
+            }
+
+            if !input_query.iter(world).any(|(input_component, action)| action == &extrude_forward && input_component.repeated(time.delta, 0.25)) {
+                return
+            }
+
+            if let Some((camera_adjusted_dir, client_id, coord_pos, selection_box, axis_lock, box_orientation)) = selection_box_query.iter(world).find(|(_, id, _, _, _, _)| **id == **client_id) {
+
+                let forward = camera_adjusted_dir.forward;
+
+                let expansion = global_axis_lock.apply(axis_lock.apply(Point::new(
+                    forward.x.round().abs() as i32,
+                    0,
+                    forward.z.round().abs() as i32
+                )));
+
+                if expansion == Point::zeros() {
+                    return
+                }
+
+                let camera_adjusted_dir = *camera_adjusted_dir;
+                let client_id = *client_id;
+                let coord_pos = coord_pos.value;
+                let aabb = selection_box.aabb;
+                let map = **map;
+                let tile_data = level_map::TileData::new(tile_selection.val(), Point::zeros()).with_orientation(tile_orientation(&camera_adjusted_dir, box_orientation));
+
+                commands.exec_mut(move |world, resources| {
+                    let (before, expanded) = expanded_bounds(world, resources, client_id, coord_pos, aabb, camera_adjusted_dir, expansion);
+
+                    let before = AABB::new(before.0, before.1.dimensions);
+                    let after = AABB::new(expanded.0, expanded.1.dimensions);
+
+                    let old_min = before.get_min();
+                    let old_max = before.get_max();
+                    let new_min = after.get_min();
+                    let new_max = after.get_max();
+
+                    //narrow the new box down to just the cells that weren't covered before
+                    let mut slab_min = new_min;
+                    let mut slab_max = new_max;
+                    let mut grew = false;
+
+                    for axis in 0..3 {
+                        if new_min[axis] < old_min[axis] {
+                            slab_max[axis] = old_min[axis] - 1;
+                            grew = true;
+                        } else if new_max[axis] > old_max[axis] {
+                            slab_min[axis] = old_max[axis] + 1;
+                            grew = true;
+                        }
+                    }
+
+                    if !grew {
+                        return
+                    }
+
+                    let slab = AABB::from_extents(slab_min, slab_max);
+
+                    //the box only grows along with a fill that's going to happen
+                    if map.can_change(world, &level_map::fill_octree_from_aabb(slab, Some(tile_data))).is_err() {
+                        return
+                    }
+
+                    set_pending_update_bounds(world, client_id, expanded.0, expanded.1);
+
+                    push_bounds_message(world, resources, client_id, expanded.0, expanded.1);
+
+                    if let Some(message_sender) = level_map::batch_changes(
+                        std::iter::once(level_map::MapChange::MapInsertion{ aabb: slab, tile_data }), 
+                        Some(client_id.val())
+                    ) {
+                        world.push((message_sender,));
+                    }
+                });
+            }
+        })
+}
+
+/// Tints the local client's active selection box when committing to its current position wouldn't do anything. The terrain tool box
+/// checks whether an insertion would change the map, and the actor tool box checks whether it overlaps any existing actors unless 
+/// AllowOverlap is on. This only reads the world and never sends any messages.
+pub fn create_validity_feedback_system() -> impl systems::Runnable {
+    SystemBuilder::new("selection_box_validity_feedback_system")
+        .read_resource::<ClientID>()
+        .read_resource::<level_map::Map>()
+        .read_resource::<editor::PaletteSelection>()
+        .read_resource::<editor::AllowOverlap>()
+        .with_query(<(Entity, Read<SelectionBox>, Read<level_map::CoordPos>, Read<ClientID>)>::query()
+            .filter(component::<TerrainToolBox>() & component::<Active>() & (maybe_changed::<SelectionBox>() | maybe_changed::<level_map::CoordPos>())))
+        .with_query(<(Entity, Read<SelectionBox>, Read<level_map::CoordPos>, Read<ClientID>)>::query()
+            .filter(component::<ActorToolBox>() & component::<Active>() & (maybe_changed::<SelectionBox>() | maybe_changed::<level_map::CoordPos>())))
+        .build(move |commands, world, (client_id, map, tile_selection, allow_overlap), queries| {
+            let (terrain_query, actor_query) = queries;
+
+            terrain_query.iter(world)
+                .filter(|(_, _, _, id)| **id == **client_id)
+                .for_each(|(entity, selection_box, coord_pos, _)| {
+                    let entity = *entity;
+                    let map = **map;
+                    let tile_data = level_map::TileData::new(tile_selection.val(), Point::zeros());
+                    let aabb = AABB::new(coord_pos.value, selection_box.aabb.dimensions);
+
+                    commands.exec_mut(move |world, _| {
+                        let valid = map.can_change(world, &level_map::fill_octree_from_aabb(aabb, Some(tile_data))).is_ok();
+                        set_placement_material(world, entity, valid);
+                    });
+                });
+
+            actor_query.iter(world)
+                .filter(|(_, _, _, id)| **id == **client_id)
+                .for_each(|(entity, selection_box, coord_pos, _)| {
+                    let entity = *entity;
+                    let aabb = AABB::new(coord_pos.value, selection_box.aabb.dimensions);
+                    let allow_overlap = allow_overlap.0;
+
+                    commands.exec_mut(move |world, _| {
+                        let valid = allow_overlap || actor::select_actors_from_range(world, aabb).is_empty();
+                        set_placement_material(world, entity, valid);
+                    });
+                });
+        })
+}
+
+/// Publishes the local client's active selection box, any eyedropper, edit or actor rejections and any clear region confirmations to the 
+/// SelectionBoxObserver node so that GDScript UI can display them
+pub fn create_observer_system() -> impl systems::Runnable {
+    SystemBuilder::new("selection_box_observer_system")
+        .read_resource::<ClientID>()
+        .read_resource::<editor::DisplayOrigin>()
+        .with_query(<(Read<ClientID>, Read<level_map::CoordPos>, Read<SelectionBox>, TryRead<ActorToolBox>)>::query()
+            .filter(component::<Active>()))
+        .with_query(<(Entity, Read<TilePickRejected>)>::query())
+        .with_query(<(Entity, Read<ClearRegionPending>)>::query())
+        .with_query(<(Entity, Read<EditRejected>)>::query())
+        .with_query(<(Entity, Read<ActorRejected>)>::query())
+        .with_query(<(Entity, Read<ActorOverlapRejected>)>::query())
+        .build(move |commands, world, (client_id, display_origin), queries| {
+
+            let (query, rejection_query, clear_region_query, edit_rejected_query, actor_rejected_query, overlap_rejected_query) = queries;
+
+            overlap_rejected_query.for_each(world, |(entity, rejected)| {
+                ACTOR_OVERLAP_REJECTIONS.with(|r| r.borrow_mut().push(rejected.coord_pos));
+                commands.remove(*entity);
+            });
+
+            actor_rejected_query.for_each(world, |(entity, rejected)| {
+                ACTOR_REJECTIONS.with(|r| r.borrow_mut().push(rejected.actor_id));
+                commands.remove(*entity);
+            });
+
+            edit_rejected_query.for_each(world, |(entity, rejected)| {
+                EDIT_REJECTIONS.with(|r| r.borrow_mut().push((rejected.aabb.get_min(), rejected.aabb.dimensions.abs())));
+                commands.remove(*entity);
+            });
+
+            rejection_query.for_each(world, |(entity, rejection)| {
+                TILE_PICK_REJECTIONS.with(|r| r.borrow_mut().push(rejection.coord_pos));
+                commands.remove(*entity);
+            });
+
+            clear_region_query.for_each(world, |(entity, pending)| {
+                CLEAR_REGION_REQUESTS.with(|r| r.borrow_mut().push(pending.count));
+                commands.remove(*entity);
+            });
+
+            let observed = query.iter(world)
+                .find(|(id, _, _, _)| **id == **client_id)
+                .map(|(_, coord_pos, selection_box, actor_tool_box)| ObservedSelectionBox {
+                    //only what gets shown is relative to the DisplayOrigin
+                    coord_pos: coord_pos.value - display_origin.0,
+                    dimensions: selection_box.aabb.dimensions,
+                    tool_type: match actor_tool_box {
+                        Some(actor_tool_box) => ToolBoxType::ActorToolBox(actor_tool_box.get_selection()),
+                        None => ToolBoxType::TerrainToolBox
+                    }
+                });
+
+            OBSERVED_SELECTION_BOX.with(|o| *o.borrow_mut() = observed);
+        })
+}
+
+/// Measures the local client's active box against the box picked by the MeasureAgainst resource, and hands the result over to 
+/// the SelectionBoxObserver
+pub fn create_measurement_system() -> impl systems::Runnable {
+    SystemBuilder::new("selection_box_measurement_system")
+        .read_resource::<ClientID>()
+        .read_resource::<editor::MeasureAgainst>()
+        .with_query(<(Read<ClientID>, Read<level_map::CoordPos>, Read<SelectionBox>, Read<SelectionSlot>, TryRead<ActorToolBox>, TryRead<Active>)>::query())
+        .build(move |_, world, (client_id, measure_against), query| {
+
+            let boxes = query.iter(world)
+                .map(|(id, coord_pos, selection_box, slot, actor_tool_box, active)| (
+                    *id, 
+                    AABB::new(coord_pos.value, selection_box.aabb.dimensions), 
+                    slot.0, 
+                    actor_tool_box.is_some(), 
+                    active.is_some()
+                ))
+                .collect::<Vec<(ClientID, AABB, u8, bool, bool)>>();
+
+            let measurement = boxes.iter()
+                .find(|(id, _, _, _, active)| *id == **client_id && *active)
+                .and_then(|(_, aabb, active_slot, is_actor_tool, _)| {
+                    let other = match **measure_against {
+                        editor::MeasureAgainst::Nothing => None,
+                        editor::MeasureAgainst::Slot(slot) => boxes.iter()
+                            .find(|(id, _, box_slot, box_is_actor_tool, _)| 
+                                *id == **client_id && *box_slot == slot && slot != *active_slot && box_is_actor_tool == is_actor_tool
+                            ),
+                        editor::MeasureAgainst::Client(other_id) => boxes.iter()
+                            .find(|(id, _, _, _, active)| id.val() == other_id && other_id != client_id.val() && *active),
+                    };
+
+                    other.map(|(_, other_aabb, _, _, _)| measure_between(*aabb, *other_aabb))
+                });
+
+            OBSERVED_MEASUREMENT.with(|m| *m.borrow_mut() = measurement);
+        })
+}
+
+/// Toggles MeasureMode with the toggle_measure action
+pub fn create_measure_mode_toggle_system() -> impl systems::Runnable {
+
+    let toggle_measure = input::ActionRegistry::TOGGLE_MEASURE;
+
+    SystemBuilder::new("measure_mode_toggle_system")
+        .read_resource::<editor::EditorPaused>()
+        .write_resource::<editor::MeasureMode>()
+        .with_query(<(Read<input::InputActionComponent>, Read<input::Action>)>::query().filter(!component::<HeldWhilePaused>()))
+        .build(move |_, world, (editor_paused, measure_mode), query| {
+
+            if editor_paused.0 {
+                return
+            }
+
+            if query.iter(world).any(|(input_component, action)| action == &toggle_measure && input_component.just_pressed()) {
+                measure_mode.0 = !measure_mode.0;
+            }
+        })
+}
+
+/// While MeasureMode is on, works out the volume, occupied tile count and diagonal length of the local client's active terrain 
+/// box whenever it changes and hands them over to the SelectionBoxObserver. Nothing is sent over the network.
+pub fn create_measure_stats_system() -> impl systems::Runnable {
+
+    //the center and dimensions of the last box measured, so that it's measured again right away when measure mode is turned on
+    let mut last_measured: Option<(Point, Point)> = None;
+
+    SystemBuilder::new("measure_stats_system")
+        .read_resource::<ClientID>()
+        .read_resource::<level_map::Map>()
+        .read_resource::<editor::MeasureMode>()
+        .with_query(<(Read<SelectionBox>, Read<level_map::CoordPos>, Read<ClientID>)>::query()
+            .filter(component::<TerrainToolBox>() & component::<Active>()))
+        .build(move |commands, world, (client_id, map, measure_mode), query| {
+
+            if !measure_mode.0 {
+                if last_measured.take().is_some() {
+                    OBSERVED_MEASURE_STATS.with(|m| *m.borrow_mut() = None);
+                }
+                return
+            }
+
+            if let Some((selection_box, coord_pos, _)) = query.iter(world).find(|(_, _, id)| **id == **client_id) {
 
-                                commands.exec_mut(move |world, _| {
-                                    actor_tool_rotation(world, entity, rotation);
+                let measured = (coord_pos.value, selection_box.aabb.dimensions);
 
-                                    world.push(
-                                        (MessageSender{
-                                            data_type: DataType::ActorToolRotation {
-                                                client_id,
-                                                rotation
-                                            },
-                                            message_type: MessageType::Ordered
-                                        },)
-                                    );
-                                });
+                if last_measured == Some(measured) {
+                    return
+                }
 
-                            });
+                last_measured = Some(measured);
 
-                    }
+                let aabb = AABB::new(coord_pos.value, selection_box.aabb.dimensions);
+                let map = **map;
+
+                commands.exec_mut(move |world, _| {
+                    let dimensions = aabb.dimensions.abs();
+
+                    let stats = MeasureStats {
+                        volume: dimensions.x as i64 * dimensions.y as i64 * dimensions.z as i64,
+                        occupied: map.tiles_in(world, aabb).len(),
+                        diagonal: level_map::map_coords_to_world(dimensions).norm(),
+                    };
+
+                    OBSERVED_MEASURE_STATS.with(|m| *m.borrow_mut() = Some(stats));
                 });
+            }
         })
 }
 
-/// Expands the dimensions of the selection box
-pub fn create_expansion_system() -> impl systems::Runnable {    
+/// Distances between the centers of a and b, and the size of the region that they overlap
+fn measure_between(a: AABB, b: AABB) -> Measurement {
 
-    let expand_selection_forward = input::Action("expand_selection_forward".to_string());
-    let expand_selection_back = input::Action("expand_selection_back".to_string());
-    let expand_selection_left = input::Action("expand_selection_left".to_string());
-    let expand_selection_right = input::Action("expand_selection_right".to_string());
-    let expand_selection_up = input::Action("expand_selection_up".to_string());
-    let expand_selection_down = input::Action("expand_selection_down".to_string());
+    let center = |aabb: AABB| aabb.get_min().map(|v| v as f32) + aabb.dimensions.abs().map(|v| v as f32) / 2.;
 
-    SystemBuilder::new("selection_expansion_system")
-        .read_resource::<crate::Time>()
-        .read_resource::<ClientID>()
-        .with_query(<(Read<input::InputActionComponent>, Read<input::Action>)>::query())
-        .with_query(<(Read<CameraAdjustedDirection>, Read<ClientID>, Read<level_map::CoordPos>, Read<SelectionBox>)>::query()
-            .filter(component::<TerrainToolBox>() & component::<Active>()))
-        .build(move |commands, world, (time, client_id), queries| {
-            let (input_query, selection_box_query) = queries;
+    let difference = center(a) - center(b);
 
-            let inputs = input_query.iter(world)
-                .map(|(input, action)| (*input, (*action).clone()))
-                .collect::<Vec<(input::InputActionComponent, input::Action)>>();
+    let a_min = a.get_min();
+    let b_min = b.get_min();
+    let a_max = a_min + a.dimensions.abs();
+    let b_max = b_min + b.dimensions.abs();
 
-            //left: movement, right: expansion
-            let mut combined_expansion: Option<Point> = None;
-            let mut entity: Option<(CameraAdjustedDirection, Point, AABB, ClientID)> = None;
+    let overlap = Point::new(
+        std::cmp::min(a_max.x, b_max.x) - std::cmp::max(a_min.x, b_min.x),
+        std::cmp::min(a_max.y, b_max.y) - std::cmp::max(a_min.y, b_min.y),
+        std::cmp::min(a_max.z, b_max.z) - std::cmp::max(a_min.z, b_min.z),
+    );
 
-            for(input_component, action) in inputs.iter().filter(|(_, a)|
-                a == &expand_selection_forward ||
-                a == &expand_selection_back ||
-                a == &expand_selection_left ||
-                a == &expand_selection_right ||
-                a == &expand_selection_up ||
-                a == &expand_selection_down
-            ) {                    
-                
-                if input_component.repeated(time.delta, 0.25) {
+    Measurement {
+        manhattan: difference.abs().sum(),
+        euclidean: difference.norm(),
+        overlap: if overlap.x > 0 && overlap.y > 0 && overlap.z > 0 { overlap } else { Point::zeros() }
+    }
+}
 
-                    selection_box_query.iter(world)
-                        .filter(|(_, id, _, _)| **id == **client_id)
-                        .for_each(|(camera_adjusted_dir, client_id, coord_pos, selection_box)| {
-
-                        entity = Some((*camera_adjusted_dir, coord_pos.value, selection_box.aabb, *client_id));
-
-                        let mut expansion = Point::zeros();
-
-                        if action == &expand_selection_forward {
-                            expansion.z += 1;
-                        } else if action == &expand_selection_back {
-                            expansion.z -= 1;
-                        } else if action == &expand_selection_left {
-                            expansion.x -= 1;
-                        } else if action == &expand_selection_right {
-                            expansion.x += 1;
-                        } else if action == &expand_selection_down {
-                            expansion.y -= 1;
-                        } else if action == &expand_selection_up {
-                            expansion.y += 1;
-                        }
+/// Swaps the selection box's material between the regular and invalid placement materials, only writing when it actually changes
+fn set_placement_material(world: &mut World, selection_entity: Entity, valid: bool) {
+    let mut material = custom_mesh::Material::from_str(if valid { SELECTION_BOX_MATERIAL } else { INVALID_SELECTION_BOX_MATERIAL });
 
-                        let forward = camera_adjusted_dir.forward;
-                        let right = camera_adjusted_dir.right;
+    if let Some(mut entry) = world.entry(selection_entity) {
+        let current = entry.get_component::<custom_mesh::Material>().ok().copied();
 
-                        let mut adjusted = Point::new(
-                            forward.x.round().abs() as i32,
-                            0,
-                            forward.z.round().abs() as i32
-                        ) * expansion.z as i32 + Point::new(
-                            right.x.round().abs() as i32,
-                            0,
-                            right.z.round().abs() as i32
-                        ) * expansion.x as i32;
+        //only valid boxes are tinted with their client's color, so that the invalid material always shows the same way
+        if let Some(tint) = current.and_then(|current| current.tint()).filter(|_| valid) {
+            material = material.with_tint(tint);
+        }
+
+        let changed = current.map(|current| current != material).unwrap_or(true);
 
-                        adjusted.y = expansion.y as i32;
+        if changed {
+            entry.add_component(material);
+        }
+    }
+}
 
-                        combined_expansion = Some(adjusted);
+/// Tints each selection box with the color that has been assigned to the client it belongs to, unless it's showing the invalid 
+/// placement material
+pub fn create_client_color_system() -> impl systems::Runnable {
+    SystemBuilder::new("selection_box_client_color_system")
+        .read_resource::<editor::ClientColors>()
+        .with_query(<(Entity, Read<ClientID>, Read<custom_mesh::Material>)>::query()
+            .filter(component::<SelectionBox>()))
+        .build(|commands, world, client_colors, query| {
+
+            query.iter(world)
+                .filter_map(|(entity, client_id, material)| {
+                    match client_colors.get(client_id.val()) {
+                        Some(color) if material.tint() != Some(color) && material.name() != Some(INVALID_SELECTION_BOX_MATERIAL) => {
+                            Some((*entity, material.with_tint(color)))
+                        },
+                        _ => None
+                    }
+                })
+                .collect::<Vec<(Entity, custom_mesh::Material)>>()
+                .into_iter()
+                .for_each(|(entity, material)| {
+                    commands.add_component(entity, material);
+                });
+        })
+}
 
-                    }); 
+/// Pulses the material of active selection boxes so they stand out, and settles boxes that are no longer active back to their static 
+/// look. BoxPulse turns it off or tones it down. Boxes tagged with ConfirmingHold flash at full strength whatever BoxPulse says. This 
+/// only ever touches materials, never geometry or the network.
+pub fn create_pulse_system() -> impl systems::Runnable {
+    SystemBuilder::new("selection_box_pulse_system")
+        .read_resource::<crate::Time>()
+        .read_resource::<editor::BoxPulse>()
+        .with_query(<(Entity, TryRead<ConfirmingHold>)>::query()
+            .filter(component::<SelectionBox>() & component::<Active>() & !component::<Pulse>()))
+        .with_query(<(Entity, Read<Pulse>, TryRead<Active>, TryRead<ConfirmingHold>)>::query())
+        .with_query(<(Write<Pulse>, Read<node::NodeRef>, TryRead<ConfirmingHold>)>::query()
+            .filter(component::<Active>()))
+        .build(|commands, world, (time, box_pulse), queries| {
+
+            let (new_query, settle_query, pulse_query) = queries;
+
+            //with the pulse turned off, every box gets settled the same way that boxes which are no longer active do, apart from boxes 
+            //that are flashing while insertion is held to confirm
+            settle_query.for_each(world, |(entity, pulse, active, confirming)| {
+                if active.is_none() || (!box_pulse.enabled && confirming.is_none()) {
+                    unsafe { apply_pulse(pulse, 1.); }
+                    commands.remove_component::<Pulse>(*entity);
                 }
-            }
+            });
 
-            if let Some(combined_expansion) = combined_expansion {
-                if let Some((camera_adjusted_dir, coord_pos_value, aabb, client_id)) = entity {
-                    
-                    commands.exec_mut(move |world, _| {
-                        let mut query = <(Write<UpdateBounds>, Read<ClientID>)>::query();
+            new_query.for_each(world, |(entity, confirming)| {
+                if box_pulse.enabled || confirming.is_some() {
+                    commands.add_component(*entity, Pulse::default());
+                }
+            });
 
-                        let mut existing_expansion: Option<(Point, AABB)> = None;
+            pulse_query.for_each_mut(world, |(pulse, node_ref, confirming)| {
 
-                        let mut new_aabb = aabb;
-                                    
-                        let diff = expansion_movement_helper(combined_expansion, camera_adjusted_dir, &mut new_aabb);
+                let (amplitude, speed) = match confirming {
+                    Some(_) => (1., PULSE_SPEED * CONFIRM_FLASH_SPEED),
+                    None if box_pulse.enabled => (box_pulse.amplitude.max(0.).min(1.), PULSE_SPEED),
+                    None => return
+                };
 
-                        let move_to_pos = coord_pos_value - diff;
+                let geometry = match unsafe { node_ref.val().assume_safe().cast::<GeometryInstance>() } {
+                    Some(geometry) => geometry,
+                    None => return
+                };
 
-                        if let Some((update_to, _)) = query.iter_mut(world).find(|(_, id)| **id == client_id) {
-                            
-                            update_to.coord_pos -= diff;
-                            update_to.aabb.dimensions += combined_expansion;
+                //the material gets replaced whenever the box's Material changes, so take a fresh copy of whatever is there now
+                let current = geometry.material_override();
+                if current.is_some() && current != pulse.material {
+                    let copy = current.and_then(|material| unsafe { material.assume_safe().duplicate(false) })
+                        .and_then(|copy| copy.cast::<gdnative::api::Material>());
 
-                            existing_expansion = Some((update_to.coord_pos, update_to.aabb));
-                        }
+                    if let Some(copy) = copy {
+                        pulse.base_alpha = unsafe { copy.assume_safe().cast::<SpatialMaterial>() }
+                            .map(|spatial_material| spatial_material.albedo().a)
+                            .unwrap_or(1.);
 
-                        let mut update_selection = DataType::UpdateSelectionBounds{ client_id: client_id.val(), coord_pos: move_to_pos, aabb: new_aabb };
+                        geometry.set_material_override(copy.clone());
+                        pulse.material = Some(copy);
+                    }
+                }
 
-                        match existing_expansion {
-                            Some(existing_expansion) => {
-                                if let DataType::UpdateSelectionBounds{client_id:_, coord_pos, aabb} = &mut update_selection {
+                pulse.phase = (pulse.phase + time.delta * speed) % (std::f32::consts::PI * 2.);
 
-                                    *coord_pos = existing_expansion.0;
-                                    *aabb = existing_expansion.1;
-                                }
-                            },
-                            None => {
-                                if let DataType::UpdateSelectionBounds{client_id:_, coord_pos, aabb} = &mut update_selection {
-                                    world.push(
-                                        (
-                                            UpdateBounds {
-                                                aabb: *aabb,
-                                                coord_pos: *coord_pos
-                                            },
-                                            client_id
-                                        )
-                                    );
-                                }
-                            }
-                        }
+                unsafe { apply_pulse(pulse, 1. - amplitude * (0.5 - 0.5 * pulse.phase.sin())); }
+            });
+        })
+}
 
-                        world.push((MessageSender{
-                            data_type: update_selection,
-                            message_type: MessageType::Ordered
-                        },));
+/// Keeps the line_thickness parameter of selection box shader materials in step with the BoxStyle resource. Materials get swapped 
+/// and duplicated by other systems, so rather than tracking those changes the parameter is compared every frame and only written 
+/// when it differs.
+pub fn create_line_thickness_system() -> impl systems::Runnable {
+    SystemBuilder::new("selection_box_line_thickness_system")
+        .read_resource::<editor::BoxStyle>()
+        .with_query(<Read<node::NodeRef>>::query()
+            .filter(component::<SelectionBox>()))
+        .build(|_, world, box_style, query| {
 
-                    });
+            let line_thickness = box_style.sanitized().line_thickness;
+
+            query.for_each(world, |node_ref| {
+
+                let material = unsafe { node_ref.val().assume_safe().cast::<GeometryInstance>() }
+                    .and_then(|geometry| geometry.material_override());
+
+                let shader_material = match material.as_ref().and_then(|material| unsafe { material.assume_safe().cast::<ShaderMaterial>() }) {
+                    Some(shader_material) => shader_material,
+                    None => return
+                };
+
+                if shader_material.get_shader_param(LINE_THICKNESS_SHADER_PARAM).try_to_f64() != Some(line_thickness as f64) {
+                    shader_material.set_shader_param(LINE_THICKNESS_SHADER_PARAM, line_thickness);
                 }
-            }  
+            });
         })
 }
 
+/// Writes the pulse amount, between 0 and 1, to the pulse's copy of the material
+unsafe fn apply_pulse(pulse: &Pulse, amount: f32) {
+    let material = match pulse.material {
+        Some(material) => material.assume_safe(),
+        None => return
+    };
+
+    if let Some(shader_material) = material.cast::<ShaderMaterial>() {
+        shader_material.set_shader_param(PULSE_SHADER_PARAM, amount);
+    } else if let Some(spatial_material) = material.cast::<SpatialMaterial>() {
+        let mut albedo = spatial_material.albedo();
+        albedo.a = pulse.base_alpha * (0.5 + 0.5 * amount);
+        spatial_material.set_albedo(albedo);
+    }
+}
+
 pub fn create_update_bounds_system() -> impl systems::Runnable {
     SystemBuilder::new("selection_box_move_to_system")
         .with_query(<(Entity, Read<ClientID>, Read<SelectionBox>)>::query())
@@ -962,9 +4593,14 @@ pub fn create_update_bounds_system() -> impl systems::Runnable {
 
             selection_box_query.for_each(world, |(entity, client_id, selection_box)| {
 
-                if let Some((update_entity, _, update_to)) = move_tos.iter().find(|(_,id,_)| id == client_id) {
+                //should there somehow be more than one for the client, only the last one found is applied and they all get removed
+                let update_entities = move_tos.iter()
+                    .filter(|(_, id, _)| id == client_id)
+                    .map(|(update_entity, _, _)| *update_entity)
+                    .collect::<Vec<Entity>>();
+
+                if let Some((_, _, update_to)) = move_tos.iter().rev().find(|(_,id,_)| id == client_id) {
                     
-                    let update_entity = *update_entity;
                     let entity = *entity;
                     let update_to = *update_to;
                     let selection_box = *selection_box;
@@ -976,16 +4612,25 @@ pub fn create_update_bounds_system() -> impl systems::Runnable {
                                 coord_pos.value = update_to.coord_pos;
                             }
 
-                            if selection_box.aabb != update_to.aabb { //only write to SelectionBox if there is an actual change
+                            //a box sized to its actor's footprint only changes size when it's turned or given another actor, so it 
+                            //keeps its dimensions whatever the update says
+                            let mut aabb = update_to.aabb;
+                            if entry.get_component::<FixedFootprint>().is_ok() {
+                                aabb.dimensions = selection_box.aabb.dimensions;
+                            }
+
+                            if selection_box.aabb != aabb { //only write to SelectionBox if there is an actual change
                                 if entry.get_component::<Active>().is_ok() { //only update bounds if this is the active toolbox
                                     if let Ok(selection_box) = entry.get_component_mut::<SelectionBox>() {
-                                        selection_box.aabb = update_to.aabb;
+                                        selection_box.aabb = aabb;
                                     }
                                 }
                             }
                         }
 
-                        world.remove(update_entity);
+                        for update_entity in &update_entities {
+                            world.remove(*update_entity);
+                        }
                     });
 
                 }
@@ -994,19 +4639,32 @@ pub fn create_update_bounds_system() -> impl systems::Runnable {
 }
 
 pub fn create_system() -> impl systems::Runnable {
+
+    let mut last_style: Option<(editor::BoxStyle, editor::BoxRenderMode)> = None;
     
     SystemBuilder::new("selection_box_system")
-        .with_query(<(Read<SelectionBox>, Write<custom_mesh::MeshData>,)>::query()
-            .filter(maybe_changed::<SelectionBox>(),)
+        .read_resource::<editor::BoxStyle>()
+        .read_resource::<editor::BoxRenderMode>()
+        .with_query(<(Read<SelectionBox>, TryRead<ExpansionAxisLock>, Write<custom_mesh::MeshData>,)>::query()
+            .filter(maybe_changed::<SelectionBox>() | maybe_changed::<ExpansionAxisLock>())
         )
-        .build(move |_, world, _, query| {
+        .with_query(<(Read<SelectionBox>, TryRead<ExpansionAxisLock>, Write<custom_mesh::MeshData>,)>::query())
+        .build(move |_, world, (box_style, render_mode), queries| {
+
+            let (changed_query, all_query) = queries;
+
+            let box_style = box_style.sanitized();
+            let render_mode = **render_mode;
+
+            //every box's mesh depends on the style, so rebuild all of them when it changes
+            let style_changed = last_style.map_or(true, |last_style| last_style != (box_style, render_mode));
+            last_style = Some((box_style, render_mode));
+
+            let mut update_mesh = |(selection_box, axis_lock, mesh_data): (&SelectionBox, Option<&ExpansionAxisLock>, &mut custom_mesh::MeshData)| {
 
-            query.for_each_mut(world, |(selection_box, mesh_data)| {
+                mesh_data.clear();
 
-                mesh_data.verts.clear();
-                mesh_data.normals.clear();
-                mesh_data.uvs.clear();
-                mesh_data.indices.clear();
+                let axis_lock = axis_lock.copied().unwrap_or_default();
 
                 //offset that the next face will begin on, increments by the number of verts for each face
                 //at the end of each loop
@@ -1026,13 +4684,71 @@ pub fn create_system() -> impl systems::Runnable {
                     true_dimensions.z.abs()
                 );
 
+                if render_mode == editor::BoxRenderMode::Wireframe {
+
+                    mesh_data.primitive = Mesh::PRIMITIVE_LINES;
+
+                    let corner = |x: bool, y: bool, z: bool| Vector3D::new(
+                        if x { max.x } else { min.x },
+                        if y { max.y } else { min.y },
+                        if z { max.z } else { min.z }
+                    );
+
+                    //each edge runs along one axis from a corner on the min side of it, tinted by that axis like the faces are
+                    for axis in 0..3 {
+
+                        let color = if [axis_lock.x, axis_lock.y, axis_lock.z][axis] {
+                            LOCKED_AXIS_COLORS[axis]
+                        } else {
+                            UNLOCKED_AXIS_COLOR
+                        };
+
+                        for (a, b) in [(false, false), (true, false), (false, true), (true, true)].iter().copied() {
+
+                            let (start, end) = match axis {
+                                0 => (corner(false, a, b), corner(true, a, b)),
+                                1 => (corner(a, false, b), corner(a, true, b)),
+                                _ => (corner(a, b, false), corner(a, b, true)),
+                            };
+
+                            for pt in [start, end].iter() {
+                                let normal = (pt - true_center).normalize();
+
+                                mesh_data.indices.push(mesh_data.verts.len() as i32);
+                                mesh_data.verts.push(Vector3::new(pt.x, pt.y, pt.z));
+                                mesh_data.normals.push(Vector3::new(normal.x, normal.y, normal.z));
+                                mesh_data.uvs.push(Vector2::new(0., 0.));
+                                mesh_data.colors.push(color);
+                            }
+                        }
+                    }
+
+                    return
+                }
+
+                mesh_data.primitive = Mesh::PRIMITIVE_TRIANGLES;
+
                 for i in 0..3 { 
 
                     let mut verts: Vec<Vector3> = Vec::new();  
                     let mut normals: Vec<Vector3> = Vec::new();
                     let mut uvs: Vec<Vector2> = Vec::new();
 
-                    let max_margin = 0.9;
+                    // faces are tinted by the axis their normals point along
+                    let axis = match i {
+                        0 => 1,
+                        1 => 0,
+                        _ => 2
+                    };
+
+                    let color = if [axis_lock.x, axis_lock.y, axis_lock.z][axis] {
+                        LOCKED_AXIS_COLORS[axis]
+                    } else {
+                        UNLOCKED_AXIS_COLOR
+                    };
+
+                    //the bevel can't reach past the middle of the smallest axis, or the inner edges would cross over and invert the faces
+                    let max_margin = box_style.margin;
 
                     let smaller_x = Float::min(max_margin, abs_dimensions.x /2.0);
                     let smaller_y = Float::min(max_margin, abs_dimensions.y /2.0);
@@ -1287,30 +5003,386 @@ pub fn create_system() -> impl systems::Runnable {
                         indices.push(k+1);
                         indices.push(k);
 
-                        indices.push(k+2);
-                        indices.push(k+3);
-                        indices.push(k+1);
+                        indices.push(k+2);
+                        indices.push(k+3);
+                        indices.push(k+1);
+
+                    }
+
+                    //increase the offset for the next loop by the number of verts in the face before consuming verts
+                    offset += verts.len() as i32;
+
+                    mesh_data.colors.extend(verts.iter().map(|_| color));
+                    mesh_data.verts.extend(verts);
+                    mesh_data.normals.extend(normals);
+                    mesh_data.uvs.extend(uvs);
+                    mesh_data.indices.extend(indices);
+ 
+                }
+
+                // godot_print!("Updated selection box mesh");
+                
+            };
+
+            if style_changed {
+                all_query.for_each_mut(world, &mut update_mesh);
+            } else {
+                changed_query.for_each_mut(world, &mut update_mesh);
+            }
+
+        })
+}
+
+/// Sets the dimensions of the client's active terrain tool box directly, anchoring it the same way that expansion does. Negative 
+/// dimensions are allowed for mirrored boxes, but dimensions of zero on any axis are rejected.
+pub fn set_selection_box_dimensions(world: &mut World, resources: &Resources, client_id: ClientID, dimensions: Point) -> Result<(), Error> {
+
+    if dimensions.x == 0 || dimensions.y == 0 || dimensions.z == 0 {
+        return Err(Error::new(ErrorKind::InvalidInput, "Selection box dimensions can't be zero"))
+    }
+
+    let mut query = <(Read<ClientID>, Read<CameraAdjustedDirection>, Read<level_map::CoordPos>, Read<SelectionBox>)>::query()
+        .filter(component::<TerrainToolBox>() & component::<Active>());
+
+    let (camera_adjusted_dir, mut coord_pos, mut aabb) = query.iter(world)
+        .find(|(id, _, _, _)| **id == client_id)
+        .map(|(_, camera_adjusted_dir, coord_pos, selection_box)| (*camera_adjusted_dir, coord_pos.value, selection_box.aabb))
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, "Client doesn't have an active terrain tool box"))?;
+
+    //build off of any update that hasn't been applied yet so that we don't lose it
+    let mut update_query = <(Read<UpdateBounds>, Read<ClientID>)>::query();
+    if let Some((update_to, _)) = update_query.iter(world).find(|(_, id)| **id == client_id) {
+        coord_pos = update_to.coord_pos;
+        aabb = update_to.aabb;
+    }
+
+    let mut new_aabb = aabb;
+    let from_far_side = resources.get::<editor::ExpandFromFarSide>().map(|far_side| far_side.0).unwrap_or(false);
+
+    let diff = expansion_movement_helper(dimensions - aabb.dimensions, camera_adjusted_dir, from_far_side, &mut new_aabb);
+
+    send_update_bounds(world, resources, client_id, coord_pos - diff, new_aabb);
+
+    Ok(())
+}
+
+/// Sets the pending UpdateBounds for the client to the given bounds, creating it if it doesn't exist yet, and sends the 
+/// UpdateSelectionBounds message so that other clients follow along.
+pub fn send_update_bounds(world: &mut World, resources: &Resources, client_id: ClientID, coord_pos: Point, aabb: AABB) {
+
+    set_pending_update_bounds(world, client_id, coord_pos, aabb);
+
+    push_bounds_message(world, resources, client_id, coord_pos, aabb);
+}
+
+/// Takes a snapshot of every client's selection boxes that are in their current slots. Boxes in other slots get created on the other
+/// clients as they are switched to, so they are left out.
+pub fn selection_box_snapshots(world: &World) -> Vec<SelectionBoxSnapshot> {
+
+    let mut query = <(Read<ClientID>, Read<level_map::CoordPos>, Read<SelectionBox>, TryRead<ActorToolBox>, TryRead<SelectionBoxRotation>, TryRead<Active>)>::query()
+        .filter(component::<CurrentSlot>() & (component::<TerrainToolBox>() | component::<ActorToolBox>()));
+
+    query.iter(world)
+        .map(|(client_id, coord_pos, selection_box, actor_tool, rotation, active)| SelectionBoxSnapshot {
+            client_id: client_id.val(),
+            box_type: match actor_tool {
+                Some(actor_tool) => ToolBoxType::ActorToolBox(actor_tool.get_selection()),
+                None => ToolBoxType::TerrainToolBox
+            },
+            active: active.is_some(),
+            coord_pos: coord_pos.value,
+            rotation: rotation.map(|rotation| rotation.value).unwrap_or_else(Rotation3::identity),
+            aabb: selection_box.aabb,
+        })
+        .collect()
+}
+
+/// Builds the selection box described by the snapshot, or brings the existing box of that client and tool type up to date with it 
+/// so that receiving the same box twice never leaves a duplicate behind. The local client's own boxes are only ever created from a 
+/// snapshot when they are missing, such as after a resync, since the local state of an existing one is always more recent.
+pub fn instantiate_snapshot(world: &mut World, resources: &mut Resources, snapshot: SelectionBoxSnapshot) {
+
+    let is_local = resources.get::<ClientID>().map_or(false, |client_id| client_id.val() == snapshot.client_id);
+    let is_actor_tool = matches!(snapshot.box_type, ToolBoxType::ActorToolBox(_));
+
+    let mut query = <(Entity, Read<ClientID>, TryRead<ActorToolBox>)>::query()
+        .filter(component::<SelectionBox>() & component::<CurrentSlot>());
+
+    let existing = query.iter(world)
+        .find(|(_, client_id, actor_tool)| client_id.val() == snapshot.client_id && actor_tool.is_some() == is_actor_tool)
+        .map(|(entity, _, actor_tool)| (*entity, actor_tool.map(|actor_tool| actor_tool.get_selection())));
+
+    let (entity, current_actor) = match existing {
+        Some(_) if is_local => return,
+        Some(existing) => existing,
+        None => (initialize_selection_box(world, resources, snapshot.client_id, snapshot.box_type, None), None)
+    };
+
+    if let Some(mut entry) = world.entry(entity) {
+        if let Ok(pos) = entry.get_component_mut::<level_map::CoordPos>() {
+            pos.value = snapshot.coord_pos;
+        }
+        if let Ok(selection_box) = entry.get_component_mut::<SelectionBox>() {
+            selection_box.aabb = snapshot.aabb;
+        }
+    }
+
+    match snapshot.box_type {
+        ToolBoxType::TerrainToolBox => {
+            if snapshot.active {
+                set_active_selection_box::<TerrainToolBox>(world, ClientID::new(snapshot.client_id));
+            }
+        },
+        ToolBoxType::ActorToolBox(actor_id) => {
+
+            //choosing the actor again would only rebuild the same preview
+            if current_actor != Some(actor_id) {
+                if let Err(err) = update_chosen_actor(world, entity, actor_id) {
+                    println!("Couldn't choose actor {} for client {}: {}", actor_id, snapshot.client_id, err);
+                }
+            }
+
+            actor_tool_rotation(world, entity, snapshot.rotation);
+
+            if snapshot.active {
+                set_active_selection_box::<ActorToolBox>(world, ClientID::new(snapshot.client_id));
+            }
+        }
+    }
+}
+
+/// Gathers the tiles and actors within the client's active box without changing anything, or returns None if the client doesn't have 
+/// an active box
+pub fn inspect_active(world: &mut World, map: &level_map::Map, client_id: ClientID) -> Option<BoxContents> {
+
+    let mut query = <(Read<ClientID>, Read<level_map::CoordPos>, Read<SelectionBox>)>::query()
+        .filter(component::<Active>());
+
+    let aabb = query.iter(world)
+        .find(|(id, _, _)| **id == client_id)
+        .map(|(_, coord_pos, selection_box)| AABB::new(coord_pos.value, selection_box.aabb.dimensions))?;
+
+    Some(BoxContents {
+        aabb,
+        tiles: map.tiles_in(world, aabb),
+        actors: actor::select_actors_from_range(world, aabb),
+    })
+}
+
+/// Puts the client's active box exactly at coord_pos with the given dimensions and sends the update, for when a position and size are 
+/// typed in rather than reached with the keyboard. When clamp is set the box is pushed back inside the map's bounds, if it has any.
+pub fn set_bounds(world: &mut World, resources: &Resources, client_id: ClientID, coord_pos: Point, dimensions: Point, clamp: bool) -> Result<(), Error> {
+
+    if dimensions.x == 0 || dimensions.y == 0 || dimensions.z == 0 {
+        return Err(Error::new(ErrorKind::InvalidInput, "Selection box dimensions can't be zero"))
+    }
+
+    let mut query = <(Read<ClientID>, Read<SelectionBox>, TryRead<FixedFootprint>)>::query()
+        .filter(component::<Active>());
+
+    let (mut aabb, fixed_footprint) = query.iter(world)
+        .find(|(id, _, _)| **id == client_id)
+        .map(|(_, selection_box, fixed_footprint)| (selection_box.aabb, fixed_footprint.is_some()))
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, "Client doesn't have an active selection box"))?;
+
+    if fixed_footprint && dimensions != aabb.dimensions {
+        return Err(Error::new(ErrorKind::InvalidInput, "The active box is sized to its actor's footprint and can't be resized"))
+    }
+
+    aabb.dimensions = dimensions;
+
+    let bounds = resources.get::<level_map::Map>().and_then(|map| map.bounds());
+
+    let coord_pos = match bounds {
+        Some(_) if clamp => keep_within_bounds(bounds, editor::EdgeBehavior::Clamp, coord_pos, dimensions).unwrap_or(coord_pos),
+        _ => coord_pos
+    };
+
+    send_update_bounds(world, resources, client_id, coord_pos, aabb);
+
+    Ok(())
+}
+
+/// Which side of the actors' combined extents align_actors lines them up on
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Alignment {
+    Min,
+    Center,
+    Max,
+}
+
+/// Lines up the actors under the client's active box along the axis, so that they share the min, center or max of the space they 
+/// take up together, like the align tools of modelling programs. Extents come from each actor's Bounds turned by its Rotation, so 
+/// rotated actors line up by the cells they actually cover. The moves are sent as a single ActorBatch so that they're undone in one 
+/// step. Returns how many actors were moved.
+pub fn align_actors(world: &mut World, resources: &Resources, client_id: ClientID, axis: usize, alignment: Alignment) -> Result<usize, Error> {
+
+    if axis > 2 {
+        return Err(Error::new(ErrorKind::InvalidInput, "Axis has to be 0, 1 or 2"))
+    }
+
+    if !resources.get::<editor::ClientRole>().map(|client_role| client_role.can_edit()).unwrap_or(true) {
+        return Err(Error::new(ErrorKind::PermissionDenied, "Spectators can't move actors"))
+    }
+
+    let mut box_query = <(Read<ClientID>, Read<level_map::CoordPos>, Read<SelectionBox>)>::query()
+        .filter(component::<Active>());
+
+    let range = box_query.iter(world)
+        .find(|(id, _, _)| **id == client_id)
+        .map(|(_, coord_pos, selection_box)| AABB::new(coord_pos.value, selection_box.aabb.dimensions))
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, "Client doesn't have an active selection box"))?;
+
+    let filter = resources.get::<editor::ActorFilter>().map(|filter| filter.clone()).unwrap_or_default();
+
+    let entities = actor::select_actors_from_range_filtered(world, range, |entry| filter.allows(entry));
+
+    let actors = entities.into_iter()
+        .filter_map(|entity| world.entry_ref(entity).ok().and_then(|entry| {
+            let actor_id = entry.get_component::<actor::ActorID>().ok()?.val();
+            let bounds = entry.get_component::<actor::Bounds>().ok()?;
+            let coord_pos = entry.get_component::<level_map::CoordPos>().ok()?.value;
+            let rotation = entry.get_component::<transform::rotation::Rotation>().ok()?.value;
+
+            Some((actor_id, coord_pos, rotation, actor_extents(coord_pos, bounds, rotation)))
+        }))
+        .collect::<Vec<(u128, Point, Rotation3<f32>, (Point, Point))>>();
+
+    if actors.len() < 2 {
+        return Ok(0)
+    }
+
+    let aligned_mins = aligned_mins(&actors.iter().map(|(_, _, _, extents)| *extents).collect::<Vec<(Point, Point)>>(), axis, alignment);
+
+    let moves = actors.into_iter().zip(aligned_mins)
+        .filter_map(|((actor_id, coord_pos, rotation, (min, _)), aligned_min)| {
+
+            if aligned_min == min[axis] {
+                return None
+            }
+
+            let mut aligned = coord_pos;
+            aligned[axis] += aligned_min - min[axis];
+
+            Some(actor::ActorChange::ActorMove{ actor_id, coord_pos: aligned, rotation })
+        })
+        .collect::<Vec<actor::ActorChange>>();
+
+    let moved = moves.len();
+
+    if moved > 0 {
+        push_actor_batch(world, moves, client_id.val());
+    }
+
+    Ok(moved)
+}
+
+/// The min corner and size of an actor at coord_pos, worked out the same way as when the actor's node is positioned
+fn actor_extents(coord_pos: Point, bounds: &actor::Bounds, rotation: Rotation3<f32>) -> (Point, Point) {
+    let dimensions = bounds.get_scaled_and_rotated_aabb(rotation).dimensions.abs();
+
+    (coord_pos - dimensions / 2, dimensions)
+}
+
+/// Returns where along the axis the min corner of each of the actors, given by their min corner and size, has to go for them to 
+/// line up with the alignment
+fn aligned_mins(extents: &[(Point, Point)], axis: usize, alignment: Alignment) -> Vec<i32> {
+
+    let extents_min = extents.iter().map(|(min, _)| min[axis]).min().unwrap_or_default();
+    let extents_max = extents.iter().map(|(min, dimensions)| min[axis] + dimensions[axis]).max().unwrap_or_default();
+
+    extents.iter()
+        .map(|(_, dimensions)| match alignment {
+            Alignment::Min => extents_min,
+            Alignment::Max => extents_max - dimensions[axis],
+            //actors with an odd size can't be centered exactly, so they're rounded towards the min
+            Alignment::Center => (extents_min + extents_max - dimensions[axis]).div_euclid(2),
+        })
+        .collect()
+}
+
+/// Sets the pending UpdateBounds for the client to the given bounds, creating it if it doesn't exist yet. Any extra UpdateBounds that 
+/// have somehow built up for the client are removed so that there's only ever one.
+pub fn set_pending_update_bounds(world: &mut World, client_id: ClientID, coord_pos: Point, aabb: AABB) {
+
+    let mut query = <(Entity, Write<UpdateBounds>, Read<ClientID>)>::query();
 
-                    }
+    let mut pending = query.iter_mut(world)
+        .filter(|(_, _, id)| **id == client_id)
+        .map(|(entity, update_to, _)| {
+            update_to.coord_pos = coord_pos;
+            update_to.aabb = aabb;
+            update_to.frames = 0;
+            *entity
+        })
+        .collect::<Vec<Entity>>();
+
+    if pending.is_empty() {
+        world.push(
+            (
+                UpdateBounds {
+                    coord_pos,
+                    aabb,
+                    frames: 0
+                },
+                client_id
+            )
+        );
+    } else {
+        pending.remove(0);
+
+        for duplicate in pending {
+            world.remove(duplicate);
+        }
+    }
+}
 
-                    //increase the offset for the next loop by the number of verts in the face before consuming verts
-                    offset += verts.len() as i32;
+/// Returns the bounds that the client's box is going to be updated to, if there are any waiting
+fn pending_update_bounds(world: &World, client_id: ClientID) -> Option<(Point, AABB)> {
+    let mut query = <(Read<UpdateBounds>, Read<ClientID>)>::query();
 
-                    mesh_data.verts.extend(verts);
-                    mesh_data.normals.extend(normals);
-                    mesh_data.uvs.extend(uvs);
-                    mesh_data.indices.extend(indices);
- 
+    query.iter(world)
+        .find(|(_, id)| **id == client_id)
+        .map(|(update_to, _)| (update_to.coord_pos, update_to.aabb))
+}
+
+/// Counts how many frames each UpdateBounds has been waiting, removing any that have waited longer than STALE_UPDATE_BOUNDS_FRAMES 
+/// so that they can't pile up for boxes that aren't around to apply them
+pub fn create_stale_update_bounds_system() -> impl systems::Runnable {
+    SystemBuilder::new("stale_update_bounds_system")
+        .with_query(<(Entity, Write<UpdateBounds>)>::query())
+        .build(|commands, world, _, query| {
+            query.for_each_mut(world, |(entity, update_to)| {
+                update_to.frames += 1;
+
+                if update_to.frames > STALE_UPDATE_BOUNDS_FRAMES {
+                    commands.remove(*entity);
                 }
+            });
+        })
+}
 
-                // godot_print!("Updated selection box mesh");
-                
-            })
+/// Moves the client's active selection box straight to coord_pos, without any of the camera adjustment or stepping that regular 
+/// movement goes through. Called by every client when handling SetSelectionCoord, so nothing needs to be sent from here.
+pub fn set_selection_coord(world: &mut World, client_id: ClientID, coord_pos: Point) {
 
-        })
+    let mut query = <(Write<level_map::CoordPos>, Read<SelectionBox>, Read<ClientID>)>::query()
+        .filter(component::<Active>());
+
+    let aabb = match query.iter_mut(world).find(|(_, _, id)| **id == client_id) {
+        Some((box_coord_pos, selection_box, _)) => {
+            box_coord_pos.value = coord_pos;
+            selection_box.aabb
+        },
+        None => return
+    };
+
+    set_pending_update_bounds(world, client_id, coord_pos, aabb);
 }
 
-fn expansion_movement_helper(expansion: Point, camera_adjusted_dir: CameraAdjustedDirection, new_aabb: &mut AABB) -> Point {
+/// Applies the expansion to new_aabb and returns how far the box has to move so that it grows towards the camera's right, or away 
+/// from the camera when from_far_side is set, rather than out of both sides
+fn expansion_movement_helper(expansion: Point, camera_adjusted_dir: CameraAdjustedDirection, from_far_side: bool, new_aabb: &mut AABB) -> Point {
 
     let original = *new_aabb;
 
@@ -1334,8 +5406,18 @@ fn expansion_movement_helper(expansion: Point, camera_adjusted_dir: CameraAdjust
     let mut new_min = new_aabb.get_min();
     let mut new_max = new_aabb.get_max();
 
-    // Adjust the offset based off of camera direction
-    if camera_adjusted_dir.right.x < 0. { 
+    let forward = camera_adjusted_dir.forward;
+    let right = camera_adjusted_dir.right;
+
+    // Adjust the offset based off of camera direction. Forward and right lie on different axes, so adding them up gives the 
+    // direction each axis grows in when growing away from the camera.
+    let (flip_x, flip_z) = if from_far_side {
+        (forward.x.round() + right.x.round() < 0., forward.z.round() + right.z.round() < 0.)
+    } else {
+        (right.x < 0., right.z < 0.)
+    };
+
+    if flip_x { 
         let tmp_min = min.x;
         let tmp_new_min = new_min.x;
         min.x = max.x; 
@@ -1343,7 +5425,7 @@ fn expansion_movement_helper(expansion: Point, camera_adjusted_dir: CameraAdjust
         max.x = tmp_min;
         new_max.x = tmp_new_min;
     } 
-    if camera_adjusted_dir.right.z < 0. { 
+    if flip_z { 
         let tmp_min = min.z;
         let tmp_new_min = new_min.z;
         min.z = max.z; 
@@ -1359,96 +5441,130 @@ fn expansion_movement_helper(expansion: Point, camera_adjusted_dir: CameraAdjust
     )
 } 
 
+/// Turns the actor tool box and its preview actor by tool_rotation. A box with a FixedFootprint is resized from its actor's Bounds at 
+/// the new rotation, rather than rotating the box itself, so that turning it many times never leaves it bigger than the actor.
 pub fn actor_tool_rotation(world: &mut World, selection_entity: Entity, tool_rotation: Rotation3<f32>) {
 
-    if let Some(Some((actor_entity, rotation, aabb))) = world.entry(selection_entity).map(|mut entry| {
+    let actor_entity = world.entry(selection_entity)
+        .and_then(|entry| entry.get_component::<EntityRef>().map(|entity_ref| entity_ref.0).ok());
+
+    let bounds = actor_entity
+        .and_then(|actor_entity| world.entry(actor_entity))
+        .and_then(|entry| entry.get_component::<actor::Bounds>().ok().copied());
+
+    if let Some(Some((rotation, aabb))) = world.entry(selection_entity).map(|mut entry| {
+        let fixed_footprint = entry.get_component::<FixedFootprint>().is_ok();
+
         entry.get_component_mut::<SelectionBoxRotation>().map(|selection_box_rot| {
             selection_box_rot.value *= tool_rotation;
             selection_box_rot.value
         }).ok().and_then(|rotation| {
             entry.get_component_mut::<SelectionBox>().map(|selection_box| {
-                selection_box.aabb = selection_box.aabb.rotate(tool_rotation);
+                match bounds {
+                    Some(bounds) if fixed_footprint => {
+                        let center = selection_box.aabb.center;
+                        selection_box.aabb = bounds.get_scaled_and_rotated_aabb(rotation);
+                        selection_box.aabb.center = center;
+                    },
+                    _ => selection_box.aabb.rotate_about_center(tool_rotation)
+                }
                 selection_box.aabb
-            }).ok().and_then(|aabb| {
-                entry.get_component_mut::<EntityRef>().map(|entity_ref| entity_ref.0)
-                    .ok().map(|entity| (entity, rotation, aabb))
-            })
+            }).ok().map(|aabb| (rotation, aabb))
         })
     }) {
-        if let Some(mut entry) = world.entry(actor_entity) { 
-            entry.add_component(transform::rotation::Rotation{
-                value: rotation
-            }); 
+        if let Some(actor_entity) = actor_entity {
+            if let Some(mut entry) = world.entry(actor_entity) { 
+                entry.add_component(transform::rotation::Rotation{
+                    value: rotation
+                }); 
+            }
+            actor::position_actor_helper(world, actor_entity, aabb);
         }
-        actor::position_actor_helper(world, actor_entity, aabb);
     }
 }
 
-/// Updates the selection box with the new chosen actor (new_entity should be newly duplicated into this world)
-pub fn update_chosen_actor(world: &mut World, selection_entity: Entity, actor_id: i64) {
+/// Updates the selection box with the new chosen actor, cloning it from the actor palette into this world as the box's preview. 
+/// Returns an error if the actor couldn't be cloned, such as when the palette has been reloaded without it, in which case the box is 
+/// left without a preview rather than with a half-initialized one.
+pub fn update_chosen_actor(world: &mut World, selection_entity: Entity, actor_id: i64) -> Result<(), Error> {
 
     // Check to see if there is an EntityRef which points to our old entity, and remove it
-    if let Some(Some(old_entity)) = world.entry(selection_entity).map(|entry| {
-        entry.get_component::<EntityRef>().map(|entity_ref| entity_ref.0).ok()
+    if let Some(Some(old_entity)) = world.entry(selection_entity).map(|mut entry| {
+        let old_entity = entry.get_component::<EntityRef>().map(|entity_ref| entity_ref.0).ok();
+        entry.remove_component::<EntityRef>();
+        old_entity
     }) {
-        if let Some(Some(node)) = world.entry(old_entity).map(|actor_entry| {
+        match world.entry(old_entity).map(|actor_entry| {
             actor_entry.get_component::<node::NodeRef>().map(|node_ref| node_ref.val()).ok()
         }) {
-            node::free(world, node)
+            Some(Some(node)) => node::free(world, node),
+            _ => { world.remove(old_entity); }
         }
     }
 
-    if let Some(actor_world) = ActorPalette::get_world() {
-        let actor_world = &mut actor_world.borrow_mut();
-        if let Some(actor_world) = actor_world.as_mut() {
-            ENTITY_REFS.with(|e| {
-                let entity_refs = e.borrow();
+    let actor_world = ActorPalette::get_world()
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, "The actor palette hasn't been loaded"))?;
+    let actor_world = &mut actor_world.borrow_mut();
+    let actor_world = actor_world.as_mut()
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, "The actor palette hasn't been loaded"))?;
 
-                if let Some(actor_entity) = entity_refs.get(&actor_id) {
+    let actor_entity = ENTITY_REFS.with(|e| e.borrow().get(&actor_id).copied())
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, format!("Actor {} isn't in the actor palette", actor_id)))?;
 
-                    MERGER.with(|m| {
-                        let mut merger = m.borrow_mut();
-                        let new_entity = world.clone_from_single(actor_world, *actor_entity, &mut *merger);
-
-                        if let Some(bounds) = world.entry(new_entity).map(|entry| {
-                            entry.get_component::<actor::Bounds>().map(|b| *b).ok()
-                        }).flatten() { 
-                            if let Some(Some((node_ref, rotation, aabb))) = world.entry(selection_entity).map(|mut entry| {
-                                entry.add_component(EntityRef(new_entity));
-                                entry.get_component::<SelectionBoxRotation>()
-                                    .map(|box_rotation| box_rotation.value)
-                                    .ok().and_then(|rotation| {
-                                        entry.get_component_mut::<SelectionBox>().map(|selection_box| {
-                                            selection_box.aabb = bounds.get_scaled_and_rotated_aabb(rotation);
-                                            selection_box.aabb
-                                        }).ok().and_then(|aabb| {
-                                            entry.get_component::<node::NodeRef>().map(|n| n.val()).ok()
-                                                .map(|node_ref| (node_ref, rotation, aabb))
-                                        })
-                                    })
-                            }) {
+    let new_entity = MERGER.with(|m| {
+        let mut merger = m.borrow_mut();
+        world.clone_from_single(actor_world, actor_entity, &mut *merger)
+    });
 
-                                if let Some(mut entry) = world.entry(new_entity) {
-                                    entry.add_component(transform::rotation::Rotation{
-                                        value: rotation
-                                    });
+    let bounds = world.entry(new_entity)
+        .and_then(|entry| entry.get_component::<actor::Bounds>().map(|b| *b).ok());
+
+    let selection = world.entry(selection_entity).and_then(|entry| {
+        match (entry.get_component::<SelectionBoxRotation>(), entry.get_component::<node::NodeRef>()) {
+            (Ok(box_rotation), Ok(node_ref)) => Some((box_rotation.value, node_ref.val())),
+            _ => None
+        }
+    });
 
-                                    entry.add_component(node::NodeParent::new(node_ref));
+    let (bounds, (rotation, node_ref)) = match (bounds, selection) {
+        (Some(bounds), Some(selection)) => (bounds, selection),
+        (None, _) => {
+            world.remove(new_entity);
+            return Err(Error::new(ErrorKind::InvalidData, format!("Actor {} doesn't have any Bounds", actor_id)))
+        },
+        (_, None) => {
+            world.remove(new_entity);
+            return Err(Error::new(ErrorKind::NotFound, "The selection box isn't an actor tool box with a node"))
+        }
+    };
 
-                                }
-                                actor::position_actor_helper(world, new_entity, aabb);
-                            }
-                        }
-                    });
-                }
-            });
+    let aabb = bounds.get_scaled_and_rotated_aabb(rotation);
+
+    if let Some(mut entry) = world.entry(selection_entity) {
+        entry.add_component(EntityRef(new_entity));
+        entry.add_component(FixedFootprint{});
+
+        if let Ok(selection_box) = entry.get_component_mut::<SelectionBox>() {
+            selection_box.aabb = aabb;
         }
     }
+
+    if let Some(mut entry) = world.entry(new_entity) {
+        entry.add_component(transform::rotation::Rotation{
+            value: rotation
+        });
+
+        entry.add_component(node::NodeParent::new(node_ref));
+    }
+
+    actor::position_actor_helper(world, new_entity, aabb);
+
+    Ok(())
 }
 
 pub fn get_box_entity_by_client_id<T: legion::storage::Component>(world: &mut World, client_id: ClientID) -> Option<Entity> {
 
-    let mut query = <(Entity, Read<ClientID>)>::query().filter(component::<SelectionBox>() & component::<T>());
+    let mut query = <(Entity, Read<ClientID>)>::query().filter(component::<SelectionBox>() & component::<T>() & component::<CurrentSlot>());
 
     query.iter(world)
         .filter(|(_, id)| id.val() == client_id.val())
@@ -1477,8 +5593,8 @@ pub fn set_active_selection_box<T: legion::storage::Component>(world: &mut World
         }
     }
 
-    //enable selection box that is not yet active and that is this component type
-    let mut query = <(Entity, Read<ClientID>, Read<node::NodeRef>)>::query().filter(component::<SelectionBox>() & !component::<Active>() & component::<T>());
+    //enable selection box that is not yet active and that is this component type, in its current slot
+    let mut query = <(Entity, Read<ClientID>, Read<node::NodeRef>)>::query().filter(component::<SelectionBox>() & !component::<Active>() & component::<T>() & component::<CurrentSlot>());
     let results = query.iter(world)
         .filter(|(_, id, _)| {
             client_id == **id
@@ -1498,4 +5614,485 @@ pub fn set_active_selection_box<T: legion::storage::Component>(world: &mut World
     }
 
 }
-    
\ No newline at end of file
+    
+
+/// Makes slot the current slot for the client's boxes of tool type T, creating a box for the slot where the current one is if it 
+/// doesn't exist yet. If the tool was active, the box in the new slot is shown and activated in place of the old one.
+pub fn set_active_slot<T: legion::storage::Component>(world: &mut World, resources: &mut Resources, client_id: ClientID, slot: u8) {
+
+    let current = match get_box_entity_by_client_id::<T>(world, client_id) {
+        Some(current) => current,
+        None => return
+    };
+
+    let mut query = <(Entity, Read<ClientID>, Read<SelectionSlot>)>::query().filter(component::<SelectionBox>() & component::<T>());
+
+    let existing = query.iter(world)
+        .find(|(_, id, box_slot)| **id == client_id && box_slot.0 == slot)
+        .map(|(entity, _, _)| *entity);
+
+    let target = match existing {
+        Some(entity) if entity == current => return,
+        Some(entity) => entity,
+        None => match create_slot_box(world, resources, client_id, current, slot) {
+            Some(entity) => entity,
+            None => return
+        }
+    };
+
+    let was_active = world.entry_ref(current).map(|entry| entry.get_component::<Active>().is_ok()).unwrap_or(false);
+
+    if let Some(mut entry) = world.entry(current) {
+        entry.remove_component::<CurrentSlot>();
+        entry.remove_component::<Active>();
+
+        if let Ok(node_ref) = entry.get_component::<node::NodeRef>() {
+            if let Some(mesh) = unsafe { node_ref.val().assume_safe().cast::<Spatial>() } {
+                mesh.set_visible(false);
+            }
+        }
+    }
+
+    if let Some(mut entry) = world.entry(target) {
+        entry.add_component(CurrentSlot{});
+    }
+
+    if was_active {
+        set_active_selection_box::<T>(world, client_id);
+    }
+}
+
+/// Switches slots for whichever tool type the client currently has active
+pub fn set_active_tool_slot(world: &mut World, resources: &mut Resources, client_id: ClientID, slot: u8) {
+    if get_box_entity_by_client_id::<Active>(world, client_id)
+        .and_then(|entity| world.entry_ref(entity).ok().map(|entry| entry.get_component::<ActorToolBox>().is_ok()))
+        .unwrap_or(false) {
+        
+        set_active_slot::<ActorToolBox>(world, resources, client_id, slot);
+    } else {
+        set_active_slot::<TerrainToolBox>(world, resources, client_id, slot);
+    }
+}
+
+/// Creates a box of the same tool type as template in the given slot, starting out where the template is
+fn create_slot_box(world: &mut World, resources: &mut Resources, client_id: ClientID, template: Entity, slot: u8) -> Option<Entity> {
+
+    let (tool_type, camera, coord_pos, aabb, rotation) = world.entry_ref(template).ok().and_then(|entry| {
+        let tool_type = match entry.get_component::<ActorToolBox>() {
+            Ok(actor_tool) => ToolBoxType::ActorToolBox(actor_tool.get_selection()),
+            Err(_) => ToolBoxType::TerrainToolBox
+        };
+
+        Some((
+            tool_type,
+            entry.get_component::<RelativeCamera>().ok().map(|relative_cam| relative_cam.val()),
+            entry.get_component::<level_map::CoordPos>().ok().copied()?,
+            entry.get_component::<SelectionBox>().ok()?.aabb,
+            entry.get_component::<SelectionBoxRotation>().ok().map(|rotation| rotation.value),
+        ))
+    })?;
+
+    let entity = initialize_selection_box(world, resources, client_id.val(), tool_type, camera);
+
+    if let Some(mut entry) = world.entry(entity) {
+        entry.add_component(SelectionSlot(slot));
+        entry.remove_component::<CurrentSlot>();
+
+        if let Ok(pos) = entry.get_component_mut::<level_map::CoordPos>() {
+            *pos = coord_pos;
+        }
+        if let Ok(selection_box) = entry.get_component_mut::<SelectionBox>() {
+            selection_box.aabb = aabb;
+        }
+        if let (Ok(box_rotation), Some(rotation)) = (entry.get_component_mut::<SelectionBoxRotation>(), rotation) {
+            box_rotation.value = rotation;
+        }
+    }
+
+    if let ToolBoxType::ActorToolBox(actor_id) = tool_type {
+        if let Err(err) = update_chosen_actor(world, entity, actor_id) {
+            godot_print!("Couldn't choose actor {} for the slot: {}", actor_id, err);
+        }
+    }
+
+    Some(entity)
+}
+
+/// Cycles the local client's active tool through its selection slots with the next_box and prev_box actions
+pub fn create_slot_switch_system() -> impl systems::Runnable {
+    let next_box = input::ActionRegistry::NEXT_BOX;
+    let prev_box = input::ActionRegistry::PREV_BOX;
+
+    SystemBuilder::new("slot_switch_system")
+        .read_resource::<editor::EditorPaused>()
+        .read_resource::<ClientID>()
+        .with_query(<(Read<SelectionSlot>, Read<ClientID>)>::query()
+            .filter(component::<SelectionBox>() & component::<Active>()))
+        .with_query(<(Read<input::InputActionComponent>, Read<input::Action>)>::query().filter(!component::<HeldWhilePaused>()))
+        .build(move |commands, world, (editor_paused, client_id), queries| {
+
+            if editor_paused.0 {
+                return
+            }
+
+            let (selection_box_query, input_query) = queries;
+
+            let step: i32 = input_query.iter(world)
+                .filter(|(input_component, _)| input_component.just_pressed())
+                .map(|(_, action)| if action == &next_box { 1 } else if action == &prev_box { -1 } else { 0 })
+                .sum();
+
+            if step == 0 {
+                return
+            }
+
+            if let Some((slot, _)) = selection_box_query.iter(world).find(|(_, id)| **id == **client_id) {
+
+                let slot = (slot.0 as i32 + step).rem_euclid(SELECTION_SLOTS as i32) as u8;
+                let client_id = **client_id;
+
+                commands.exec_mut(move |world, resources| {
+                    set_active_tool_slot(world, resources, client_id, slot);
+
+                    world.push(
+                        (MessageSender{
+                            data_type: DataType::SetActiveSlot{
+                                client_id: client_id.val(),
+                                slot
+                            },
+                            message_type: MessageType::Ordered
+                        },)
+                    );
+                });
+            }
+        })
+}
+
+/// Sends a SelectionPresence ping with where the local client's active box is every PRESENCE_INTERVAL, so that other clients can 
+/// show a marker for it
+pub fn create_presence_system() -> impl systems::Runnable {
+
+    let mut last_sent: Option<Instant> = None;
+
+    SystemBuilder::new("selection_presence_system")
+        .read_resource::<ClientID>()
+        .with_query(<(Read<level_map::CoordPos>, Read<ClientID>)>::query()
+            .filter(component::<SelectionBox>() & component::<Active>()))
+        .build(move |commands, world, client_id, query| {
+
+            if last_sent.map(|last_sent| last_sent.elapsed() < PRESENCE_INTERVAL).unwrap_or(false) {
+                return
+            }
+
+            if let Some((coord_pos, _)) = query.iter(world).find(|(_, id)| **id == **client_id) {
+
+                last_sent = Some(Instant::now());
+
+                commands.push(
+                    (
+                        MessageSender{
+                            data_type: DataType::SelectionPresence{
+                                client_id: client_id.val(),
+                                coord_pos: coord_pos.value
+                            },
+                            message_type: MessageType::Instant
+                        },
+                    )
+                );
+            }
+        })
+}
+
+/// Outlines aabb as the client's claimed region, replacing the client's previous claim if it had one
+pub fn set_region_claim(world: &mut World, resources: &Resources, client_id: ClientID, aabb: AABB) {
+
+    release_region_claim(world, client_id);
+
+    let min = level_map::map_coords_to_world(aabb.get_min());
+    let max = level_map::map_coords_to_world(aabb.get_min() + aabb.dimensions.abs());
+
+    let corners = (0..8).map(|i| Vector3::new(
+        if i & 1 == 0 { min.x } else { max.x },
+        if i & 2 == 0 { min.y } else { max.y },
+        if i & 4 == 0 { min.z } else { max.z },
+    )).collect::<Vec<Vector3>>();
+
+    let geometry = ImmediateGeometry::new();
+
+    let material = SpatialMaterial::new();
+    material.set_flag(SpatialMaterial::FLAG_UNSHADED, true);
+    material.set_feature(SpatialMaterial::FEATURE_TRANSPARENT, true);
+
+    if let Some(color) = resources.get::<editor::ClientColors>().and_then(|client_colors| client_colors.get(client_id.val())) {
+        material.set_albedo(Color { a: CLAIM_OUTLINE_ALPHA, ..color });
+    }
+
+    geometry.set_material_override(material);
+
+    geometry.begin(Mesh::PRIMITIVE_LINES, Null::null());
+
+    //every pair of corners whose indices differ by a single bit shares an edge
+    for i in 0..8 {
+        for bit in &[1, 2, 4] {
+            if i & bit == 0 {
+                geometry.add_vertex(corners[i]);
+                geometry.add_vertex(corners[i | bit]);
+            }
+        }
+    }
+
+    geometry.end();
+
+    let owner = unsafe { crate::OWNER_NODE.as_mut().unwrap().assume_safe() };
+
+    let node = unsafe { node::add_node(&owner, geometry.upcast()) };
+
+    world.push(
+        (
+            RegionClaim{ aabb },
+            client_id,
+            node::NodeRef::new(node),
+        )
+    );
+}
+
+/// Removes the client's claimed region and its outline, if it has one
+pub fn release_region_claim(world: &mut World, client_id: ClientID) {
+
+    let mut query = <(Read<node::NodeRef>, Read<ClientID>)>::query()
+        .filter(component::<RegionClaim>());
+
+    query.iter(world)
+        .filter(|(_, id)| **id == client_id)
+        .map(|(node_ref, _)| node_ref.val())
+        .collect::<Vec<Ref<Node>>>()
+        .into_iter()
+        .for_each(|node_ref| {
+            node::free(world, node_ref);
+        });
+}
+
+/// Moves the presence marker for a remote client to coord_pos, creating it tinted with the client's color if it doesn't exist yet
+pub fn update_presence(world: &mut World, resources: &Resources, client_id: ClientID, coord_pos: Point) {
+
+    let position = level_map::map_coords_to_world(coord_pos) + level_map::map_coords_to_world(Point::new(1,1,1)) / 2.;
+
+    let mut query = <(Write<transform::position::Position>, Read<ClientID>)>::query()
+        .filter(component::<PresenceMarker>());
+
+    if let Some((marker_position, _)) = query.iter_mut(world).find(|(_, id)| **id == client_id) {
+        marker_position.value = position;
+        return
+    }
+
+    let mesh = CubeMesh::new();
+    mesh.set_size(Vector3::new(PRESENCE_MARKER_SIZE, PRESENCE_MARKER_SIZE, PRESENCE_MARKER_SIZE));
+
+    let material = SpatialMaterial::new();
+    material.set_flag(SpatialMaterial::FLAG_UNSHADED, true);
+
+    if let Some(color) = resources.get::<editor::ClientColors>().and_then(|client_colors| client_colors.get(client_id.val())) {
+        material.set_albedo(color);
+    }
+
+    let marker = MeshInstance::new();
+    marker.set_mesh(mesh);
+    marker.set_material_override(material);
+
+    let owner = unsafe { crate::OWNER_NODE.as_mut().unwrap().assume_safe() };
+
+    let node = unsafe { node::add_node(&owner, marker.upcast()) };
+
+    world.push(
+        (
+            PresenceMarker{},
+            client_id,
+            node::NodeRef::new(node),
+            transform::position::Position{ value: position },
+        )
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_moves_add_up_to_whole_cells() {
+        let mut progress = Vector3D::zeros();
+        let direction = Point::new(1, 0, -1);
+
+        let steps = (0..10)
+            .map(|_| accumulate_progress(&mut progress, direction, 0.25))
+            .collect::<Vec<Point>>();
+
+        //only every fourth quarter of a cell makes a step, and nothing is lost in between
+        assert_eq!(steps.iter().filter(|step| **step != Point::zeros()).count(), 2);
+        assert_eq!(steps.iter().sum::<Point>(), Point::new(2, 0, -2));
+        assert_eq!(progress, Vector3D::new(0.5, 0., -0.5));
+    }
+
+    #[test]
+    fn turning_around_drops_partial_progress() {
+        let mut progress = Vector3D::zeros();
+
+        accumulate_progress(&mut progress, Point::new(1, 0, 1), 0.75);
+        let step = accumulate_progress(&mut progress, Point::new(-1, 0, 0), 0.5);
+
+        assert_eq!(step, Point::zeros());
+        assert_eq!(progress, Vector3D::new(-0.5, 0., 0.));
+    }
+
+    #[test]
+    fn moving_forward_and_left_together_moves_diagonally() {
+        let camera_adjusted_dir = CameraAdjustedDirection::default();
+
+        let combined = camera_adjusted_movement(&camera_adjusted_dir, Point::new(0, 0, 1)) 
+            + camera_adjusted_movement(&camera_adjusted_dir, Point::new(-1, 0, 0));
+
+        assert_eq!(combined, Point::new(-1, 0, 1));
+    }
+
+    #[test]
+    fn aligning_lines_actors_up_on_the_min_center_or_max() {
+        let extents = [
+            (Point::new(0, 0, 0), Point::new(2, 1, 1)),
+            (Point::new(5, 0, 0), Point::new(3, 1, 1)),
+        ];
+
+        assert_eq!(aligned_mins(&extents, 0, Alignment::Min), vec![0, 0]);
+        assert_eq!(aligned_mins(&extents, 0, Alignment::Max), vec![6, 5]);
+        assert_eq!(aligned_mins(&extents, 0, Alignment::Center), vec![3, 2]);
+    }
+
+    #[test]
+    fn centering_odd_sizes_rounds_towards_the_min() {
+        let extents = [
+            (Point::new(-5, 0, 0), Point::new(3, 1, 1)),
+            (Point::new(0, 0, 0), Point::new(2, 1, 1)),
+        ];
+
+        //-5 / 2 would round towards zero, to -2, instead
+        assert_eq!(aligned_mins(&extents, 0, Alignment::Center), vec![-3, -3]);
+    }
+
+    #[test]
+    fn rotated_actors_align_by_the_cells_they_cover() {
+        let bounds = actor::Bounds(Vector3D::new(2., level_map::TILE_DIMENSIONS.y, 1.));
+        let turned = Rotation3::from_axis_angle(&Vector3D::y_axis(), std::f32::consts::FRAC_PI_2);
+
+        let extents = [
+            actor_extents(Point::new(0, 0, 0), &bounds, Rotation3::identity()),
+            actor_extents(Point::new(10, 0, 0), &bounds, turned),
+        ];
+
+        assert_eq!(extents[1].1, Point::new(1, 1, 2));
+
+        assert_eq!(aligned_mins(&extents, 2, Alignment::Min), vec![-1, -1]);
+        assert_eq!(aligned_mins(&extents, 2, Alignment::Max), vec![0, -1]);
+    }
+
+    #[test]
+    fn mirrored_boxes_give_the_same_corners_as_their_positive_counterparts() {
+        let mirrored = SelectionBox::from_aabb(AABB::new(Point::new(2, 0, -1), Point::new(-3, 2, -4)));
+
+        let min = mirrored.aabb.get_min();
+        let positive = SelectionBox::from_aabb(AABB::from_extents(min, min + Point::new(2, 1, 3)));
+
+        let corners = mirrored.corners();
+
+        assert_eq!(corners, positive.corners());
+        assert_eq!(mirrored.world_corners(), positive.world_corners());
+
+        assert_eq!(corners[0], min);
+        assert_eq!(corners[7], min + Point::new(2, 1, 3));
+
+        //each bit of the index picks the max side of one axis, whichever way the box was mirrored
+        for (i, corner) in corners.iter().enumerate() {
+            for axis in 0..3 {
+                let expected = if i & (1 << axis) == 0 { corners[0][axis] } else { corners[7][axis] };
+                assert_eq!(corner[axis], expected);
+            }
+        }
+    }
+
+    #[test]
+    fn normalizing_a_mirrored_box_keeps_it_over_the_same_cells() {
+        let coord_pos = Point::new(2, 0, -1);
+
+        for dimensions in &[Point::new(-3, 2, -4), Point::new(4, -1, 1), Point::new(-2, -5, -3)] {
+            let before = SelectionBox::from_aabb(AABB::new(coord_pos, *dimensions)).corners();
+
+            let mut selection_box = SelectionBox::from_aabb(AABB::new(coord_pos, *dimensions));
+            let normalized_pos = selection_box.normalize_in_place(coord_pos);
+
+            assert_eq!(selection_box.aabb.dimensions, dimensions.abs());
+            assert_eq!(SelectionBox::from_aabb(AABB::new(normalized_pos, selection_box.aabb.dimensions)).corners(), before);
+        }
+    }
+
+    #[test]
+    fn expanding_up_or_down_does_nothing_while_y_is_locked() {
+        let camera_adjusted_dir = CameraAdjustedDirection::default();
+
+        let global_y_lock = editor::AxisLock { y: true, ..editor::AxisLock::default() };
+        let box_y_lock = ExpansionAxisLock { y: true, ..ExpansionAxisLock::default() };
+
+        for action in &[input::ActionRegistry::EXPAND_SELECTION_UP, input::ActionRegistry::EXPAND_SELECTION_DOWN] {
+            let expansion = expansion_for_action(action, &camera_adjusted_dir);
+
+            assert_ne!(locked_expansion(expansion, ExpansionAxisLock::default(), editor::AxisLock::default(), false), Point::zeros());
+
+            assert_eq!(locked_expansion(expansion, ExpansionAxisLock::default(), global_y_lock, false), Point::zeros());
+            assert_eq!(locked_expansion(expansion, box_y_lock, editor::AxisLock::default(), false), Point::zeros());
+        }
+
+        //the other axes still expand
+        let expansion = expansion_for_action(&input::ActionRegistry::EXPAND_SELECTION_RIGHT, &camera_adjusted_dir);
+        assert_ne!(locked_expansion(expansion, box_y_lock, global_y_lock, false), Point::zeros());
+    }
+
+    #[test]
+    fn checkerboard_fills_go_out_as_a_single_batch() {
+        let tiles = [level_map::TileData::new(1, Point::zeros()), level_map::TileData::new(2, Point::zeros())];
+        let aabb = AABB::new(Point::new(0, 0, 0), Point::new(-3, 2, 3));
+
+        let changes = checkerboard_changes(aabb, tiles);
+
+        //one change for the whole box, then one for each of the 9 cells out of 18 that get the second tile
+        assert_eq!(changes.len(), 10);
+        assert_eq!(changes[0].get_aabb(), aabb);
+
+        for change in &changes[1..] {
+            match change {
+                level_map::MapChange::MapInsertion{ aabb: cell, tile_data } => {
+                    assert_eq!(tile_data.get_tile(), 2);
+                    assert_eq!((cell.center.x + cell.center.y + cell.center.z).rem_euclid(2), 1);
+                },
+                level_map::MapChange::MapRemoval(_) => panic!("A checkerboard fill shouldn't remove anything")
+            }
+        }
+
+        match level_map::batch_changes(changes, Some(3)).map(|message_sender| message_sender.data_type) {
+            Some(DataType::MapChangeBatch{ changes, store_history }) => {
+                assert_eq!(changes.len(), 10);
+                assert_eq!(store_history, Some(3));
+            },
+            _ => panic!("Expected the fill to be sent as a single MapChangeBatch")
+        }
+    }
+
+    #[test]
+    fn grid_lines_run_through_the_inside_of_the_box() {
+        let lines = grid_lines(Vector3D::zeros(), Vector3D::new(1., 1., 1.), Point::new(2, 2, 2));
+
+        //3 by 3 boundaries on each axis, less the 4 edges of the box
+        assert_eq!(lines.len(), 15);
+
+        assert!(lines.contains(&(Vector3D::new(1., 1., 0.), Vector3D::new(1., 1., 2.))));
+        assert!(lines.contains(&(Vector3D::new(0., 1., 1.), Vector3D::new(2., 1., 1.))));
+        assert!(lines.contains(&(Vector3D::new(1., 0., 1.), Vector3D::new(1., 2., 1.))));
+
+        assert!(!lines.contains(&(Vector3D::new(0., 0., 0.), Vector3D::new(2., 0., 0.))));
+    }
+}
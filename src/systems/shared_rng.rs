@@ -0,0 +1,61 @@
+//! A random number generator that gives the same results on every client in a session.
+//! 
+//! Every client is handed the same seed through a DataType::SessionSeed message when it connects, so as long as each client pulls
+//! from the SharedRng resource the same number of times and in the same order, they all end up with identical results. That means
+//! random decisions for tools have to be made when their messages are handled, in the order those messages are received, and never
+//! when input is read or in response to anything that only happens on one client.
+
+/// Resource holding the session's random number generator, implemented as SplitMix64
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SharedRng {
+    state: u64
+}
+
+impl SharedRng {
+
+    pub fn new(seed: u64) -> Self {
+        SharedRng {
+            state: seed
+        }
+    }
+
+    /// Creates a SharedRng with a random seed, used by the host to start a session
+    pub fn from_entropy() -> Self {
+        SharedRng::new(uuid::Uuid::new_v4().as_u128() as u64)
+    }
+
+    /// The current state of the generator, which can be sent as the seed for a client to continue from the same point
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    /// Returns a value from min up to but not including max, or min if the range is empty
+    pub fn next_range(&mut self, min: i32, max: i32) -> i32 {
+        if max <= min {
+            return min
+        }
+
+        let span = (max as i64 - min as i64) as u64;
+
+        (min as i64 + ((self.next_u32() as u64 * span) >> 32) as i64) as i32
+    }
+}
+
+impl Default for SharedRng {
+    fn default() -> Self {
+        SharedRng::from_entropy()
+    }
+}
@@ -15,7 +15,9 @@ pub fn create_system() -> impl systems::Runnable {
         .with_query(<(Entity, Write<Smoothing>)>::query())
         .build(move |commands, world, time, query| {
             query.for_each_mut(world, |(entity, mut smoothing)| {
-                smoothing.current = smoothing.current + (smoothing.heading - smoothing.current) * time.delta * smoothing.speed;
+                //fast speeds or long frames would carry it past the heading, so it stops there instead
+                let step = (time.delta * smoothing.speed).min(1.0);
+                smoothing.current = smoothing.current + (smoothing.heading - smoothing.current) * step;
 
                 if (smoothing.current - smoothing.heading).norm() < 1.0e-5 {
                     commands.remove_component::<Smoothing>(*entity);